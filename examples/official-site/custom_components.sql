@@ -137,6 +137,7 @@ and SQLPage adds a few more:
 - `typeof`: returns the type of a value (`string`, `number`, `boolean`, `object`, `array`, `null`)
 - `rfc2822_date`: formats a date as a string in the [RFC 2822](https://tools.ietf.org/html/rfc2822#section-3.3) format, that is, `Thu, 21 Dec 2000 16:01:07 +0200`
 - `url_encode`: percent-encodes a string for use in a URL. For instance, `{{url_encode "hello world"}}` returns `hello%20world`.
+- `component_or_text`: displays a value that can contain [another component](/component.sql?component=table#component), like `{"component": "button", "rows": [...]}`. Use it with three braces: `{{{component_or_text my_value}}}`. Other values are displayed as escaped text, like `{{my_value}}`.
 
 ### Attributes
 
@@ -146,6 +147,24 @@ SQLPage adds the following attributes to the context of your components:
  - `@component_index` : the index of the current component in the page. Useful to generate unique ids or classes.
  - `@row_index` : the index of the current row in the current component. Useful to implement special behavior on the first row, for instance.
  - `@csp_nonce` : a random nonce that you must use as the `nonce` attribute of your `<script>` tags if you include external scripts.
+ - `@column_types` : an object associating each column of the current row with the kind of value it contains in the database:
+   `number`, `boolean`, `date`, `time`, `datetime`, `json`, `binary` or `text`.
+   Useful to right-align numbers or format dates without having to pass additional parameters to the component.
+   It is only available for rows that come from a database query, not for rows containing only literal values.
+
+For instance, the following component displays each row in a table, with numeric columns aligned to the right:
+
+```handlebars
+<table>
+{{#each_row}}
+  <tr>
+    {{#each this}}
+    <td {{#if (eq (lookup @../column_types @key) "number")}}class="text-end"{{/if}}>{{this}}</td>
+    {{/each}}
+  </tr>
+{{/each_row}}
+</table>
+```
 
 ## External javascript
 
@@ -159,6 +178,26 @@ or inside your own custom components using
 </script>
 ```
 
+### Loading the data of a component from javascript
+
+The rows displayed by a component can also be loaded as JSON, by adding `_sqlpage_data` to the URL of the page,
+with the `@component_index` of the component as its value.
+SQLPage then runs the same SQL file, and returns a JSON array containing only the data rows of this component,
+without its top-level properties.
+This lets your scripts reuse the queries of the page, for instance to draw a custom visualization:
+
+```handlebars
+<div id="chart-{{@component_index}}"></div>
+<script nonce="{{@csp_nonce}}">
+  fetch(location.pathname + "?_sqlpage_data={{@component_index}}&" + location.search.slice(1))
+    .then(response => response.json())
+    .then(rows => drawChart(document.getElementById("chart-{{@component_index}}"), rows));
+</script>
+```
+
+The first component after the shell has index 2, the next one 3, and so on.
+Since the whole SQL file is executed again, statements that modify the database are executed again too.
+
 ## Overwriting the default components
 
 You can overwrite the default components, including the `shell` component,
@@ -36,6 +36,20 @@ Then, you can set its row-level parameters by writing a second SELECT statement:
 SELECT my_column_1 AS row_level_parameter_1, my_column_2 AS row_level_parameter_2 FROM my_table;
 ```
 
+### Displaying NULL values
+
+Every component accepts a `null_display` top-level parameter, that controls what is displayed
+when a row-level parameter is `NULL`, without having to wrap every column in `COALESCE`:
+
+ - a text, like `''N/A''`, replaces all the NULL values of the rows,
+ - a JSON object, like `{"email": "unknown", "phone": "-"}`, sets a different replacement for each column,
+ - the JSON value `false` for a column hides the rows where this column is NULL.
+
+```sql
+SELECT ''list'' AS component, json_object(''description'', ''No description'', ''title'', json(''false'')) AS null_display;
+SELECT name AS title, description FROM products;
+```
+
 This page documents all the components provided by default in SQLPage and their parameters.
 Use this as a reference when building your SQL application.
 If at any point you need help, you can ask for it on the [SQLPage forum](https://github.com/sqlpage/SQLPage/discussions).
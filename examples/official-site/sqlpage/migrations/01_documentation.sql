@@ -276,7 +276,7 @@ INSERT INTO parameter(component, name, description, type, top_level, optional) S
     ('id', 'A unique identifier for the form, which can then be used to validate the form from a button outside of the form.', 'TEXT', TRUE, TRUE),
     ('auto_submit', 'Automatically submit the form when the user changes any of its fields, and remove the validation button.', 'BOOLEAN', TRUE, TRUE),
     -- item level
-    ('type', 'The type of input to use: text for a simple text field, textarea for a multi-line text input control, number to accept only numbers, checkbox, switch, or radio for a button that is part of a group specified in the ''name'' parameter, header for a form header, hidden for a value that will be submitted but not shown to the user. text by default.', 'TEXT', FALSE, TRUE),
+    ('type', 'The type of input to use: text for a simple text field, textarea for a multi-line text input control, number to accept only numbers, checkbox, switch, or radio for a button that is part of a group specified in the ''name'' parameter, header for a form header, hidden for a value that will be submitted but not shown to the user, week, month or date_range to select a period. text by default.', 'TEXT', FALSE, TRUE),
     ('name', 'The name of the input field, that you can use in the target page to get the value the user entered for the field.', 'TEXT', FALSE, FALSE),
     ('label', 'A friendly name for the text field to show to the user.', 'TEXT', FALSE, TRUE),
     ('placeholder', 'A placeholder text that will be shown in the field when is is empty.', 'TEXT', FALSE, TRUE),
@@ -296,6 +296,7 @@ INSERT INTO parameter(component, name, description, type, top_level, optional) S
     ('description_md', 'A helper text to display near the input field - formatted using markdown.', 'TEXT', FALSE, TRUE),
     ('pattern', 'A regular expression that the value must match. For instance, [0-9]{3} will only accept 3 digits.', 'TEXT', FALSE, TRUE),
     ('autofocus', 'Automatically focus the field when the page is loaded', 'BOOLEAN', FALSE, TRUE),
+    ('week_start', 'For week fields, the first day of the week, like "monday" or "sunday". Defaults to the first day of the week in the language of the page, set with the language property of the shell.', 'TEXT', FALSE, TRUE),
     ('width', 'Width of the form field, between 1 and 12.', 'INTEGER', FALSE, TRUE),
     ('autocomplete', 'Whether the browser should suggest previously entered values for this field.', 'BOOLEAN', FALSE, TRUE),
     ('minlength', 'Minimum length of text allowed in the field.', 'INTEGER', FALSE, TRUE),
@@ -365,6 +366,24 @@ When loading the page, the value for `:username` will be `NULL` if no value has
     {"type": "switch", "label": "A disabled toggle switch", "name": "my_field", "disabled": true}
     ]')),
 
+ ('form','Fields of type `week`, `month` and `date_range` let users select a period.
+In addition to the value of the field, the form submits the first day of the period as `name_start`,
+and the day after its last day as `name_end`, both as ISO dates like `2024-03-01`, so that the target page
+can filter rows without having to compute the boundaries of the period itself:
+
+```sql
+select * from orders where ordered_at >= :month_start and ordered_at < :month_end;
+```
+
+This works for both date and timestamp columns, including the orders made on the last day of the period.
+Weeks start on the first day of the week in the language of the page, unless `week_start` is set.
+The default value of a `date_range` is written as its first and last days, separated by a slash: `2024-03-01/2024-03-15`.',
+    json('[{"component":"form", "method": "GET"},
+    {"type": "week", "label": "Week", "name": "week", "value": "2024-W10", "week_start": "monday", "width": 4},
+    {"type": "month", "label": "Month", "name": "month", "value": "2024-03", "width": 4},
+    {"type": "date_range", "label": "Period", "name": "period", "value": "2024-03-01/2024-03-15", "width": 4}
+    ]')),
+
     ('form', 'This example illustrates the use of the `select` type.
 In this select input, the various options are hardcoded, but they could also be loaded from a database table,
 [using a function to convert the rows into a json array](/blog.sql?post=JSON%20in%20SQL%3A%20A%20Comprehensive%20Guide) like 
@@ -609,6 +628,53 @@ copy product(name, description) from ''product_data_input''
 with (header true, delimiter '','', quote ''"'');
 ```
 
+The following options of the `copy` statement describe the format of the CSV file:
+
+- `delimiter '';''`: the character that separates the values (a comma by default),
+- `quote ''"''`: the character around values that contain delimiters or line breaks,
+- `escape ''\''`: the character that escapes quotes inside quoted values,
+- `null ''NA''`: the text that represents a `NULL` value (an empty value by default),
+- `header false`: the file has no header line, and its columns are in the same order as in the `copy` statement.
+
+Exported files sometimes start with a few lines of description before the header,
+or name their columns differently from your table.
+Options that do not exist in the `copy` syntax can be given after a `#` at the end of the file name,
+separated by `&`:
+
+- `skip=N` ignores the first N lines of the file,
+- `column=Header` reads the values of a column of the table from the CSV column named `Header`,
+- `max_errors=N` skips the rows that cannot be imported, instead of stopping the whole import at the first invalid row,
+- `commit_every=N` commits the imported rows every N rows.
+
+```sql
+copy contacts(name, email) from ''contacts_file#skip=2&name=Full Name&email=E-mail address''
+with (delimiter '';'');
+```
+
+By default, a single malformed row, or a row rejected by the database (because of a constraint, or a value of the wrong type),
+makes the whole `copy` statement fail. With `max_errors=N`, these rows are skipped and the other rows are imported,
+as long as there are at most N invalid rows. The `copy` statement then returns one row for each skipped line,
+with its `line` number in the file and the `error` message, that you can display with any component:
+
+```sql
+select ''table'' as component, ''Rows that could not be imported'' as description;
+copy contacts(name, email) from ''contacts_file#max_errors=100'';
+```
+
+By default, PostgreSQL imports a whole CSV file in a single transaction, and the other databases save each row separately.
+With `commit_every=N`, the rows are inserted in transactions of N rows instead: huge files do not hold one giant transaction,
+and they are imported much faster than row by row.
+When the import fails, only the rows inserted since the last commit are cancelled,
+and the error message tells how many rows were committed before the failure.
+The `copy` statement returns one row after each commit, with the `line` of the file
+and the total number of `committed_rows`, to show the progress of the import.
+Do not use `commit_every` inside a transaction started with `begin`, since the import commits it.
+
+```sql
+select ''table'' as component, ''Import progress'' as description;
+copy measurements(sensor, measured_at, value) from ''measurements_file#commit_every=10000&max_errors=100'';
+```
+
 If you want to pre-process the data before inserting it into the final table,
 you can use a temporary table to store the data, and then insert it into the final table:
 
@@ -635,6 +701,88 @@ name,description,price
 "SQLite","A lightweight relational database",0
 "MySQL","A popular open-source relational database",0
 ```
+
+### Importing spreadsheets
+
+Files created with Excel, LibreOffice or Google Sheets can be imported directly with the `format xlsx` option.
+It reads `.xlsx`, `.xls`, `.xlsb` and `.ods` files.
+By default, the first sheet is imported. To import another sheet, add its name after a `#` in the name of the form field:
+
+```sql
+copy product(name, description, price) from ''product_data_input#Products'' with (format xlsx);
+```
+
+The header row is the first row that contains the names of all the columns to import,
+so titles and empty rows above the table are ignored.
+With `header false`, the columns of the sheet are used in order, starting from the first row.
+Dates are imported as text in the `YYYY-MM-DD` format (`YYYY-MM-DD HH:MM:SS` when they have a time),
+that all databases can convert to dates.
+
+### Importing Parquet files
+
+[Parquet](https://parquet.apache.org/) files exported by data pipelines can be imported with the `format parquet` option.
+The columns of the file are matched with the columns of the table by name.
+If you do not list the columns in the `copy` statement, all the columns of the file are imported.
+
+The file can be uploaded, or read from the disk. Prefix the name with `file:` to read a file
+with this path, relative to the web root. Only the files inside the web root can be imported:
+
+```sql
+copy sales from ''file:exports/sales_2024.parquet'' with (format parquet);
+```
+
+Parquet values are converted to text that all databases can convert to the type of the column:
+dates become `YYYY-MM-DD`, timestamps `YYYY-MM-DD HH:MM:SS` in UTC, and lists, maps and structures become JSON.
+The file is read while its rows are inserted, in batches of several rows per `INSERT` statement,
+so large files can be imported without loading them in memory.
+
+### Importing JSON
+
+The same statement can import a JSON array of objects, with the `format json` option.
+Each object is inserted as a row, and its keys are matched with the column names.
+Missing keys and `null` values are inserted as `NULL`, and numbers, booleans, arrays and objects are inserted as text.
+
+Use the special name `''request_body''` instead of a form field name to import the data sent directly in the body of the request,
+for instance by another application calling your page as an API with a `Content-Type: application/json` header:
+
+```sql
+copy product(name, description, price) from ''request_body'' (format json);
+```
+
+```json
+[
+  {"name": "SQLPage", "description": "A tool to create websites using SQL", "price": 0},
+  {"name": "SQLite", "description": "A lightweight relational database", "price": 0}
+]
+```
+
+Large JSON exports and log files are often in the [newline-delimited JSON](https://github.com/ndjson/ndjson-spec) format,
+with one object per line. Import them with the `format ndjson` option (or `format jsonl`).
+The lines are read and inserted one by one, so files of several gigabytes can be imported without loading them in memory.
+Empty lines are ignored.
+
+```sql
+copy access_log(time, status, path) from ''log_file'' (format ndjson);
+```
+
+### Importing data from a URL
+
+A `copy` statement can also download the data to import from a URL.
+This lets a page, called every night by a scheduler, load the export of a partner directly into a staging table.
+For security, only the URLs listed in the [`import_url_allowlist`](https://github.com/sqlpage/SQLPage/blob/main/configuration.md#configuring-sqlpage) configuration option can be used:
+
+```json
+{ "import_url_allowlist": ["https://partner.example.com/exports/"] }
+```
+
+```sql
+delete from staging_prices;
+copy staging_prices(product, price) from ''https://partner.example.com/exports/prices.csv'';
+```
+
+All the formats and options described above can be used.
+The downloaded data is limited to `max_uploaded_file_size` bytes.
+Redirects are not followed: if the server answers with a redirect, use the URL it redirects to instead.
 ',
     json('[{"component":"form", "title": "CSV import", "validate": "Load data", "action": "examples/handle_csv_upload.sql"}, 
     {"name": "product_data_input", "type": "file", "accept": "text/csv",  "label": "Products", "description": "Upload a CSV with a name, description, and price columns", "required": true}
@@ -277,7 +277,19 @@ VALUES (
 SELECT ''text'' AS component;
 SELECT ''The value of the HOME environment variable is '' AS contents;
 SELECT sqlpage.environment_variable(''HOME'') as contents, true as code;
-```'
+```
+
+### Restricting access to environment variables
+
+Environment variables are a good place to store secrets like API keys, instead of hard-coding them in SQL files committed to git.
+If you want to make sure SQL files can only read the variables meant for them, list them in the
+`allowed_environment_variables` [configuration option](https://github.com/sqlpage/SQLPage/blob/main/configuration.md):
+
+```json
+{ "allowed_environment_variables": ["DEPLOYMENT_NAME", "MYAPP_*"] }
+```
+
+Reading any other environment variable then results in an error.'
     );
 INSERT INTO sqlpage_function_parameters (
         "function",
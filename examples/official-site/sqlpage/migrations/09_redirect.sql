@@ -53,6 +53,22 @@ VALUES (
         'TEXT',
         TRUE,
         FALSE
+    ),
+    (
+        'redirect',
+        'status',
+        'The HTTP status code of the redirection: 301 (moved permanently), 302 (found), 303 (see other), 307 (temporary redirect) or 308 (permanent redirect). Defaults to 302.',
+        'INTEGER',
+        TRUE,
+        TRUE
+    ),
+    (
+        'redirect',
+        'message',
+        'A message displayed once at the top of the page the user is redirected to, like "Your comment was published."',
+        'TEXT',
+        TRUE,
+        TRUE
     );
 -- Insert an example usage of the http_header component into the example table
 INSERT INTO example (component, description)
@@ -66,4 +82,22 @@ SELECT ''redirect'' AS component, ''login.sql'' AS link
 WHERE NOT EXISTS (SELECT 1 FROM login_session WHERE id = sqlpage.cookie(''session_id''));
 ```
 '
-    );
\ No newline at end of file
+    ),
+    (
+        'redirect',
+        '
+After a form is submitted, redirect the user with a *303 See Other* status.
+The browser then loads the target page with a `GET` request,
+so reloading it does not submit the form a second time (the [POST/Redirect/GET](https://en.wikipedia.org/wiki/Post/Redirect/Get) pattern):
+
+```sql
+INSERT INTO comments (text) VALUES (:text);
+SELECT ''redirect'' AS component, ''comments.sql'' AS link, 303 AS status, ''Your comment was published.'' AS message;
+```
+
+To apply this pattern to all the pages of a website without writing redirects,
+set `post_redirect_get` to `true` in the [configuration](https://github.com/sqlpage/SQLPage/blob/main/configuration.md).
+Pages that handle a POST request without errors then redirect the user back to the page of the form,
+and `post_redirect_message` sets the message displayed there.
+'
+    );
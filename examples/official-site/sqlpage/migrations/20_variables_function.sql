@@ -9,7 +9,8 @@ VALUES (
     'variables',
     '0.15.0',
     'variable',
-    'Returns a JSON string containing all variables passed as URL parameters or posted through a form.
+    'Returns a JSON string containing all variables passed as URL parameters or posted through a form,
+and the variables defined in the page with [`SET`](/extensions-to-sql.md#the-set-command).
 
 The database''s json handling functions can then be used to process the data.
 
@@ -95,6 +96,6 @@ VALUES (
     'variables',
     1,
     'method',
-    'Optional. The HTTP request method (GET or POST). Must be a literal string. When not provided, all variables are returned.',
+    'Optional. The kind of variables to return: `get` for URL parameters, `post` for form fields, or `set` for the variables defined with `SET` in the page. When not provided, all variables are returned.',
     'TEXT'
 );
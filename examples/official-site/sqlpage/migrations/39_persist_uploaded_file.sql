@@ -62,4 +62,11 @@ VALUES (
         'Optional. Comma-separated list of allowed file extensions. By default: jpg,jpeg,png,gif,bmp,webp,pdf,txt,doc,docx,xls,xlsx,csv,mp3,mp4,wav,avi,mov.
 Changing this may be dangerous ! If you add "sql", "svg" or "html" to the list, an attacker could execute arbitrary SQL queries on your database, or impersonate other users.',
         'TEXT'
+    ),
+    (
+        'persist_uploaded_file',
+        4,
+        'max_size',
+        'Optional. Maximum size of the file, in bytes. Larger files are not saved, and the function returns an error. By default, the size is only limited by the `max_uploaded_file_size` [configuration option](https://github.com/sqlpage/SQLPage/blob/main/configuration.md), which applies to the whole request.',
+        'INTEGER'
     );
@@ -73,6 +73,11 @@ invalid characters are replaced with the Unicode replacement character `�` (U+
 
 If you need to handle binary data,
 use [`sqlpage.request_body_base64()`](?function=request_body_base64) instead.
+
+### Size limit
+
+Request bodies larger than twice the `max_uploaded_file_size` [configuration option](https://github.com/sqlpage/SQLPage/blob/main/configuration.md)
+are rejected with a `413 Payload Too Large` error, before the page is executed.
 '
     );
 
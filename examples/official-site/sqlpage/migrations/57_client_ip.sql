@@ -24,23 +24,21 @@ The function returns:
 - The IP address of the client as a string
 - `null` if the client IP cannot be determined (e.g., when serving through a Unix socket)
 
-### ⚠️ Important Notes for Production Use
+### Running behind a reverse proxy
 
-When [running behind a reverse proxy](/your-first-sql-website/nginx.sql) (e.g., Nginx, Apache, Cloudflare):
-- This function will return the IP address of the reverse proxy, not the actual client
-- To get the real client IP, use [`sqlpage.header`](?function=header): `sqlpage.header(''x-forwarded-for'')` or `sqlpage.header(''x-real-ip'')`
-  - The exact header name depends on your reverse proxy configuration
+When [running behind a reverse proxy](/your-first-sql-website/nginx.sql) (e.g., Nginx, Apache, Cloudflare),
+the connection to SQLPage is made by the proxy, not by the client.
+List the addresses of your proxies in the `trusted_proxies` [configuration option](/configuration.md):
 
-Example with reverse proxy:
-```sql
--- Choose the appropriate header based on your setup
-select coalesce(
-    sqlpage.header(''x-forwarded-for''),
-    sqlpage.header(''x-real-ip''),
-    sqlpage.client_ip()
-) as real_client_ip;
+```json
+{ "trusted_proxies": ["127.0.0.1", "10.0.0.0/8"] }
 ```
 
-For security-critical applications, ensure your reverse proxy is properly configured to set and validate these headers.
+When a request comes from a trusted proxy, `sqlpage.client_ip()` returns the client address
+found in the standard `Forwarded` header, or in the `X-Forwarded-For` header.
+These headers are read from right to left, skipping the addresses of trusted proxies,
+so that a client cannot fake its address by sending its own `X-Forwarded-For` header.
+
+Forwarding headers are ignored for requests that do not come from a trusted proxy.
 '
         );
\ No newline at end of file
@@ -0,0 +1,92 @@
+INSERT INTO
+    sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES
+    (
+        'validate',
+        '0.36.0',
+        'checklist',
+        'Checks the values submitted in a form against a set of validation rules,
+before they are inserted into the database.
+
+Returns `NULL` if all the fields are valid,
+and a JSON object associating the name of each invalid field to an error message otherwise.
+The result can be passed directly to the `errors` property of the [form component](documentation.sql?component=form#component),
+which displays the error messages next to the corresponding fields.
+
+### Example
+
+```sql
+set errors = sqlpage.validate(''{
+    "username": {"required": true, "min_length": 3, "max_length": 20},
+    "age": {"min": 18, "message": "You must be an adult to register"},
+    "country": {"one_of": ["France", "Germany", "Italy"]}
+}'');
+
+insert into users (username, age, country)
+select :username, :age, :country
+where $errors is null;
+
+select ''redirect'' as component, ''welcome.sql'' as link where $errors is null;
+
+select ''form'' as component, $errors as errors;
+select ''username'' as name, :username as value;
+select ''age'' as name, ''number'' as type, :age as value;
+select ''country'' as name, :country as value;
+```
+
+### Rules
+
+Each field can have the following rules:
+- `required`: the field must be present and non-empty. Empty fields that are not required are not checked against the other rules.
+- `min_length` and `max_length`: the number of characters of the value.
+- `min` and `max`: the value must be a number in the given range.
+- `one_of`: an array of the accepted values.
+- `format`: one of `email`, `phone` (in international format) or `url`. See [`sqlpage.normalize_email`](?function=normalize_email), [`sqlpage.normalize_phone`](?function=normalize_phone) and [`sqlpage.normalize_url`](?function=normalize_url).
+- `check`: the result of a SQL expression. The field is invalid if the expression is false.
+- `message`: an error message to display instead of the default one when any of the rules fails.
+
+Values are taken from the form fields (POST variables), or from the URL parameters if there is no form field with the given name.
+
+### Cross-field checks
+
+Rules that involve several fields are written as SQL expressions, evaluated by your database.
+For instance, in SQLite:
+
+```sql
+set errors = sqlpage.validate(json_object(
+    ''start_date'', json_object(''required'', true),
+    ''end_date'', json_object(
+        ''check'', :end_date >= :start_date,
+        ''message'', ''The end date must be after the start date''
+    )
+));
+```
+'
+    );
+
+INSERT INTO
+    sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES
+    (
+        'validate',
+        1,
+        'rules',
+        'A JSON object associating field names to their validation rules. See the list of available rules above.',
+        'JSON'
+    );
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'form', * FROM (VALUES
+    ('errors', 'A JSON object associating field names to error messages, as returned by sqlpage.validate. Invalid fields are highlighted and the error message is displayed below them.', 'JSON', TRUE, TRUE),
+    ('error', 'An error message to display below the field, which is then highlighted as invalid.', 'TEXT', FALSE, TRUE)
+);
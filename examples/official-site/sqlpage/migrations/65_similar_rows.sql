@@ -0,0 +1,91 @@
+INSERT INTO
+    sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES
+    (
+        'similar_rows',
+        '0.36.0',
+        'copy',
+        'Finds the rows of a table that look like a duplicate of the values entered in a form.
+This lets data entry forms warn the user that a record *may already exist* before inserting it,
+even when it was typed slightly differently.
+
+Returns a JSON array of the matching rows, sorted from the most similar to the least similar,
+or `NULL` if there is no similar row.
+Each row contains the compared columns, the key column if one is given, and an additional `_similarity` column,
+between 0 (nothing in common) and 1 (the same words).
+At most 10 rows are returned.
+
+### Example
+
+```sql
+set duplicates = sqlpage.similar_rows(''customers'', json_object(''name'', :name, ''email'', :email), 0.5, ''id'');
+
+select ''alert'' as component,
+    ''This customer may already exist'' as title,
+    ''warning'' as color
+where $duplicates is not null;
+
+select ''list'' as component where $duplicates is not null;
+select value->>''name'' as title, value->>''email'' as description,
+    ''customer.sql?id='' || (value->>''id'') as link
+from json_each($duplicates);
+```
+
+### Details
+
+Values are compared using [trigram similarity](https://www.postgresql.org/docs/current/pgtrgm.html):
+two strings are similar when they share many three-letter sequences, ignoring case and punctuation.
+The similarity is computed by SQLPage, so it is the same on all databases.
+When several columns are given, the similarity of a row is the average similarity of its columns.
+Empty and `NULL` values are ignored.
+
+The table is not read entirely: the database first selects at most 1000 candidate rows.
+On PostgreSQL with the [`pg_trgm`](https://www.postgresql.org/docs/current/pgtrgm.html) extension installed,
+the candidates are the rows whose `similarity` is above the minimum, and an index on the compared columns makes the search fast.
+On other databases, the candidates are the rows that contain the first two letters of one of the words of the values,
+selected with `LIKE`.
+'
+    );
+
+INSERT INTO
+    sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES
+    (
+        'similar_rows',
+        1,
+        'table',
+        'The name of the table (or view) in which to look for similar rows.',
+        'TEXT'
+    ),
+    (
+        'similar_rows',
+        2,
+        'values',
+        'A JSON object associating column names to the values to compare them with.',
+        'JSON'
+    ),
+    (
+        'similar_rows',
+        3,
+        'min_similarity',
+        'Optional. The minimum similarity, between 0 and 1, for a row to be returned. Defaults to 0.5.',
+        'REAL'
+    ),
+    (
+        'similar_rows',
+        4,
+        'key',
+        'Optional. The name of a column, like the primary key, that is returned with the compared columns to identify the similar rows.',
+        'TEXT'
+    );
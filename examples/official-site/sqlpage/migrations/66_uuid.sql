@@ -0,0 +1,30 @@
+INSERT INTO
+    sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES
+    (
+        'uuid',
+        '0.36.0',
+        'fingerprint',
+        'Returns a random [UUID](https://en.wikipedia.org/wiki/Universally_unique_identifier) (version 4),
+generated using a cryptographically secure random number generator.
+
+UUIDs are 36-character strings like `9c1d2a8e-4f3b-4c6e-9a7d-2b5e8f1c3d4a`,
+that can be used as primary keys, invitation codes, or public identifiers that cannot be guessed.
+This function works the same way on all databases, even those that do not have a builtin UUID generator.
+
+### Example
+
+```sql
+insert into invitation (code, email) values (sqlpage.uuid(), :email)
+returning ''text'' as component,
+    ''Invitation link: '' || sqlpage.link(''accept.sql'', json_object(''code'', code)) as contents;
+```
+
+To generate random tokens of a specific length, use [`sqlpage.random_string`](?function=random_string).
+'
+    );
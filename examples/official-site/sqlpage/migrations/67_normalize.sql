@@ -0,0 +1,111 @@
+INSERT INTO
+    sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES
+    (
+        'normalize_email',
+        '0.36.0',
+        'mail-check',
+        'Checks that a string is a syntactically valid email address, and returns it in a canonical form.
+
+Returns `NULL` if the address is not valid.
+Surrounding spaces are removed, and the domain name is converted to lowercase.
+The part before the `@` is kept as is, since some mail servers treat it as case-sensitive.
+
+This only checks the syntax of the address: it does not check that the domain exists or accepts emails.
+
+### Example
+
+```sql
+set email = sqlpage.normalize_email(:email);
+
+insert into subscriber (email) select $email where $email is not null;
+
+select ''alert'' as component, ''Invalid email address'' as title, ''red'' as color
+where $email is null;
+```
+'
+    ),
+    (
+        'normalize_phone',
+        '0.36.0',
+        'phone-check',
+        'Converts a phone number to the international [E.164](https://en.wikipedia.org/wiki/E.164) format, like `+14155552671`.
+
+Returns `NULL` if the input is not a valid phone number.
+Spaces, dashes, dots and parentheses are removed.
+Numbers starting with `+` or `00` are international numbers.
+Other numbers are national numbers: they are converted using the given default country calling code, after removing their leading `0`.
+
+This checks the general structure of the number (between 8 and 15 digits including the country code),
+not the numbering plan of each country.
+
+### Example
+
+```sql
+-- For a French website: 06 12 34 56 78 becomes +33612345678
+insert into contact (name, phone) values (:name, sqlpage.normalize_phone(:phone, ''33''));
+```
+'
+    ),
+    (
+        'normalize_url',
+        '0.36.0',
+        'link',
+        'Checks that a string is a valid `http` or `https` URL, and returns it in a canonical form.
+
+Returns `NULL` if the input is not a valid web URL.
+URLs entered without a scheme, like `example.com/page`, are considered `https` URLs.
+The scheme and domain name are converted to lowercase, and special characters are percent-encoded.
+URLs with other schemes, such as `javascript:`, are rejected, which makes this function useful
+to validate links entered by users before displaying them.
+
+### Example
+
+```sql
+update profile set website = sqlpage.normalize_url(:website) where id = $id;
+```
+'
+    );
+
+INSERT INTO
+    sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES
+    (
+        'normalize_email',
+        1,
+        'email',
+        'The email address to validate.',
+        'TEXT'
+    ),
+    (
+        'normalize_phone',
+        1,
+        'phone',
+        'The phone number to normalize.',
+        'TEXT'
+    ),
+    (
+        'normalize_phone',
+        2,
+        'default_country_code',
+        'Optional. The country calling code of national numbers, without the `+`. For instance `1` for the USA or `33` for France. If omitted, only international numbers are accepted.',
+        'TEXT'
+    ),
+    (
+        'normalize_url',
+        1,
+        'url',
+        'The URL to validate.',
+        'TEXT'
+    );
@@ -0,0 +1,39 @@
+INSERT INTO parameter(component, name, description_md, type, top_level, optional) SELECT 'form', * FROM (VALUES
+    ('spam_protection', 'Protect a public form against spam robots.
+When set to true, the form contains an invisible *honeypot* field that only robots fill in,
+and a signed token that SQLPage checks when the form is submitted:
+submissions that are sent too quickly after the form was displayed (see the `form_min_fill_seconds` [configuration option](https://github.com/sqlpage/SQLPage/blob/main/configuration.md)),
+whose browser did not solve the proof-of-work challenge (see `form_proof_of_work_difficulty`),
+or that reuse the token of a previous submission, are rejected with an HTTP 403 error before your SQL code runs.
+
+Each token is only valid for the page the form is submitted to (its `action`).
+Once a protected form has been displayed, submissions to its target page without a token are rejected too.
+Tokens are signed with the `encryption_key` configuration option, that must be set to use this property.
+
+The proof-of-work challenge requires a secure context: the website must be served over HTTPS (or on localhost).', 'BOOLEAN', TRUE, TRUE),
+    ('captcha', 'Display a captcha that the user must solve before submitting the form, and verify it on the server.
+Requires the `captcha_provider` (`turnstile` or `hcaptcha`), `captcha_site_key` and `captcha_secret_key` [configuration options](https://github.com/sqlpage/SQLPage/blob/main/configuration.md).
+Implies `spam_protection`.', 'BOOLEAN', TRUE, TRUE)
+);
+
+INSERT INTO example(component, description, properties) VALUES
+    ('form', '### Spam protection
+
+Public forms, like contact forms, are often targeted by spam robots.
+With `spam_protection`, SQLPage rejects submissions from most robots before your `INSERT` statement runs,
+without annoying your users.
+
+```sql
+select ''form'' as component, true as spam_protection, ''contact.sql'' as action;
+select ''email'' as name, ''email'' as type;
+select ''message'' as name, ''textarea'' as type;
+```
+
+Add the target page to `spam_protected_paths` in [`sqlpage.json`](https://github.com/sqlpage/SQLPage/blob/main/configuration.md),
+like `"spam_protected_paths": ["/contact.sql"]`, so that it rejects submissions without a token from the moment the server starts,
+and not only once the form has been displayed.
+
+For stronger protection, use `true as captcha` to display a [Cloudflare Turnstile](https://www.cloudflare.com/products/turnstile/)
+or [hCaptcha](https://www.hcaptcha.com/) challenge.
+',
+    json('[{"component":"form", "spam_protection": true}, {"name": "email", "type": "email"}, {"name": "message", "type": "textarea"}]'));
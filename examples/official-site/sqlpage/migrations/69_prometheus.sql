@@ -0,0 +1,52 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('prometheus', 'chart-dots-3', 'Exposes metrics computed in SQL in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/),
+so that business metrics defined in your database (number of orders, active users, queue lengths...) can be collected by
+[Prometheus](https://prometheus.io/) and other compatible monitoring tools, and displayed in dashboards or used in alerts.
+
+This is a header component: it must be the first component of the page, and the page must not use a shell.
+Each row is a sample of a metric.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'prometheus', * FROM (VALUES
+    ('name', 'The name of the metric. It can contain letters, digits, underscores and colons, and must not start with a digit.', 'TEXT', FALSE, FALSE),
+    ('value', 'The numeric value of the sample.', 'REAL', FALSE, FALSE),
+    ('labels', 'A JSON object associating label names to label values, used to distinguish the samples of a metric.', 'JSON', FALSE, TRUE),
+    ('help', 'A description of the metric. Only the first help text of each metric is used.', 'TEXT', FALSE, TRUE),
+    ('type', 'The type of the metric: counter, gauge, histogram, summary, or untyped.', 'TEXT', FALSE, TRUE),
+    ('timestamp', 'The time of the sample, in milliseconds since the Unix epoch. Omit it to let Prometheus use the collection time.', 'INTEGER', FALSE, TRUE)
+) x;
+
+INSERT INTO example(component, description, properties) VALUES
+    ('prometheus', '
+### Exposing business metrics
+
+Create a `metrics.sql` file:
+
+```sql
+select ''authentication'' as component,
+    ''$argon2id$v=19$m=19456,t=2,p=1$...'' as password_hash,
+    sqlpage.basic_auth_password() as password;
+
+select ''prometheus'' as component;
+
+select
+    ''orders_total'' as name,
+    ''Number of orders, by status'' as help,
+    ''counter'' as type,
+    count(*) as value,
+    json_object(''status'', status) as labels
+from orders
+group by status;
+```
+
+and add it to the `scrape_configs` of Prometheus, with the same credentials in its `basic_auth` section.
+The page returns:
+
+```
+# HELP orders_total Number of orders, by status
+# TYPE orders_total counter
+orders_total{status="paid"} 1234
+orders_total{status="cancelled"} 56
+```
+
+The [authentication component](?component=authentication) protects the metrics with HTTP basic authentication.
+', NULL);
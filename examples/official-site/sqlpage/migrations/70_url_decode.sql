@@ -0,0 +1,51 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'url_decode',
+        '0.36.0',
+        'percentage',
+        'Decodes a string that was encoded for use in a URL, such as a value from a query string.
+This is the reverse of [`sqlpage.url_encode`](?function=url_encode).
+
+Percent-encoded characters like `%20` or `%C3%A9` are decoded, and `+` is decoded as a space,
+as in the URLs generated by HTML forms.
+
+### Example
+
+```sql
+select ''text'' as component;
+select sqlpage.url_decode(''https%3A%2F%2Fexample.com%2F%3Fq%3Dhello+world'') as contents;
+```
+
+#### Result
+
+`https://example.com/?q=hello world`
+
+Values of URL parameters accessed with `$parameter` are already decoded by SQLPage:
+`sqlpage.url_decode` is useful for encoded strings stored in the database, received from [`sqlpage.fetch`](?function=fetch),
+or embedded inside other parameters.
+
+### Details
+
+- If the argument is `NULL`, the function returns `NULL`.
+- If the decoded bytes are not valid UTF-8 text, the function returns an error.
+'
+    );
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'url_decode',
+        1,
+        'string',
+        'The percent-encoded string to decode.',
+        'TEXT'
+    );
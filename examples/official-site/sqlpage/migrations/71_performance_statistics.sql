@@ -0,0 +1,64 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'performance_statistics',
+        '0.36.0',
+        'gauge',
+        'Returns performance statistics collected by SQLPage since it started, as a JSON array of rows.
+Use it to find slow queries, see which pages are used the most, and check whether your database connection pool is large enough.
+
+The statistics are kept in memory, and reset when SQLPage restarts.
+
+### Built-in performance page
+
+In development mode, SQLPage serves a built-in performance dashboard at `/_sqlpage/performance.sql`.
+It is not available in production, where statistics could reveal information about your application.
+
+### Building your own dashboard
+
+The array returned by the function can be passed directly to the [dynamic](?component=dynamic) component,
+to display it in a table.
+Make sure to restrict access to this page, for instance using [`sqlpage.basic_auth_username`](?function=basic_auth_username).
+
+```sql
+select ''table'' as component, true as sort;
+select ''dynamic'' as component, sqlpage.performance_statistics(''statements'') as properties;
+```
+
+You can also save snapshots of the statistics in a table to follow their evolution over time.
+For instance, on PostgreSQL:
+
+```sql
+insert into page_statistics (taken_at, page, executions, average_ms)
+select now(), s->>''page'', (s->>''executions'')::int, (s->>''average_ms'')::float
+from json_array_elements(sqlpage.performance_statistics(''pages'')::json) as s;
+```
+
+### Sections
+
+- `statements`: the 50 statements that took the most time in total, with the file and line where they are defined, their number of `executions` and `errors`, and their `total_ms`, `average_ms` and `max_ms` durations in milliseconds.
+- `pages`: the 50 most requested pages, with the same counters.
+- `caches`: the number of `hits` and `misses` of the caches of parsed SQL files and templates, and their `hit_rate`.
+- `database`: the current number of open `connections` in the pool, and how many are idle or busy.
+
+Durations include the time spent sending results to the browser, because SQLPage streams rows while queries are running.
+'
+    );
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'performance_statistics',
+        1,
+        'section',
+        'The statistics to return: `statements`, `pages`, `caches` or `database`.',
+        'TEXT'
+    );
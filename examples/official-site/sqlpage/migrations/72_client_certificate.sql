@@ -0,0 +1,56 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'client_certificate_fingerprint',
+        '0.36.0',
+        'certificate',
+        'Returns the SHA-256 fingerprint of the TLS certificate the client authenticated with, in lowercase hexadecimal.
+Returns NULL if the client did not present a certificate.
+
+Client certificates let machines in a corporate network authenticate to API pages without passwords, with *mutual TLS*.
+To enable them, [configure SQLPage](https://github.com/sqlpage/SQLPage/blob/main/configuration.md) to serve HTTPS with `https_domain`,
+and set `https_client_ca_certificate` to a PEM file containing the certificate authorities that sign the client certificates.
+SQLPage then asks clients for a certificate during the TLS handshake, and rejects the connection if the certificate is not signed by one of these authorities.
+
+Clients without a certificate can still connect, so every page reserved to authenticated machines must check the fingerprint.
+
+### Example
+
+```sql
+select ''status_code'' as component, 403 as status
+where not exists (
+    select 1 from machines where certificate_fingerprint = sqlpage.client_certificate_fingerprint()
+);
+
+select ''json'' as component;
+select * from deployments;
+```
+
+When SQLPage runs behind a reverse proxy that terminates TLS, the certificate is not visible to SQLPage.
+In this case, configure the proxy to verify client certificates and to forward the fingerprint in a header,
+and read it with [`sqlpage.header`](?function=header).
+'
+    ),
+    (
+        'client_certificate_subject',
+        '0.36.0',
+        'certificate',
+        'Returns the distinguished name of the subject of the TLS certificate the client authenticated with,
+like `CN=build-server, O=Example Corp`.
+Returns NULL if the client did not present a certificate.
+
+Client certificates must be enabled in the configuration, as explained in [`sqlpage.client_certificate_fingerprint`](?function=client_certificate_fingerprint).
+The subject is chosen by the authority that signed the certificate. Use the fingerprint to identify a specific certificate.
+
+### Example
+
+```sql
+select ''text'' as component;
+select ''Welcome, '' || sqlpage.client_certificate_subject() as contents;
+```
+'
+    );
@@ -0,0 +1,52 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'uploaded_file_size',
+        '0.36.0',
+        'file-analytics',
+        'Returns the size of an uploaded file, in bytes.
+Returns NULL if no file was uploaded with the given name.
+
+### Example: rejecting large files
+
+```sql
+select ''redirect'' as component, ''file_too_large.sql'' as link
+where sqlpage.uploaded_file_size(''myfile'') > 1000000;
+```
+
+### Example: recording uploads
+
+Together with [`sqlpage.uploaded_file_name`](?function=uploaded_file_name#function)
+and [`sqlpage.uploaded_file_mime_type`](?function=uploaded_file_mime_type#function),
+this function makes it possible to keep track of the uploaded files:
+
+```sql
+insert into documents (file_name, mime_type, size, path)
+values (
+    sqlpage.uploaded_file_name(''myfile''),
+    sqlpage.uploaded_file_mime_type(''myfile''),
+    sqlpage.uploaded_file_size(''myfile''),
+    sqlpage.persist_uploaded_file(''myfile'')
+);
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'uploaded_file_size',
+        1,
+        'name',
+        'Name of the file input field in the form.',
+        'TEXT'
+    );
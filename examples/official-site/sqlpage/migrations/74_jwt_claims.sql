@@ -0,0 +1,91 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'jwt_claims',
+        '0.36.0',
+        'key',
+        'Verifies a [JSON Web Token](https://jwt.io/introduction) (JWT) and returns all its claims as a JSON object.
+
+JSON Web Tokens are issued by identity providers (Keycloak, Auth0, Azure AD, ...) and by other applications
+to prove the identity of a user or of a machine.
+This function lets your API pages accept such tokens directly, without going through a full [OpenID Connect](/sso) login flow.
+
+The token is accepted if:
+ - its signature was made with one of the keys configured in [`sqlpage.json`](https://github.com/sqlpage/SQLPage/blob/main/configuration.md):
+   - `jwt_secret`: a shared secret, for tokens signed with `HS256`, `HS384` or `HS512`,
+   - `jwt_jwks_url`: the URL of the JSON Web Key Set published by your identity provider, for tokens signed with public keys (`RS256`, `ES256`, ...). The keys are downloaded when SQLPage starts.
+ - it has not expired (`exp` claim), and it is already valid (`nbf` claim).
+
+The function returns NULL if the token is NULL, if it is invalid, or if it has expired.
+
+The issuer (`iss`) and the audience (`aud`) of the token are not checked:
+if your identity provider issues tokens for other applications too, check them in SQL.
+
+The token can be passed directly from the `Authorization` header: the `Bearer ` prefix is ignored.
+
+### Example: protecting an API endpoint
+
+```sql
+set claims = sqlpage.jwt_claims(sqlpage.header(''authorization''));
+
+select ''status_code'' as component, 401 as status
+where $claims is null or $claims->>''aud'' <> ''my-api'';
+
+select ''json'' as component;
+select * from orders where customer_id = $claims->>''sub'';
+```
+'
+    ),
+    (
+        'jwt_claim',
+        '0.36.0',
+        'key',
+        'Verifies a [JSON Web Token](https://jwt.io/introduction) (JWT) and returns one of its claims.
+
+The token is verified in the same way as in [`sqlpage.jwt_claims`](?function=jwt_claims#function).
+The function returns NULL if the token is invalid or expired, or if it does not contain the claim.
+Claims that are not strings, like arrays or numbers, are returned as JSON.
+
+### Example
+
+```sql
+select ''redirect'' as component, ''/login.sql'' as link
+where sqlpage.jwt_claim($token, ''sub'') is null;
+
+select ''text'' as component, ''Welcome, '' || sqlpage.jwt_claim($token, ''name'') as contents;
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'jwt_claims',
+        1,
+        'token',
+        'The JSON Web Token, optionally preceded by `Bearer `.',
+        'TEXT'
+    ),
+    (
+        'jwt_claim',
+        1,
+        'token',
+        'The JSON Web Token, optionally preceded by `Bearer `.',
+        'TEXT'
+    ),
+    (
+        'jwt_claim',
+        2,
+        'claim',
+        'The name of the claim to return, such as `sub`, `email` or `exp`.',
+        'TEXT'
+    );
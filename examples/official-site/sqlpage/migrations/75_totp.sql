@@ -0,0 +1,84 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'totp_secret',
+        '0.36.0',
+        'shield-lock',
+        'Generates a new random secret for [time-based one-time passwords](https://en.wikipedia.org/wiki/Time-based_one-time_password) (TOTP),
+the 6-digit codes displayed by authenticator apps like Google Authenticator, Microsoft Authenticator, or FreeOTP.
+
+Use it to offer two-factor authentication on your website:
+store the secret with the user in your database, and let the user add it to their authenticator app.
+Then, check the codes entered by the user with [`sqlpage.totp_verify`](?function=totp_verify#function).
+
+The secret is encoded in base32, the format expected by authenticator apps.
+Keep it as confidential as a password: anyone who knows it can generate valid codes.
+
+### Example: enabling two-factor authentication
+
+```sql
+update users set totp_secret = sqlpage.totp_secret()
+where id = $user_id and totp_secret is null;
+
+select ''text'' as component,
+    ''Open [this link](otpauth://totp/MyWebsite:'' || email || ''?secret='' || totp_secret || ''&issuer=MyWebsite) on your phone, '' ||
+    ''or enter this key in your authenticator app: `'' || totp_secret || ''`'' as contents_md
+from users where id = $user_id;
+```
+'
+    ),
+    (
+        'totp_verify',
+        '0.36.0',
+        'shield-check',
+        'Checks a one-time password generated by an authenticator app from a secret created with [`sqlpage.totp_secret`](?function=totp_secret#function).
+
+Returns the text `''true''` if the code is valid, and `''false''` otherwise, including when the secret or the code is NULL.
+
+Codes change every 30 seconds. To tolerate small differences between the clock of the server and the clock of the phone,
+the codes of the previous and of the next 30 seconds are also accepted.
+Codes have 6 digits and are computed with HMAC-SHA1, the default settings of all common authenticator apps.
+
+A code remains valid for a short time after it has been used.
+To prevent an attacker who sees a code from reusing it, you can record the last code used by each user, and reject it the next time.
+
+### Example: checking the second factor after the password
+
+```sql
+set secret = (select totp_secret from users where id = $user_id);
+
+select ''redirect'' as component, ''/login.sql?error=invalid_code'' as link
+where sqlpage.totp_verify($secret, :code) <> ''true'';
+
+-- the code is valid: create the session
+insert into sessions (id, user_id) values (sqlpage.random_string(32), $user_id)
+returning ''cookie'' as component, ''session'' as name, id as value;
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'totp_verify',
+        1,
+        'secret',
+        'The base32 secret of the user, as returned by `sqlpage.totp_secret()`. Spaces and lowercase letters are accepted.',
+        'TEXT'
+    ),
+    (
+        'totp_verify',
+        2,
+        'code',
+        'The 6-digit code entered by the user.',
+        'TEXT'
+    );
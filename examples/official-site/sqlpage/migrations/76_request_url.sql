@@ -0,0 +1,56 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'host',
+        '0.36.0',
+        'world-www',
+        'Returns the host name that was used to access the current page, followed by the port if it is not the default one.
+For instance, `example.com` or `localhost:8080`.
+
+You can use it to serve several sites or several customers from the same SQLPage application,
+without hardcoding the domain names in your SQL files.
+
+### Example: one site per customer
+
+```sql
+select ''shell'' as component, name as title, logo_url as image
+from tenants
+where domain = sqlpage.host();
+```
+
+> The host is resolved in this order:
+> - `Forwarded` header
+> - `X-Forwarded-Host` header
+> - `Host` header
+>
+> These headers are sent by the browser or by your reverse proxy, and can be forged by malicious clients.
+> If you use the host to choose which data to show, make sure your reverse proxy only forwards requests for the domains you serve.
+'
+    ),
+    (
+        'url',
+        '0.36.0',
+        'link',
+        'Returns the full URL of the current page, including the protocol, the host, the path and the query string.
+For instance, `https://example.com/products.sql?category=books`.
+
+This is the same as `sqlpage.protocol() || ''://'' || sqlpage.host() || sqlpage.path()`, followed by the URL parameters.
+
+### Example: coming back to the current page after logging in
+
+```sql
+select ''redirect'' as component,
+    ''/login.sql?next='' || sqlpage.url_encode(sqlpage.url()) as link
+where $user_id is null;
+```
+
+The protocol and the host are resolved in the same way as in [`sqlpage.protocol`](?function=protocol#function)
+and [`sqlpage.host`](?function=host#function).
+When redirecting to a URL received in a parameter, like `next` above, check that it points to your own site,
+to avoid sending your users to malicious websites.
+'
+    );
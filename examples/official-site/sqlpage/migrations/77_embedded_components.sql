@@ -0,0 +1,48 @@
+INSERT INTO example(component, description, properties) VALUES
+    ('table', '### Components inside cells
+
+A cell can contain another component instead of a simple value.
+Return a JSON object with a `component` property, the top-level properties of the component,
+and its items in a `rows` array.
+
+```sql
+select ''table'' as component;
+select
+    name,
+    json_object(
+        ''component'', ''button'',
+        ''rows'', json_array(
+            json_object(''title'', ''Edit'', ''link'', ''edit.sql?id='' || id, ''icon'', ''pencil''),
+            json_object(''title'', ''Delete'', ''link'', ''delete.sql?id='' || id, ''icon'', ''trash'', ''color'', ''red'')
+        )
+    ) as actions
+from users;
+```
+
+On PostgreSQL, use `json_build_object` and `json_build_array` instead.
+The same syntax works in the `description` and `footer` of the [card component](?component=card).
+',
+    json('[{"component":"table"},
+    {"name": "Ada", "actions": {"component": "button", "size": "sm", "rows": [{"title": "Edit", "icon": "pencil"}, {"title": "Delete", "icon": "trash", "color": "red"}]}},
+    {"name": "Alan", "actions": {"component": "button", "size": "sm", "rows": [{"title": "Edit", "icon": "pencil"}, {"title": "Delete", "icon": "trash", "color": "red"}]}}
+    ]')),
+    ('card', '### Components inside cards
+
+The `description` and the `footer` of a card can contain another component,
+described by a JSON object with a `component` property and, optionally, a `rows` array.
+
+```sql
+select ''card'' as component, 2 as columns;
+select
+    name as title,
+    json_object(
+        ''component'', ''chart'', ''type'', ''line'',
+        ''rows'', (select json_group_array(json_object(''x'', day, ''y'', sales)) from sales where sales.shop_id = shops.id)
+    ) as description
+from shops;
+```
+',
+    json('[{"component":"card", "columns": 2},
+    {"title": "Downtown", "description": {"component": "chart", "type": "line", "rows": [{"x": 1, "y": 10}, {"x": 2, "y": 25}, {"x": 3, "y": 18}]}},
+    {"title": "Airport", "description": {"component": "chart", "type": "line", "rows": [{"x": 1, "y": 30}, {"x": 2, "y": 22}, {"x": 3, "y": 35}]}}
+    ]'));
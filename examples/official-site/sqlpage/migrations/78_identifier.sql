@@ -0,0 +1,63 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'identifier',
+        '0.36.0',
+        'list-check',
+        'Inserts the name of a table or of a column, chosen from a fixed list, into a query.
+
+Variables like `$sort` are sent to the database as values, never as SQL code.
+This protects your website against SQL injection, but it also means that
+`ORDER BY $sort` sorts by the constant text of the variable, not by the column it names.
+`sqlpage.identifier` is the safe way to let the user choose a column or a table:
+
+```sql
+select ''table'' as component;
+select name, created_at, price from products
+order by sqlpage.identifier($sort, ''name,created_at,price'');
+```
+
+The first argument is the name chosen by the user, and the second one is the list of allowed names, separated by commas.
+The list must be written directly in the SQL file.
+When the page is requested with `?sort=price`, SQLPage sends `... ORDER BY price` to the database.
+When `$sort` is NULL, the first allowed name is used.
+Any other value, like `?sort=price;drop table products`, is rejected with an error, before the query is sent to the database.
+Names are compared without case sensitivity.
+
+It can also be used in place of a table name:
+
+```sql
+select * from sqlpage.identifier($category, ''books,movies,games'') where id = $id;
+```
+
+Allowed names can only contain letters, digits and underscores, and can be prefixed with a table name, like `products.price`.
+SQLPage prepares one version of the query for each allowed name, so the lists should stay short:
+a query can contain several `sqlpage.identifier` calls, as long as there are at most 1024 combinations of names.
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'identifier',
+        1,
+        'name',
+        'The name of the table or column to use, usually a variable like `$sort`. NULL selects the first allowed name.',
+        'TEXT'
+    ),
+    (
+        'identifier',
+        2,
+        'allowed_names',
+        'The comma-separated list of the names that can be used, as a literal string, like `''name,created_at,price''`.',
+        'TEXT'
+    );
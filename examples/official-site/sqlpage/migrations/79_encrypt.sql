@@ -0,0 +1,93 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'encrypt',
+        '0.36.0',
+        'lock',
+        'Encrypts a text into a token that can be sent to the user, and read back later with [`sqlpage.decrypt`](?function=decrypt#function).
+
+The token cannot be read, modified, or created without the `encryption_key` set in the [configuration file](https://github.com/sqlpage/SQLPage/blob/main/configuration.md).
+This makes it possible to store information in links and cookies without storing it in the database,
+and to be sure that the information you get back is the one you sent.
+
+The result is a URL-safe text, that can be used in links and cookies without [encoding](?function=url_encode#function) it.
+Encrypting the same value twice gives different tokens. The time of the encryption is stored in the token.
+
+### Example: password reset links
+
+```sql
+select ''text'' as component,
+    ''[Reset your password](https://example.com/reset.sql?token='' || sqlpage.encrypt(id) || '')'' as contents_md
+from users where email = :email;
+```
+
+Then, in `reset.sql`, the link is valid for one hour:
+
+```sql
+set user_id = sqlpage.decrypt($token, 3600);
+select ''redirect'' as component, ''/expired_link.sql'' as link where $user_id is null;
+```
+
+An encrypted token remains valid until it expires, even if it has already been used.
+To make a token usable only once, include a value that changes when it is used, like the current password hash of the user,
+and check it when the token is decrypted.
+'
+    ),
+    (
+        'decrypt',
+        '0.36.0',
+        'lock-open',
+        'Returns the text contained in a token created by [`sqlpage.encrypt`](?function=encrypt#function).
+
+Returns `NULL` when the token is `NULL`, when it was modified, when it was created with a different `encryption_key`,
+or when it is older than the optional maximum age.
+Always check the result for `NULL` before trusting it.
+
+### Example: remember-me cookie
+
+```sql
+-- when logging in
+select ''cookie'' as component, ''remember_me'' as name, sqlpage.encrypt($user_id) as value,
+    30 * 24 * 3600 as max_age;
+```
+
+```sql
+-- on the other pages
+set user_id = sqlpage.decrypt(sqlpage.cookie(''remember_me''), 2592000); -- 30 days
+select ''redirect'' as component, ''/login.sql'' as link where $user_id is null;
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'encrypt',
+        1,
+        'value',
+        'The text to encrypt. Encrypting `NULL` returns `NULL`.',
+        'TEXT'
+    ),
+    (
+        'decrypt',
+        1,
+        'token',
+        'A token returned by `sqlpage.encrypt`.',
+        'TEXT'
+    ),
+    (
+        'decrypt',
+        2,
+        'max_age_seconds',
+        'Optional. Tokens encrypted more than this number of seconds ago are rejected.',
+        'INTEGER'
+    );
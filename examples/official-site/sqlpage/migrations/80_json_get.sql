@@ -0,0 +1,63 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'json_get',
+        '0.36.0',
+        'braces',
+        'Extracts a value from a JSON document, using a path like `$.items[0].name`.
+
+Every database has its own JSON functions, with a different syntax and different behaviors.
+`sqlpage.json_get` is evaluated by SQLPage itself, so it works the same way on all databases.
+It is especially useful to read the responses of [`sqlpage.fetch`](?function=fetch#function),
+or the JSON payloads sent to your website by webhooks.
+
+### Example: reading the response of an API
+
+```sql
+set weather = sqlpage.fetch(''https://api.example.com/weather?city=Paris'');
+
+select ''text'' as component,
+    ''It is '' || sqlpage.json_get($weather, ''$.current.temperature'') || ''°C in '' ||
+    sqlpage.json_get($weather, ''$.location.name'') as contents;
+```
+
+### Paths
+
+A path starts with `$`, which represents the whole document, followed by:
+ - `.key` to get the value of a key in an object. Keys containing dots or brackets can be quoted, as in `."key.with.dots"` or `["key"]`.
+ - `[index]` to get an element of an array. The first element has index `0`, and negative indices count from the end: `[-1]` is the last element.
+
+### Return value
+
+ - Strings are returned without their quotes: `sqlpage.json_get(''{"name": "Ada"}'', ''$.name'')` returns `Ada`.
+ - Numbers and booleans are returned as text: `42`, `true`.
+ - Objects and arrays are returned as JSON, and can be passed to `sqlpage.json_get` again.
+ - When there is no value at the given path, or when the value is `null`, the function returns `NULL`.
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'json_get',
+        1,
+        'json',
+        'The JSON document. Returns `NULL` when the document is `NULL`, and an error when it is not valid JSON.',
+        'JSON'
+    ),
+    (
+        'json_get',
+        2,
+        'path',
+        'The path of the value to extract, like `$.user.name` or `$.items[0]`.',
+        'TEXT'
+    );
@@ -0,0 +1,108 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'get_bool',
+        '0.36.0',
+        'toggle-right',
+        'Reads a URL parameter as a boolean, and returns `1` or `0`.
+
+This makes it easy to use checkboxes in filter forms, without long chains of `CAST` and `COALESCE`.
+The values `1`, `true`, `t`, `on`, `yes` and `y` are true, and `0`, `false`, `f`, `off`, `no`, `n` and the empty string are false, regardless of their case.
+When the parameter is missing, or has any other value, the default value is returned.
+
+When the parameter is given several times, the last value is used.
+This lets you use the classic pattern of a hidden field followed by a checkbox with the same name,
+so that unchecking the box is different from not submitting the form at all.
+
+### Example: filtering a list with a checkbox
+
+```sql
+select ''form'' as component, ''get'' as method;
+select ''active'' as name, ''checkbox'' as type, ''Only active users'' as label, 1 as value,
+    sqlpage.get_bool(''active'', false) = ''1'' as checked;
+
+select ''list'' as component;
+select name as title from users
+where sqlpage.get_bool(''active'', false) = ''0'' or active;
+```
+
+In PostgreSQL, you can also cast the result to a boolean: `sqlpage.get_bool(''active'', false)::boolean`.
+'
+    ),
+    (
+        'get_int',
+        '0.36.0',
+        'number',
+        'Reads a URL parameter as an integer, and returns it limited to the range between `min` and `max`.
+
+When the parameter is missing, or is not an integer, the default value is returned.
+Values outside of the range are replaced by the closest limit, so users cannot request
+a negative page number, or a page with millions of rows.
+
+### Example: pagination
+
+```sql
+set page = sqlpage.get_int(''page'', 1, 1);
+set page_size = sqlpage.get_int(''page_size'', 20, 1, 100);
+
+select ''table'' as component;
+select * from products
+order by name
+limit $page_size offset ($page - 1) * $page_size;
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'get_bool',
+        1,
+        'name',
+        'The name of the URL parameter.',
+        'TEXT'
+    ),
+    (
+        'get_bool',
+        2,
+        'default_value',
+        'Optional. The value to use when the parameter is missing or is not a boolean, like `true` or `false`. Without a default value, the function returns `NULL` in this case.',
+        'BOOLEAN'
+    ),
+    (
+        'get_int',
+        1,
+        'name',
+        'The name of the URL parameter.',
+        'TEXT'
+    ),
+    (
+        'get_int',
+        2,
+        'default_value',
+        'Optional. The value to use when the parameter is missing or is not an integer. Without a default value, the function returns `NULL` in this case.',
+        'INTEGER'
+    ),
+    (
+        'get_int',
+        3,
+        'min',
+        'Optional. The smallest value that can be returned.',
+        'INTEGER'
+    ),
+    (
+        'get_int',
+        4,
+        'max',
+        'Optional. The largest value that can be returned.',
+        'INTEGER'
+    );
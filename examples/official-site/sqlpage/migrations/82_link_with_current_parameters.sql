@@ -0,0 +1,63 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'link_with_current_parameters',
+        '0.36.0',
+        'link',
+        'Builds a URL that keeps the parameters of the current page URL, and replaces some of them.
+
+This is what pagination links, sort buttons and filter links need:
+going to the next page should not lose the search terms and filters that the user selected.
+Instead of concatenating strings for each parameter, you only give the parameters that change.
+
+The parameters in the `overrides` JSON object replace the parameters of the current URL that have the same name.
+Parameters set to `null` are removed from the URL.
+Arrays become several parameters ending with `[]`, like `tags[]=a&tags[]=b`.
+All the names and values are properly URL-encoded.
+
+### Example: pagination that keeps the filters
+
+On the page `products.sql?category=tools&search=saw&page=3`:
+
+```sql
+select ''button'' as component;
+select ''Previous page'' as title,
+    sqlpage.link_with_current_parameters(null, json_object(''page'', $page - 1)) as link;
+select ''Next page'' as title,
+    sqlpage.link_with_current_parameters(null, json_object(''page'', $page + 1)) as link;
+select ''Clear the search'' as title,
+    sqlpage.link_with_current_parameters(null, json_object(''search'', null, ''page'', null)) as link;
+```
+
+The links are `/products.sql?category=tools&search=saw&page=2`, `/products.sql?category=tools&search=saw&page=4`,
+and `/products.sql?category=tools`.
+
+To build a URL that does not keep the current parameters, use [`sqlpage.link`](?function=link).
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'link_with_current_parameters',
+        1,
+        'path',
+        'The path of the page to link to, like `products.sql`. When `NULL`, the link points to the current page.',
+        'TEXT'
+    ),
+    (
+        'link_with_current_parameters',
+        2,
+        'overrides',
+        'Optional. A JSON object with the parameters to add or replace. Parameters with a `null` value are removed.',
+        'JSON'
+    );
@@ -0,0 +1,39 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('profile', 'report-analytics', 'Computes statistics about each column of a query, for quick data quality checks in administration pages.
+
+The rows are not displayed: instead, the component shows one line per column, with the type of its values,
+the percentage of null values, the number of distinct values, the smallest and largest values,
+and a histogram of the values. For numbers, the histogram counts the values in intervals of the same width.
+For text, it shows the most frequent values.
+
+On large tables, the statistics are computed on a random sample of the rows, so that the page uses a bounded amount of memory.
+All the rows are still read from the database: to profile a very large table quickly, sample it in SQL,
+for instance with `TABLESAMPLE` in PostgreSQL.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'profile', * FROM (VALUES
+    ('title', 'A title displayed above the statistics.', 'TEXT', TRUE, TRUE),
+    ('description', 'A text displayed below the statistics, before the number of rows.', 'TEXT', TRUE, TRUE),
+    ('sample_size', 'The maximum number of rows used to compute the statistics. Defaults to 10000. When the query returns more rows, a random sample of this size is used.', 'INTEGER', TRUE, TRUE),
+    ('bins', 'The number of bars in the histograms. Defaults to 10.', 'INTEGER', TRUE, TRUE),
+    ('id', 'An optional ID to be used as an anchor for links.', 'TEXT', TRUE, TRUE),
+    ('class', 'An optional CSS class to be added to the component for custom styling', 'TEXT', TRUE, TRUE)
+) x;
+
+INSERT INTO example(component, description, properties) VALUES
+    ('profile', '
+### Checking the quality of imported data
+
+```sql
+select ''profile'' as component, ''Imported customers'' as title, 5 as bins;
+select * from customers;
+```
+
+Each column of the query is described by a line of the profile.
+', json('[
+        {"component":"profile", "title":"Imported customers", "bins":5},
+        {"name":"Ophir", "country":"France", "age":29, "email":"ophir@example.com"},
+        {"name":"Max", "country":"France", "age":37, "email":null},
+        {"name":"Lea", "country":"Germany", "age":45, "email":"lea@example.com"},
+        {"name":"Sam", "country":"Canada", "age":null, "email":"sam@example.com"},
+        {"name":"Ana", "country":"France", "age":52, "email":"ana@example.com"}
+    ]'));
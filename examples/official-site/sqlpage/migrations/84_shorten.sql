@@ -0,0 +1,60 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'shorten',
+        '0.36.0',
+        'link-plus',
+        'Creates a short link to a URL, and returns its path, like `/_sqlpage/s/8yTeBWJZ`.
+
+Reports with many filters have long URLs, that are hard to share in an email or a chat message.
+Visiting the short link redirects to the original URL, and counts the click.
+Shortening the same URL several times returns the same link.
+
+The links are stored in a `sqlpage_short_links` table, that you create in a [migration](/your-first-sql-website/migrations.sql):
+
+```sql
+create table sqlpage_short_links (
+    code varchar(64) primary key,
+    url text not null,
+    clicks integer not null default 0
+);
+```
+
+You can add other columns, like a creation date with a default value.
+Short links never expire: delete their rows from the table to remove them.
+
+### Example: sharing the current report
+
+```sql
+select ''button'' as component;
+select ''Share this report'' as title, ''share'' as icon,
+    sqlpage.shorten(sqlpage.link_with_current_parameters(null, null)) as link;
+```
+
+### Example: the most visited links
+
+```sql
+select ''table'' as component;
+select url, clicks from sqlpage_short_links order by clicks desc limit 10;
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'shorten',
+        1,
+        'url',
+        'The URL to shorten. It can be a path on the same site, like `/report.sql?year=2024`, or a full URL. When it is `NULL`, the function returns `NULL`.',
+        'TEXT'
+    );
@@ -0,0 +1,77 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'state_encode',
+        '0.36.0',
+        'share',
+        'Turns a JSON value, like the state of the filters of a page, into a short token that can be put in a link,
+and read back with [`sqlpage.state_decode`](?function=state_decode#function).
+
+A page with many filters needs dozens of URL parameters to be shared.
+Instead, you can build a single JSON object with all of them, and share a link that contains only its token.
+The JSON is compressed, so the token is usually much shorter than the parameters it replaces.
+
+The token is signed with the `encryption_key` set in the [configuration file](https://github.com/sqlpage/SQLPage/blob/main/configuration.md):
+users cannot modify it, but they can read its contents.
+Use [`sqlpage.encrypt`](?function=encrypt#function) for values that must stay secret.
+The same state always gives the same token, and tokens never expire.
+
+### Example: sharing a search
+
+```sql
+select ''button'' as component;
+select ''Share this search'' as title, ''share'' as icon,
+    ''search.sql?state='' || sqlpage.state_encode(json_object(
+        ''query'', $query,
+        ''categories'', json($categories),
+        ''min_price'', $min_price,
+        ''sort'', $sort
+    )) as link;
+```
+'
+    ),
+    (
+        'state_decode',
+        '0.36.0',
+        'share-2',
+        'Returns the JSON value contained in a token created by [`sqlpage.state_encode`](?function=state_encode#function).
+
+Returns `NULL` when the token is `NULL`, when it was modified, or when it was created with a different `encryption_key`.
+
+### Example: restoring a shared search
+
+In `search.sql`, the values of the shared state are used when the page is opened from a shared link:
+
+```sql
+set state = sqlpage.state_decode($state);
+set query = coalesce($query, sqlpage.json_get($state, ''$.query''));
+set min_price = coalesce($min_price, sqlpage.json_get($state, ''$.min_price''));
+```
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'state_encode',
+        1,
+        'state',
+        'A JSON value, usually an object. Encoding `NULL` returns `NULL`.',
+        'JSON'
+    ),
+    (
+        'state_decode',
+        1,
+        'token',
+        'A token returned by `sqlpage.state_encode`.',
+        'TEXT'
+    );
@@ -0,0 +1,84 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'as_of',
+        '0.36.0',
+        'history',
+        'Reads a table as it was at a given time. It is used in place of a table name, in the `from` clause of a query:
+
+```sql
+select name, price from sqlpage.as_of(''products'', $as_of) as products;
+```
+
+This lets a page display the state of the data at a date chosen by the user, for audits or to compare with the past.
+When the timestamp is `NULL`, the current rows are returned.
+
+### How the history is stored
+
+On **SQL Server**, use a [system-versioned temporal table](https://learn.microsoft.com/en-us/sql/relational-databases/tables/temporal-tables).
+SQLPage sends `products FOR SYSTEM_TIME AS OF ...` to the database, that finds the old versions of the rows by itself.
+On SQL Server, the timestamp cannot be `NULL`. To default to the current time, set the variable first:
+`set as_of = coalesce($as_of, sysutcdatetime());`
+
+The other databases have no temporal tables. The old versions of the rows are stored in a history table,
+named like the table with a `_history` suffix by default, that you keep up to date yourself, for instance with triggers.
+Both tables have the same columns, including:
+ - `valid_from`: when the version of the row was created,
+ - `valid_to`: when it was replaced or deleted. It is only used in the history table.
+
+SQLPage replaces the call with a query that returns the rows of the table created before the timestamp,
+and the rows of the history table that were valid at that time.
+
+### Example: a price list at a chosen date
+
+```sql
+select ''form'' as component, ''get'' as method, ''Show'' as validate;
+select ''as_of'' as name, ''Prices on'' as label, ''datetime-local'' as type, $as_of as value;
+
+select ''table'' as component;
+select name, price from sqlpage.as_of(''products'', $as_of) as products order by name;
+```
+
+With the history in a table with a different name, give it as the third argument:
+
+```sql
+select * from sqlpage.as_of(''products'', $as_of, ''audit.products_versions'') as products;
+```
+
+The names of the tables must be written directly in the SQL file, and can only contain letters, digits and underscores,
+optionally prefixed with the name of a schema, like `audit.products_versions`.
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'as_of',
+        1,
+        'table',
+        'The name of the table, as a literal string, like `''products''`.',
+        'TEXT'
+    ),
+    (
+        'as_of',
+        2,
+        'timestamp',
+        'The time at which to read the table, usually a variable like `$as_of`. NULL reads the current rows.',
+        'TIMESTAMP'
+    ),
+    (
+        'as_of',
+        3,
+        'history_table',
+        'Optional. The name of the history table, as a literal string. Defaults to the name of the table followed by `_history`. Not used on SQL Server.',
+        'TEXT'
+    );
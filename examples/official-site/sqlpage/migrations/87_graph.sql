@@ -0,0 +1,63 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('graph', 'affiliate', 'Displays a network of nodes connected by edges, for dependencies between services, relationships between people, or any other links between the rows of your database.
+
+Rows with an `id` are nodes, and rows with a `source` and a `target` are edges between two nodes,
+so that the nodes and the edges are usually selected by two different queries.
+Nodes that appear only in edges are created automatically.
+
+The positions of the nodes are computed on the server with a force-directed layout:
+connected nodes attract each other, and all nodes repel each other.
+The graph is rendered as an image, without any JavaScript.
+
+Large graphs are simplified so that they stay readable: when there are more than `max_nodes` nodes,
+the nodes of each `group` are merged into a single node, and if there are still too many nodes,
+only the most connected ones are displayed.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'graph', * FROM (VALUES
+    -- top level
+    ('title', 'A title displayed above the graph.', 'TEXT', TRUE, TRUE),
+    ('height', 'The height of the graph, relative to its width of 1000. Defaults to 500.', 'INTEGER', TRUE, TRUE),
+    ('max_nodes', 'The maximum number of nodes displayed. Defaults to 300. Larger graphs are simplified by merging the nodes of each group, then by hiding the least connected nodes.', 'INTEGER', TRUE, TRUE),
+    ('directed', 'Draw an arrow at the end of each edge, pointing to its target.', 'BOOLEAN', TRUE, TRUE),
+    ('id', 'An optional ID to be used as an anchor for links.', 'TEXT', TRUE, TRUE),
+    ('class', 'An optional CSS class to be added to the component for custom styling', 'TEXT', TRUE, TRUE),
+    -- nodes
+    ('id', 'The unique identifier of a node, used as the source and target of edges.', 'TEXT', FALSE, TRUE),
+    ('label', 'The text displayed below a node, or on an edge. Nodes are labelled with their id by default.', 'TEXT', FALSE, TRUE),
+    ('group', 'The group of a node. Nodes of the same group have the same color, and are merged into a single node when the graph has more than max_nodes nodes.', 'TEXT', FALSE, TRUE),
+    ('color', 'The color of a node or an edge, such as "red" or "green". Nodes are colored by group by default.', 'COLOR', FALSE, TRUE),
+    ('size', 'The radius of a node, between 1 and 50. By default, the most connected nodes are the largest.', 'REAL', FALSE, TRUE),
+    ('link', 'A URL opened when the node is clicked.', 'URL', FALSE, TRUE),
+    ('tooltip', 'A text displayed when the mouse hovers over the node.', 'TEXT', FALSE, TRUE),
+    -- edges
+    ('source', 'The id of the node an edge starts from.', 'TEXT', FALSE, TRUE),
+    ('target', 'The id of the node an edge goes to.', 'TEXT', FALSE, TRUE),
+    ('width', 'The thickness of an edge. Merged edges are thicker by default.', 'REAL', FALSE, TRUE)
+) x;
+
+INSERT INTO example(component, description, properties) VALUES
+    ('graph', '
+### Dependencies between services
+
+```sql
+select ''graph'' as component, ''Services'' as title, true as directed;
+select name as id, team as "group", ''service.sql?name='' || name as link from services;
+select caller as source, callee as target, protocol as label from service_calls;
+```
+', json('[
+        {"component":"graph", "title":"Services", "directed":true, "height":400},
+        {"id":"website", "group":"frontend"},
+        {"id":"mobile app", "group":"frontend"},
+        {"id":"api", "group":"backend", "size":16, "tooltip":"Public API"},
+        {"id":"billing", "group":"backend"},
+        {"id":"search", "group":"backend"},
+        {"id":"postgres", "group":"storage", "color":"red"},
+        {"id":"elasticsearch", "group":"storage"},
+        {"source":"website", "target":"api", "label":"REST"},
+        {"source":"mobile app", "target":"api", "label":"REST"},
+        {"source":"api", "target":"billing"},
+        {"source":"api", "target":"search"},
+        {"source":"billing", "target":"postgres"},
+        {"source":"api", "target":"postgres"},
+        {"source":"search", "target":"elasticsearch"}
+    ]'));
@@ -0,0 +1,48 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('xlsx', 'file-spreadsheet', 'Lets the user download data as an Excel spreadsheet.
+Each column from the items in the component will map to a column in the spreadsheet, with the column names in a bold header row.
+
+This is a **header component**: it must be used at the top of the page, without a [shell](?component=shell),
+and the page is downloaded as a file instead of being displayed.
+The spreadsheet is compressed and sent to the browser while the rows are read from the database,
+so even large tables can be exported without being loaded in memory.
+
+Numbers stay numbers in Excel, and the `column_formats` property applies
+[Excel number formats](https://support.microsoft.com/en-us/office/number-format-codes-5026bbd6-04bc-48cd-bf33-80f18b4eae68)
+to columns, such as `#,##0.00` for amounts or `yyyy-mm-dd` for dates.
+In formatted columns, texts that contain numbers or dates (like `2024-03-01` or `2024-03-01 18:30:00`) are converted,
+so that users can sort and compute with them without re-importing the file.
+
+An Excel sheet cannot contain more than 1048576 rows: use the [csv](?component=csv) component for larger exports.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'xlsx', * FROM (VALUES
+    ('filename', 'The name of the file that should be downloaded (without the extension). Defaults to "export".', 'TEXT', TRUE, TRUE),
+    ('sheet_name', 'The name of the sheet in the spreadsheet, of at most 31 characters. Defaults to "Sheet1".', 'TEXT', TRUE, TRUE),
+    ('column_formats', 'A JSON object associating column names to Excel number formats, like {"price": "#,##0.00 €", "created_at": "yyyy-mm-dd hh:mm"}.', 'JSON', TRUE, TRUE)
+) x;
+
+INSERT INTO example(component, description) VALUES
+    ('xlsx', '
+### Downloading an order report
+
+#### `orders_download.sql`
+
+```sql
+select
+    ''xlsx'' as component,
+    ''orders'' as filename,
+    ''Orders of '' || $month as sheet_name,
+    json_object(''total'', ''#,##0.00 €'', ''ordered_at'', ''yyyy-mm-dd hh:mm'', ''discount'', ''0%'') as column_formats;
+
+select id, customer, ordered_at, total, discount
+from orders
+where strftime(''%Y-%m'', ordered_at) = $month;
+```
+
+#### `index.sql`
+
+```sql
+select ''button'' as component;
+select ''Download the orders in Excel'' as title, ''orders_download.sql?month=2024-03'' as link, ''file-spreadsheet'' as icon;
+```
+');
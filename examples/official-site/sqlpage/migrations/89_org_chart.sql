@@ -0,0 +1,52 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('org_chart', 'hierarchy-2', 'Displays people in a hierarchy, such as the organization chart of a company, from a table where each person has the id of their manager.
+
+Each row is a person, with an `id`, a `name`, and the `parent_id` of the person they report to.
+The rows can be in any order. People without a `parent_id`, or whose manager is not in the results, are displayed at the top of the chart.
+
+The teams of the people below `expanded_levels` are collapsed: users click on a person to show or hide their team.
+Each manager displays the number of people in their team, including the teams of their reports.
+The chart is rendered without JavaScript.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'org_chart', * FROM (VALUES
+    -- top level
+    ('title', 'A title displayed above the chart.', 'TEXT', TRUE, TRUE),
+    ('expanded_levels', 'The number of levels of the hierarchy that are displayed when the page is loaded. Defaults to 2: the people at the top and their direct reports. The other teams are displayed when users click on their manager.', 'INTEGER', TRUE, TRUE),
+    ('empty_description', 'A text displayed when there are no people in the chart.', 'TEXT', TRUE, TRUE),
+    ('id', 'An optional ID to be used as an anchor for links.', 'TEXT', TRUE, TRUE),
+    ('class', 'An optional CSS class to be added to the component for custom styling', 'TEXT', TRUE, TRUE),
+    -- row level
+    ('id', 'The unique identifier of the person.', 'TEXT', FALSE, FALSE),
+    ('parent_id', 'The id of the person they report to. Leave it empty for the people at the top of the hierarchy.', 'TEXT', FALSE, TRUE),
+    ('name', 'The name of the person.', 'TEXT', FALSE, FALSE),
+    ('description', 'A text displayed below the name, such as the job title of the person.', 'TEXT', FALSE, TRUE),
+    ('image_url', 'The URL of a photo of the person. People without a photo are displayed with their initials.', 'URL', FALSE, TRUE),
+    ('color', 'The background color of the initials of people without a photo.', 'COLOR', FALSE, TRUE),
+    ('link', 'A URL opened when the name of the person is clicked, such as the page of their profile.', 'URL', FALSE, TRUE)
+) x;
+
+INSERT INTO example(component, description, properties) VALUES
+    ('org_chart', '
+### The organization chart of a company
+
+```sql
+select ''org_chart'' as component, ''Our team'' as title, 3 as expanded_levels;
+select
+    id,
+    manager_id as parent_id,
+    first_name || '' '' || last_name as name,
+    job_title as description,
+    photo_url as image_url,
+    ''employee.sql?id='' || id as link
+from employees;
+```
+', json('[
+        {"component":"org_chart", "title":"Our team", "expanded_levels":3},
+        {"id":1, "parent_id":null, "name":"Alice Smith", "description":"Chief Executive Officer", "image_url":"/favicon.ico"},
+        {"id":2, "parent_id":1, "name":"Bob Martin", "description":"Chief Technology Officer", "color":"green"},
+        {"id":3, "parent_id":1, "name":"Carol Jones", "description":"Head of Sales", "color":"orange"},
+        {"id":4, "parent_id":2, "name":"Dave Brown", "description":"Developer", "color":"green"},
+        {"id":5, "parent_id":2, "name":"Erin Davis", "description":"Designer", "color":"green"},
+        {"id":6, "parent_id":3, "name":"Frank Miller", "description":"Account Manager", "color":"orange"},
+        {"id":7, "parent_id":4, "name":"Grace Wilson", "description":"Intern", "color":"green"}
+    ]'));
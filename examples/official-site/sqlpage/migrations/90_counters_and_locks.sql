@@ -0,0 +1,93 @@
+INSERT INTO sqlpage_functions (
+        "name",
+        "introduced_in_version",
+        "icon",
+        "description_md"
+    )
+VALUES (
+        'counter_next',
+        '0.36.0',
+        'sort-ascending-numbers',
+        'Increments a named counter, and returns its new value. The first value of a counter is 1.
+
+Pages that increment the same counter at the same time always get different values,
+which makes it suitable for invoice numbers, ticket numbers, or any other sequence that must not have duplicates.
+
+```sql
+set invoice_number = sqlpage.counter_next(''invoice_number'');
+insert into invoices (number, customer) values ($invoice_number, :customer);
+```
+
+### Storing the counters
+
+The counters are stored in a `sqlpage_counters` table, that you create in a [migration](/your-first-sql-website/migrations.sql):
+
+```sql
+create table sqlpage_counters (
+    name varchar(255) primary key,
+    value bigint not null
+);
+```
+
+Each call increments the counter and reads its new value in a single statement,
+so the counters are shared by all the SQLPage servers that use the same database.
+'
+    ),
+    (
+        'with_lock',
+        '0.36.0',
+        'lock',
+        'Acquires a named lock until the end of the page, so that only one page at a time
+executes the statements that follow it. Other pages that call `sqlpage.with_lock` with the same name wait for the lock to be released.
+
+Returns the name of the lock.
+If the lock is still held by another page after the timeout, the page stops with a *503 Service Unavailable* error.
+
+```sql
+set lock = sqlpage.with_lock(''stock_'' || $product_id, 5);
+set stock = (select quantity from stock where product_id = $product_id);
+update stock set quantity = quantity - 1 where product_id = $product_id and $stock > 0;
+```
+
+The lock is released when the page has finished executing, even if it failed.
+Calling `sqlpage.with_lock` again with the name of a lock that the page already holds returns immediately.
+
+### Database support
+
+The locks use the locking primitives of the database, so they exclude the pages executed by all the SQLPage servers that share the database:
+ - `pg_advisory_lock` on PostgreSQL,
+ - `GET_LOCK` on MySQL and MariaDB,
+ - `sp_getapplock` on SQL Server.
+
+SQLite has no such locks: with SQLite, the locks only exclude the pages executed by the same SQLPage server.
+'
+    );
+
+INSERT INTO sqlpage_function_parameters (
+        "function",
+        "index",
+        "name",
+        "description_md",
+        "type"
+    )
+VALUES (
+        'counter_next',
+        1,
+        'name',
+        'The name of the counter. Each name has its own sequence of values.',
+        'TEXT'
+    ),
+    (
+        'with_lock',
+        1,
+        'name',
+        'The name of the lock, between 1 and 64 characters.',
+        'TEXT'
+    ),
+    (
+        'with_lock',
+        2,
+        'timeout',
+        'Optional. The maximum number of seconds to wait for other pages to release the lock. Defaults to 10.',
+        'REAL'
+    );
@@ -0,0 +1,42 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('etag', 'refresh', 'Lets browsers reuse the version of the page they already have, when it has not changed.
+
+This is a **header component**: it must be used at the top of the page, before any component that displays something.
+The page is sent with a weak [ETag](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/ETag),
+a hash of all the rows returned by its queries, including the shell. When the browser loads the page again, it sends the ETag back,
+and if the rows are the same, SQLPage answers *304 Not Modified* instead of sending the page again.
+This is useful for dashboards that are reloaded often, but rarely change.
+
+By default, the page is still executed each time, to know whether it changed.
+With `max_age`, SQLPage remembers the ETag of the page for that number of seconds,
+and does not execute the page again when the browser already has its latest version:
+the statements of the page are not run at all, so changes made to the database during that time are only visible after it.
+The ETag is remembered separately for each user, identified by their cookies and `Authorization` header.
+Still, during `max_age`, a user whose access was revoked without changing their cookies keeps getting *304 Not Modified*
+for the version of the page they already have: do not use `max_age` on pages whose permissions are checked in the page itself
+and can change at any time.
+
+Pages that use this component are not streamed to the browser: they are rendered entirely in memory before being sent,
+so it should not be used on pages that display a lot of rows.
+Pages that display an error, or that set a status other than 200, are sent without ETag.
+Changes to the [custom components](custom_components.sql) of the page only change its ETag when SQLPage restarts.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'etag', * FROM (VALUES
+    ('max_age', 'The number of seconds during which the page is not executed again for browsers that already have its latest version. Defaults to 0: the page is executed for every request.', 'REAL', TRUE, TRUE)
+) x;
+
+INSERT INTO example(component, description) VALUES
+    ('etag', '
+### A dashboard refreshed every minute
+
+The statistics are computed at most once every 30 seconds for each browser that displays the dashboard,
+and the page is only sent again when they changed.
+
+```sql
+select ''etag'' as component, 30 as max_age;
+select ''shell'' as component, ''Sales'' as title, 60 as refresh;
+
+select ''big_number'' as component;
+select ''Orders today'' as title, count(*) as value from orders where ordered_at >= current_date;
+```
+');
@@ -0,0 +1,56 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('download', 'file-download', 'Sends a file to the browser, instead of a web page.
+
+This is a **header component**: it must be used at the top of the page, before any component that displays something.
+It lets you serve files that are stored in the database, without writing them to disk first.
+The contents of the file are usually taken from a binary column (`BLOB` in SQLite and MySQL, `BYTEA` in PostgreSQL, `VARBINARY` in SQL Server).
+SQLPage returns binary columns that do not contain text as [data URLs](https://developer.mozilla.org/en-US/docs/Web/URI/Reference/Schemes/data),
+that the `download` component decodes. Data URLs created with [`sqlpage.read_file_as_data_url`](functions.sql?function=read_file_as_data_url) can also be stored in a text column and served in the same way.
+
+The size of the file is sent to the browser in the `Content-Length` header, so that it can display the progress of the download.
+The whole file is loaded in memory, so this component is not meant for files of several hundreds of megabytes.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'download', * FROM (VALUES
+    ('contents', 'The contents of the file: a binary column, a data URL, or a text.', 'TEXT', TRUE, FALSE),
+    ('filename', 'The name under which the browser saves the file.', 'TEXT', TRUE, TRUE),
+    ('content_type', 'The media type of the file, such as application/pdf. Defaults to the type of the data URL, or to the type guessed from the extension of the filename.', 'TEXT', TRUE, TRUE),
+    ('inline', 'Let the browser display the file (for instance images and PDF files) instead of downloading it.', 'BOOLEAN', TRUE, TRUE)
+) x;
+
+INSERT INTO example(component, description) VALUES
+    ('download', '
+### Serving an uploaded document
+
+A page that lists documents can link to `document.sql?id=...`, which contains:
+
+```sql
+select
+    ''download'' as component,
+    file_name as filename,
+    mime_type as content_type,
+    contents
+from documents
+where id = $id;
+```
+
+When the document is [uploaded](?component=form), it can be stored in the database with
+```sql
+insert into documents (file_name, mime_type, contents)
+values (
+    sqlpage.uploaded_file_name(''document''),
+    sqlpage.uploaded_file_mime_type(''document''),
+    sqlpage.read_file_as_data_url(sqlpage.uploaded_file_path(''document''))
+);
+```
+'),
+    ('download', '
+### Displaying an image stored in the database
+
+With `inline`, the browser displays the file instead of downloading it,
+so the page can be used as the source of an image: `select ''card'' as component; select ''photo.sql?id=1'' as top_image;`
+
+```sql
+select ''download'' as component, true as inline, ''photo.jpg'' as filename, photo as contents
+from users where id = $id;
+```
+');
@@ -0,0 +1,38 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('zip', 'file-zip', 'Lets the user download several files at once, in a single zip archive.
+
+This is a **header component**: it must be used at the top of the page, before any component that displays something.
+It is useful for actions like *download all the documents of this case*.
+
+Each row that follows the component adds a file to the archive, with the given `name` and `contents`.
+The contents are usually taken from a binary column, or from a [data URL](/functions.sql?function=read_file_as_data_url), like with the [download](?component=download) component.
+
+A row with `''csv'' as component` starts a csv file inside the archive: all the rows that follow it become the lines of the csv file,
+until the next row that has a `component` column. Use `''files'' as component` to go back to adding files.
+The archive can contain several csv files.
+
+The archive is compressed and sent to the browser while the rows are received, so it can be large.
+Files with the same name are numbered, like `report (2).pdf`. If a query fails, the error message is added to the archive in an `error-1.txt` file.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'zip', * FROM (VALUES
+    ('filename', 'The name of the archive, without the .zip extension. Defaults to "export".', 'TEXT', TRUE, TRUE),
+    ('name', 'The path of the file in the archive. Slashes create folders.', 'TEXT', FALSE, FALSE),
+    ('contents', 'The contents of the file: a binary column, a data URL, or a text.', 'TEXT', FALSE, FALSE),
+    ('component', 'Set to ''csv'' to start a csv file, whose lines are the rows that follow. It then accepts a `filename` and a `separator`. Set to ''files'' to go back to adding files.', 'TEXT', FALSE, TRUE)
+) x;
+
+INSERT INTO example(component, description) VALUES
+    ('zip', '
+### Download all the documents of a case
+
+A single archive contains the uploaded documents of the case, and the list of its invoices as a csv file.
+
+```sql
+select ''zip'' as component, ''case_'' || $id as filename;
+
+select file_name as name, contents from documents where case_id = $id;
+
+select ''csv'' as component, ''invoices'' as filename, '';'' as separator;
+select number, amount, paid_at from invoices where case_id = $id;
+```
+');
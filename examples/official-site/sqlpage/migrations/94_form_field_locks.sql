@@ -0,0 +1,38 @@
+INSERT INTO parameter(component, name, description_md, type, top_level, optional) SELECT 'form', * FROM (VALUES
+    ('field_locks', 'The link to a SQL file that tracks which fields of the form are being edited, to warn users when someone else is editing the same record.
+The browser opens a [WebSocket](https://developer.mozilla.org/en-US/docs/Web/API/WebSockets_API) to this file,
+and sends it a message when the user starts or stops editing a field, and every 5 seconds while the form is open.
+In the file, `:field` is the name of the field being edited, and `:editing` is `true` or `false`.
+The file returns the locks held by other users, as rows with a `field` and a `locked_by` column.
+These fields become read-only, with a message saying who is editing them, until their lock has not been returned for 12 seconds.', 'TEXT', TRUE, TRUE)
+);
+
+INSERT INTO example(component, description) VALUES
+    ('form', '### Field locking on shared records
+
+When several people edit the same records, show who is editing which field, to avoid conflicting edits.
+The form tells `field_locks.sql` which field the user is editing:
+
+```sql
+select ''form'' as component, ''field_locks.sql?record='' || $id as field_locks, ''save_customer.sql?id='' || $id as action;
+select ''name'' as name, name as value from customers where id = $id;
+select ''phone'' as name, phone as value from customers where id = $id;
+```
+
+`field_locks.sql` stores the locks in a table, using the session of the user to know who they are,
+and returns the fields of the record that other users are editing:
+
+```sql
+set user = (select username from user_sessions where session_token = sqlpage.cookie(''session_token''));
+
+delete from field_locks
+where (record = $record and username = $user) or updated_at < datetime(''now'', ''-15 seconds'');
+
+insert into field_locks (record, field, username, updated_at)
+select $record, :field, $user, datetime(''now'') where :editing = ''true'';
+
+select field, username as locked_by
+from field_locks
+where record = $record and username <> $user;
+```
+');
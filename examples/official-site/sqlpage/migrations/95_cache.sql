@@ -0,0 +1,49 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('cache', 'clock-bolt', 'Tells browsers how long they can reuse the page, and which pages and files to load in advance.
+
+This is a **header component**: it must be used at the top of the page, before any component that displays something.
+By default, SQLPage pages are not cached: the browser requests them again every time.
+With `max_age`, the browser reuses the page for that number of seconds without requesting it again.
+With `stale_while_revalidate`, it can keep displaying the page after that, while it loads a fresh version in the background.
+
+This is particularly useful on pages that are loaded as fragments of other pages, with the `embed` property of the [card](?component=card) component:
+the fragment is displayed instantly from the cache, and refreshed in the background.
+
+`prefetch` and `preload` send [`Link` headers](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Link)
+that make the browser start loading other pages and files before they are needed:
+`preload` is for files needed by the current page, and `prefetch` for pages the user is likely to open next.
+
+Pages are cached by the browser of each user, unless `shared` is true.
+Do not use `shared` on pages that depend on the user who requests them.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'cache', * FROM (VALUES
+    ('max_age', 'The number of seconds during which the browser can reuse the page without requesting it again.', 'INTEGER', TRUE, TRUE),
+    ('stale_while_revalidate', 'The number of seconds after max_age during which the browser can display the page it has, while it loads a fresh version in the background.', 'INTEGER', TRUE, TRUE),
+    ('stale_if_error', 'The number of seconds after max_age during which the browser can display the page it has if the server fails to answer.', 'INTEGER', TRUE, TRUE),
+    ('shared', 'Let proxies and CDNs cache the page, and send it to all users. By default, only the browser of the user caches it.', 'BOOLEAN', TRUE, TRUE),
+    ('prefetch', 'A link, or a JSON array of links, to pages that the user is likely to open next. The browser loads them in the background when it is idle.', 'TEXT', TRUE, TRUE),
+    ('preload', 'A link, or a JSON array of links, to files needed by the current page, such as images, scripts, or fragments loaded with embed. The browser starts loading them immediately.', 'TEXT', TRUE, TRUE)
+) x;
+
+INSERT INTO example(component, description) VALUES
+    ('cache', '
+### A dashboard made of cached fragments
+
+The dashboard displays cards whose contents are loaded from other pages.
+It starts loading the first fragment while the dashboard itself is received:
+
+```sql
+select ''cache'' as component, ''/sales_card.sql?_sqlpage_embed=1'' as preload;
+select ''card'' as component;
+select ''/sales_card.sql'' as embed;
+select ''/stock_card.sql'' as embed;
+```
+
+`sales_card.sql` can be reused for a minute, and then displayed while its fresh version is loaded, for up to 10 minutes:
+
+```sql
+select ''cache'' as component, 60 as max_age, 600 as stale_while_revalidate;
+select ''big_number'' as component;
+select ''Sales today'' as title, sum(amount) as value from orders where ordered_at >= current_date;
+```
+');
@@ -0,0 +1,67 @@
+INSERT INTO component(name, icon, description, introduced_in_version) VALUES
+    ('event_stream', 'broadcast', 'Keeps the page open, and sends its rows again each time they change, as [server-sent events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events).
+
+This is a **header component**: it must be used at the top of the page, before any other component.
+The page is not displayed as HTML: each row is sent as a JSON object, that the browser receives with an
+[`EventSource`](https://developer.mozilla.org/en-US/docs/Web/API/EventSource) in javascript.
+
+After the page has been executed, SQLPage waits, and then executes the whole page again,
+sending the rows that follow the `event_stream` component as new events.
+The page is executed again after `interval` seconds, or when a notification is sent on `channel`
+with PostgreSQL''s [`NOTIFY`](https://www.postgresql.org/docs/current/sql-notify.html) command.
+This continues until the user leaves the page that displays the events.
+
+Between two executions, the page does not hold a database connection.
+When using `channel`, SQLPage listens to each channel on one dedicated connection, opened with the same `database_url` and `database_password` as the main connection, and shared by all the event streams waiting for that channel. At most 32 channels can be listened to at the same time.', '0.36.0');
+
+INSERT INTO parameter(component, name, description, type, top_level, optional) SELECT 'event_stream', * FROM (VALUES
+    ('interval', 'The number of seconds to wait before executing the page again. Can be a decimal number, like 0.5.', 'REAL', TRUE, TRUE),
+    ('channel', 'The name of a PostgreSQL notification channel. The page is executed again each time a notification is sent on it, with NOTIFY or pg_notify. Only available with PostgreSQL.', 'TEXT', TRUE, TRUE)
+) x;
+
+INSERT INTO example(component, description) VALUES
+    ('event_stream', '
+### A live order counter
+
+`order_count.sql` sends the number of orders every 5 seconds:
+
+```sql
+select ''event_stream'' as component, 5 as interval;
+select count(*) as orders from orders;
+```
+
+The dashboard displays it, and loads a script that updates it without reloading the page:
+
+```sql
+select ''shell'' as component, ''Dashboard'' as title, ''/order_count.js'' as javascript;
+select ''big_number'' as component;
+select ''Orders'' as title, ''order-count'' as id, count(*) as value from orders;
+```
+
+`order_count.js`:
+
+```js
+new EventSource("order_count.sql").onmessage = (event) => {
+  const { orders } = JSON.parse(event.data);
+  document.querySelector("#order-count .h1").textContent = orders;
+};
+```
+'),
+    ('event_stream', '
+### Updates when the data changes
+
+With PostgreSQL, the page can be executed again only when the data changes, instead of at a fixed interval.
+The page that creates orders notifies the event streams:
+
+```sql
+insert into orders(customer, amount) values (:customer, :amount);
+select pg_notify(''new_orders'', :customer);
+```
+
+and the event stream sends the latest orders each time it is notified:
+
+```sql
+select ''event_stream'' as component, ''new_orders'' as channel;
+select customer, amount from orders order by ordered_at desc limit 10;
+```
+');
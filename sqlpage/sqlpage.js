@@ -296,6 +296,214 @@ function sqlpage_form() {
   for (const form of auto_submit_forms) {
     form.addEventListener("change", () => form.submit());
   }
+
+  const spam_protection_tokens = document.querySelectorAll(
+    "input[name=_sqlpage_form_token]:not([data-initialized])",
+  );
+  for (const token_input of spam_protection_tokens) {
+    setup_proof_of_work(token_input);
+  }
+
+  const period_fields = document.querySelectorAll(
+    "[data-sqlpage-period]:not([data-initialized])",
+  );
+  for (const period_field of period_fields) {
+    setup_period_field(period_field);
+  }
+
+  const locked_forms = document.querySelectorAll(
+    "form[data-field-locks]:not([data-initialized])",
+  );
+  for (const form of locked_forms) {
+    setup_field_locks(form);
+  }
+}
+
+const FIELD_LOCKS_HEARTBEAT_MS = 5000;
+/** Fields are unlocked when their lock was not confirmed by the server for this long */
+const FIELD_LOCKS_EXPIRY_MS = 12000;
+
+/**
+ * Tells the SQL file of the field_locks property which field the user is editing, over a WebSocket,
+ * and makes the fields that other users are editing read-only.
+ * The server answers each message with rows like {"field": "name", "locked_by": "Alice"}.
+ */
+function setup_field_locks(form) {
+  form.dataset.initialized = "true";
+  const url = new URL(form.dataset.fieldLocks, window.location.href);
+  url.protocol = url.protocol === "https:" ? "wss:" : "ws:";
+  const locks = new Map();
+  let editing = null;
+  let socket = null;
+
+  const send = () => {
+    if (socket?.readyState !== WebSocket.OPEN) return;
+    socket.send(JSON.stringify({ field: editing, editing: editing !== null }));
+  };
+  const set_locked = (field, locked_by) => {
+    const inputs = form.querySelectorAll(`[name="${CSS.escape(field)}"]`);
+    for (const input of inputs) {
+      input.readOnly = locked_by !== null;
+      input.classList.toggle("border-warning", locked_by !== null);
+      input.title =
+        locked_by === null ? "" : `${locked_by} is editing this field`;
+    }
+  };
+  const connect = () => {
+    socket = new WebSocket(url);
+    socket.addEventListener("open", send);
+    socket.addEventListener("message", (event) => {
+      const { field, locked_by } = JSON.parse(event.data);
+      if (!field || field === editing) return;
+      if (locked_by === null || locked_by === undefined) {
+        locks.delete(field);
+        set_locked(field, null);
+      } else {
+        locks.set(field, Date.now());
+        set_locked(field, String(locked_by));
+      }
+    });
+    socket.addEventListener("close", () => {
+      if (form.isConnected) setTimeout(connect, FIELD_LOCKS_HEARTBEAT_MS);
+    });
+  };
+
+  form.addEventListener("focusin", (event) => {
+    if (!event.target.name || locks.has(event.target.name)) return;
+    editing = event.target.name;
+    send();
+  });
+  form.addEventListener("focusout", () => {
+    editing = null;
+    send();
+  });
+  setInterval(() => {
+    send();
+    for (const [field, confirmed_at] of locks) {
+      if (Date.now() - confirmed_at > FIELD_LOCKS_EXPIRY_MS) {
+        locks.delete(field);
+        set_locked(field, null);
+      }
+    }
+  }, FIELD_LOCKS_HEARTBEAT_MS);
+  connect();
+}
+
+/** Week, month and date range fields also post the first day of the selected period, and the day after its last day */
+function setup_period_field(field) {
+  field.dataset.initialized = "true";
+  const type = field.dataset.sqlpagePeriod;
+  const [value_input, last_input] = field.querySelectorAll(
+    "input:not([type=hidden])",
+  );
+  // The hidden inputs are next to the input group, to keep its rounded corners
+  const container = field.parentElement;
+  const start_input = container.querySelector("[data-period-start]");
+  const end_input = container.querySelector("[data-period-end]");
+  const days_before_monday = period_days_before_monday(field.dataset.weekStart);
+  const update = () => {
+    if (last_input) last_input.min = value_input.value;
+    const bounds = period_bounds(
+      type,
+      value_input.value,
+      last_input?.value,
+      days_before_monday,
+    );
+    if (start_input) start_input.value = bounds?.[0] ?? "";
+    end_input.value = bounds?.[1] ?? "";
+  };
+  field.addEventListener("change", update);
+  update();
+}
+
+const WEEK_DAYS = [
+  "monday",
+  "tuesday",
+  "wednesday",
+  "thursday",
+  "friday",
+  "saturday",
+  "sunday",
+];
+
+/** Number of days between the first day of the week and monday, from the week_start of the field or the language of the page */
+function period_days_before_monday(week_start) {
+  let first_day = WEEK_DAYS.indexOf(week_start?.toLowerCase()) + 1;
+  if (!first_day) {
+    try {
+      const locale = new Intl.Locale(
+        document.documentElement.lang || navigator.language,
+      );
+      first_day = (locale.getWeekInfo?.() ?? locale.weekInfo)?.firstDay;
+    } catch (e) {
+      console.error("Unable to find the first day of the week", e);
+    }
+  }
+  return (8 - (first_day || 1)) % 7;
+}
+
+/** The first day of a period, and the day after its last day, as ISO dates */
+function period_bounds(type, value, last, days_before_monday) {
+  const day = 24 * 3600 * 1000;
+  const iso = (time) => new Date(time).toISOString().slice(0, 10);
+  if (type === "month") {
+    const [year, month] = value.split("-").map(Number);
+    if (!year || !month) return null;
+    return [iso(Date.UTC(year, month - 1, 1)), iso(Date.UTC(year, month, 1))];
+  }
+  if (type === "week") {
+    const match = value.match(/^(\d+)-W(\d+)$/);
+    if (!match) return null;
+    // ISO weeks start on monday, and the first week of the year contains January 4th
+    const january_4 = Date.UTC(+match[1], 0, 4);
+    const first_monday =
+      january_4 - ((new Date(january_4).getUTCDay() + 6) % 7) * day;
+    const start =
+      first_monday + ((match[2] - 1) * 7 - days_before_monday) * day;
+    return [iso(start), iso(start + 7 * day)];
+  }
+  const start = Date.parse(value);
+  const end = Date.parse(last) + day;
+  if (Number.isNaN(start) || Number.isNaN(end) || end <= start) return null;
+  return [value, iso(end)];
+}
+
+/** Forms with spam_protection must solve a proof-of-work challenge before being submitted */
+function setup_proof_of_work(token_input) {
+  token_input.dataset.initialized = "true";
+  const token = token_input.value;
+  const difficulty = +token.split(".")[1];
+  if (!difficulty) return;
+  const form = token_input.form;
+  // Start solving the challenge immediately, while the user fills the form
+  const solution = solve_proof_of_work(token, difficulty);
+  let solved = false;
+  form.addEventListener("submit", async (event) => {
+    if (solved) return;
+    event.preventDefault();
+    const input = document.createElement("input");
+    input.type = "hidden";
+    input.name = "_sqlpage_pow";
+    input.value = await solution;
+    form.appendChild(input);
+    solved = true;
+    form.requestSubmit(event.submitter);
+  });
+}
+
+/** Finds a number n such that sha256(`${token}:${n}`) starts with `difficulty` zero bits */
+async function solve_proof_of_work(token, difficulty) {
+  const encoder = new TextEncoder();
+  for (let n = 0; ; n++) {
+    const data = encoder.encode(`${token}:${n}`);
+    const hash = new Uint8Array(await crypto.subtle.digest("SHA-256", data));
+    let zero_bits = 0;
+    for (const byte of hash) {
+      zero_bits += Math.clz32(byte) - 24;
+      if (byte !== 0) break;
+    }
+    if (zero_bits >= difficulty) return n.toString();
+  }
 }
 
 function get_tabler_color(name) {
@@ -319,6 +527,54 @@ function load_scripts() {
   }
 }
 
+/** When the page is displayed in an iframe (_sqlpage_embed=iframe), keep the parent page informed of its height and of navigation events */
+function sqlpage_iframe() {
+  if (!document.body.hasAttribute("data-sqlpage-iframe")) return;
+  if (window.parent === window || document.body.dataset.sqlpageFramed) return;
+  document.body.dataset.sqlpageFramed = "initialized";
+  // Messages contain the url of the page, so they are only sent to the websites allowed to embed it
+  const origins = JSON.parse(document.body.dataset.sqlpageIframe || "[]") || [];
+  if (origins.length === 0) return;
+  const post = (message) => {
+    for (const origin of origins) {
+      window.parent.postMessage(
+        { ...message, url: window.location.href },
+        origin,
+      );
+    }
+  };
+  new ResizeObserver(() =>
+    post({ type: "sqlpage:resize", height: document.body.scrollHeight }),
+  ).observe(document.body);
+  post({ type: "sqlpage:load" });
+  document.addEventListener("submit", (event) =>
+    post({ type: "sqlpage:submit", action: event.target.action }),
+  );
+  window.addEventListener("message", (event) => {
+    if (event.source !== window.parent) return;
+    if (!origins.includes("*") && !origins.includes(event.origin)) return;
+    if (event.data?.type === "sqlpage:reload") window.location.reload();
+  });
+}
+
+/** Displays the message left by the previous page in the sqlpage_flash cookie, once */
+function sqlpage_flash_message() {
+  const match = document.cookie.match(/(?:^|;\s*)sqlpage_flash=([^;]*)/);
+  if (!match) return;
+  document.cookie = "sqlpage_flash=; Max-Age=0; Path=/; SameSite=Lax";
+  const alert = document.createElement("div");
+  alert.className = "alert alert-success alert-dismissible";
+  alert.setAttribute("role", "alert");
+  alert.textContent = decodeURIComponent(match[1]);
+  const close = document.createElement("a");
+  close.className = "btn-close";
+  close.dataset.bsDismiss = "alert";
+  close.setAttribute("aria-label", "close");
+  alert.appendChild(close);
+  const main = document.getElementById("sqlpage_main_wrapper") || document.body;
+  main.prepend(alert);
+}
+
 function add_init_fn(f) {
   document.addEventListener("DOMContentLoaded", f);
   document.addEventListener("fragment-loaded", f);
@@ -330,6 +586,8 @@ add_init_fn(sqlpage_map);
 add_init_fn(sqlpage_card);
 add_init_fn(sqlpage_form);
 add_init_fn(load_scripts);
+add_init_fn(sqlpage_iframe);
+add_init_fn(sqlpage_flash_message);
 
 function init_bootstrap_components(event) {
   if (window.bootstrap) {
@@ -0,0 +1,76 @@
+/* Embeds SQLPage components in other websites.
+
+<script src="https://my-sqlpage-site.com/sqlpage_embed.js"></script>
+<sqlpage-component src="https://my-sqlpage-site.com/report.sql?year=2024"></sqlpage-component>
+
+SQLPAGE_STYLESHEET and SQLPAGE_CREDENTIALED_ORIGINS are defined by the server when it serves this file.
+*/
+
+const SQLPAGE_EMBED_SCRIPT_URL = document.currentScript?.src || document.baseURI;
+/* The cookies of the visitor are only sent when this website is explicitly allowed to embed pages */
+const SQLPAGE_EMBED_CREDENTIALS = SQLPAGE_CREDENTIALED_ORIGINS.includes(
+  window.location.origin,
+)
+  ? "include"
+  : "omit";
+
+class SQLPageComponent extends HTMLElement {
+  static observedAttributes = ["src"];
+
+  connectedCallback() {
+    this.load();
+  }
+
+  attributeChangedCallback() {
+    if (this.isConnected) this.load();
+  }
+
+  /** The url of the embedded page, with the parameters that make SQLPage render only its components */
+  embedUrl(format) {
+    const url = new URL(this.getAttribute("src"), document.baseURI);
+    url.searchParams.set("_sqlpage_embed", format);
+    return url;
+  }
+
+  /** Fetches the rows returned by the embedded page as JSON, without rendering them */
+  async fetchData() {
+    const response = await fetch(this.embedUrl("json"), {
+      credentials: SQLPAGE_EMBED_CREDENTIALS,
+    });
+    return response.json();
+  }
+
+  async load() {
+    if (!this.hasAttribute("src")) return;
+    const root = this.shadowRoot || this.attachShadow({ mode: "open" });
+    const response = await fetch(this.embedUrl(""), {
+      credentials: SQLPAGE_EMBED_CREDENTIALS,
+    });
+    const html = await response.text();
+    const stylesheet = new URL(SQLPAGE_STYLESHEET, SQLPAGE_EMBED_SCRIPT_URL);
+    // The theme attribute makes the css variables of the stylesheet apply inside the shadow root
+    root.innerHTML = `<link rel="stylesheet" href="${stylesheet}">
+      <div data-bs-theme="${this.getAttribute("theme") || "light"}" class="page-body">${html}</div>`;
+    // The scripts of the components do not run inside the shadow root
+    if (root.querySelector("[data-pre-init=chart], [data-pre-init=map]")) {
+      console.warn(
+        `${this.getAttribute("src")} contains charts or maps, which cannot be displayed in a sqlpage-component. Embed it in an iframe with _sqlpage_embed=iframe instead.`,
+      );
+    }
+    this.dispatchEvent(new CustomEvent("sqlpage:load", { bubbles: true }));
+  }
+}
+
+/* Resizes <iframe src="https://my-sqlpage-site.com/report.sql?_sqlpage_embed=iframe"> elements to fit their contents */
+window.addEventListener("message", (event) => {
+  if (event.data?.type !== "sqlpage:resize") return;
+  for (const iframe of document.querySelectorAll("iframe")) {
+    if (iframe.contentWindow === event.source) {
+      iframe.style.height = `${event.data.height}px`;
+    }
+  }
+});
+
+if (!customElements.get("sqlpage-component")) {
+  customElements.define("sqlpage-component", SQLPageComponent);
+}
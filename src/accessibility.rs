@@ -0,0 +1,222 @@
+//! Development-time accessibility checks for rendered components.
+//!
+//! Component HTML is generated from templates, so authors cannot easily fix accessibility problems
+//! in the markup itself. When the `accessibility_audit` configuration option is enabled in development mode,
+//! every row sent to a component is inspected, and warnings pointing to the offending query columns are logged.
+//!
+//! The audit currently detects:
+//!  - images rendered without a textual alternative,
+//!  - form fields and icon-only buttons without a label,
+//!  - theme colors that do not have enough contrast with the page background.
+
+use serde_json::Value as JsonValue;
+
+/// Minimum contrast ratio recommended by WCAG 2.1 for large text and user interface components.
+const MIN_CONTRAST_RATIO: f64 = 3.0;
+
+/// Background colors of the default light and dark themes.
+const LIGHT_BACKGROUND: u32 = 0x00ff_ffff;
+const DARK_BACKGROUND: u32 = 0x0015_1f2c;
+
+/// Named colors available in components, with their value in the default theme.
+const THEME_COLORS: &[(&str, u32)] = &[
+    ("blue", 0x0020_6bc4),
+    ("azure", 0x0042_99e1),
+    ("indigo", 0x0042_63eb),
+    ("purple", 0x00ae_3ec9),
+    ("pink", 0x00d6_336c),
+    ("red", 0x00d6_3939),
+    ("orange", 0x00f7_6707),
+    ("yellow", 0x00f5_9f00),
+    ("lime", 0x0074_b816),
+    ("green", 0x002f_b344),
+    ("teal", 0x000c_a678),
+    ("cyan", 0x0017_a2b8),
+    ("dark", 0x0018_2433),
+    ("black", 0x0000_0000),
+    ("white", 0x00ff_ffff),
+];
+
+/// Images displayed by components, and the properties used as their alternative text.
+/// (component, image property, properties that can provide the alt text)
+const IMAGE_COLUMNS: &[(&str, &str, &[&str])] = &[
+    ("card", "top_image", &["title"]),
+    ("hero", "image", &["title"]),
+    ("list", "image_url", &["title"]),
+    ("datagrid", "image_url", &["title"]),
+    ("button", "image", &["title", "tooltip"]),
+    ("shell", "image", &["title"]),
+    ("carousel", "image", &["title"]),
+];
+
+/// Form field types that are not displayed to the user, and thus do not need a label.
+const UNLABELED_FIELD_TYPES: &[&str] = &["hidden", "header"];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccessibilityAudit {
+    dark_theme: bool,
+}
+
+impl AccessibilityAudit {
+    /// Creates an audit for a page, using the properties of its shell to determine the page background.
+    #[must_use]
+    pub fn new(shell_properties: &JsonValue) -> Self {
+        let dark_theme = shell_properties.get("theme").and_then(JsonValue::as_str) == Some("dark");
+        Self { dark_theme }
+    }
+
+    /// Returns a list of human-readable accessibility problems found in a row sent to a component.
+    #[must_use]
+    pub fn check_row(self, component: &str, row: &JsonValue) -> Vec<String> {
+        let Some(row) = row.as_object() else {
+            return Vec::new();
+        };
+        let is_set = |key: &str| row.get(key).is_some_and(is_non_empty);
+        let mut problems = Vec::new();
+
+        for &(image_component, image_column, alt_columns) in IMAGE_COLUMNS {
+            if component == image_component
+                && is_set(image_column)
+                && !alt_columns.iter().any(|c| is_set(c))
+            {
+                problems.push(format!(
+                    "the '{image_column}' column displays an image without alternative text. \
+                    Set the '{}' column to describe the image.",
+                    alt_columns.join("' or '")
+                ));
+            }
+        }
+
+        if component == "form" && is_set("name") {
+            let field_type = row
+                .get("type")
+                .and_then(JsonValue::as_str)
+                .unwrap_or("text");
+            let has_label = match row.get("label") {
+                Some(label) => is_non_empty(label),
+                None => true, // the name is used as a label
+            };
+            if !has_label && !UNLABELED_FIELD_TYPES.contains(&field_type) {
+                problems.push(format!(
+                    "the '{}' form field has an empty 'label' column. Screen readers will not be able to describe it.",
+                    row.get("name").and_then(JsonValue::as_str).unwrap_or_default()
+                ));
+            }
+        }
+
+        if component == "button" && is_set("icon") && !is_set("title") && !is_set("tooltip") {
+            problems.push(
+                "a button only displays an icon. Set the 'title' or 'tooltip' column to give it an accessible name."
+                    .to_string(),
+            );
+        }
+
+        for (column, value) in row {
+            if column != "color" && !column.ends_with("_color") {
+                continue;
+            }
+            let Some(color_name) = value.as_str() else {
+                continue;
+            };
+            if let Some(ratio) = self.insufficient_contrast(color_name) {
+                problems.push(format!(
+                    "the '{column}' column uses the color '{color_name}', which has a contrast ratio of {ratio:.1}:1 \
+                    with the {} page background. At least {MIN_CONTRAST_RATIO}:1 is recommended.",
+                    if self.dark_theme { "dark" } else { "light" }
+                ));
+            }
+        }
+        problems
+    }
+
+    fn insufficient_contrast(self, color_name: &str) -> Option<f64> {
+        let &(_, color) = THEME_COLORS.iter().find(|(name, _)| *name == color_name)?;
+        let background = if self.dark_theme {
+            DARK_BACKGROUND
+        } else {
+            LIGHT_BACKGROUND
+        };
+        let ratio = contrast_ratio(color, background);
+        (ratio < MIN_CONTRAST_RATIO).then_some(ratio)
+    }
+}
+
+fn is_non_empty(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::String(s) => !s.trim().is_empty(),
+        _ => true,
+    }
+}
+
+/// [Relative luminance](https://www.w3.org/TR/WCAG21/#dfn-relative-luminance) of an RGB color
+fn relative_luminance(rgb: u32) -> f64 {
+    let channel = |shift: u32| {
+        let c = f64::from((rgb >> shift) & 0xff) / 255.0;
+        if c <= 0.039_28 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(16) + 0.7152 * channel(8) + 0.0722 * channel(0)
+}
+
+/// [Contrast ratio](https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio) between two RGB colors
+fn contrast_ratio(a: u32, b: u32) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_missing_alt_text() {
+        let audit = AccessibilityAudit::default();
+        let problems = audit.check_row("card", &json!({"top_image": "cat.jpg"}));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("'top_image'"), "{problems:?}");
+        let ok = audit.check_row("card", &json!({"top_image": "cat.jpg", "title": "A cat"}));
+        assert!(ok.is_empty(), "{ok:?}");
+    }
+
+    #[test]
+    fn test_missing_label() {
+        let audit = AccessibilityAudit::default();
+        assert_eq!(
+            audit
+                .check_row("form", &json!({"name": "age", "label": ""}))
+                .len(),
+            1
+        );
+        assert!(audit.check_row("form", &json!({"name": "age"})).is_empty());
+        assert!(audit
+            .check_row(
+                "form",
+                &json!({"name": "id", "type": "hidden", "label": ""})
+            )
+            .is_empty());
+        assert_eq!(audit.check_row("button", &json!({"icon": "edit"})).len(), 1);
+    }
+
+    #[test]
+    fn test_contrast() {
+        let light = AccessibilityAudit::new(&json!({"component": "shell"}));
+        assert_eq!(
+            light.check_row("alert", &json!({"color": "yellow"})).len(),
+            1
+        );
+        assert!(light
+            .check_row("alert", &json!({"color": "red"}))
+            .is_empty());
+        let dark = AccessibilityAudit::new(&json!({"component": "shell", "theme": "dark"}));
+        assert_eq!(dark.check_row("alert", &json!({"color": "black"})).len(), 1);
+        assert!(dark
+            .check_row("alert", &json!({"color": "yellow"}))
+            .is_empty());
+        assert!((contrast_ratio(0, 0x00ff_ffff) - 21.0).abs() < 0.01);
+    }
+}
@@ -1,4 +1,7 @@
+use crate::webserver::alerts::AlertDefinition;
+use crate::webserver::client_ip::IpNetwork;
 use crate::webserver::content_security_policy::ContentSecurityPolicyTemplate;
+use crate::webserver::rate_limit::{default_window_seconds, PathRateLimit};
 use crate::webserver::routing::RoutingConfig;
 use anyhow::Context;
 use clap::Parser;
@@ -36,10 +39,31 @@ pub enum Commands {
         /// Name of the migration.
         migration_name: String,
     },
+    /// Render pages to static files that can be hosted without a running sqlpage server.
+    Render {
+        /// The directory where the rendered pages and assets are written.
+        #[clap(short, long)]
+        out: PathBuf,
+        /// Pages to render, with optional query parameters, like "report.sql?year=2024".
+        /// All the .sql files in the web root are rendered if no page is given.
+        pages: Vec<String>,
+    },
+    /// Document the pages of the site: their titles, the parameters they use, and the comments at the start of their files.
+    Docs {
+        /// The file where the documentation is written.
+        /// Defaults to a `sqlpage_docs.sql` page in the web root, or to the standard output with --json.
+        #[clap(short, long)]
+        out: Option<PathBuf>,
+        /// Write the documentation as JSON, instead of a page of the site.
+        #[clap(long)]
+        json: bool,
+    },
 }
 
 #[cfg(not(feature = "lambda-web"))]
 const DEFAULT_DATABASE_FILE: &str = "sqlpage.db";
+/// Encryption keys are hashed, so they can be any string, but short keys are easy to guess
+const MIN_ENCRYPTION_KEY_LENGTH: usize = 32;
 
 impl AppConfig {
     pub fn from_cli(cli: &Cli) -> anyhow::Result<Self> {
@@ -150,6 +174,24 @@ impl AppConfig {
             }
         }
         anyhow::ensure!(self.max_pending_rows > 0, "max_pending_rows cannot be null");
+        if let Some(timeout) = self.request_timeout_seconds {
+            anyhow::ensure!(
+                timeout > 0.0 && timeout.is_finite(),
+                "request_timeout_seconds must be a positive number of seconds"
+            );
+        }
+        if self.preview_databases_directory.is_some() {
+            anyhow::ensure!(
+                self.database_url.starts_with("sqlite:"),
+                "Preview databases are SQLite files: preview_databases_directory can only be used with a SQLite database_url"
+            );
+        }
+        if let Some(key) = &self.encryption_key {
+            anyhow::ensure!(
+                key.len() >= MIN_ENCRYPTION_KEY_LENGTH,
+                "encryption_key must be at least {MIN_ENCRYPTION_KEY_LENGTH} characters long"
+            );
+        }
         Ok(())
     }
 }
@@ -229,6 +271,17 @@ pub struct AppConfig {
     #[serde(default = "default_oidc_scopes")]
     pub oidc_scopes: String,
 
+    /// Shared secret used to verify the signature of JSON Web Tokens signed with HMAC (HS256, HS384, HS512),
+    /// in `sqlpage.jwt_claims`.
+    pub jwt_secret: Option<String>,
+    /// URL of the JSON Web Key Set containing the public keys used to verify JSON Web Tokens
+    /// in `sqlpage.jwt_claims`. The keys are downloaded when the server starts.
+    pub jwt_jwks_url: Option<String>,
+
+    /// Secret used by `sqlpage.encrypt` and `sqlpage.decrypt` to protect values sent to the users.
+    /// Changing it invalidates all the values encrypted before.
+    pub encryption_key: Option<String>,
+
     /// A domain name to use for the HTTPS server. If this is set, the server will perform all the necessary
     /// steps to set up an HTTPS server automatically. All you need to do is point your domain name to the
     /// server's IP address.
@@ -254,6 +307,10 @@ pub struct AppConfig {
     #[serde(default = "default_https_acme_directory_url")]
     pub https_acme_directory_url: String,
 
+    /// PEM file containing the certificate authorities that sign client certificates.
+    /// When set, the HTTPS server asks clients for a certificate, and verifies it against these authorities.
+    pub https_client_ca_certificate: Option<PathBuf>,
+
     /// Whether we should run in development or production mode. Used to determine
     /// whether to show error messages to the user.
     #[serde(default)]
@@ -274,6 +331,10 @@ pub struct AppConfig {
     #[serde(default = "default_max_pending_rows")]
     pub max_pending_rows: usize,
 
+    /// Maximum number of seconds spent generating a page. When it is reached, the statement
+    /// being executed is cancelled, with all the operations it started, and the page ends with an error.
+    pub request_timeout_seconds: Option<f64>,
+
     /// Whether to compress the http response body when the client supports it.
     #[serde(default = "default_compress_responses")]
     pub compress_responses: bool,
@@ -300,6 +361,115 @@ pub struct AppConfig {
 
     #[serde(default = "default_markdown_allow_dangerous_protocol")]
     pub markdown_allow_dangerous_protocol: bool,
+
+    /// Whether to log warnings about accessibility problems (missing labels, missing alt text,
+    /// low contrast colors) in the rows sent to components. Only used in development mode.
+    #[serde(default)]
+    pub accessibility_audit: bool,
+
+    /// Whether adding `?_sqlpage_explain` to the URL of a page displays its SQL statements,
+    /// the values of their parameters and their query plans, instead of executing them.
+    /// Ignored in production.
+    #[serde(default)]
+    pub allow_explain_mode: bool,
+
+    /// Origins (like `https://intranet.example.com`) of the websites allowed to embed pages from this site
+    /// using the `sqlpage-component` custom element. `*` allows any website.
+    #[serde(default)]
+    pub embed_allowed_origins: Vec<String>,
+
+    /// Names of the environment variables that can be read with `sqlpage.environment_variable`.
+    /// A trailing `*` matches any suffix (`MYAPP_*`). When not set, all environment variables can be read.
+    #[serde(default)]
+    pub allowed_environment_variables: Option<Vec<String>>,
+
+    /// Minimum number of seconds between the display of a form with `spam_protection` and its submission.
+    /// Faster submissions are rejected, since they are most likely sent by robots.
+    #[serde(default = "default_form_min_fill_seconds")]
+    pub form_min_fill_seconds: u64,
+
+    /// Difficulty of the proof-of-work challenge that browsers solve before submitting a form with `spam_protection`,
+    /// as a number of leading zero bits in a SHA-256 hash. Each additional bit doubles the work. 0 disables the challenge.
+    #[serde(default = "default_form_proof_of_work_difficulty")]
+    pub form_proof_of_work_difficulty: u8,
+
+    /// Pages that only accept form submissions with a spam protection token,
+    /// even before a protected form submitted to them has been displayed. A trailing `*` matches any suffix.
+    #[serde(default)]
+    pub spam_protected_paths: Vec<String>,
+
+    /// Captcha service used by forms with the `captcha` property: `turnstile` (Cloudflare) or `hcaptcha`.
+    #[serde(default)]
+    pub captcha_provider: Option<CaptchaProvider>,
+
+    /// Public key of the website, given by the captcha provider
+    #[serde(default)]
+    pub captcha_site_key: Option<String>,
+
+    /// Secret key used to verify captcha responses with the captcha provider
+    #[serde(default)]
+    pub captcha_secret_key: Option<String>,
+
+    /// Addresses (or CIDR ranges) of the reverse proxies allowed to set the `Forwarded` and `X-Forwarded-For` headers.
+    /// Requests from these proxies get the client address from these headers.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNetwork>,
+
+    /// SQL queries evaluated periodically, that notify a webhook when they start and stop returning rows.
+    #[serde(default)]
+    pub alerts: Vec<AlertDefinition>,
+
+    /// Secret token that gives access to the administration API under `/_sqlpage/admin/`.
+    /// The API is disabled when no token is set.
+    #[serde(default)]
+    pub admin_api_token: Option<String>,
+
+    /// Directory of the `SQLite` databases of the previews, relative to the configuration directory.
+    /// Requests with an `X-SQLPage-Preview` header use the database of the preview it names.
+    /// Previews are disabled when it is not set.
+    #[serde(default)]
+    pub preview_databases_directory: Option<PathBuf>,
+
+    /// Domain whose subdomains select a preview: `my-branch.preview.example.com` uses the preview `my-branch`.
+    #[serde(default)]
+    pub preview_domain: Option<String>,
+
+    /// Maximum number of preview databases open at the same time.
+    /// The unused ones are closed to open new ones.
+    #[serde(default = "default_max_preview_databases")]
+    pub max_preview_databases: usize,
+
+    /// URLs from which `COPY` statements can download the data to import.
+    /// A URL is allowed if it has the same scheme, host and port as one of these, and its path is below the allowed path.
+    #[serde(default)]
+    pub import_url_allowlist: Vec<String>,
+
+    /// Answer POST requests that display a page without errors with a `303 See Other` redirect,
+    /// so that reloading the page does not submit the form again.
+    #[serde(default)]
+    pub post_redirect_get: bool,
+
+    /// Where browsers are redirected after a POST request, when `post_redirect_get` is enabled.
+    /// By default, they go back to the page that submitted the form.
+    #[serde(default)]
+    pub post_redirect_target: Option<String>,
+
+    /// Message displayed once on the page the browser is redirected to after a POST request
+    #[serde(default)]
+    pub post_redirect_message: Option<String>,
+
+    /// Maximum number of requests each client IP address can make in each window of `rate_limit_window_seconds`.
+    /// Requests are not limited when it is not set.
+    #[serde(default)]
+    pub rate_limit_requests: Option<u32>,
+
+    /// Duration of the windows in which the requests of each client are counted
+    #[serde(default = "default_window_seconds")]
+    pub rate_limit_window_seconds: u64,
+
+    /// Stricter limits on some paths, like `/login.sql`, counted separately from `rate_limit_requests`.
+    #[serde(default)]
+    pub rate_limits: Vec<PathRateLimit>,
 }
 
 impl AppConfig {
@@ -500,7 +670,7 @@ fn create_default_database(configuration_directory: &Path) -> String {
 }
 
 #[cfg(any(test, not(feature = "lambda-web")))]
-fn encode_uri(path: &Path) -> std::borrow::Cow<str> {
+pub(crate) fn encode_uri(path: &Path) -> std::borrow::Cow<'_, str> {
     const ASCII_SET: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
         .remove(b'-')
         .remove(b'_')
@@ -566,6 +736,18 @@ fn default_markdown_allow_dangerous_protocol() -> bool {
     false
 }
 
+fn default_form_min_fill_seconds() -> u64 {
+    2
+}
+
+fn default_form_proof_of_work_difficulty() -> u8 {
+    14
+}
+
+fn default_max_preview_databases() -> usize {
+    16
+}
+
 fn default_oidc_client_id() -> String {
     "sqlpage".to_string()
 }
@@ -587,6 +769,13 @@ impl DevOrProd {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaProvider {
+    Turnstile,
+    Hcaptcha,
+}
+
 #[must_use]
 pub fn test_database_url() -> String {
     std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string())
@@ -0,0 +1,277 @@
+//! Statistics about the columns of a query, displayed by the `profile` component.
+//!
+//! The rows sent to the component are not displayed. Instead, a random sample of at most `sample_size` rows
+//! is kept in memory while they are received, and when the component is closed, one row of statistics
+//! is rendered for each column: its type, the percentage of null values, the number of distinct values,
+//! the smallest and largest values, and a histogram of the values.
+
+use anyhow::Context;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_SAMPLE_SIZE: usize = 10_000;
+const DEFAULT_BINS: usize = 10;
+
+pub struct DataProfile {
+    properties: JsonValue,
+    sample_size: usize,
+    bins: usize,
+    row_count: usize,
+    sample: Vec<Map<String, JsonValue>>,
+    /// Column names, in the order in which they first appeared in the rows
+    columns: Vec<String>,
+}
+
+impl DataProfile {
+    /// Creates an empty profile, configured by the top-level properties of the component
+    pub fn new(properties: JsonValue) -> anyhow::Result<Self> {
+        let sample_size =
+            positive_integer(&properties, "sample_size")?.unwrap_or(DEFAULT_SAMPLE_SIZE);
+        let bins = positive_integer(&properties, "bins")?.unwrap_or(DEFAULT_BINS);
+        Ok(Self {
+            properties,
+            sample_size,
+            bins,
+            row_count: 0,
+            sample: Vec::new(),
+            columns: Vec::new(),
+        })
+    }
+
+    /// Adds a row to the profile. Once `sample_size` rows have been received,
+    /// each new row replaces a random row of the sample, so that all the rows have the same chance to be in it.
+    pub fn add_row(&mut self, row: &JsonValue) {
+        let Some(row) = row.as_object() else {
+            return;
+        };
+        for column in row.keys() {
+            if !self.columns.contains(column) {
+                self.columns.push(column.clone());
+            }
+        }
+        self.row_count += 1;
+        if self.sample.len() < self.sample_size {
+            self.sample.push(row.clone());
+        } else {
+            let position = rand::random_range(0..self.row_count);
+            if let Some(sampled) = self.sample.get_mut(position) {
+                sampled.clone_from(row);
+            }
+        }
+    }
+
+    /// The properties to render the component with: the ones it was opened with,
+    /// and the number of rows that were received and sampled
+    #[must_use]
+    pub fn properties(&self) -> JsonValue {
+        let mut properties = match &self.properties {
+            JsonValue::Object(properties) => properties.clone(),
+            _ => Map::new(),
+        };
+        properties.insert("row_count".into(), self.row_count.into());
+        properties.insert("sampled_rows".into(), self.sample.len().into());
+        properties.insert(
+            "sampled".into(),
+            (self.sample.len() < self.row_count).into(),
+        );
+        JsonValue::Object(properties)
+    }
+
+    /// One row of statistics for each column
+    #[must_use]
+    pub fn column_statistics(&self) -> Vec<JsonValue> {
+        self.columns
+            .iter()
+            .map(|column| {
+                let values: Vec<&JsonValue> = self
+                    .sample
+                    .iter()
+                    .map(|row| row.get(column).unwrap_or(&JsonValue::Null))
+                    .collect();
+                column_statistics(column, &values, self.bins)
+            })
+            .collect()
+    }
+}
+
+fn positive_integer(properties: &JsonValue, name: &str) -> anyhow::Result<Option<usize>> {
+    let value = match properties.get(name) {
+        None | Some(JsonValue::Null) => return Ok(None),
+        Some(JsonValue::String(s)) => s.parse().ok(),
+        Some(value) => value.as_u64().and_then(|n| usize::try_from(n).ok()),
+    };
+    value
+        .filter(|&n| n > 0)
+        .map(Some)
+        .with_context(|| format!("The profile component's {name} must be a positive integer"))
+}
+
+fn column_statistics(column: &str, values: &[&JsonValue], bins: usize) -> JsonValue {
+    let non_null: Vec<&JsonValue> = values.iter().copied().filter(|v| !v.is_null()).collect();
+    let numbers: Option<Vec<f64>> = non_null.iter().map(|v| as_number(v)).collect();
+    let column_type = if non_null.is_empty() {
+        "empty"
+    } else if non_null.iter().all(|v| v.is_boolean()) {
+        "boolean"
+    } else if numbers.is_some() {
+        "number"
+    } else {
+        "text"
+    };
+    let texts: Vec<String> = non_null.iter().map(|v| as_text(v)).collect();
+    let distinct = texts.iter().collect::<HashSet<_>>().len();
+    let (min, max, histogram) = match numbers {
+        Some(numbers) if column_type == "number" => {
+            let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            (
+                json!(min),
+                json!(max),
+                number_histogram(&numbers, min, max, bins),
+            )
+        }
+        _ => (
+            texts.iter().min().map_or(JsonValue::Null, |s| json!(s)),
+            texts.iter().max().map_or(JsonValue::Null, |s| json!(s)),
+            frequent_values(&texts, bins),
+        ),
+    };
+    json!({
+        "column": column,
+        "type": column_type,
+        "count": non_null.len(),
+        "null_percent": percent(values.len() - non_null.len(), values.len()),
+        "distinct": distinct,
+        "min": min,
+        "max": max,
+        "histogram": histogram,
+    })
+}
+
+fn as_number(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(n) => n.as_f64(),
+        JsonValue::String(s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn as_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (part as f64 * 1000.0 / total as f64).round() / 10.0
+}
+
+/// Counts the numbers in `bins` intervals of the same width between the smallest and largest values
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn number_histogram(numbers: &[f64], min: f64, max: f64, bins: usize) -> JsonValue {
+    let bins = if max > min { bins } else { 1 };
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0; bins];
+    for &n in numbers {
+        let bin = if width > 0.0 {
+            (((n - min) / width) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        counts[bin] += 1;
+    }
+    let bars = counts.iter().enumerate().map(|(i, &count)| {
+        let start = min + width * i as f64;
+        (format!("{start} – {}", start + width), count)
+    });
+    histogram_bars(bars, numbers.len())
+}
+
+/// The `limit` most frequent values, the most frequent first
+fn frequent_values(texts: &[String], limit: usize) -> JsonValue {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for text in texts {
+        *counts.entry(text).or_default() += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    let bars = counts
+        .into_iter()
+        .take(limit)
+        .map(|(label, count)| (label.to_string(), count));
+    histogram_bars(bars, texts.len())
+}
+
+/// The bars of a histogram, with their count, their percentage of the total,
+/// and their height as a percentage of the highest bar
+fn histogram_bars(bars: impl Iterator<Item = (String, usize)>, total: usize) -> JsonValue {
+    let bars: Vec<(String, usize)> = bars.collect();
+    let highest = bars.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    bars.into_iter()
+        .map(|(label, count)| {
+            json!({
+                "label": label,
+                "count": count,
+                "percent": percent(count, total),
+                "height": percent(count, highest),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_column_statistics() {
+    let mut profile = DataProfile::new(json!({"component": "profile", "bins": 2})).unwrap();
+    for row in [
+        json!({"age": 20, "name": "Ophir", "active": true}),
+        json!({"age": 40, "name": "Max", "active": false}),
+        json!({"age": null, "name": "Max"}),
+        json!({"age": "30", "name": "Lea", "active": true}),
+    ] {
+        profile.add_row(&row);
+    }
+    let statistics = profile.column_statistics();
+    assert_eq!(
+        statistics[0],
+        json!({
+            "column": "age", "type": "number", "count": 3, "null_percent": 25.0, "distinct": 3,
+            "min": 20.0, "max": 40.0,
+            "histogram": [
+                {"label": "20 – 30", "count": 1, "percent": 33.3, "height": 50.0},
+                {"label": "30 – 40", "count": 2, "percent": 66.7, "height": 100.0},
+            ]
+        })
+    );
+    assert_eq!(statistics[1]["type"], "text");
+    assert_eq!(statistics[1]["min"], "Lea");
+    assert_eq!(
+        statistics[1]["histogram"][0],
+        json!({"label": "Max", "count": 2, "percent": 50.0, "height": 100.0})
+    );
+    assert_eq!(statistics[2]["type"], "boolean");
+    assert_eq!(statistics[2]["null_percent"], 25.0);
+    assert_eq!(profile.properties()["sampled"], false);
+}
+
+#[test]
+fn test_sampling() {
+    let mut profile = DataProfile::new(json!({"sample_size": "10"})).unwrap();
+    for i in 0..1000 {
+        profile.add_row(&json!({ "id": i }));
+    }
+    let properties = profile.properties();
+    assert_eq!(properties["row_count"], 1000);
+    assert_eq!(properties["sampled_rows"], 10);
+    assert_eq!(properties["sampled"], true);
+    assert_eq!(profile.column_statistics()[0]["count"], 10);
+    assert!(DataProfile::new(json!({"sample_size": 0})).is_err());
+}
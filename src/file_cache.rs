@@ -9,7 +9,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{
     AtomicU64,
-    Ordering::{Acquire, Release},
+    Ordering::{Acquire, Relaxed, Release},
 };
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -73,6 +73,9 @@ pub struct FileCache<T: AsyncFromStrWithState> {
     /// Files that are loaded at the beginning of the program,
     /// and used as fallback when there is no match for the request in the file system
     static_files: HashMap<PathBuf, Cached<T>>,
+    /// Number of times a file was served from the cache, and number of times it had to be loaded and parsed
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl<T: AsyncFromStrWithState> FileStore for FileCache<T> {
@@ -93,6 +96,8 @@ impl<T: AsyncFromStrWithState> FileCache<T> {
         Self {
             cache: Arc::default(),
             static_files: HashMap::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
@@ -102,6 +107,33 @@ impl<T: AsyncFromStrWithState> FileCache<T> {
         self.static_files.insert(path, Cached::new(contents));
     }
 
+    /// Returns the number of cache hits and misses since the server started
+    pub fn hits_and_misses(&self) -> (u64, u64) {
+        (self.hits.load(Relaxed), self.misses.load(Relaxed))
+    }
+
+    /// Lists the files loaded from the file system, with the last time their freshness was checked
+    pub async fn cached_files(&self) -> Vec<(PathBuf, DateTime<Utc>)> {
+        let mut files: Vec<_> = self
+            .cache
+            .read()
+            .await
+            .iter()
+            .map(|(path, cached)| (path.clone(), cached.last_check_time()))
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Removes all the files loaded from the file system, so that they are loaded again at their next use.
+    /// Static files are kept. Returns the number of removed files.
+    pub async fn clear(&self) -> usize {
+        let mut cache = self.cache.write().await;
+        let count = cache.len();
+        cache.clear();
+        count
+    }
+
     /// Gets a file from the cache, or loads it from the file system if it's not there
     /// This is a privileged operation; it should not be used for user-provided paths
     pub async fn get(&self, app_state: &AppState, path: &Path) -> anyhow::Result<Arc<T>> {
@@ -124,6 +156,7 @@ impl<T: AsyncFromStrWithState> FileCache<T> {
                     "Cache answer without filesystem lookup for {}",
                     path.display()
                 );
+                self.hits.fetch_add(1, Relaxed);
                 return Ok(Arc::clone(&cached.content));
             }
             match app_state
@@ -137,6 +170,7 @@ impl<T: AsyncFromStrWithState> FileCache<T> {
                         path.display()
                     );
                     cached.update_check_time();
+                    self.hits.fetch_add(1, Relaxed);
                     return Ok(Arc::clone(&cached.content));
                 }
                 Ok(true) => log::trace!("{} was changed, updating cache...", path.display()),
@@ -148,6 +182,7 @@ impl<T: AsyncFromStrWithState> FileCache<T> {
             }
         }
         // Read lock is released
+        self.misses.fetch_add(1, Relaxed);
         log::trace!("Loading and parsing {}", path.display());
         let file_contents = app_state
             .file_system
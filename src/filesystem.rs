@@ -104,6 +104,39 @@ impl FileSystem {
         }
     }
 
+    /// Opens a file of the web root that is on the local disk, with its size, to read only a part of it.
+    /// Returns None when the file is not on the disk, but may be in the database.
+    pub(crate) async fn open_local_file(
+        &self,
+        app_state: &AppState,
+        path: &Path,
+    ) -> anyhow::Result<Option<(tokio::fs::File, u64)>> {
+        let local_path = self.safe_local_path(app_state, path, false)?;
+        let file = match tokio::fs::File::open(&local_path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound && self.db_fs_queries.is_some() => {
+                return Ok(None)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(ErrorWithStatus {
+                    status: actix_web::http::StatusCode::NOT_FOUND,
+                }
+                .into())
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Unable to read local file {}", path.display()))
+            }
+        };
+        let metadata = file.metadata().await?;
+        anyhow::ensure!(
+            metadata.is_file(),
+            "Unable to read local file {}: it is not a file",
+            path.display()
+        );
+        Ok(Some((file, metadata.len())))
+    }
+
     fn safe_local_path(
         &self,
         app_state: &AppState,
@@ -0,0 +1,514 @@
+//! Nodes and edges displayed by the `graph` component, placed with a force-directed layout.
+//!
+//! Rows with an `id` are nodes, and rows with a `source` and a `target` are edges between them,
+//! so that the nodes and the edges can be selected by two different queries.
+//! The layout is computed on the server when the component is closed, and the graph is rendered as an SVG image.
+//! When there are more than `max_nodes` nodes, the nodes of each `group` are merged into a single node,
+//! and if there are still too many, only the most connected nodes are displayed.
+
+use anyhow::{bail, Context};
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+
+const DEFAULT_MAX_NODES: usize = 300;
+const DEFAULT_HEIGHT: f64 = 500.0;
+const WIDTH: f64 = 1000.0;
+/// Free space around the graph, for the labels of the nodes
+const MARGIN: f64 = 40.0;
+/// Limits the time spent computing the layout of large graphs
+const MAX_FORCE_COMPUTATIONS: usize = 20_000_000;
+const MAX_ITERATIONS: usize = 300;
+const MIN_ITERATIONS: usize = 30;
+const COLORS: [&str; 10] = [
+    "blue", "green", "orange", "purple", "red", "cyan", "yellow", "pink", "teal", "indigo",
+];
+
+struct Node {
+    id: String,
+    properties: Map<String, JsonValue>,
+    /// Number of nodes merged into this one, when it represents a group
+    merged_nodes: Option<usize>,
+}
+
+struct Edge {
+    source: usize,
+    target: usize,
+    properties: Map<String, JsonValue>,
+    /// Number of edges merged into this one, between the nodes of two groups
+    merged_edges: usize,
+}
+
+pub struct NetworkGraph {
+    properties: JsonValue,
+    max_nodes: usize,
+    height: f64,
+    nodes: Vec<Node>,
+    node_indices: HashMap<String, usize>,
+    edges: Vec<Edge>,
+}
+
+impl NetworkGraph {
+    /// Creates an empty graph, configured by the top-level properties of the component
+    pub fn new(properties: JsonValue) -> anyhow::Result<Self> {
+        let max_nodes = positive_number(&properties, "max_nodes")?.map_or(DEFAULT_MAX_NODES, |n| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let n = n as usize;
+            n.max(1)
+        });
+        let height = positive_number(&properties, "height")?.unwrap_or(DEFAULT_HEIGHT);
+        Ok(Self {
+            properties,
+            max_nodes,
+            height,
+            nodes: Vec::new(),
+            node_indices: HashMap::new(),
+            edges: Vec::new(),
+        })
+    }
+
+    /// Adds a node, or an edge between two nodes.
+    /// The nodes of an edge do not have to be declared: they are created with their id as label.
+    pub fn add_row(&mut self, row: &JsonValue) -> anyhow::Result<()> {
+        let Some(row) = row.as_object() else {
+            return Ok(());
+        };
+        match (row.get("source"), row.get("target"), row.get("id")) {
+            (Some(source), Some(target), _) => {
+                let source = self.node_index(as_id(source));
+                let target = self.node_index(as_id(target));
+                self.edges.push(Edge {
+                    source,
+                    target,
+                    properties: row.clone(),
+                    merged_edges: 1,
+                });
+            }
+            (None, None, Some(id)) => {
+                let index = self.node_index(as_id(id));
+                self.nodes[index].properties.clone_from(row);
+            }
+            _ => bail!(
+                "Each row of the graph component must be a node, with an id, \
+                or an edge, with a source and a target. Invalid row: {}",
+                JsonValue::Object(row.clone())
+            ),
+        }
+        Ok(())
+    }
+
+    fn node_index(&mut self, id: String) -> usize {
+        if let Some(&index) = self.node_indices.get(&id) {
+            return index;
+        }
+        let index = self.nodes.len();
+        self.node_indices.insert(id.clone(), index);
+        self.nodes.push(Node {
+            id,
+            properties: Map::new(),
+            merged_nodes: None,
+        });
+        index
+    }
+
+    /// The properties to render the component with: the ones it was opened with,
+    /// and the positions of the nodes and edges
+    #[must_use]
+    pub fn into_properties(self) -> JsonValue {
+        let mut properties = match self.properties {
+            JsonValue::Object(properties) => properties,
+            _ => Map::new(),
+        };
+        let node_count = self.nodes.len();
+        let edge_count = self.edges.len();
+        let (nodes, edges) = reduce(self.nodes, self.edges, self.max_nodes);
+        let positions = layout(nodes.len(), &edges, self.height);
+        let degrees = degrees(nodes.len(), &edges);
+        let radii: Vec<f64> = nodes
+            .iter()
+            .zip(&degrees)
+            .map(|(node, &degree)| node_radius(node, degree))
+            .collect();
+        let groups = group_colors(&nodes);
+        let rendered_nodes: Vec<JsonValue> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let mut rendered = node.properties.clone();
+                let (x, y) = positions[i];
+                rendered.insert("x".into(), json!(round(x)));
+                rendered.insert("y".into(), json!(round(y)));
+                rendered.insert("radius".into(), json!(round(radii[i])));
+                rendered
+                    .entry("label")
+                    .or_insert_with(|| json!(node.id.clone()));
+                if let Some(merged_nodes) = node.merged_nodes {
+                    rendered.insert("merged_nodes".into(), merged_nodes.into());
+                }
+                if !matches!(rendered.get("color"), Some(JsonValue::String(_))) {
+                    let group = rendered.get("group").map(as_id);
+                    let color = group.and_then(|g| groups.get(&g).copied());
+                    rendered.insert("color".into(), color.unwrap_or("azure").into());
+                }
+                JsonValue::Object(rendered)
+            })
+            .collect();
+        let rendered_edges: Vec<JsonValue> = edges
+            .iter()
+            .map(|edge| {
+                let mut rendered = edge.properties.clone();
+                let (x1, y1) = positions[edge.source];
+                let (x2, y2) = positions[edge.target];
+                // The arrow of directed edges ends on the border of the target node
+                let length = (x2 - x1).hypot(y2 - y1).max(f64::EPSILON);
+                let shortening = (radii[edge.target] / length).min(1.0);
+                rendered.insert("x1".into(), json!(round(x1)));
+                rendered.insert("y1".into(), json!(round(y1)));
+                rendered.insert("x2".into(), json!(round(x2 - (x2 - x1) * shortening)));
+                rendered.insert("y2".into(), json!(round(y2 - (y2 - y1) * shortening)));
+                rendered.insert("label_x".into(), json!(round(x1.midpoint(x2))));
+                rendered.insert("label_y".into(), json!(round(y1.midpoint(y2))));
+                if edge.merged_edges > 1 {
+                    rendered.insert("merged_edges".into(), edge.merged_edges.into());
+                }
+                let merged_edges = u32::try_from(edge.merged_edges).unwrap_or(u32::MAX);
+                rendered
+                    .entry("width")
+                    .or_insert_with(|| json!(round(1.0 + f64::from(merged_edges).ln())));
+                JsonValue::Object(rendered)
+            })
+            .collect();
+        properties.insert("width".into(), json!(WIDTH));
+        properties.insert("height".into(), json!(self.height));
+        properties.insert("node_count".into(), node_count.into());
+        properties.insert("edge_count".into(), edge_count.into());
+        properties.insert("reduced".into(), (rendered_nodes.len() < node_count).into());
+        properties.insert("displayed_nodes".into(), rendered_nodes.len().into());
+        properties.insert("nodes".into(), rendered_nodes.into());
+        properties.insert("edges".into(), rendered_edges.into());
+        JsonValue::Object(properties)
+    }
+}
+
+fn as_id(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn positive_number(properties: &JsonValue, name: &str) -> anyhow::Result<Option<f64>> {
+    let value = match properties.get(name) {
+        None | Some(JsonValue::Null) => return Ok(None),
+        Some(JsonValue::String(s)) => s.trim().parse().ok(),
+        Some(value) => value.as_f64(),
+    };
+    value
+        .filter(|&n: &f64| n > 0.0 && n.is_finite())
+        .map(Some)
+        .with_context(|| format!("The graph component's {name} must be a positive number"))
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn node_radius(node: &Node, degree: usize) -> f64 {
+    if let Some(size) = node.properties.get("size").and_then(JsonValue::as_f64) {
+        return size.clamp(1.0, 50.0);
+    }
+    let weight = node.merged_nodes.unwrap_or(degree);
+    (5.0 + 2.0 * (weight as f64).sqrt()).min(30.0)
+}
+
+/// Gives a color to each group, in the order in which the groups appear
+fn group_colors(nodes: &[Node]) -> HashMap<String, &'static str> {
+    let mut colors = HashMap::new();
+    for group in nodes.iter().filter_map(|n| n.properties.get("group")) {
+        let next_color = COLORS[colors.len() % COLORS.len()];
+        colors.entry(as_id(group)).or_insert(next_color);
+    }
+    colors
+}
+
+fn degrees(node_count: usize, edges: &[Edge]) -> Vec<usize> {
+    let mut degrees = vec![0; node_count];
+    for edge in edges {
+        degrees[edge.source] += 1;
+        degrees[edge.target] += 1;
+    }
+    degrees
+}
+
+/// Merges the nodes of each group, then keeps only the most connected nodes, until there are at most `max_nodes` nodes.
+/// The edges between merged nodes are merged too.
+fn reduce(nodes: Vec<Node>, edges: Vec<Edge>, max_nodes: usize) -> (Vec<Node>, Vec<Edge>) {
+    if nodes.len() <= max_nodes {
+        return (nodes, edges);
+    }
+    // Replace each grouped node with the node of its group
+    let mut reduced_nodes: Vec<Node> = Vec::new();
+    let mut group_nodes: HashMap<String, usize> = HashMap::new();
+    let mut new_indices = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let Some(group) = node.properties.get("group").map(as_id) else {
+            new_indices.push(Some(reduced_nodes.len()));
+            reduced_nodes.push(node);
+            continue;
+        };
+        if let Some(&index) = group_nodes.get(&group) {
+            if let Some(merged_nodes) = &mut reduced_nodes[index].merged_nodes {
+                *merged_nodes += 1;
+            }
+            new_indices.push(Some(index));
+            continue;
+        }
+        new_indices.push(Some(reduced_nodes.len()));
+        group_nodes.insert(group.clone(), reduced_nodes.len());
+        let mut properties = Map::new();
+        properties.insert("group".into(), group.clone().into());
+        if let Some(color) = node.properties.get("color") {
+            properties.insert("color".into(), color.clone());
+        }
+        reduced_nodes.push(Node {
+            id: group,
+            properties,
+            merged_nodes: Some(1),
+        });
+    }
+    for node in &mut reduced_nodes {
+        if let Some(merged_nodes) = node.merged_nodes {
+            let label = format!("{} ({merged_nodes})", node.id);
+            node.properties.insert("label".into(), label.into());
+        }
+    }
+    let edges = merge_edges(edges, &new_indices);
+    if reduced_nodes.len() <= max_nodes {
+        return (reduced_nodes, edges);
+    }
+    // Keep the most connected nodes
+    let degrees = degrees(reduced_nodes.len(), &edges);
+    let mut by_degree: Vec<usize> = (0..reduced_nodes.len()).collect();
+    by_degree.sort_by_key(|&i| std::cmp::Reverse(degrees[i]));
+    let mut kept = vec![false; reduced_nodes.len()];
+    for &i in by_degree.iter().take(max_nodes) {
+        kept[i] = true;
+    }
+    let mut new_indices = Vec::with_capacity(reduced_nodes.len());
+    let mut kept_nodes = Vec::with_capacity(max_nodes);
+    for (i, node) in reduced_nodes.into_iter().enumerate() {
+        if kept[i] {
+            new_indices.push(Some(kept_nodes.len()));
+            kept_nodes.push(node);
+        } else {
+            new_indices.push(None);
+        }
+    }
+    let edges = merge_edges(edges, &new_indices);
+    (kept_nodes, edges)
+}
+
+/// Moves the edges to the new indices of their nodes, removes the edges of removed nodes and inside groups,
+/// and merges the edges that have the same nodes
+fn merge_edges(edges: Vec<Edge>, new_indices: &[Option<usize>]) -> Vec<Edge> {
+    let mut merged: Vec<Edge> = Vec::new();
+    let mut positions: HashMap<(usize, usize), usize> = HashMap::new();
+    for mut edge in edges {
+        let (Some(source), Some(target)) = (new_indices[edge.source], new_indices[edge.target])
+        else {
+            continue;
+        };
+        let was_merged = (source, target) != (edge.source, edge.target);
+        if source == target && was_merged {
+            continue;
+        }
+        if let Some(&position) = positions.get(&(source, target)) {
+            merged[position].merged_edges += edge.merged_edges;
+            merged[position].properties.remove("label");
+            continue;
+        }
+        positions.insert((source, target), merged.len());
+        edge.source = source;
+        edge.target = target;
+        merged.push(edge);
+    }
+    merged
+}
+
+/// Fruchterman-Reingold force-directed layout: connected nodes attract each other, and all the nodes repel each other.
+/// The nodes start on a spiral, so that the layout of a graph is always the same.
+#[allow(clippy::cast_precision_loss)]
+fn layout(node_count: usize, edges: &[Edge], height: f64) -> Vec<(f64, f64)> {
+    if node_count == 0 {
+        return Vec::new();
+    }
+    let n = node_count as f64;
+    let ideal_distance = (WIDTH * height / n).sqrt();
+    let mut positions: Vec<(f64, f64)> = (0..node_count)
+        .map(|i| {
+            let radius = ((i as f64 + 0.5) / n).sqrt() * height / 2.0;
+            let angle = i as f64 * 2.399_963; // golden angle
+            (radius * angle.cos(), radius * angle.sin())
+        })
+        .collect();
+    let iterations =
+        (MAX_FORCE_COMPUTATIONS / (node_count * node_count)).clamp(MIN_ITERATIONS, MAX_ITERATIONS);
+    let mut displacements = vec![(0.0, 0.0); node_count];
+    for iteration in 0..iterations {
+        let temperature = WIDTH / 10.0 * (1.0 - iteration as f64 / iterations as f64);
+        displacements.fill((0.0, 0.0));
+        for i in 0..node_count {
+            for j in (i + 1)..node_count {
+                let (dx, dy, distance) = difference(positions[i], positions[j]);
+                let force = ideal_distance * ideal_distance / distance;
+                add(
+                    &mut displacements[i],
+                    dx / distance * force,
+                    dy / distance * force,
+                );
+                add(
+                    &mut displacements[j],
+                    -dx / distance * force,
+                    -dy / distance * force,
+                );
+            }
+        }
+        for edge in edges.iter().filter(|e| e.source != e.target) {
+            let (dx, dy, distance) = difference(positions[edge.source], positions[edge.target]);
+            let force = distance * distance / ideal_distance;
+            add(
+                &mut displacements[edge.source],
+                -dx / distance * force,
+                -dy / distance * force,
+            );
+            add(
+                &mut displacements[edge.target],
+                dx / distance * force,
+                dy / distance * force,
+            );
+        }
+        for (position, &(dx, dy)) in positions.iter_mut().zip(&displacements) {
+            // Gravity keeps the unconnected parts of the graph close to each other
+            let (dx, dy) = (dx - position.0 * 0.1, dy - position.1 * 0.1);
+            let length = dx.hypot(dy).max(f64::EPSILON);
+            let step = length.min(temperature);
+            position.0 += dx / length * step;
+            position.1 += dy / length * step;
+        }
+    }
+    fit(&mut positions, height);
+    positions
+}
+
+fn difference(a: (f64, f64), b: (f64, f64)) -> (f64, f64, f64) {
+    let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+    (dx, dy, dx.hypot(dy).max(0.01))
+}
+
+fn add(displacement: &mut (f64, f64), dx: f64, dy: f64) {
+    displacement.0 += dx;
+    displacement.1 += dy;
+}
+
+/// Scales and moves the nodes to fill the image, keeping the proportions of the layout
+fn fit(positions: &mut [(f64, f64)], height: f64) {
+    let (min_x, max_x, min_y, max_y) = positions.iter().fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(min_x, max_x, min_y, max_y), &(x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    let available_width = (WIDTH - 2.0 * MARGIN).max(1.0);
+    let available_height = (height - 2.0 * MARGIN).max(1.0);
+    let scale = (available_width / (max_x - min_x).max(f64::EPSILON))
+        .min(available_height / (max_y - min_y).max(f64::EPSILON));
+    let offset_x = (WIDTH - (max_x - min_x) * scale) / 2.0;
+    let offset_y = (height - (max_y - min_y) * scale) / 2.0;
+    for position in positions {
+        position.0 = (position.0 - min_x) * scale + offset_x;
+        position.1 = (position.1 - min_y) * scale + offset_y;
+    }
+}
+
+fn round(n: f64) -> f64 {
+    (n * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+fn graph(properties: JsonValue, rows: &[JsonValue]) -> JsonValue {
+    let mut graph = NetworkGraph::new(properties).unwrap();
+    for row in rows {
+        graph.add_row(row).unwrap();
+    }
+    graph.into_properties()
+}
+
+#[test]
+fn test_layout() {
+    let properties = graph(
+        json!({"component": "graph", "height": 400}),
+        &[
+            json!({"id": "app", "label": "Application"}),
+            json!({"source": "app", "target": "db"}),
+            json!({"source": "app", "target": "cache"}),
+            json!({"source": "cache", "target": "db", "label": "fills"}),
+        ],
+    );
+    assert_eq!(properties["node_count"], 3);
+    assert_eq!(properties["reduced"], false);
+    let nodes = properties["nodes"].as_array().unwrap();
+    assert_eq!(nodes[0]["label"], "Application");
+    assert_eq!(nodes[1]["label"], "db");
+    for node in nodes {
+        let (x, y) = (node["x"].as_f64().unwrap(), node["y"].as_f64().unwrap());
+        assert!((MARGIN..=WIDTH - MARGIN).contains(&x), "{node}");
+        assert!((MARGIN..=400.0 - MARGIN).contains(&y), "{node}");
+    }
+    let edges = properties["edges"].as_array().unwrap();
+    assert_eq!(edges.len(), 3);
+    assert_eq!(edges[2]["label"], "fills");
+    assert_eq!(
+        properties,
+        graph(
+            json!({"component": "graph", "height": 400}),
+            &[
+                json!({"id": "app", "label": "Application"}),
+                json!({"source": "app", "target": "db"}),
+                json!({"source": "app", "target": "cache"}),
+                json!({"source": "cache", "target": "db", "label": "fills"}),
+            ],
+        ),
+        "the layout should not be random"
+    );
+    assert!(NetworkGraph::new(json!({"max_nodes": -1})).is_err());
+    let mut graph = NetworkGraph::new(json!({})).unwrap();
+    assert!(graph.add_row(&json!({"name": "not a node"})).is_err());
+}
+
+#[test]
+fn test_reduce_large_graph() {
+    let mut rows = Vec::new();
+    for i in 0..10 {
+        rows.push(json!({"id": i, "group": if i < 6 { "small" } else { "big" }}));
+    }
+    rows.push(json!({"id": "alone"}));
+    rows.push(json!({"source": 0, "target": 7}));
+    rows.push(json!({"source": 1, "target": 8}));
+    rows.push(json!({"source": 0, "target": 1}));
+    rows.push(json!({"source": "alone", "target": 9}));
+    let properties = graph(json!({"max_nodes": 3}), &rows);
+    assert_eq!(properties["node_count"], 11);
+    assert_eq!(properties["reduced"], true);
+    let nodes = properties["nodes"].as_array().unwrap();
+    let labels: Vec<&str> = nodes.iter().map(|n| n["label"].as_str().unwrap()).collect();
+    assert_eq!(labels, ["small (6)", "big (4)", "alone"]);
+    let edges = properties["edges"].as_array().unwrap();
+    assert_eq!(edges.len(), 2, "{edges:?}");
+    assert_eq!(edges[0]["merged_edges"], 2);
+
+    let properties = graph(json!({"max_nodes": 2}), &rows);
+    let nodes = properties["nodes"].as_array().unwrap();
+    let labels: Vec<&str> = nodes.iter().map(|n| n["label"].as_str().unwrap()).collect();
+    assert_eq!(labels, ["small (6)", "big (4)"]);
+}
@@ -70,20 +70,35 @@
 
 extern crate core;
 
+pub mod accessibility;
 pub mod app_config;
+pub mod data_profile;
 pub mod dynamic_component;
 pub mod file_cache;
 pub mod filesystem;
+pub mod graph_layout;
+pub mod org_chart;
 pub mod render;
 pub mod template_helpers;
 pub mod templates;
 pub mod utils;
 pub mod webserver;
+pub mod xlsx_export;
+pub mod zip_export;
 
 use crate::app_config::AppConfig;
 use crate::filesystem::FileSystem;
+use crate::webserver::alerts::AlertTriggers;
+use crate::webserver::config_reload::ConfigReloads;
+use crate::webserver::database::previews::PreviewDatabases;
+use crate::webserver::database::statement_hooks::{StatementHook, StatementHooks};
+use crate::webserver::database::statistics::{PerformanceStatistics, PERFORMANCE_PAGE};
 use crate::webserver::database::ParsedSqlFile;
+use crate::webserver::event_stream::ChannelListeners;
 use crate::webserver::oidc::OidcState;
+use crate::webserver::page_etags::PageEtags;
+use crate::webserver::rate_limit::RateLimiter;
+use crate::webserver::spam_protection::SpamProtection;
 use file_cache::FileCache;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -95,6 +110,7 @@ use webserver::Database;
 /// or in `$SQLPAGE_CONFIGURATION_DIRECTORY/templates/component_name.handlebars` in the filesystem.
 pub const TEMPLATES_DIR: &str = "sqlpage/templates/";
 pub const MIGRATIONS_DIR: &str = "migrations";
+pub const PREVIEW_FIXTURES_DIR: &str = "preview_fixtures";
 pub const ON_CONNECT_FILE: &str = "on_connect.sql";
 pub const ON_RESET_FILE: &str = "on_reset.sql";
 pub const DEFAULT_404_FILE: &str = "default_404.sql";
@@ -106,6 +122,16 @@ pub struct AppState {
     file_system: FileSystem,
     config: AppConfig,
     pub oidc_state: Option<Arc<OidcState>>,
+    jwt_keys: Option<crate::webserver::jwt::JwtKeys>,
+    statistics: PerformanceStatistics,
+    alert_triggers: AlertTriggers,
+    statement_hooks: StatementHooks,
+    previews: PreviewDatabases,
+    page_etags: PageEtags,
+    rate_limiter: RateLimiter,
+    spam_protection: SpamProtection,
+    config_reloads: ConfigReloads,
+    channel_listeners: ChannelListeners,
 }
 
 impl AppState {
@@ -130,7 +156,19 @@ impl AppState {
             ),
         );
 
+        if !config.environment.is_prod() {
+            sql_file_cache.add_static(
+                PathBuf::from(PERFORMANCE_PAGE),
+                ParsedSqlFile::new(
+                    &db,
+                    include_str!("performance.sql"),
+                    Path::new(PERFORMANCE_PAGE),
+                ),
+            );
+        }
+
         let oidc_state = crate::webserver::oidc::initialize_oidc_state(config).await?;
+        let jwt_keys = crate::webserver::jwt::load_jwt_keys(config).await?;
 
         Ok(AppState {
             db,
@@ -139,8 +177,24 @@ impl AppState {
             file_system,
             config: config.clone(),
             oidc_state,
+            jwt_keys,
+            statistics: PerformanceStatistics::default(),
+            alert_triggers: AlertTriggers::new(&config.alerts),
+            statement_hooks: StatementHooks::default(),
+            previews: PreviewDatabases::default(),
+            page_etags: PageEtags::default(),
+            rate_limiter: RateLimiter::new(config),
+            spam_protection: SpamProtection::new(config),
+            config_reloads: ConfigReloads::default(),
+            channel_listeners: ChannelListeners::default(),
         })
     }
+
+    /// Registers a hook that is called before and after the execution of every SQL statement.
+    /// See [`StatementHook`] for details.
+    pub fn add_statement_hook(&mut self, hook: impl StatementHook + 'static) {
+        self.statement_hooks.add(Box::new(hook));
+    }
 }
 
 impl std::fmt::Debug for AppState {
@@ -28,6 +28,17 @@ async fn start() -> anyhow::Result<()> {
                 )?;
                 return Ok(());
             }
+            app_config::Commands::Render { out, pages } => {
+                let db = Database::init(&app_config).await?;
+                webserver::database::migrations::apply(&app_config, &db).await?;
+                let state = AppState::init_with_db(&app_config, db).await?;
+                webserver::static_export::export_static_site(state, &out, &pages).await?;
+                return Ok(());
+            }
+            app_config::Commands::Docs { out, json } => {
+                webserver::site_docs::generate_docs(&app_config, out.as_deref(), json)?;
+                return Ok(());
+            }
         }
     }
 
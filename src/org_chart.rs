@@ -0,0 +1,230 @@
+//! People displayed by the `org_chart` component, arranged in a hierarchy.
+//!
+//! Each row is a person with an `id`, and the `parent_id` of their manager.
+//! The rows can be received in any order: the hierarchy is built when the component is closed,
+//! and the people are rendered depth-first, each one followed by the people who report to them.
+//! Each rendered row says how deep the person is in the hierarchy, and how many levels end after them,
+//! so that the template can nest the people without recursion.
+
+use anyhow::{bail, Context};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+
+const DEFAULT_EXPANDED_LEVELS: usize = 2;
+
+struct Person {
+    properties: Map<String, JsonValue>,
+    parent_id: Option<String>,
+}
+
+pub struct OrgChart {
+    properties: JsonValue,
+    expanded_levels: usize,
+    people: Vec<Person>,
+    indices: HashMap<String, usize>,
+}
+
+impl OrgChart {
+    /// Creates an empty chart, configured by the top-level properties of the component
+    pub fn new(properties: JsonValue) -> anyhow::Result<Self> {
+        let expanded_levels = match properties.get("expanded_levels") {
+            None | Some(JsonValue::Null) => Some(DEFAULT_EXPANDED_LEVELS),
+            Some(JsonValue::String(s)) => s.parse().ok(),
+            Some(value) => value.as_u64().and_then(|n| usize::try_from(n).ok()),
+        }
+        .context("The org_chart component's expanded_levels must be a positive integer")?;
+        Ok(Self {
+            properties,
+            expanded_levels,
+            people: Vec::new(),
+            indices: HashMap::new(),
+        })
+    }
+
+    pub fn add_row(&mut self, row: &JsonValue) -> anyhow::Result<()> {
+        let Some(row) = row.as_object() else {
+            return Ok(());
+        };
+        let Some(id) = row.get("id").and_then(as_id) else {
+            bail!("Each row of the org_chart component must have an id, and the parent_id of the person they report to. Invalid row: {}", JsonValue::Object(row.clone()));
+        };
+        if self.indices.contains_key(&id) {
+            bail!("The org_chart component received two people with the id {id:?}");
+        }
+        let parent_id = row.get("parent_id").and_then(as_id).filter(|p| *p != id);
+        self.indices.insert(id, self.people.len());
+        self.people.push(Person {
+            properties: row.clone(),
+            parent_id,
+        });
+        Ok(())
+    }
+
+    /// The properties to render the component with: the ones it was opened with, and the number of people
+    #[must_use]
+    pub fn properties(&self) -> JsonValue {
+        let mut properties = match &self.properties {
+            JsonValue::Object(properties) => properties.clone(),
+            _ => Map::new(),
+        };
+        properties.insert("people_count".into(), self.people.len().into());
+        JsonValue::Object(properties)
+    }
+
+    /// The people, depth-first. People whose manager is not in the chart are at the top of the hierarchy,
+    /// and so is the first person of each cycle of people who report to each other.
+    #[must_use]
+    pub fn into_rows(self) -> Vec<JsonValue> {
+        let mut reports: Vec<Vec<usize>> = vec![Vec::new(); self.people.len()];
+        let mut roots = Vec::new();
+        for (index, person) in self.people.iter().enumerate() {
+            match person.parent_id.as_ref().and_then(|p| self.indices.get(p)) {
+                Some(&parent) => reports[parent].push(index),
+                None => roots.push(index),
+            }
+        }
+        // The people in the order in which they are rendered, with their depth and the person they are rendered under
+        let mut order: Vec<(usize, usize, Option<usize>)> = Vec::with_capacity(self.people.len());
+        let mut visited = vec![false; self.people.len()];
+        for start in roots.into_iter().chain(0..self.people.len()) {
+            if visited[start] {
+                continue;
+            }
+            let mut stack = vec![(start, 0, None)];
+            while let Some((index, depth, manager)) = stack.pop() {
+                if visited[index] {
+                    continue;
+                }
+                visited[index] = true;
+                order.push((index, depth, manager));
+                for &report in reports[index].iter().rev() {
+                    if !visited[report] {
+                        stack.push((report, depth + 1, Some(index)));
+                    }
+                }
+            }
+        }
+        let mut descendants = vec![0_usize; self.people.len()];
+        let mut direct_reports = vec![0_usize; self.people.len()];
+        for &(index, _, manager) in order.iter().rev() {
+            if let Some(manager) = manager {
+                descendants[manager] += descendants[index] + 1;
+                direct_reports[manager] += 1;
+            }
+        }
+
+        let mut people: Vec<Option<Map<String, JsonValue>>> = self
+            .people
+            .into_iter()
+            .map(|p| Some(p.properties))
+            .collect();
+        order
+            .iter()
+            .enumerate()
+            .map(|(position, &(index, depth, _))| {
+                let mut row = people[index].take().unwrap_or_default();
+                // The levels that end after a person, when the next person has a lower depth
+                let next_depth = order.get(position + 1).map_or(0, |&(_, d, _)| d);
+                let closed_levels = depth.saturating_sub(next_depth);
+                row.insert("depth".into(), depth.into());
+                row.insert("direct_reports".into(), direct_reports[index].into());
+                row.insert("descendants".into(), descendants[index].into());
+                row.insert("expanded".into(), (depth + 1 < self.expanded_levels).into());
+                row.insert(
+                    "closed_levels".into(),
+                    vec![JsonValue::Null; closed_levels].into(),
+                );
+                if !matches!(row.get("image_url"), Some(JsonValue::String(_))) {
+                    let name = row.get("name").and_then(as_id).unwrap_or_default();
+                    row.insert("initials".into(), initials(&name).into());
+                }
+                JsonValue::Object(row)
+            })
+            .collect()
+    }
+}
+
+fn as_id(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(s) if s.is_empty() => None,
+        JsonValue::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// The first letters of the first two words of a name, displayed when a person has no photo
+fn initials(name: &str) -> String {
+    name.split_whitespace()
+        .take(2)
+        .filter_map(|word| word.chars().next())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+#[cfg(test)]
+fn chart_rows(rows: &[JsonValue]) -> Vec<JsonValue> {
+    let mut chart = OrgChart::new(serde_json::json!({"component": "org_chart"})).unwrap();
+    for row in rows {
+        chart.add_row(row).unwrap();
+    }
+    chart.into_rows()
+}
+
+#[test]
+fn test_hierarchy() {
+    use serde_json::json;
+    let rows = chart_rows(&[
+        json!({"id": 2, "parent_id": 1, "name": "Bob Martin"}),
+        json!({"id": 3, "parent_id": 2, "name": "Carol"}),
+        json!({"id": 1, "parent_id": null, "name": "Alice Smith", "image_url": "alice.jpg"}),
+        json!({"id": 4, "parent_id": 1, "name": "Dave"}),
+        json!({"id": 5, "parent_id": 42, "name": "Eve"}),
+    ]);
+    let summary: Vec<(&str, u64, u64, usize)> = rows
+        .iter()
+        .map(|r| {
+            (
+                r["name"].as_str().unwrap(),
+                r["depth"].as_u64().unwrap(),
+                r["descendants"].as_u64().unwrap(),
+                r["closed_levels"].as_array().unwrap().len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("Alice Smith", 0, 3, 0),
+            ("Bob Martin", 1, 1, 0),
+            ("Carol", 2, 0, 1),
+            ("Dave", 1, 0, 1),
+            ("Eve", 0, 0, 0),
+        ]
+    );
+    assert_eq!(rows[0]["direct_reports"], 2);
+    assert_eq!(rows[0]["expanded"], true);
+    assert_eq!(rows[1]["expanded"], false);
+    assert_eq!(rows[0].get("initials"), None);
+    assert_eq!(rows[1]["initials"], "BM");
+}
+
+#[test]
+fn test_cycles() {
+    use serde_json::json;
+    let rows = chart_rows(&[
+        json!({"id": "a", "parent_id": "b", "name": "A"}),
+        json!({"id": "b", "parent_id": "a", "name": "B"}),
+        json!({"id": "c", "parent_id": "c", "name": "C"}),
+    ]);
+    let depths: Vec<(&str, u64)> = rows
+        .iter()
+        .map(|r| (r["name"].as_str().unwrap(), r["depth"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(depths, [("C", 0), ("A", 0), ("B", 1)]);
+    let mut chart = OrgChart::new(json!({})).unwrap();
+    chart.add_row(&json!({"id": 1})).unwrap();
+    assert!(chart.add_row(&json!({"id": 1})).is_err());
+    assert!(chart.add_row(&json!({"name": "no id"})).is_err());
+    assert!(OrgChart::new(json!({"expanded_levels": -1})).is_err());
+}
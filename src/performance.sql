@@ -0,0 +1,32 @@
+SELECT
+    'shell' as component,
+    'Performance statistics' as title,
+    'gauge' as icon,
+    '/' as link;
+
+SELECT
+    'text' as component,
+    'Performance statistics' as title,
+    '
+Statistics collected since SQLPage started.
+Durations include the time spent sending the results to the browser.
+
+> This page is only available in development mode.
+> In production, you can build your own dashboard with [`sqlpage.performance_statistics`](https://sql-page.com/functions.sql?function=performance_statistics).
+' as contents_md;
+
+SELECT 'title' as component, 'Database connection pool' as contents, 2 as level;
+SELECT 'table' as component;
+SELECT 'dynamic' as component, sqlpage.performance_statistics('database') as properties;
+
+SELECT 'title' as component, 'Slowest statements' as contents, 2 as level;
+SELECT 'table' as component, TRUE as sort, TRUE as search, 'query' as markdown;
+SELECT 'dynamic' as component, sqlpage.performance_statistics('statements') as properties;
+
+SELECT 'title' as component, 'Most frequent pages' as contents, 2 as level;
+SELECT 'table' as component, TRUE as sort, TRUE as search;
+SELECT 'dynamic' as component, sqlpage.performance_statistics('pages') as properties;
+
+SELECT 'title' as component, 'Caches' as contents, 2 as level;
+SELECT 'table' as component;
+SELECT 'dynamic' as component, sqlpage.performance_statistics('caches') as properties;
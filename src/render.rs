@@ -2,7 +2,7 @@
 //!
 //! This module is responsible for transforming database query results into formatted HTTP responses
 //! by utilizing a component-based rendering system. It supports multiple output formats including HTML,
-//! JSON, and CSV.
+//! JSON, CSV, and Excel.
 //!
 //! # Components
 //!
@@ -37,14 +37,26 @@
 //! * HTML: Renders templated HTML output using components
 //! * JSON: Generates JSON responses for API endpoints
 //! * CSV: Creates downloadable CSV files
+//! * Excel: Creates downloadable xlsx spreadsheets
 //!
 //! For more details on available components and their usage, see the
 //! [SQLPage documentation](https://sql-page.com/documentation.sql).
 
+use crate::accessibility::AccessibilityAudit;
+use crate::data_profile::DataProfile;
+use crate::graph_layout::NetworkGraph;
+use crate::org_chart::OrgChart;
 use crate::templates::SplitTemplate;
+use crate::webserver::byte_ranges::ranged_response;
+use crate::webserver::event_stream::EventStreamDirective;
 use crate::webserver::http::RequestContext;
+use crate::webserver::page_etags::EtagDirective;
+use crate::webserver::post_redirect::flash_cookie;
 use crate::webserver::response_writer::{AsyncResponseWriter, ResponseWriter};
+use crate::webserver::spam_protection;
 use crate::webserver::ErrorWithStatus;
+use crate::xlsx_export::{XlsxBodyRenderer, XLSX_CONTENT_TYPE};
+use crate::zip_export::ZipBodyRenderer;
 use crate::AppState;
 use actix_web::cookie::time::format_description::well_known::Rfc3339;
 use actix_web::cookie::time::OffsetDateTime;
@@ -52,7 +64,8 @@ use actix_web::http::{header, StatusCode};
 use actix_web::{HttpResponse, HttpResponseBuilder, ResponseError};
 use anyhow::{bail, format_err, Context as AnyhowContext};
 use awc::cookie::time::Duration;
-use handlebars::{BlockContext, JsonValue, RenderError, Renderable};
+use handlebars::template::{HelperTemplate, Parameter, TemplateElement};
+use handlebars::{BlockContext, JsonTruthy, JsonValue, RenderError, Renderable, Template};
 use serde::Serialize;
 use serde_json::{json, Value};
 use std::borrow::Cow;
@@ -81,6 +94,7 @@ pub struct HeaderContext {
     pub writer: ResponseWriter,
     response: HttpResponseBuilder,
     has_status: bool,
+    column_types: JsonValue,
 }
 
 impl HeaderContext {
@@ -102,8 +116,15 @@ impl HeaderContext {
             writer,
             response,
             has_status: false,
+            column_types: JsonValue::Null,
         }
     }
+
+    /// Sets the types of the columns of the rows of the current query
+    pub fn set_column_types(&mut self, column_types: JsonValue) {
+        self.column_types = column_types;
+    }
+
     pub async fn handle_row(self, data: JsonValue) -> anyhow::Result<PageContext> {
         log::debug!("Handling header row: {data}");
         let comp_opt =
@@ -116,8 +137,15 @@ impl HeaderContext {
             Some(HeaderComponent::Redirect) => self.redirect(&data).map(PageContext::Close),
             Some(HeaderComponent::Json) => self.json(&data),
             Some(HeaderComponent::Csv) => self.csv(&data).await,
+            Some(HeaderComponent::Xlsx) => self.xlsx(&data),
+            Some(HeaderComponent::Zip) => Ok(self.zip(&data)),
+            Some(HeaderComponent::Prometheus) => Ok(self.prometheus()),
             Some(HeaderComponent::Cookie) => self.add_cookie(&data).map(PageContext::Header),
             Some(HeaderComponent::Authentication) => self.authentication(data).await,
+            Some(HeaderComponent::Etag) => self.etag(&data).map(PageContext::Header),
+            Some(HeaderComponent::Download) => self.download(&data).map(PageContext::Close),
+            Some(HeaderComponent::Cache) => self.cache(&data).map(PageContext::Header),
+            Some(HeaderComponent::EventStream) => self.event_stream(&data),
             None => self.start_body(data).await,
         }
     }
@@ -232,16 +260,133 @@ impl HeaderContext {
         Ok(self)
     }
 
+    fn etag(mut self, data: &JsonValue) -> anyhow::Result<Self> {
+        let max_age = match data.get("max_age") {
+            None | Some(JsonValue::Null) => 0.,
+            Some(JsonValue::Number(n)) => n.as_f64().unwrap_or(-1.),
+            Some(JsonValue::String(s)) => s.trim().parse().unwrap_or(-1.),
+            Some(other) => bail!("etag: max_age must be a number of seconds, not {other}"),
+        };
+        let max_age = std::time::Duration::try_from_secs_f64(max_age).map_err(|_| {
+            anyhow::anyhow!("etag: max_age must be a positive number of seconds, not {max_age}")
+        })?;
+        self.response
+            .extensions_mut()
+            .insert(EtagDirective { max_age });
+        Ok(self)
+    }
+
+    /// Lets browsers reuse the page, and start loading the pages and files it will need
+    fn cache(mut self, data: &JsonValue) -> anyhow::Result<Self> {
+        let mut directives = vec![];
+        let shared = data.get("shared");
+        let is_shared =
+            shared.is_some_and(|v| v != &json!(false) && v != &json!(0) && !v.is_null());
+        directives.push(if is_shared { "public" } else { "private" }.to_string());
+        for (property, directive) in [
+            ("max_age", "max-age"),
+            ("stale_while_revalidate", "stale-while-revalidate"),
+            ("stale_if_error", "stale-if-error"),
+        ] {
+            let seconds = match data.get(property) {
+                None | Some(JsonValue::Null) => continue,
+                Some(JsonValue::Number(n)) => n.as_u64(),
+                Some(JsonValue::String(s)) => s.trim().parse().ok(),
+                Some(_) => None,
+            };
+            let seconds = seconds.with_context(|| {
+                format!("cache: {property} must be a positive integer number of seconds")
+            })?;
+            directives.push(format!("{directive}={seconds}"));
+        }
+        self.response
+            .insert_header((header::CACHE_CONTROL, directives.join(", ")));
+        // The urls are between angle brackets in the headers
+        for url in get_object_urls(data, "prefetch")? {
+            let url = url.replace('>', "%3E");
+            self.response
+                .append_header((header::LINK, format!("<{url}>; rel=prefetch")));
+        }
+        for url in get_object_urls(data, "preload")? {
+            let url = url.replace('>', "%3E");
+            let destination = preload_destination(&url);
+            // Fetches and fonts are cors requests, the preloaded response is only reused if it is one too
+            let crossorigin = if matches!(destination, "fetch" | "font") {
+                "; crossorigin"
+            } else {
+                ""
+            };
+            self.response.append_header((
+                header::LINK,
+                format!("<{url}>; rel=preload; as={destination}{crossorigin}"),
+            ));
+        }
+        Ok(self)
+    }
+
     fn redirect(mut self, data: &JsonValue) -> anyhow::Result<HttpResponse> {
-        self.response.status(StatusCode::FOUND);
+        let status = match data.get("status") {
+            None | Some(JsonValue::Null) => StatusCode::FOUND,
+            Some(status) => redirect_status(status)?,
+        };
+        self.response.status(status);
         self.has_status = true;
         let link = get_object_str(data, "link")
             .with_context(|| "The redirect component requires a 'link' property")?;
         self.response.insert_header((header::LOCATION, link));
+        if let Some(message) = get_object_str(data, "message") {
+            self.response.cookie(flash_cookie(message));
+        }
         let response = self.response.body(());
         Ok(response)
     }
 
+    /// Answers to the HTTP request with the contents of a file, usually taken from a binary column
+    fn download(mut self, data: &JsonValue) -> anyhow::Result<HttpResponse> {
+        let contents = get_object_str(data, "contents")
+            .with_context(|| "The download component requires a 'contents' property")?;
+        let filename = get_object_str(data, "filename");
+        let (data_url_type, body) = match contents.strip_prefix("data:") {
+            Some(data_url) => {
+                let (mime, bytes) = decode_data_url(data_url)?;
+                (Some(mime), bytes)
+            }
+            None => (None, contents.as_bytes().to_vec()),
+        };
+        let content_type = match get_object_str(data, "content_type") {
+            Some(content_type) => content_type.to_string(),
+            None => data_url_type
+                .filter(|mime| !mime.is_empty() && mime != "application/octet-stream")
+                .or_else(|| {
+                    filename
+                        .and_then(|f| mime_guess::from_path(f).first())
+                        .map(|mime| mime.to_string())
+                })
+                .unwrap_or_else(|| "application/octet-stream".into()),
+        };
+        self.response
+            .insert_header((header::CONTENT_TYPE, content_type));
+        let inline = data.get("inline");
+        let inline = inline.is_some_and(|v| v != &json!(false) && v != &json!(0) && !v.is_null());
+        let disposition = match (inline, filename) {
+            (false, Some(filename)) => Some(header::ContentDisposition::attachment(filename)),
+            (false, None) => Some(header::ContentDisposition {
+                disposition: header::DispositionType::Attachment,
+                parameters: vec![],
+            }),
+            (true, Some(filename)) => Some(header::ContentDisposition {
+                disposition: header::DispositionType::Inline,
+                parameters: vec![header::DispositionParam::Filename(filename.to_string())],
+            }),
+            (true, None) => None,
+        };
+        if let Some(disposition) = disposition {
+            self.response.insert_header(disposition);
+        }
+        let range = self.request_context.range.as_ref();
+        Ok(ranged_response(self.response, range, body))
+    }
+
     /// Answers to the HTTP request with a single json object
     fn json(mut self, data: &JsonValue) -> anyhow::Result<PageContext> {
         self.response
@@ -276,6 +421,55 @@ impl HeaderContext {
         }
     }
 
+    /// Sends the rows as server-sent events, and executes the page again to send new events
+    fn event_stream(mut self, data: &JsonValue) -> anyhow::Result<PageContext> {
+        let directive = EventStreamDirective::from_properties(data)?;
+        self.response
+            .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+            .insert_header((header::CACHE_CONTROL, "no-cache"));
+        self.response.extensions_mut().insert(directive);
+        let renderer = AnyRenderBodyContext::Json(JsonBodyRenderer::new_event_stream(self.writer));
+        let http_response = self.response.take();
+        Ok(PageContext::Body {
+            renderer,
+            http_response,
+        })
+    }
+
+    /// Answers with a zip archive of the files and csv exports in the following rows
+    fn zip(mut self, options: &JsonValue) -> PageContext {
+        self.response
+            .insert_header((header::CONTENT_TYPE, "application/zip"));
+        let filename = get_object_str(options, "filename")
+            .or_else(|| get_object_str(options, "title"))
+            .unwrap_or("export");
+        let extension = if filename.contains('.') { "" } else { ".zip" };
+        self.response.insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename={filename}{extension}"),
+        ));
+        let renderer = AnyRenderBodyContext::Zip(Box::new(ZipBodyRenderer::new(self.writer)));
+        let http_response = self.response.take();
+        PageContext::Body {
+            renderer,
+            http_response,
+        }
+    }
+
+    /// Answers with metrics in the Prometheus text exposition format
+    fn prometheus(mut self) -> PageContext {
+        self.response.insert_header((
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        ));
+        let renderer = AnyRenderBodyContext::Prometheus(PrometheusBodyRenderer::new(self.writer));
+        let http_response = self.response.take();
+        PageContext::Body {
+            renderer,
+            http_response,
+        }
+    }
+
     async fn csv(mut self, options: &JsonValue) -> anyhow::Result<PageContext> {
         self.response
             .insert_header((header::CONTENT_TYPE, "text/csv; charset=utf-8"));
@@ -297,6 +491,26 @@ impl HeaderContext {
         })
     }
 
+    fn xlsx(mut self, options: &JsonValue) -> anyhow::Result<PageContext> {
+        self.response
+            .insert_header((header::CONTENT_TYPE, XLSX_CONTENT_TYPE));
+        let filename = get_object_str(options, "filename")
+            .or_else(|| get_object_str(options, "title"))
+            .unwrap_or("export");
+        let extension = if filename.contains('.') { "" } else { ".xlsx" };
+        self.response.insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename={filename}{extension}"),
+        ));
+        let xlsx_renderer = XlsxBodyRenderer::new(self.writer, options)?;
+        let renderer = AnyRenderBodyContext::Xlsx(Box::new(xlsx_renderer));
+        let http_response = self.response.take();
+        Ok(PageContext::Body {
+            renderer,
+            http_response,
+        })
+    }
+
     async fn authentication(mut self, mut data: JsonValue) -> anyhow::Result<PageContext> {
         let password_hash = take_object_str(&mut data, "password_hash");
         let password = take_object_str(&mut data, "password");
@@ -327,11 +541,30 @@ impl HeaderContext {
         Ok(PageContext::Close(http_response))
     }
 
-    async fn start_body(self, data: JsonValue) -> anyhow::Result<PageContext> {
-        let html_renderer =
-            HtmlRenderContext::new(self.app_state, self.request_context, self.writer, data)
-                .await
-                .with_context(|| "Failed to create a render context from the header context.")?;
+    async fn start_body(mut self, data: JsonValue) -> anyhow::Result<PageContext> {
+        let component_data_index = self.request_context.component_data_index;
+        if self.request_context.is_embedded_data || component_data_index.is_some() {
+            self.response
+                .insert_header((header::CONTENT_TYPE, "application/json"));
+            let mut json_renderer = JsonBodyRenderer::new_array(self.writer);
+            if let Some(index) = component_data_index {
+                json_renderer = json_renderer.only_component(index);
+            }
+            json_renderer.handle_row(&data)?;
+            return Ok(PageContext::Body {
+                renderer: AnyRenderBodyContext::Json(json_renderer),
+                http_response: self.response,
+            });
+        }
+        let html_renderer = HtmlRenderContext::new(
+            self.app_state,
+            self.request_context,
+            self.writer,
+            data,
+            self.column_types,
+        )
+        .await
+        .with_context(|| "Failed to create a render context from the header context.")?;
         let renderer = AnyRenderBodyContext::Html(html_renderer);
         let http_response = self.response;
         Ok(PageContext::Body {
@@ -374,6 +607,79 @@ fn get_object_str<'a>(json: &'a JsonValue, key: &str) -> Option<&'a str> {
         .and_then(JsonValue::as_str)
 }
 
+/// A url, or a json array of urls
+fn get_object_urls(json: &JsonValue, key: &str) -> anyhow::Result<Vec<String>> {
+    let urls = match json.get(key) {
+        None | Some(JsonValue::Null) => return Ok(vec![]),
+        Some(JsonValue::String(s)) if s.trim_start().starts_with('[') => {
+            serde_json::from_str(s).with_context(|| format!("Invalid list of urls in {key}"))?
+        }
+        Some(JsonValue::String(s)) => JsonValue::String(s.clone()),
+        Some(other) => other.clone(),
+    };
+    match urls {
+        JsonValue::String(url) => Ok(vec![url]),
+        JsonValue::Array(urls) => urls
+            .into_iter()
+            .map(|url| match url {
+                JsonValue::String(url) => Ok(url),
+                other => bail!("Invalid url in {key}: {other}"),
+            })
+            .collect(),
+        other => bail!("{key} must be a url or a json array of urls, not {other}"),
+    }
+}
+
+/// The `as` attribute of a preload link, from the extension of the preloaded file
+fn preload_destination(url: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    match (mime.type_().as_str(), mime.subtype().as_str()) {
+        ("image", _) => "image",
+        ("font", _) => "font",
+        ("text", "css") => "style",
+        (_, "javascript") => "script",
+        _ => "fetch",
+    }
+}
+
+/// The status of the `redirect` component: 301, 302, 303, 307 or 308
+fn redirect_status(status: &JsonValue) -> anyhow::Result<StatusCode> {
+    let code = match status {
+        JsonValue::Number(n) => n.as_u64(),
+        JsonValue::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    match code {
+        Some(code @ (301 | 302 | 303 | 307 | 308)) => Ok(StatusCode::from_u16(
+            u16::try_from(code).expect("redirect status codes fit in a u16"),
+        )?),
+        _ => bail!(
+            "Invalid redirect status {status}. The status of the redirect component must be 301, 302, 303, 307 or 308"
+        ),
+    }
+}
+
+/// Decodes the part of a data URL that follows `data:`, into its media type and its contents
+pub(crate) fn decode_data_url(data_url: &str) -> anyhow::Result<(String, Vec<u8>)> {
+    use base64::Engine;
+    let (metadata, encoded) = data_url
+        .split_once(',')
+        .with_context(|| "Invalid data URL: missing comma")?;
+    let (mime, is_base64) = match metadata.strip_suffix(";base64") {
+        Some(mime) => (mime, true),
+        None => (metadata, false),
+    };
+    let bytes = if is_base64 {
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .with_context(|| "Invalid base64 in data URL")?
+    } else {
+        percent_encoding::percent_decode_str(encoded).collect()
+    };
+    Ok((mime.trim().to_string(), bytes))
+}
+
 fn take_object_str(json: &mut JsonValue, key: &str) -> Option<String> {
     match json.get_mut(key)?.take() {
         JsonValue::String(s) => Some(s),
@@ -388,6 +694,9 @@ pub enum AnyRenderBodyContext {
     Html(HtmlRenderContext<ResponseWriter>),
     Json(JsonBodyRenderer<ResponseWriter>),
     Csv(CsvBodyRenderer),
+    Xlsx(Box<XlsxBodyRenderer>),
+    Zip(Box<ZipBodyRenderer>),
+    Prometheus(PrometheusBodyRenderer<ResponseWriter>),
 }
 
 /**
@@ -403,6 +712,9 @@ impl AnyRenderBodyContext {
             AnyRenderBodyContext::Html(render_context) => render_context.handle_row(data).await,
             AnyRenderBodyContext::Json(json_body_renderer) => json_body_renderer.handle_row(data),
             AnyRenderBodyContext::Csv(csv_renderer) => csv_renderer.handle_row(data).await,
+            AnyRenderBodyContext::Xlsx(xlsx_renderer) => xlsx_renderer.handle_row(data),
+            AnyRenderBodyContext::Zip(zip_renderer) => zip_renderer.handle_row(data),
+            AnyRenderBodyContext::Prometheus(renderer) => renderer.handle_row(data),
         }
     }
     pub async fn handle_error(&mut self, error: &anyhow::Error) -> anyhow::Result<()> {
@@ -413,6 +725,14 @@ impl AnyRenderBodyContext {
                 json_body_renderer.handle_error(error)
             }
             AnyRenderBodyContext::Csv(csv_renderer) => csv_renderer.handle_error(error).await,
+            AnyRenderBodyContext::Xlsx(xlsx_renderer) => xlsx_renderer.handle_error(error),
+            AnyRenderBodyContext::Zip(zip_renderer) => zip_renderer.handle_error(error),
+            AnyRenderBodyContext::Prometheus(renderer) => renderer.handle_error(error),
+        }
+    }
+    pub fn set_column_types(&mut self, column_types: JsonValue) {
+        if let AnyRenderBodyContext::Html(render_context) = self {
+            render_context.row_settings.column_types = column_types;
         }
     }
     pub async fn finish_query(&mut self) -> anyhow::Result<()> {
@@ -420,25 +740,46 @@ impl AnyRenderBodyContext {
             AnyRenderBodyContext::Html(render_context) => render_context.finish_query().await,
             AnyRenderBodyContext::Json(_json_body_renderer) => Ok(()),
             AnyRenderBodyContext::Csv(_csv_renderer) => Ok(()),
+            AnyRenderBodyContext::Xlsx(_xlsx_renderer) => Ok(()),
+            AnyRenderBodyContext::Zip(_zip_renderer) => Ok(()),
+            AnyRenderBodyContext::Prometheus(_renderer) => Ok(()),
         }
     }
 
     pub async fn flush(&mut self) -> anyhow::Result<()> {
         match self {
             AnyRenderBodyContext::Html(HtmlRenderContext { writer, .. })
-            | AnyRenderBodyContext::Json(JsonBodyRenderer { writer, .. }) => {
+            | AnyRenderBodyContext::Json(JsonBodyRenderer { writer, .. })
+            | AnyRenderBodyContext::Prometheus(PrometheusBodyRenderer { writer, .. }) => {
                 writer.async_flush().await?;
             }
             AnyRenderBodyContext::Csv(csv_renderer) => csv_renderer.flush().await?,
+            AnyRenderBodyContext::Xlsx(xlsx_renderer) => xlsx_renderer.flush().await?,
+            AnyRenderBodyContext::Zip(zip_renderer) => zip_renderer.flush().await?,
         }
         Ok(())
     }
 
+    /// Resolves when the client stops receiving the response
+    pub async fn closed(&self) {
+        match self {
+            AnyRenderBodyContext::Html(HtmlRenderContext { writer, .. })
+            | AnyRenderBodyContext::Json(JsonBodyRenderer { writer, .. })
+            | AnyRenderBodyContext::Prometheus(PrometheusBodyRenderer { writer, .. }) => {
+                writer.closed().await;
+            }
+            _ => std::future::pending().await,
+        }
+    }
+
     pub async fn close(self) -> ResponseWriter {
         match self {
             AnyRenderBodyContext::Html(render_context) => render_context.close().await,
             AnyRenderBodyContext::Json(json_body_renderer) => json_body_renderer.close(),
             AnyRenderBodyContext::Csv(csv_renderer) => csv_renderer.close().await,
+            AnyRenderBodyContext::Xlsx(xlsx_renderer) => xlsx_renderer.close(),
+            AnyRenderBodyContext::Zip(zip_renderer) => zip_renderer.close(),
+            AnyRenderBodyContext::Prometheus(renderer) => renderer.writer,
         }
     }
 }
@@ -449,6 +790,9 @@ pub struct JsonBodyRenderer<W: std::io::Write> {
     prefix: &'static [u8],
     suffix: &'static [u8],
     separator: &'static [u8],
+    /// Written after each row, so that the client can handle it without waiting for the next one
+    row_end: &'static [u8],
+    component_filter: Option<ComponentRowsFilter>,
 }
 
 impl<W: std::io::Write> JsonBodyRenderer<W> {
@@ -459,6 +803,8 @@ impl<W: std::io::Write> JsonBodyRenderer<W> {
             prefix: b"[\n",
             suffix: b"\n]",
             separator: b",\n",
+            row_end: b"",
+            component_filter: None,
         };
         let _ = renderer.write_prefix();
         renderer
@@ -470,6 +816,8 @@ impl<W: std::io::Write> JsonBodyRenderer<W> {
             prefix: b"",
             suffix: b"",
             separator: b"\n",
+            row_end: b"",
+            component_filter: None,
         };
         renderer.write_prefix().unwrap();
         renderer
@@ -481,25 +829,59 @@ impl<W: std::io::Write> JsonBodyRenderer<W> {
             prefix: b"data: ",
             suffix: b"\n\n",
             separator: b"\n\ndata: ",
+            row_end: b"",
+            component_filter: None,
+        };
+        renderer.write_prefix().unwrap();
+        renderer
+    }
+    /// Server-sent events that are each sent as soon as their row is received
+    pub fn new_event_stream(writer: W) -> JsonBodyRenderer<W> {
+        let mut renderer = Self {
+            writer,
+            is_first: true,
+            prefix: b"data: ",
+            suffix: b"",
+            separator: b"data: ",
+            row_end: b"\n\n",
+            component_filter: None,
         };
         renderer.write_prefix().unwrap();
         renderer
     }
+    /// Only write the data rows of the component whose `@component_index` is `component_index`
+    #[must_use]
+    pub fn only_component(mut self, component_index: usize) -> Self {
+        self.component_filter = Some(ComponentRowsFilter {
+            component_index,
+            current_index: None,
+        });
+        self
+    }
     fn write_prefix(&mut self) -> anyhow::Result<()> {
         self.writer.write_all(self.prefix)?;
         Ok(())
     }
     pub fn handle_row(&mut self, data: &JsonValue) -> anyhow::Result<()> {
+        if let Some(filter) = &mut self.component_filter {
+            if !filter.accepts(data) {
+                return Ok(());
+            }
+        }
+        self.write_row(data)
+    }
+    fn write_row(&mut self, data: &JsonValue) -> anyhow::Result<()> {
         if self.is_first {
             self.is_first = false;
         } else {
             let _ = self.writer.write_all(self.separator);
         }
         serde_json::to_writer(&mut self.writer, data)?;
+        self.writer.write_all(self.row_end)?;
         Ok(())
     }
     pub fn handle_error(&mut self, error: &anyhow::Error) -> anyhow::Result<()> {
-        self.handle_row(&json!({
+        self.write_row(&json!({
             "error": error.to_string()
         }))
     }
@@ -510,6 +892,127 @@ impl<W: std::io::Write> JsonBodyRenderer<W> {
     }
 }
 
+/// Index given to the first component after the shell, numbered like in [`HtmlRenderContext`]
+const FIRST_COMPONENT_INDEX: usize = 2;
+
+/// Follows the components opened by the rows of a page, to select the data rows of one of them
+struct ComponentRowsFilter {
+    component_index: usize,
+    /// `None` until the first component after the shell is opened
+    current_index: Option<usize>,
+}
+
+impl ComponentRowsFilter {
+    fn accepts(&mut self, row: &JsonValue) -> bool {
+        match get_object_str(row, "component") {
+            Some(component) if component.starts_with(PAGE_SHELL_COMPONENT) => false,
+            Some(_) => {
+                self.current_index =
+                    Some(self.current_index.map_or(FIRST_COMPONENT_INDEX, |i| i + 1));
+                false
+            }
+            // Data rows before any component are rendered with the default component
+            None => {
+                *self.current_index.get_or_insert(FIRST_COMPONENT_INDEX) == self.component_index
+            }
+        }
+    }
+}
+
+/// Writes rows with `name`, `value`, and optional `labels`, `help`, `type` and `timestamp` properties
+/// in the [Prometheus text exposition format](https://prometheus.io/docs/instrumenting/exposition_formats/)
+pub struct PrometheusBodyRenderer<W: std::io::Write> {
+    writer: W,
+    /// Metrics for which the HELP and TYPE lines were already written
+    described_metrics: std::collections::HashSet<String>,
+}
+
+impl<W: std::io::Write> PrometheusBodyRenderer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            described_metrics: std::collections::HashSet::new(),
+        }
+    }
+
+    pub fn handle_row(&mut self, data: &JsonValue) -> anyhow::Result<()> {
+        let name = get_object_str(data, "name").with_context(|| {
+            format!("The prometheus component requires a 'name' property, got {data}")
+        })?;
+        let is_valid_name = name.chars().enumerate().all(|(i, c)| {
+            c.is_ascii_alphabetic() || c == '_' || c == ':' || (i > 0 && c.is_ascii_digit())
+        });
+        if name.is_empty() || !is_valid_name {
+            bail!("Invalid prometheus metric name: {name:?}. Metric names can only contain letters, digits, '_' and ':'.");
+        }
+        let value = prometheus_value(data.get("value").unwrap_or(&JsonValue::Null))
+            .with_context(|| format!("Invalid value for the prometheus metric {name}"))?;
+        if !self.described_metrics.contains(name) {
+            if let Some(help) = get_object_str(data, "help") {
+                let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+                writeln!(self.writer, "# HELP {name} {help}")?;
+            }
+            if let Some(metric_type) = get_object_str(data, "type") {
+                writeln!(self.writer, "# TYPE {name} {metric_type}")?;
+            }
+            self.described_metrics.insert(name.to_string());
+        }
+        write!(self.writer, "{name}")?;
+        let labels = match data.get("labels") {
+            Some(JsonValue::String(s)) => serde_json::from_str(s).with_context(|| {
+                format!("The labels of {name} are not a valid json object: {s}")
+            })?,
+            Some(labels) => labels.clone(),
+            None => JsonValue::Null,
+        };
+        if let Some(labels) = labels.as_object().filter(|l| !l.is_empty()) {
+            let mut separator = '{';
+            for (label, value) in labels {
+                let value = match value {
+                    JsonValue::String(s) => Cow::Borrowed(s.as_str()),
+                    other => Cow::Owned(other.to_string()),
+                };
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                write!(self.writer, "{separator}{label}=\"{value}\"")?;
+                separator = ',';
+            }
+            write!(self.writer, "}}")?;
+        }
+        write!(self.writer, " {value}")?;
+        if let Some(timestamp) = data.get("timestamp").and_then(JsonValue::as_i64) {
+            write!(self.writer, " {timestamp}")?;
+        }
+        writeln!(self.writer)?;
+        Ok(())
+    }
+
+    pub fn handle_error(&mut self, error: &anyhow::Error) -> anyhow::Result<()> {
+        for line in error.to_string().lines() {
+            writeln!(self.writer, "# error: {line}")?;
+        }
+        Ok(())
+    }
+}
+
+fn prometheus_value(value: &JsonValue) -> anyhow::Result<Cow<'_, str>> {
+    Ok(match value {
+        JsonValue::Number(n) => Cow::Owned(n.to_string()),
+        JsonValue::Bool(b) => Cow::Borrowed(if *b { "1" } else { "0" }),
+        JsonValue::String(s) if matches!(s.as_str(), "NaN" | "+Inf" | "-Inf") => Cow::Borrowed(s),
+        JsonValue::String(s) => {
+            let n: f64 = s
+                .trim()
+                .parse()
+                .with_context(|| format!("{s:?} is not a number"))?;
+            Cow::Owned(n.to_string())
+        }
+        other => bail!("expected a number, got {other}"),
+    })
+}
+
 pub struct CsvBodyRenderer {
     // The writer is a large struct, so we store it on the heap
     writer: Box<csv_async::AsyncWriter<AsyncResponseWriter>>,
@@ -621,9 +1124,47 @@ pub struct HtmlRenderContext<W: std::io::Write> {
     shell_renderer: SplitTemplateRenderer,
     current_statement: usize,
     request_context: RequestContext,
+    accessibility_audit: Option<AccessibilityAudit>,
+    // Stored on the heap to keep the renderer small
+    row_settings: Box<RowSettings>,
+}
+
+/// Settings that apply to each row of the current query and component
+struct RowSettings {
+    /// Types of the columns of the rows of the current query
+    column_types: JsonValue,
+    /// The `null_display` property of the current component
+    null_display: JsonValue,
+    /// Number of components rendered inside the values of a row
+    embedded_components: usize,
+    /// The rows of the current component, when it is rendered only once it is closed
+    deferred: Option<DeferredComponent>,
+}
+
+/// Components that need all their rows before they can be rendered
+enum DeferredComponent {
+    /// Statistics about the rows of a `profile` component
+    Profile(DataProfile),
+    /// The nodes and edges of a `graph` component
+    Graph(NetworkGraph),
+    /// The people of an `org_chart` component
+    OrgChart(OrgChart),
+}
+
+/// Key of the object that replaces a column containing a component, with the HTML of the component
+pub const EMBEDDED_HTML_KEY: &str = "_sqlpage_embedded_html";
+/// Components embedded in rows are numbered separately from the components of the page, to keep their ids unique
+const FIRST_EMBEDDED_COMPONENT_INDEX: usize = 1_000_000;
+
+fn is_embedded_component(value: &JsonValue) -> bool {
+    value.get("component").is_some_and(JsonValue::is_string)
 }
 
 const DEFAULT_COMPONENT: &str = "table";
+const PROFILE_COMPONENT: &str = "profile";
+const GRAPH_COMPONENT: &str = "graph";
+const ORG_CHART_COMPONENT: &str = "org_chart";
+const FORM_COMPONENT: &str = "form";
 const PAGE_SHELL_COMPONENT: &str = "shell";
 const FRAGMENT_SHELL_COMPONENT: &str = "shell-empty";
 
@@ -633,6 +1174,7 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
         request_context: RequestContext,
         mut writer: W,
         initial_row: JsonValue,
+        column_types: JsonValue,
     ) -> anyhow::Result<HtmlRenderContext<W>> {
         log::debug!("Creating the shell component for the page");
 
@@ -656,9 +1198,17 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
         }
         let mut rows_iter = initial_rows.into_iter().map(Cow::into_owned);
 
-        let shell_row = rows_iter
+        let mut shell_row = rows_iter
             .next()
             .expect("shell row should exist at this point");
+        if request_context.is_iframe {
+            if let Some(shell_properties) = shell_row.as_object_mut() {
+                shell_properties.insert("iframe".into(), JsonValue::Bool(true));
+                // The framed page only sends messages to the websites allowed to embed it
+                let origins = &app_state.config.embed_allowed_origins;
+                shell_properties.insert("iframe_origins".into(), json!(origins));
+            }
+        }
         let mut shell_component =
             get_object_str(&shell_row, "component").expect("shell should exist");
         if request_context.is_embedded && shell_component != FRAGMENT_SHELL_COMPONENT {
@@ -675,6 +1225,9 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
         )
         .await
         .with_context(|| "The shell component should always exist")?;
+        let accessibility_audit = (app_state.config.accessibility_audit
+            && !app_state.config.environment.is_prod())
+        .then(|| AccessibilityAudit::new(&shell_row));
         log::debug!("Rendering the shell with properties: {shell_row}");
         shell_renderer.render_start(&mut writer, shell_row)?;
 
@@ -685,6 +1238,13 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
             shell_renderer,
             current_statement: 1,
             request_context,
+            accessibility_audit,
+            row_settings: Box::new(RowSettings {
+                column_types,
+                null_display: JsonValue::Null,
+                embedded_components: 0,
+                deferred: None,
+            }),
         };
 
         for row in rows_iter {
@@ -698,19 +1258,52 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
         component.starts_with(PAGE_SHELL_COMPONENT)
     }
 
+    /// Adds a signed spam protection token to the properties of the forms with `spam_protection` or `captcha`
+    fn add_form_token<'a>(
+        &self,
+        component: &str,
+        data: &'a JsonValue,
+    ) -> anyhow::Result<Cow<'a, JsonValue>> {
+        let is_set = |property| data.get(property).is_some_and(|v| v.is_truthy(false));
+        let captcha = is_set("captcha");
+        if component != FORM_COMPONENT || !(captcha || is_set("spam_protection")) {
+            return Ok(Cow::Borrowed(data));
+        }
+        let action = spam_protection::form_action(
+            &self.request_context.path,
+            data.get("action").and_then(JsonValue::as_str),
+        );
+        let config = &self.app_state.config;
+        let token = self.app_state.spam_protection.new_form_token(
+            &action,
+            config.form_proof_of_work_difficulty,
+            captcha,
+        )?;
+        let mut data = data.clone();
+        if let Some(properties) = data.as_object_mut() {
+            properties.insert(spam_protection::TOKEN_FIELD.into(), token.into());
+        }
+        Ok(Cow::Owned(data))
+    }
+
     pub async fn handle_row(&mut self, data: &JsonValue) -> anyhow::Result<()> {
         let new_component = get_object_str(data, "component");
         let current_component = self
             .current_component
             .as_ref()
             .map(SplitTemplateRenderer::name);
+        self.audit_accessibility(new_component.or(current_component), data);
         if let Some(comp_str) = new_component {
             if Self::is_shell_component(comp_str) {
                 bail!("There cannot be more than a single shell per page. You are trying to open the {} component, but a shell component is already opened for the current page. You can fix this by removing the extra shell component, or by moving this component to the top of the SQL file, before any other component that displays data.", comp_str);
             }
 
+            let data = self.add_form_token(comp_str, data)?;
             match self.open_component_with_data(comp_str, &data).await {
-                Ok(_) => (),
+                Ok(_) => {
+                    self.row_settings.null_display =
+                        data.get("null_display").cloned().unwrap_or_default();
+                }
                 Err(err) => match HeaderComponent::try_from(comp_str) {
                     Ok(_) => bail!("The {comp_str} component cannot be used after data has already been sent to the client's browser. \n\
                                     This component must be used before any other component. \n\
@@ -719,20 +1312,111 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
                     Err(()) => return Err(err),
                 },
             }
-        } else if current_component.is_none() {
-            self.open_component_with_data(DEFAULT_COMPONENT, &JsonValue::Null)
-                .await?;
-            self.render_current_template_with_data(&data).await?;
         } else {
+            if current_component.is_none() {
+                self.open_component_with_data(DEFAULT_COMPONENT, &JsonValue::Null)
+                    .await?;
+            }
+            match &mut self.row_settings.deferred {
+                Some(DeferredComponent::Profile(profile)) => {
+                    profile.add_row(data);
+                    return Ok(());
+                }
+                Some(DeferredComponent::Graph(graph)) => return graph.add_row(data),
+                Some(DeferredComponent::OrgChart(chart)) => return chart.add_row(data),
+                None => {}
+            }
+            let data = if self.row_settings.null_display.is_null() {
+                Cow::Borrowed(data)
+            } else if let Some(data) = apply_null_display(data, &self.row_settings.null_display) {
+                Cow::Owned(data)
+            } else {
+                return Ok(());
+            };
+            let data = self.render_embedded_components(data).await?;
             self.render_current_template_with_data(&data).await?;
         }
         Ok(())
     }
 
+    /// Replaces the values of the row that describe a component, like `{"component": "button", "rows": [...]}`,
+    /// with the HTML of this component, that templates display with the `component_or_text` helper.
+    async fn render_embedded_components<'a>(
+        &mut self,
+        data: Cow<'a, JsonValue>,
+    ) -> anyhow::Result<Cow<'a, JsonValue>> {
+        let has_embedded_components = data
+            .as_object()
+            .is_some_and(|row| row.values().any(is_embedded_component));
+        if !has_embedded_components {
+            return Ok(data);
+        }
+        let mut data = data.into_owned();
+        for (column, value) in data.as_object_mut().into_iter().flatten() {
+            if is_embedded_component(value) {
+                let html = self
+                    .render_embedded_component(value)
+                    .await
+                    .with_context(|| {
+                        format!("Unable to render the component embedded in the {column:?} column")
+                    })?;
+                *value = json!({ EMBEDDED_HTML_KEY: html });
+            }
+        }
+        Ok(Cow::Owned(data))
+    }
+
+    async fn render_embedded_component(
+        &mut self,
+        definition: &JsonValue,
+    ) -> anyhow::Result<String> {
+        let mut properties = definition.clone();
+        let rows = properties
+            .as_object_mut()
+            .and_then(|properties| properties.remove("rows"))
+            .unwrap_or_default();
+        let component = get_object_str(&properties, "component").unwrap_or_default();
+        self.row_settings.embedded_components += 1;
+        let mut renderer = Self::create_renderer(
+            component,
+            Arc::clone(&self.app_state),
+            FIRST_EMBEDDED_COMPONENT_INDEX + self.row_settings.embedded_components,
+            self.request_context.content_security_policy.nonce,
+        )
+        .await?;
+        let mut html = Vec::new();
+        renderer.render_start(&mut html, properties)?;
+        match rows {
+            JsonValue::Array(rows) => {
+                for row in rows {
+                    renderer.render_item(&mut html, row, &JsonValue::Null)?;
+                }
+            }
+            JsonValue::Null => {}
+            row => renderer.render_item(&mut html, row, &JsonValue::Null)?,
+        }
+        renderer.render_end(&mut html)?;
+        Ok(String::from_utf8(html)?)
+    }
+
+    fn audit_accessibility(&self, component: Option<&str>, data: &JsonValue) {
+        let Some(audit) = &self.accessibility_audit else {
+            return;
+        };
+        let component = component.unwrap_or(DEFAULT_COMPONENT);
+        for problem in audit.check_row(component, data) {
+            log::warn!(
+                "Accessibility problem in query {} ({component} component): {problem}",
+                self.current_statement
+            );
+        }
+    }
+
     #[allow(clippy::unused_async)]
     pub async fn finish_query(&mut self) -> anyhow::Result<()> {
         log::debug!("-> Query {} finished", self.current_statement);
         self.current_statement += 1;
+        self.row_settings.column_types = JsonValue::Null;
         Ok(())
     }
 
@@ -779,12 +1463,20 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
         if self.current_component.is_none() {
             self.set_current_component(DEFAULT_COMPONENT).await?;
         }
-        self.current_component
+        let component = self
+            .current_component
             .as_mut()
-            .expect("just set the current component")
-            .render_item(&mut self.writer, json!(data))?;
+            .expect("just set the current component");
+        let row_index = component.row_index;
+        component
+            .render_item(
+                &mut self.writer,
+                json!(data),
+                &self.row_settings.column_types,
+            )
+            .map_err(|error| self.component_render_error(error, RenderedPart::Row(row_index)))?;
         self.shell_renderer
-            .render_item(&mut self.writer, JsonValue::Null)?;
+            .render_item(&mut self.writer, JsonValue::Null, &JsonValue::Null)?;
         Ok(())
     }
 
@@ -814,11 +1506,11 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
         let current_component_index = self
             .current_component
             .as_ref()
-            .map_or(1, |c| c.component_index);
+            .map_or(FIRST_COMPONENT_INDEX, |c| c.component_index + 1);
         let new_component = Self::create_renderer(
             component,
             Arc::clone(&self.app_state),
-            current_component_index + 1,
+            current_component_index,
             self.request_context.content_security_policy.nonce,
         )
         .await?;
@@ -832,27 +1524,93 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
     ) -> anyhow::Result<Option<SplitTemplateRenderer>> {
         self.close_component()?;
         let old_component = self.set_current_component(component).await?;
+        // These components are rendered when they are closed, once all their rows have been received
+        let deferred = match component {
+            PROFILE_COMPONENT => Some(DeferredComponent::Profile(DataProfile::new(json!(data))?)),
+            GRAPH_COMPONENT => Some(DeferredComponent::Graph(NetworkGraph::new(json!(data))?)),
+            ORG_CHART_COMPONENT => Some(DeferredComponent::OrgChart(OrgChart::new(json!(data))?)),
+            _ => None,
+        };
+        if deferred.is_some() {
+            self.row_settings.deferred = deferred;
+            return Ok(old_component);
+        }
         self.current_component
             .as_mut()
             .expect("just set the current component")
-            .render_start(&mut self.writer, json!(data))?;
+            .render_start(&mut self.writer, json!(data))
+            .map_err(|error| self.component_render_error(error, RenderedPart::TopLevel))?;
         Ok(old_component)
     }
 
     fn close_component(&mut self) -> anyhow::Result<()> {
+        if let Some(deferred) = self.row_settings.deferred.take() {
+            self.render_deferred(deferred)?;
+        }
         if let Some(old_component) = self.current_component.as_mut() {
-            old_component.render_end(&mut self.writer)?;
+            old_component
+                .render_end(&mut self.writer)
+                .map_err(|error| self.component_render_error(error, RenderedPart::End))?;
         }
         Ok(())
     }
 
-    pub async fn close(mut self) -> W {
-        if let Some(old_component) = self.current_component.as_mut() {
-            let res = old_component
-                .render_end(&mut self.writer)
-                .map_err(|e| format_err!("Unable to render the component closing: {e}"));
-            self.handle_result_and_log(&res).await;
+    fn render_deferred(&mut self, deferred: DeferredComponent) -> anyhow::Result<()> {
+        let Some(component) = self.current_component.as_mut() else {
+            return Ok(());
+        };
+        let (properties, rows) = match deferred {
+            DeferredComponent::Profile(profile) => {
+                (profile.properties(), profile.column_statistics())
+            }
+            DeferredComponent::Graph(graph) => (graph.into_properties(), Vec::new()),
+            DeferredComponent::OrgChart(chart) => (chart.properties(), chart.into_rows()),
+        };
+        if let Err(error) = component.render_start(&mut self.writer, properties) {
+            return Err(self.component_render_error(error, RenderedPart::TopLevel));
+        }
+        for (row_index, row) in rows.into_iter().enumerate() {
+            let component = self
+                .current_component
+                .as_mut()
+                .expect("the deferred component is open");
+            if let Err(error) = component.render_item(&mut self.writer, row, &JsonValue::Null) {
+                return Err(self.component_render_error(error, RenderedPart::Row(row_index)));
+            }
         }
+        Ok(())
+    }
+
+    /// Adds the location of the error in the page to an error of the template of the current component
+    fn component_render_error(&self, error: RenderError, part: RenderedPart) -> anyhow::Error {
+        let template = self
+            .current_component
+            .as_ref()
+            .map(|component| &component.split_template);
+        let properties = template
+            .zip(error.line_no.zip(error.column_no))
+            .and_then(|(template, position)| {
+                [
+                    &template.before_list,
+                    &template.list_content,
+                    &template.after_list,
+                ]
+                .into_iter()
+                .find_map(|part| properties_used_at(part, position))
+            })
+            .unwrap_or_default();
+        anyhow::Error::new(ComponentRenderError {
+            query_number: self.current_statement,
+            component: template.map(|t| t.component.clone()).unwrap_or_default(),
+            part,
+            properties,
+            error,
+        })
+    }
+
+    pub async fn close(mut self) -> W {
+        let res = self.close_component();
+        self.handle_result_and_log(&res).await;
         let res = self
             .shell_renderer
             .render_end(&mut self.writer)
@@ -862,6 +1620,102 @@ impl<W: std::io::Write> HtmlRenderContext<W> {
     }
 }
 
+/// The part of a component that was being rendered when an error occurred
+#[derive(Debug, Clone, Copy)]
+enum RenderedPart {
+    TopLevel,
+    /// A row of the component, numbered from 0
+    Row(usize),
+    End,
+}
+
+/// An error in the template of a component, with the location in the page of the data that caused it.
+/// Template errors alone do not tell which query of the SQL file produced the faulty data.
+#[derive(Debug)]
+struct ComponentRenderError {
+    query_number: usize,
+    component: String,
+    part: RenderedPart,
+    /// The properties used by the template expression that failed
+    properties: Vec<String>,
+    error: RenderError,
+}
+
+impl std::fmt::Display for ComponentRenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to render the '{}' component of query {}, ",
+            self.component, self.query_number
+        )?;
+        match self.part {
+            RenderedPart::TopLevel => write!(f, "with its top-level properties")?,
+            RenderedPart::Row(index) => write!(f, "at row {}", index + 1)?,
+            RenderedPart::End => write!(f, "at the end of the component")?,
+        }
+        if !self.properties.is_empty() {
+            write!(
+                f,
+                ". The error comes from the template code that uses: {}",
+                self.properties.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ComponentRenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The names of the variables used by the template expression that starts at `position` (line and column)
+fn properties_used_at(template: &Template, position: (usize, usize)) -> Option<Vec<String>> {
+    template
+        .elements
+        .iter()
+        .zip(&template.mapping)
+        .find_map(|(element, mapping)| {
+            let (TemplateElement::Expression(helper)
+            | TemplateElement::HtmlExpression(helper)
+            | TemplateElement::HelperBlock(helper)) = element
+            else {
+                return None;
+            };
+            if (mapping.0, mapping.1) == position {
+                let mut properties = Vec::new();
+                collect_properties(helper, &mut properties);
+                return Some(properties);
+            }
+            [&helper.template, &helper.inverse]
+                .into_iter()
+                .flatten()
+                .find_map(|nested| properties_used_at(nested, position))
+        })
+}
+
+fn collect_properties(helper: &HelperTemplate, properties: &mut Vec<String>) {
+    let parameters = std::iter::once(&helper.name)
+        .chain(&helper.params)
+        .chain(helper.hash.values());
+    for parameter in parameters {
+        match parameter {
+            Parameter::Path(handlebars::Path::Relative((_, name)))
+                if name != "this" && !properties.contains(name) =>
+            {
+                properties.push(name.clone());
+            }
+            Parameter::Subexpression(subexpression) => {
+                if let TemplateElement::Expression(helper) = subexpression.as_element() {
+                    collect_properties(helper, properties);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 struct HandlebarWriterOutput<W: std::io::Write>(W);
 
 impl<W: std::io::Write> handlebars::Output for HandlebarWriterOutput<W> {
@@ -870,6 +1724,31 @@ impl<W: std::io::Write> handlebars::Output for HandlebarWriterOutput<W> {
     }
 }
 
+/// Replaces the NULL values of a row according to the `null_display` property of its component:
+/// a replacement for all the columns, or an object with a replacement for each column.
+/// A replacement of `false` hides the rows where the column is NULL.
+fn apply_null_display(data: &JsonValue, null_display: &JsonValue) -> Option<JsonValue> {
+    let Some(row) = data.as_object() else {
+        return Some(data.clone());
+    };
+    let mut row = row.clone();
+    for (column, value) in &mut row {
+        if !value.is_null() {
+            continue;
+        }
+        let replacement = match null_display {
+            JsonValue::Object(columns) => columns.get(column),
+            all_columns => Some(all_columns),
+        };
+        match replacement {
+            Some(JsonValue::Bool(false)) => return None,
+            Some(replacement) => value.clone_from(replacement),
+            None => {}
+        }
+    }
+    Some(JsonValue::Object(row))
+}
+
 pub struct SplitTemplateRenderer {
     split_template: Arc<SplitTemplate>,
     // LocalVars is a large struct, so we store it on the heap
@@ -904,11 +1783,7 @@ impl SplitTemplateRenderer {
         }
     }
     fn name(&self) -> &str {
-        self.split_template
-            .list_content
-            .name
-            .as_deref()
-            .unwrap_or_default()
+        &self.split_template.component
     }
 
     fn render_start<W: std::io::Write>(
@@ -951,6 +1826,7 @@ impl SplitTemplateRenderer {
         &mut self,
         writer: W,
         data: JsonValue,
+        column_types: &JsonValue,
     ) -> Result<(), RenderError> {
         log::trace!("Rendering a new item in the page: {data:?}");
         if let Some(local_vars) = self.local_vars.take() {
@@ -964,6 +1840,9 @@ impl SplitTemplateRenderer {
             blk.set_local_var("component_index", self.component_index.into());
             blk.set_local_var("row_index", self.row_index.into());
             blk.set_local_var("csp_nonce", self.nonce.into());
+            if !column_types.is_null() {
+                blk.set_local_var("column_types", column_types.clone());
+            }
             render_context.push_block(blk);
             let mut output = HandlebarWriterOutput(writer);
             self.split_template.list_content.render(
@@ -1033,8 +1912,8 @@ mod tests {
         let app_state = Arc::new(AppState::init(&config).await.unwrap());
         let mut rdr = SplitTemplateRenderer::new(Arc::new(split), app_state, 0, 0);
         rdr.render_start(&mut output, json!({"name": "SQL"}))?;
-        rdr.render_item(&mut output, json!({"x": 1}))?;
-        rdr.render_item(&mut output, json!({"x": 2}))?;
+        rdr.render_item(&mut output, json!({"x": 1}), &JsonValue::Null)?;
+        rdr.render_item(&mut output, json!({"x": 2}), &JsonValue::Null)?;
         rdr.render_end(&mut output)?;
         assert_eq!(
             String::from_utf8_lossy(&output),
@@ -1043,6 +1922,91 @@ mod tests {
         Ok(())
     }
 
+    #[actix_web::test]
+    async fn test_column_types() -> anyhow::Result<()> {
+        let template = Template::compile(
+            "{{#each_row}}{{x}} is a {{lookup @column_types \"x\"}}. {{/each_row}}",
+        )?;
+        let split = split_template(template);
+        let mut output = Vec::new();
+        let config = app_config::tests::test_config();
+        let app_state = Arc::new(AppState::init(&config).await.unwrap());
+        let mut rdr = SplitTemplateRenderer::new(Arc::new(split), app_state, 0, 0);
+        rdr.render_start(&mut output, json!(null))?;
+        rdr.render_item(&mut output, json!({"x": 1}), &json!({"x": "number"}))?;
+        rdr.render_item(&mut output, json!({"x": "a"}), &json!({"x": "text"}))?;
+        rdr.render_end(&mut output)?;
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "1 is a number. a is a text. "
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_render_error_properties() -> anyhow::Result<()> {
+        let template = Template::compile(
+            "<ul>{{#each_row}}\n{{#if title}}<li>{{no_such_helper (concat name \"!\") size=this.size}}</li>{{/if}}{{/each_row}}</ul>",
+        )?;
+        let split = split_template(template);
+        let config = app_config::tests::test_config();
+        let app_state = Arc::new(AppState::init(&config).await.unwrap());
+        let mut rdr = SplitTemplateRenderer::new(Arc::new(split), app_state, 0, 0);
+        let mut output = Vec::new();
+        rdr.render_start(&mut output, json!(null))?;
+        let error = rdr
+            .render_item(&mut output, json!({"title": "x"}), &JsonValue::Null)
+            .unwrap_err();
+        let position = error.line_no.zip(error.column_no).unwrap();
+        assert_eq!(
+            properties_used_at(&rdr.split_template.list_content, position),
+            Some(vec!["name".to_string(), "this.size".to_string()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_preload_destination() {
+        assert_eq!(preload_destination("/logo.png?v=2"), "image");
+        assert_eq!(preload_destination("/style.css"), "style");
+        assert_eq!(preload_destination("/app.js"), "script");
+        assert_eq!(
+            preload_destination("/fragment.sql?_sqlpage_embed=1"),
+            "fetch"
+        );
+    }
+
+    #[test]
+    fn test_decode_data_url() {
+        assert_eq!(
+            decode_data_url("image/png;base64,iVBORw0KGgo=").unwrap(),
+            ("image/png".into(), b"\x89PNG\r\n\x1a\n".to_vec())
+        );
+        assert_eq!(
+            decode_data_url("text/plain;charset=utf-8,caf%C3%A9").unwrap(),
+            (
+                "text/plain;charset=utf-8".into(),
+                "café".as_bytes().to_vec()
+            )
+        );
+        assert!(decode_data_url("image/png;base64").is_err());
+    }
+
+    #[test]
+    fn test_null_display() {
+        let row = json!({"name": "Alice", "age": null, "city": null});
+        assert_eq!(
+            apply_null_display(&row, &json!("N/A")),
+            Some(json!({"name": "Alice", "age": "N/A", "city": "N/A"}))
+        );
+        assert_eq!(
+            apply_null_display(&row, &json!({"age": "-"})),
+            Some(json!({"name": "Alice", "age": "-", "city": null}))
+        );
+        assert_eq!(apply_null_display(&row, &json!({"city": false})), None);
+        assert_eq!(apply_null_display(&row, &json!({"name": false})), Some(row));
+    }
+
     #[actix_web::test]
     async fn test_delayed() -> anyhow::Result<()> {
         let template = Template::compile(
@@ -1054,8 +2018,8 @@ mod tests {
         let app_state = Arc::new(AppState::init(&config).await.unwrap());
         let mut rdr = SplitTemplateRenderer::new(Arc::new(split), app_state, 0, 0);
         rdr.render_start(&mut output, json!(null))?;
-        rdr.render_item(&mut output, json!({"x": 1}))?;
-        rdr.render_item(&mut output, json!({"x": 2}))?;
+        rdr.render_item(&mut output, json!({"x": 1}), &JsonValue::Null)?;
+        rdr.render_item(&mut output, json!({"x": 2}), &JsonValue::Null)?;
         rdr.render_end(&mut output)?;
         assert_eq!(
             String::from_utf8_lossy(&output),
@@ -1072,8 +2036,15 @@ enum HeaderComponent {
     Redirect,
     Json,
     Csv,
+    Xlsx,
+    Zip,
+    Prometheus,
     Cookie,
     Authentication,
+    Etag,
+    Download,
+    Cache,
+    EventStream,
 }
 
 impl TryFrom<&str> for HeaderComponent {
@@ -1085,8 +2056,15 @@ impl TryFrom<&str> for HeaderComponent {
             "redirect" => Ok(Self::Redirect),
             "json" => Ok(Self::Json),
             "csv" => Ok(Self::Csv),
+            "xlsx" => Ok(Self::Xlsx),
+            "zip" => Ok(Self::Zip),
+            "prometheus" => Ok(Self::Prometheus),
             "cookie" => Ok(Self::Cookie),
             "authentication" => Ok(Self::Authentication),
+            "etag" => Ok(Self::Etag),
+            "download" => Ok(Self::Download),
+            "cache" => Ok(Self::Cache),
+            "event_stream" => Ok(Self::EventStream),
             _ => Err(()),
         }
     }
@@ -27,6 +27,7 @@ pub fn register_all_helpers(h: &mut Handlebars<'_>, config: &AppConfig) {
     register_helper(h, "any", HelperCheckTruthy(true));
 
     register_helper(h, "stringify", stringify_helper as H);
+    register_helper(h, "component_or_text", component_or_text_helper as H);
     register_helper(h, "parse_json", parse_json_helper as EH);
     register_helper(h, "default", default_helper as HH);
     register_helper(h, "entries", entries_helper as H);
@@ -71,6 +72,7 @@ pub fn register_all_helpers(h: &mut Handlebars<'_>, config: &AppConfig) {
     register_helper(h, "buildinfo", buildinfo_helper as EH);
     register_helper(h, "typeof", typeof_helper as H);
     register_helper(h, "rfc2822_date", rfc2822_date_helper as EH);
+    register_helper(h, "period_bounds", period_bounds_helper as HHH);
     register_helper(h, "url_encode", url_encode_helper as H);
     register_helper(h, "csv_escape", csv_escape_helper as HH);
 }
@@ -86,6 +88,14 @@ fn stringify_helper(v: &JsonValue) -> JsonValue {
     v.to_string().into()
 }
 
+/// The HTML of a component embedded in a row, or the value as escaped text, to be used in a triple-stash: `{{{component_or_text x}}}`
+fn component_or_text_helper(v: &JsonValue) -> JsonValue {
+    if let Some(html) = v.get(crate::render::EMBEDDED_HTML_KEY) {
+        return html.clone();
+    }
+    handlebars::html_escape(&handlebars::JsonRender::render(v)).into()
+}
+
 fn parse_json_helper(v: &JsonValue) -> Result<JsonValue, anyhow::Error> {
     Ok(match v {
         serde_json::value::Value::String(s) => serde_json::from_str(s)?,
@@ -203,6 +213,10 @@ impl CanHelp for AppConfigHelper {
             "max_uploaded_file_size" => Ok(JsonValue::Number(self.0.max_uploaded_file_size.into())),
             "environment" => serde_json::to_value(self.0.environment).map_err(|e| e.to_string()),
             "site_prefix" => Ok(self.0.site_prefix.clone().into()),
+            "captcha_provider" => {
+                serde_json::to_value(self.0.captcha_provider).map_err(|e| e.to_string())
+            }
+            "captcha_site_key" => Ok(self.0.captcha_site_key.clone().into()),
             other => Err(format!("unknown app config property: {other:?}")),
         }
     }
@@ -363,6 +377,60 @@ fn rfc2822_date_helper(v: &JsonValue) -> anyhow::Result<JsonValue> {
     Ok(date.format("%a, %d %b %Y %T %z").to_string().into())
 }
 
+/// `period_bounds`: the first day of the period selected in a week, month or `date_range` form field,
+/// its last day, and the day after it (`{"start": ..., "last": ..., "end": ...}`), or null when the value is not a valid period.
+/// Weeks start on monday, unless another day is given as `week_start`.
+fn period_bounds_helper(
+    field_type: &JsonValue,
+    value: &JsonValue,
+    week_start: &JsonValue,
+) -> JsonValue {
+    let (Some(field_type), Some(value)) = (field_type.as_str(), value.as_str()) else {
+        return JsonValue::Null;
+    };
+    let bounds = match field_type {
+        "week" => week_bounds(value, week_start.as_str()),
+        "month" => chrono::NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d")
+            .ok()
+            .and_then(|start| Some((start, start.checked_add_months(chrono::Months::new(1))?))),
+        "date_range" => value.split_once('/').and_then(|(start, last)| {
+            let start = chrono::NaiveDate::parse_from_str(start, "%Y-%m-%d").ok()?;
+            let last = chrono::NaiveDate::parse_from_str(last, "%Y-%m-%d").ok()?;
+            Some((start, last.succ_opt()?))
+        }),
+        _ => None,
+    };
+    let Some((start, end)) = bounds else {
+        return JsonValue::Null;
+    };
+    let last = end.pred_opt().unwrap_or(end);
+    serde_json::json!({
+        "start": start.to_string(),
+        "last": last.to_string(),
+        "end": end.to_string(),
+    })
+}
+
+/// Weeks are written like `2024-W05`, and numbered as in ISO 8601
+fn week_bounds(
+    value: &str,
+    week_start: Option<&str>,
+) -> Option<(chrono::NaiveDate, chrono::NaiveDate)> {
+    let (year, week) = value.split_once("-W")?;
+    let monday = chrono::NaiveDate::from_isoywd_opt(
+        year.parse().ok()?,
+        week.parse().ok()?,
+        chrono::Weekday::Mon,
+    )?;
+    let first_day = week_start
+        .and_then(|day| day.parse::<chrono::Weekday>().ok())
+        .unwrap_or(chrono::Weekday::Mon);
+    // The week that starts on a sunday or a saturday contains the monday of the ISO week
+    let days_before_monday = (7 - first_day.num_days_from_monday()) % 7;
+    let start = monday - chrono::Days::new(u64::from(days_before_monday));
+    Some((start, start + chrono::Days::new(7)))
+}
+
 // Percent-encode a string
 fn url_encode_helper(v: &JsonValue) -> JsonValue {
     let as_str = match v {
@@ -584,7 +652,9 @@ fn replace_helper(text: &JsonValue, original: &JsonValue, replacement: &JsonValu
 
 #[cfg(test)]
 mod tests {
-    use crate::template_helpers::{rfc2822_date_helper, CanHelp, MarkdownHelper};
+    use crate::template_helpers::{
+        period_bounds_helper, rfc2822_date_helper, CanHelp, MarkdownHelper,
+    };
     use handlebars::{JsonValue, PathAndJson, ScopedJson};
     use serde_json::Value;
 
@@ -608,6 +678,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_period_bounds() {
+        let bounds = |field_type: &str, value: &str, week_start: Value| {
+            period_bounds_helper(&field_type.into(), &value.into(), &week_start)
+        };
+        assert_eq!(
+            bounds("week", "2024-W01", Value::Null),
+            serde_json::json!({"start": "2024-01-01", "last": "2024-01-07", "end": "2024-01-08"})
+        );
+        assert_eq!(
+            bounds("week", "2024-W01", "sunday".into())["start"],
+            "2023-12-31"
+        );
+        assert_eq!(
+            bounds("month", "2024-02", Value::Null),
+            serde_json::json!({"start": "2024-02-01", "last": "2024-02-29", "end": "2024-03-01"})
+        );
+        assert_eq!(
+            bounds("date_range", "2024-12-24/2024-12-31", Value::Null)["end"],
+            "2025-01-01"
+        );
+        assert_eq!(bounds("month", "2024-13", Value::Null), Value::Null);
+        assert_eq!(bounds("text", "2024-02", Value::Null), Value::Null);
+    }
+
     #[test]
     fn test_basic_gfm_markdown() {
         let helper = MarkdownHelper::default();
@@ -704,7 +799,7 @@ mod tests {
         }
     }
 
-    fn as_args(contents: &Value) -> [PathAndJson; 1] {
+    fn as_args(contents: &Value) -> [PathAndJson<'_>; 1] {
         [as_helper_arg(CONTENT_KEY, contents)]
     }
 
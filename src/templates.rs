@@ -5,10 +5,13 @@ use crate::{AppState, FileCache, TEMPLATES_DIR};
 use async_trait::async_trait;
 use handlebars::{template::TemplateElement, Handlebars, Template};
 use include_dir::{include_dir, Dir};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 pub struct SplitTemplate {
+    /// The name of the component, like `list`
+    pub component: String,
     pub before_list: Template,
     pub list_content: Template,
     pub after_list: Template,
@@ -44,6 +47,7 @@ pub fn split_template(mut original: Template) -> SplitTemplate {
     after_list.elements = elements_after;
     after_list.mapping = mapping_after;
     SplitTemplate {
+        component: original_name,
         before_list,
         list_content,
         after_list,
@@ -58,7 +62,10 @@ impl AsyncFromStrWithState for SplitTemplate {
         source_path: &Path,
     ) -> anyhow::Result<Self> {
         log::debug!("Compiling template {:?}", source_path);
-        let tpl = Template::compile_with_name(source, "SQLPage component".to_string())?;
+        let name = source_path
+            .file_stem()
+            .map_or_else(|| "SQLPage component".into(), OsStr::to_string_lossy);
+        let tpl = Template::compile_with_name(source, name.into_owned())?;
         Ok(split_template(tpl))
     }
 }
@@ -110,6 +117,21 @@ impl AllTemplates {
         Ok(())
     }
 
+    /// Returns the number of template cache hits and misses since the server started
+    pub fn cache_hits_and_misses(&self) -> (u64, u64) {
+        self.split_templates.hits_and_misses()
+    }
+
+    /// The custom components currently loaded in the cache
+    pub async fn cached_templates(&self) -> Vec<(PathBuf, chrono::DateTime<chrono::Utc>)> {
+        self.split_templates.cached_files().await
+    }
+
+    /// Removes the custom components from the cache. The built-in components are kept.
+    pub async fn clear_cache(&self) -> usize {
+        self.split_templates.clear().await
+    }
+
     pub async fn get_template(
         &self,
         app_state: &AppState,
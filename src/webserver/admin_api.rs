@@ -0,0 +1,189 @@
+//! Administration API, used to operate a running server from scripts and dashboards.
+//!
+//! It is only available when `admin_api_token` is set in the configuration,
+//! and every request must send the token in an `Authorization: Bearer <token>` header.
+//! All the responses are JSON objects.
+
+use crate::webserver::database::previews::preview_name;
+use crate::AppState;
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::future::{ready, Ready};
+use std::path::PathBuf;
+
+const ADMIN_API_PATH: &str = "/_sqlpage/admin";
+
+/// Registers the routes of the administration API, if it is enabled
+pub fn configure(cfg: &mut web::ServiceConfig, app_state: &AppState) {
+    if app_state.config.admin_api_token.is_none() {
+        return;
+    }
+    log::info!("The administration API is enabled at {ADMIN_API_PATH}");
+    cfg.service(
+        web::scope(ADMIN_API_PATH)
+            .route("/files", web::get().to(cached_files))
+            .route("/cache/purge", web::post().to(purge_caches))
+            .route("/pool", web::get().to(pool_statistics))
+            .route("/config/reload", web::post().to(reload_config))
+            .route("/alerts/{name}/run", web::post().to(run_alert))
+            .route("/previews", web::get().to(list_previews))
+            .route("/previews/{name}", web::post().to(create_preview))
+            .route("/previews/{name}", web::delete().to(delete_preview)),
+    );
+}
+
+/// Extracted from the requests that contain the configured token
+struct Administrator;
+
+impl FromRequest for Administrator {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let expected = req
+            .app_data::<web::Data<AppState>>()
+            .and_then(|state| state.config.admin_api_token.as_deref());
+        let given = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        ready(match (expected, given) {
+            (Some(expected), Some(given)) if same_token(expected, given) => Ok(Administrator),
+            _ => Err(unauthorized()),
+        })
+    }
+}
+
+/// Compares the hashes of the tokens, so that the time taken does not reveal how much of the token is correct
+fn same_token(expected: &str, given: &str) -> bool {
+    Sha256::digest(expected.as_bytes()) == Sha256::digest(given.trim().as_bytes())
+}
+
+fn unauthorized() -> actix_web::Error {
+    let response = HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+        .json(json!({ "error": "A valid admin_api_token is required" }));
+    InternalError::from_response("invalid admin token", response).into()
+}
+
+fn files_to_json(files: Vec<(PathBuf, DateTime<Utc>)>) -> serde_json::Value {
+    files
+        .into_iter()
+        .map(|(path, last_checked_at)| {
+            json!({
+                "path": path.to_string_lossy(),
+                "last_checked_at": last_checked_at.to_rfc3339(),
+            })
+        })
+        .collect()
+}
+
+async fn cached_files(_: Administrator, state: web::Data<AppState>) -> HttpResponse {
+    let sql_files = state.sql_file_cache.cached_files().await;
+    let templates = state.all_templates.cached_templates().await;
+    HttpResponse::Ok().json(json!({
+        "sql_files": files_to_json(sql_files),
+        "templates": files_to_json(templates),
+    }))
+}
+
+/// Forgets the parsed sql files and custom components, so that they are read again from the disk or the database
+async fn purge_caches(_: Administrator, state: web::Data<AppState>) -> HttpResponse {
+    let sql_files = state.sql_file_cache.clear().await;
+    let templates = state.all_templates.clear_cache().await;
+    log::info!("Admin API: removed {sql_files} sql files and {templates} templates from the cache");
+    HttpResponse::Ok().json(json!({
+        "purged_sql_files": sql_files,
+        "purged_templates": templates,
+    }))
+}
+
+async fn pool_statistics(_: Administrator, state: web::Data<AppState>) -> HttpResponse {
+    let pool = &state.db.connection;
+    HttpResponse::Ok().json(json!({
+        "connections": pool.size(),
+        "idle_connections": pool.num_idle(),
+    }))
+}
+
+/// Reads the configuration file again, and restarts the server with it.
+/// The response is sent once the new server is started; the old one finishes the requests it was handling.
+async fn reload_config(_: Administrator, state: web::Data<AppState>) -> HttpResponse {
+    match state.config_reloads.request().await {
+        Ok(()) => {
+            log::info!("Admin API: reloaded the configuration");
+            HttpResponse::Ok().json(json!({ "reloaded": true }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(json!({ "error": format!("{e:#}") })),
+    }
+}
+
+async fn run_alert(
+    _: Administrator,
+    state: web::Data<AppState>,
+    name: web::Path<String>,
+) -> HttpResponse {
+    if state.alert_triggers.trigger(&name) {
+        HttpResponse::Accepted().json(json!({ "alert": name.as_str() }))
+    } else {
+        HttpResponse::NotFound().json(json!({ "error": format!("No alert named {name:?}") }))
+    }
+}
+
+fn preview_error(error: &anyhow::Error) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({ "error": format!("{error:#}") }))
+}
+
+async fn list_previews(_: Administrator, state: web::Data<AppState>) -> HttpResponse {
+    match state.previews.list(&state.config).await {
+        Ok(previews) => HttpResponse::Ok().json(json!({ "previews": previews })),
+        Err(e) => preview_error(&e),
+    }
+}
+
+/// Creates and seeds the database of a preview, which is the only way to create one in production
+async fn create_preview(
+    _: Administrator,
+    state: web::Data<AppState>,
+    name: web::Path<String>,
+) -> HttpResponse {
+    let name = match preview_name(&name) {
+        Ok(name) => name,
+        Err(e) => return preview_error(&e),
+    };
+    match state.previews.get(&state.config, &name, true).await {
+        Ok(_) => {
+            log::info!("Admin API: opened the preview {name:?}");
+            HttpResponse::Ok().json(json!({ "preview": name }))
+        }
+        Err(e) => preview_error(&e),
+    }
+}
+
+/// Closes the connections to the database of a preview and deletes it, usually when its branch is merged
+async fn delete_preview(
+    _: Administrator,
+    state: web::Data<AppState>,
+    name: web::Path<String>,
+) -> HttpResponse {
+    let deleted = match preview_name(&name) {
+        Ok(name) => state.previews.delete(&state.config, &name).await,
+        Err(e) => Err(e),
+    };
+    match deleted {
+        Ok(true) => {
+            log::info!("Admin API: deleted the preview {name:?}");
+            HttpResponse::Ok().json(json!({ "deleted_preview": name.as_str() }))
+        }
+        Ok(false) => {
+            HttpResponse::NotFound().json(json!({ "error": format!("No preview named {name:?}") }))
+        }
+        Err(e) => preview_error(&e),
+    }
+}
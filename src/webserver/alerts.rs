@@ -0,0 +1,198 @@
+//! Data alerts: SQL queries evaluated periodically in the background.
+//!
+//! An alert fires when its query returns at least one row. The threshold condition is written in the `WHERE`
+//! clause of the query. Notifications are sent to a webhook only when the state of the alert changes:
+//! once when it starts firing, and once when it is resolved, so that a persisting problem does not flood
+//! the notification channel.
+
+use crate::webserver::database::sql_to_json::row_to_json;
+use crate::webserver::http_client::make_http_client;
+use crate::AppState;
+use actix_web::web;
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AlertDefinition {
+    pub name: String,
+    /// The alert is firing while this query returns rows
+    pub query: String,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64,
+    /// Receives a JSON POST request when the alert starts firing and when it is resolved
+    pub webhook_url: String,
+}
+
+fn default_interval_seconds() -> u64 {
+    60
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+#[derive(Serialize)]
+struct Notification<'a> {
+    alert: &'a str,
+    status: AlertStatus,
+    rows: &'a [serde_json::Value],
+}
+
+/// The notification to send when the query of an alert returns `row_count` rows
+fn transition(was_firing: bool, row_count: usize) -> Option<AlertStatus> {
+    match (was_firing, row_count > 0) {
+        (false, true) => Some(AlertStatus::Firing),
+        (true, false) => Some(AlertStatus::Resolved),
+        _ => None,
+    }
+}
+
+/// Lets the alerts be evaluated on demand, without waiting for the end of their interval
+#[derive(Default)]
+pub struct AlertTriggers {
+    triggers: HashMap<String, Arc<Notify>>,
+    stopped: CancellationToken,
+}
+
+impl AlertTriggers {
+    #[must_use]
+    pub fn new(alerts: &[AlertDefinition]) -> Self {
+        Self {
+            triggers: alerts
+                .iter()
+                .map(|alert| (alert.name.clone(), Arc::new(Notify::new())))
+                .collect(),
+            stopped: CancellationToken::new(),
+        }
+    }
+
+    /// Evaluates the alert with the given name as soon as possible.
+    /// Returns false when there is no alert with this name.
+    #[must_use]
+    pub fn trigger(&self, name: &str) -> bool {
+        self.triggers
+            .get(name)
+            .map(|trigger| trigger.notify_one())
+            .is_some()
+    }
+
+    /// Stops evaluating the alerts, when the server is replaced by one with a new configuration
+    pub fn stop(&self) {
+        self.stopped.cancel();
+    }
+}
+
+/// Starts evaluating the configured alerts in the background
+pub fn start(state: &web::Data<AppState>) {
+    for alert in &state.config.alerts {
+        log::info!(
+            "Evaluating alert {:?} every {} seconds",
+            alert.name,
+            alert.interval_seconds
+        );
+        let trigger = state.alert_triggers.triggers.get(&alert.name).cloned();
+        actix_web::rt::spawn(run_alert(
+            web::Data::clone(state),
+            alert.clone(),
+            trigger.unwrap_or_default(),
+        ));
+    }
+}
+
+async fn run_alert(state: web::Data<AppState>, alert: AlertDefinition, trigger: Arc<Notify>) {
+    let mut interval =
+        actix_web::rt::time::interval(Duration::from_secs(alert.interval_seconds.max(1)));
+    let mut firing = false;
+    loop {
+        tokio::select! {
+            () = state.alert_triggers.stopped.cancelled() => return,
+            _ = interval.tick() => {}
+            () = trigger.notified() => log::info!("Evaluating alert {:?} on demand", alert.name),
+        }
+        let rows = match evaluate(&state, &alert).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::error!("Unable to evaluate alert {:?}: {e:#}", alert.name);
+                continue;
+            }
+        };
+        let Some(status) = transition(firing, rows.len()) else {
+            continue;
+        };
+        log::info!("Alert {:?} is now {status:?}", alert.name);
+        match notify(&state, &alert, status, &rows).await {
+            // The state only changes once the notification is delivered, so failed notifications are retried
+            Ok(()) => firing = status == AlertStatus::Firing,
+            Err(e) => log::error!("Unable to notify alert {:?}: {e:#}", alert.name),
+        }
+    }
+}
+
+async fn evaluate(
+    state: &AppState,
+    alert: &AlertDefinition,
+) -> anyhow::Result<Vec<serde_json::Value>> {
+    let rows = sqlx::query(&alert.query)
+        .fetch_all(&state.db.connection)
+        .await
+        .with_context(|| format!("Error in the query of alert {:?}", alert.name))?;
+    Ok(rows.iter().map(row_to_json).collect())
+}
+
+async fn notify(
+    state: &AppState,
+    alert: &AlertDefinition,
+    status: AlertStatus,
+    rows: &[serde_json::Value],
+) -> anyhow::Result<()> {
+    let client = make_http_client(&state.config)?;
+    let notification = Notification {
+        alert: &alert.name,
+        status,
+        rows,
+    };
+    let response = client
+        .post(&alert.webhook_url)
+        .send_json(&notification)
+        .await
+        .map_err(|e| {
+            anyhow!(
+                "Unable to send the notification to {}: {e}",
+                alert.webhook_url
+            )
+        })?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "The webhook {} responded with status {}",
+            alert.webhook_url,
+            response.status()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_transition() {
+    assert_eq!(transition(false, 3), Some(AlertStatus::Firing));
+    assert_eq!(transition(true, 5), None);
+    assert_eq!(transition(true, 0), Some(AlertStatus::Resolved));
+    assert_eq!(transition(false, 0), None);
+}
+
+#[test]
+fn test_alert_definition() {
+    let alert: AlertDefinition = serde_json::from_str(
+        r#"{"name": "low_stock", "query": "select * from products where stock < 10", "webhook_url": "https://example.com/hook"}"#,
+    )
+    .unwrap();
+    assert_eq!(alert.interval_seconds, 60);
+}
@@ -0,0 +1,178 @@
+//! Partial responses to `Range` requests, so that browsers can seek in videos and resume downloads.
+//!
+//! Only single byte ranges are supported: requests for several ranges get the whole file.
+//! Files on the local disk are not loaded in memory: only the requested part is read.
+
+use actix_web::body::SizedStream;
+use actix_web::http::header::{self, ContentEncoding, ContentRange, ContentRangeSpec, Range};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, HttpResponseBuilder};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// The size of the chunks in which files are sent
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The part of a resource that the client requested
+enum RequestedPart {
+    Whole,
+    /// The first and last bytes of the part, included
+    Range(u64, u64),
+    NotSatisfiable,
+}
+
+fn requested_part(range: Option<&Range>, length: u64) -> RequestedPart {
+    let Some(Range::Bytes(ranges)) = range else {
+        return RequestedPart::Whole;
+    };
+    // Several ranges would need a multipart response
+    let [requested] = ranges.as_slice() else {
+        return RequestedPart::Whole;
+    };
+    match requested.to_satisfiable_range(length) {
+        Some((first, last)) => RequestedPart::Range(first, last),
+        None => RequestedPart::NotSatisfiable,
+    }
+}
+
+fn not_satisfiable(length: u64) -> HttpResponse {
+    HttpResponse::RangeNotSatisfiable()
+        .insert_header(ContentRange(ContentRangeSpec::Bytes {
+            range: None,
+            instance_length: Some(length),
+        }))
+        .finish()
+}
+
+fn partial_content(response: &mut HttpResponseBuilder, first: u64, last: u64, length: u64) {
+    response
+        .status(StatusCode::PARTIAL_CONTENT)
+        .insert_header(ContentRange(ContentRangeSpec::Bytes {
+            range: Some((first, last)),
+            instance_length: Some(length),
+        }))
+        // The range is a part of the uncompressed file
+        .insert_header(ContentEncoding::Identity);
+}
+
+/// Sends the part of `bytes` requested in `range`, or all of them when there is no range.
+pub(crate) fn ranged_response(
+    mut response: HttpResponseBuilder,
+    range: Option<&Range>,
+    bytes: impl Into<Bytes>,
+) -> HttpResponse {
+    let bytes: Bytes = bytes.into();
+    response.insert_header((header::ACCEPT_RANGES, "bytes"));
+    let length = bytes.len() as u64;
+    match requested_part(range, length) {
+        RequestedPart::Whole => response.body(bytes),
+        RequestedPart::NotSatisfiable => not_satisfiable(length),
+        RequestedPart::Range(first, last) => {
+            let (Ok(first_index), Ok(last_index)) = (usize::try_from(first), usize::try_from(last))
+            else {
+                return response.body(bytes);
+            };
+            partial_content(&mut response, first, last, length);
+            response.body(bytes.slice(first_index..=last_index))
+        }
+    }
+}
+
+/// Sends the part of the file requested in `range`, or all of it when there is no range,
+/// reading it from the disk while it is sent.
+pub(crate) async fn ranged_file_response(
+    mut response: HttpResponseBuilder,
+    range: Option<&Range>,
+    mut file: tokio::fs::File,
+    length: u64,
+) -> std::io::Result<HttpResponse> {
+    response.insert_header((header::ACCEPT_RANGES, "bytes"));
+    let (first, size) = match requested_part(range, length) {
+        RequestedPart::Whole => (0, length),
+        RequestedPart::NotSatisfiable => return Ok(not_satisfiable(length)),
+        RequestedPart::Range(first, last) => {
+            partial_content(&mut response, first, last, length);
+            (first, last - first + 1)
+        }
+    };
+    file.seek(std::io::SeekFrom::Start(first)).await?;
+    let chunks = futures_util::stream::try_unfold(file.take(size), |mut part| async move {
+        let mut chunk = vec![0; FILE_CHUNK_SIZE];
+        let read = part.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        chunk.truncate(read);
+        Ok::<_, std::io::Error>(Some((Bytes::from(chunk), part)))
+    });
+    Ok(response.body(SizedStream::new(size, Box::pin(chunks))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::MessageBody;
+
+    fn respond(range: Option<&str>) -> HttpResponse {
+        let range = range.map(|r| r.parse::<Range>().unwrap());
+        ranged_response(HttpResponse::Ok(), range.as_ref(), "It works !")
+    }
+
+    fn body(response: HttpResponse) -> Bytes {
+        response.into_body().try_into_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_ranges() {
+        let full = respond(None);
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(body(full), "It works !");
+
+        let part = respond(Some("bytes=3-7"));
+        assert_eq!(part.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            part.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 3-7/10"
+        );
+        assert_eq!(body(part), "works");
+
+        assert_eq!(body(respond(Some("bytes=-6"))), "orks !");
+        assert_eq!(body(respond(Some("bytes=0-1,3-4"))), "It works !");
+        assert_eq!(
+            respond(Some("bytes=20-")).status(),
+            StatusCode::RANGE_NOT_SATISFIABLE
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_file_ranges() {
+        let path = std::env::temp_dir().join(format!("sqlpage_ranges_{}", std::process::id()));
+        std::fs::write(&path, "It works !").unwrap();
+        let respond = |range: Option<&str>| {
+            let range = range.map(|r| r.parse::<Range>().unwrap());
+            let path = path.clone();
+            async move {
+                let file = tokio::fs::File::open(path).await.unwrap();
+                ranged_file_response(HttpResponse::Ok(), range.as_ref(), file, 10)
+                    .await
+                    .unwrap()
+            }
+        };
+        let body = |response: HttpResponse| actix_web::body::to_bytes(response.into_body());
+
+        let part = respond(Some("bytes=3-7")).await;
+        assert_eq!(part.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body(part).await.unwrap(), "works");
+        assert_eq!(body(respond(None).await).await.unwrap(), "It works !");
+        assert_eq!(
+            body(respond(Some("bytes=-6")).await).await.unwrap(),
+            "orks !"
+        );
+        assert_eq!(
+            respond(Some("bytes=20-")).await.status(),
+            StatusCode::RANGE_NOT_SATISFIABLE
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
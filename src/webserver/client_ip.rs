@@ -0,0 +1,214 @@
+//! Determines the IP address of the client when `SQLPage` runs behind reverse proxies.
+//!
+//! The `Forwarded` and `X-Forwarded-For` headers can be set by anyone, so they are only used when the request
+//! comes from one of the `trusted_proxies`. The list of forwarded addresses is then read from right to left,
+//! skipping trusted proxies, and the first untrusted address is the client.
+
+use actix_web::http::header::HeaderMap;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP address, or a range of addresses in CIDR notation: `10.0.0.0/8`, `fd00::/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        match (self.address, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = s.trim().split_once('/').unwrap_or((s.trim(), ""));
+        let address: IpAddr = address
+            .parse()
+            .map_err(|e| format!("invalid IP address {address:?}: {e}"))?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = if prefix_len.is_empty() {
+            max_prefix_len
+        } else {
+            prefix_len
+                .parse()
+                .ok()
+                .filter(|&len| len <= max_prefix_len)
+                .ok_or_else(|| format!("invalid network prefix length in {s:?}"))?
+        };
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns the address of the client, using the forwarding headers set by trusted proxies
+#[must_use]
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpNetwork],
+) -> Option<IpAddr> {
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    let peer = peer?;
+    if !is_trusted(peer) {
+        return Some(peer);
+    }
+    let forwarded = forwarded_addresses(headers);
+    let mut client = peer;
+    for ip in forwarded.into_iter().rev() {
+        let Some(ip) = ip else {
+            break; // obfuscated or invalid address: we cannot go further
+        };
+        client = ip;
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// The list of addresses the request was forwarded for, from the client to the last proxy.
+/// Uses the standard `Forwarded` header if present, and `X-Forwarded-For` otherwise.
+fn forwarded_addresses(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let header_values = |name: &str| {
+        headers
+            .get_all(name)
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+    };
+    let forwarded = header_values("forwarded");
+    if forwarded.is_empty() {
+        header_values("x-forwarded-for")
+            .into_iter()
+            .map(parse_node)
+            .collect()
+    } else {
+        forwarded
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(key, _)| key.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect()
+    }
+}
+
+/// Parses `192.0.2.1`, `192.0.2.1:8080`, `"[2001:db8::1]:8080"` or `2001:db8::1`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    node.rsplit_once(':')?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+        map
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        s.parse().ok()
+    }
+
+    #[test]
+    fn test_ip_network() {
+        let net: IpNetwork = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        let single: IpNetwork = "::1".parse().unwrap();
+        assert!(single.contains("::1".parse().unwrap()));
+        assert!(!single.contains("::2".parse().unwrap()));
+        assert!("0.0.0.0/0"
+            .parse::<IpNetwork>()
+            .unwrap()
+            .contains("1.2.3.4".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_client_ip() {
+        let trusted: Vec<IpNetwork> =
+            vec!["127.0.0.1".parse().unwrap(), "10.0.0.0/8".parse().unwrap()];
+        let xff = headers(&[("x-forwarded-for", "1.1.1.1, 203.0.113.7, 10.0.0.2")]);
+        // The client can set any X-Forwarded-For: only the address added by our proxies is used
+        assert_eq!(
+            resolve_client_ip(ip("127.0.0.1"), &xff, &trusted),
+            ip("203.0.113.7")
+        );
+        // Headers from untrusted peers are ignored
+        assert_eq!(
+            resolve_client_ip(ip("198.51.100.1"), &xff, &trusted),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("127.0.0.1"), &xff, &[]),
+            ip("127.0.0.1")
+        );
+
+        let forwarded = headers(&[(
+            "forwarded",
+            "for=192.0.2.60;proto=http, for=\"[2001:db8:cafe::17]:4711\"",
+        )]);
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.1"), &forwarded, &trusted),
+            ip("2001:db8:cafe::17")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.1"), &headers(&[]), &trusted),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Reloads the configuration of a running server, on request of the administration API.
+//!
+//! The configuration is read again from the same files and environment variables as at startup.
+//! A new server is then started with it, on the sockets opened at startup,
+//! and the old server finishes the requests it was handling before closing its database connections.
+//! The addresses the server listens on (`listen_on`, `port`, `unix_socket`, `https_domain`) cannot change without a restart.
+
+use crate::app_config::{self, AppConfig};
+use crate::webserver::database::migrations;
+use crate::webserver::Database;
+use crate::AppState;
+use anyhow::anyhow;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
+
+/// Receives the result of a reload, once the new server is started
+pub(crate) type ReloadReply = oneshot::Sender<anyhow::Result<()>>;
+
+/// The requests to reload the configuration, sent to the server that runs this state
+pub struct ConfigReloads {
+    sender: mpsc::Sender<ReloadReply>,
+    receiver: Mutex<Option<mpsc::Receiver<ReloadReply>>>,
+}
+
+impl Default for ConfigReloads {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel(1);
+        Self {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+}
+
+impl ConfigReloads {
+    /// Asks the server to reload its configuration, and waits until the new server is started
+    pub(crate) async fn request(&self) -> anyhow::Result<()> {
+        if self
+            .receiver
+            .lock()
+            .expect("reload lock poisoned")
+            .is_some()
+        {
+            anyhow::bail!("The configuration can only be reloaded by a server started with the sqlpage command");
+        }
+        let (reply, result) = oneshot::channel();
+        self.sender
+            .send(reply)
+            .await
+            .map_err(|_| anyhow!("The server is stopping"))?;
+        result
+            .await
+            .map_err(|_| anyhow!("The server stopped before reloading its configuration"))?
+    }
+
+    /// Called once by the server that runs this state, to receive the reload requests
+    pub(crate) fn take_requests(&self) -> Option<mpsc::Receiver<ReloadReply>> {
+        self.receiver.lock().expect("reload lock poisoned").take()
+    }
+}
+
+/// Reads the configuration again, and prepares the state of the server that will replace the current one
+pub(crate) async fn load_new_state(current: &AppState) -> anyhow::Result<AppState> {
+    let config = app_config::load_from_cli()?;
+    warn_about_restart(&current.config, &config);
+    let db = Database::init(&config).await?;
+    migrations::apply(&config, &db).await?;
+    let mut state = AppState::init_with_db(&config, db).await?;
+    state.statement_hooks = current.statement_hooks.clone();
+    log::info!("Reloaded the configuration");
+    Ok(state)
+}
+
+fn warn_about_restart(current: &AppConfig, new: &AppConfig) {
+    if current.listen_on() != new.listen_on()
+        || current.unix_socket != new.unix_socket
+        || current.https_domain != new.https_domain
+    {
+        log::warn!("The server keeps listening on the same address until it is restarted");
+    }
+}
@@ -1,5 +1,6 @@
 use std::{mem::take, time::Duration};
 
+use super::locks::LocalLocks;
 use super::Database;
 use crate::{app_config::AppConfig, ON_CONNECT_FILE, ON_RESET_FILE};
 use anyhow::Context;
@@ -11,15 +12,22 @@ use sqlx::{
     ConnectOptions, Executor,
 };
 
+/// The options to connect to the database of the configuration, with its `database_password`
+pub(crate) fn connect_options(config: &AppConfig) -> anyhow::Result<AnyConnectOptions> {
+    let database_url = &config.database_url;
+    let mut connect_options: AnyConnectOptions = database_url
+        .parse()
+        .with_context(|| format!("\"{database_url}\" is not a valid database URL. Please change the \"database_url\" option in the configuration file."))?;
+    if let Some(password) = &config.database_password {
+        set_database_password(&mut connect_options, password);
+    }
+    Ok(connect_options)
+}
+
 impl Database {
     pub async fn init(config: &AppConfig) -> anyhow::Result<Self> {
         let database_url = &config.database_url;
-        let mut connect_options: AnyConnectOptions = database_url
-            .parse()
-            .with_context(|| format!("\"{database_url}\" is not a valid database URL. Please change the \"database_url\" option in the configuration file."))?;
-        if let Some(password) = &config.database_password {
-            set_database_password(&mut connect_options, password);
-        }
+        let mut connect_options = connect_options(config)?;
         connect_options.log_statements(log::LevelFilter::Trace);
         connect_options.log_slow_statements(
             log::LevelFilter::Warn,
@@ -51,7 +59,10 @@ impl Database {
             }
         };
         log::debug!("Initialized database pool: {connection:#?}");
-        Ok(Database { connection })
+        Ok(Database {
+            connection,
+            local_locks: LocalLocks::default(),
+        })
     }
 
     fn create_pool_options(config: &AppConfig, db_kind: AnyKind) -> PoolOptions<Any> {
@@ -1,16 +1,20 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use futures_util::StreamExt;
 use sqlparser::ast::{
     CopyLegacyCsvOption, CopyLegacyOption, CopyOption, CopySource, CopyTarget, Statement,
 };
 use sqlx::{
     any::{AnyArguments, AnyConnectionKind, AnyKind},
-    AnyConnection, Arguments, Executor, PgConnection,
+    AnyConnection, Arguments, Executor, PgConnection, TransactionManager,
 };
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt};
 
+use crate::app_config::AppConfig;
+use crate::webserver::http_client::make_http_client_without_redirects;
 use crate::webserver::http_request_info::RequestInfo;
 
 use super::make_placeholder;
@@ -29,8 +33,88 @@ pub(super) struct CsvImport {
     // A string that will be interpreted as null
     pub null_str: Option<String>,
     pub escape: Option<char>,
-    /// Reference the the uploaded file name
+    pub format: ImportFormat,
+    /// Reference the the uploaded file name, or the path of a file on disk
     pub uploaded_file: String,
+    /// The sheet to read in spreadsheets, given after a `#` in the file name
+    pub sheet: Option<String>,
+    /// Number of lines to ignore at the start of a CSV file, before the header
+    pub skip_lines: usize,
+    /// Names of the CSV columns that contain the values of the table columns, when they are different
+    pub column_headers: HashMap<String, String>,
+    /// When set, invalid rows are skipped and reported, until there are more than this number of them
+    pub max_errors: Option<usize>,
+    /// When set, the imported rows are committed every time this number of rows were imported
+    pub commit_every: Option<usize>,
+}
+
+/// The options given after a `#` at the end of the name of a CSV file
+#[derive(Debug, Default)]
+struct CsvFileOptions {
+    skip_lines: usize,
+    column_headers: HashMap<String, String>,
+    max_errors: Option<usize>,
+    commit_every: Option<usize>,
+}
+
+/// A row returned by the COPY statement, so that the page can display what happened during the import
+#[derive(Debug, PartialEq)]
+pub(super) enum ImportRow {
+    /// A row of the CSV file that could not be imported
+    Rejected { line: u64, error: String },
+    /// The rows imported until this line of the CSV file were committed
+    Committed { line: u64, committed_rows: u64 },
+}
+
+impl ImportRow {
+    pub(super) fn into_json(self) -> serde_json::Value {
+        match self {
+            Self::Rejected { line, error } => serde_json::json!({ "line": line, "error": error }),
+            Self::Committed {
+                line,
+                committed_rows,
+            } => serde_json::json!({ "line": line, "committed_rows": committed_rows }),
+        }
+    }
+}
+
+/// Name that refers to the raw request body instead of an uploaded file
+const REQUEST_BODY_SOURCE: &str = "request_body";
+
+/// Prefix of the names that refer to a file on disk, relative to the web root, instead of an uploaded file
+const LOCAL_FILE_PREFIX: &str = "file:";
+/// Maximum time to download the data of an import from a URL
+const REMOTE_IMPORT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+
+/// Maximum number of parameters in a single INSERT statement when inserting several rows at once.
+/// This is the lowest limit of all supported databases (older versions of `SQLite`).
+const MAX_BATCH_PARAMETERS: usize = 999;
+/// SQL Server does not accept more rows in a single INSERT statement
+const MAX_BATCH_ROWS: usize = 1000;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(super) enum ImportFormat {
+    #[default]
+    Csv,
+    /// A JSON array of objects, whose keys are the column names
+    Json,
+    /// Newline-delimited JSON: one object per line, read and inserted line by line
+    NdJson,
+    /// A spreadsheet, in any format supported by [`super::xlsx_import`]
+    Xlsx,
+    Parquet,
+}
+
+impl ImportFormat {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "CSV",
+            ImportFormat::Json => "JSON",
+            ImportFormat::NdJson => "NDJSON",
+            ImportFormat::Xlsx => "spreadsheet",
+            ImportFormat::Parquet => "Parquet",
+        }
+    }
 }
 
 enum CopyCsvOption<'a> {
@@ -78,9 +162,35 @@ impl CopyCsvOption<'_> {
             _ => None,
         }
     }
+
+    fn format(&self) -> Option<ImportFormat> {
+        match self {
+            CopyCsvOption::New(CopyOption::Format(f)) if f.value.eq_ignore_ascii_case("json") => {
+                Some(ImportFormat::Json)
+            }
+            CopyCsvOption::New(CopyOption::Format(f))
+                if f.value.eq_ignore_ascii_case("ndjson")
+                    || f.value.eq_ignore_ascii_case("jsonl") =>
+            {
+                Some(ImportFormat::NdJson)
+            }
+            CopyCsvOption::New(CopyOption::Format(f)) if f.value.eq_ignore_ascii_case("xlsx") => {
+                Some(ImportFormat::Xlsx)
+            }
+            CopyCsvOption::New(CopyOption::Format(f))
+                if f.value.eq_ignore_ascii_case("parquet") =>
+            {
+                Some(ImportFormat::Parquet)
+            }
+            CopyCsvOption::New(CopyOption::Format(_)) => Some(ImportFormat::Csv),
+            _ => None,
+        }
+    }
 }
 
-pub(super) fn extract_csv_copy_statement(stmt: &mut Statement) -> Option<CsvImport> {
+pub(super) fn extract_csv_copy_statement(
+    stmt: &mut Statement,
+) -> Option<anyhow::Result<CsvImport>> {
     if let Statement::Copy {
         source: CopySource::Table {
             table_name,
@@ -97,7 +207,7 @@ pub(super) fn extract_csv_copy_statement(stmt: &mut Statement) -> Option<CsvImpo
             log::warn!("COPY ... VALUES not compatible with SQLPage: {stmt}");
             return None;
         }
-        let uploaded_file = match std::mem::replace(source, CopyTarget::Stdin) {
+        let mut uploaded_file = match std::mem::replace(source, CopyTarget::Stdin) {
             CopyTarget::File { filename } => filename,
             other => {
                 log::warn!("COPY from {other} not compatible with SQLPage: {stmt}");
@@ -117,15 +227,34 @@ pub(super) fn extract_csv_copy_statement(stmt: &mut Statement) -> Option<CsvImpo
             .collect();
 
         let table_name = table_name.to_string();
-        let columns = columns.iter().map(|ident| ident.value.clone()).collect();
+        let columns: Vec<String> = columns.iter().map(|ident| ident.value.clone()).collect();
         let delimiter = all_options.iter().find_map(CopyCsvOption::delimiter);
         let quote = all_options.iter().find_map(CopyCsvOption::quote);
         let header = all_options.iter().find_map(CopyCsvOption::header);
         let null = all_options.iter().find_map(CopyCsvOption::null);
         let escape = all_options.iter().find_map(CopyCsvOption::escape);
+        let format = all_options
+            .iter()
+            .find_map(CopyCsvOption::format)
+            .unwrap_or_default();
+        let mut sheet = None;
+        let mut file_options = CsvFileOptions::default();
+        if let Some((file, options)) = uploaded_file.split_once('#') {
+            match format {
+                ImportFormat::Xlsx => sheet = Some(options.to_string()),
+                ImportFormat::Csv => match parse_csv_file_options(options, &columns) {
+                    Ok(options) => file_options = options,
+                    Err(e) => return Some(Err(e)),
+                },
+                _ => {}
+            }
+            if sheet.is_some() || format == ImportFormat::Csv {
+                uploaded_file.truncate(file.len());
+            }
+        }
         let query = stmt.to_string();
 
-        Some(CsvImport {
+        Some(Ok(CsvImport {
             query,
             table_name,
             columns,
@@ -134,57 +263,298 @@ pub(super) fn extract_csv_copy_statement(stmt: &mut Statement) -> Option<CsvImpo
             header,
             null_str: null,
             escape,
+            format,
             uploaded_file,
-        })
+            sheet,
+            skip_lines: file_options.skip_lines,
+            column_headers: file_options.column_headers,
+            max_errors: file_options.max_errors,
+            commit_every: file_options.commit_every,
+        }))
     } else {
         None
     }
 }
 
+/// Parses the options given after a `#` at the end of the name of a CSV file, like
+/// `'contacts#skip=2&name=Full Name'`, for the features that have no equivalent in the options of COPY statements:
+///  - `skip=N` ignores the first N lines of the file,
+///  - `column=Header` reads the values of a column of the table from the CSV column named `Header`,
+///  - `max_errors=N` skips the rows that cannot be imported, and fails only if there are more than N of them,
+///  - `commit_every=N` commits the imported rows every N rows, so that a late failure keeps the rows imported before it.
+fn parse_csv_file_options(options: &str, columns: &[String]) -> anyhow::Result<CsvFileOptions> {
+    let pairs = actix_web::web::Query::<Vec<(String, String)>>::from_query(options)
+        .with_context(|| format!("Invalid CSV import options: {options:?}"))?
+        .into_inner();
+    let mut file_options = CsvFileOptions::default();
+    for (key, value) in pairs {
+        if columns.contains(&key) {
+            file_options.column_headers.insert(key, value);
+        } else if key == "skip" {
+            file_options.skip_lines = value
+                .parse()
+                .with_context(|| format!("skip must be a number of lines, not {value:?}"))?;
+        } else if key == "max_errors" {
+            file_options.max_errors =
+                Some(value.parse().with_context(|| {
+                    format!("max_errors must be a number of rows, not {value:?}")
+                })?);
+        } else if key == "commit_every" {
+            let rows = value.parse().ok().filter(|&rows: &usize| rows > 0);
+            file_options.commit_every = Some(rows.with_context(|| {
+                format!("commit_every must be a positive number of rows, not {value:?}")
+            })?);
+        } else {
+            bail!(
+                "Invalid CSV import option {key:?}. \
+                Use skip=N to ignore the first lines of the file, \
+                column=Header to read a column from a CSV column with a different name, \
+                max_errors=N to skip up to N invalid rows, \
+                or commit_every=N to commit the imported rows every N rows. \
+                The columns of the import are: {}",
+                columns.join(", ")
+            );
+        }
+    }
+    if !file_options.column_headers.is_empty() && columns.is_empty() {
+        bail!("Renamed CSV columns need the list of columns to import");
+    }
+    Ok(file_options)
+}
+
+/// Where the data to import is read from
+enum ImportSource<'a> {
+    RequestBody(&'a [u8]),
+    UploadedFile(&'a Path),
+    LocalFile(PathBuf),
+    /// The data downloaded from a URL of the `import_url_allowlist`
+    Remote(Vec<u8>),
+}
+
+/// Finds the data to import: an uploaded file with the given field name, the request body,
+/// a URL, or a file on disk, relative to the web root, when the name starts with `file:`
+async fn import_source<'a>(
+    csv_import: &CsvImport,
+    request: &'a RequestInfo,
+) -> anyhow::Result<ImportSource<'a>> {
+    let name = &csv_import.uploaded_file;
+    if let Some(uploaded_file) = request.uploaded_files.get(name) {
+        return Ok(ImportSource::UploadedFile(uploaded_file.file.path()));
+    }
+    if name == REQUEST_BODY_SOURCE {
+        let body = request.raw_body.as_deref().with_context(|| {
+            "The request does not have a body. \
+            Form submissions are not available as a request body: use an uploaded file instead."
+        })?;
+        return Ok(ImportSource::RequestBody(body));
+    }
+    if name.starts_with("http://") || name.starts_with("https://") {
+        // Boxed because the future of the HTTP client is large, and most imports do not need it
+        let data = Box::pin(download(name, &request.app_state.config)).await?;
+        return Ok(ImportSource::Remote(data));
+    }
+    if let Some(path) = name.strip_prefix(LOCAL_FILE_PREFIX) {
+        let web_root = &request.app_state.config.web_root;
+        return Ok(ImportSource::LocalFile(
+            local_file_path(web_root, path).await?,
+        ));
+    }
+    bail!(
+        "The request does not contain a field named {name:?} with an uploaded file.\n\
+        Please check that :\n\
+         - you have selected a file to upload, \n\
+         - the form field name is correct.\n\
+        To import a file from the disk, use '{LOCAL_FILE_PREFIX}path/to/the/file', relative to the web root."
+    )
+}
+
+/// Resolves a path relative to the web root, and checks that it does not lead outside of it,
+/// with `..` segments, an absolute path, or a symbolic link
+async fn local_file_path(web_root: &Path, path: &str) -> anyhow::Result<PathBuf> {
+    let web_root = tokio::fs::canonicalize(web_root)
+        .await
+        .with_context(|| format!("Unable to open the web root {}", web_root.display()))?;
+    let local_path = tokio::fs::canonicalize(web_root.join(path))
+        .await
+        .with_context(|| format!("There is no file named {path:?} in the web root"))?;
+    if !local_path.starts_with(&web_root) {
+        bail!("Unable to import {path:?}: only the files in the web root can be imported");
+    }
+    Ok(local_path)
+}
+
+/// Whether the URL is in one of the locations of the allowlist: it must have the same scheme, host and port,
+/// and its path must be the allowed path or below it.
+/// URLs are compared after resolving their `.` and `..` segments, so that `/exports/../private.csv` is not allowed.
+fn is_allowed_url(url: &str, allowlist: &[String]) -> bool {
+    let Ok(url) = url::Url::parse(url) else {
+        return false;
+    };
+    // An encoded slash would let the server see a different path than the one we check
+    let path = url.path().to_ascii_lowercase();
+    if path.contains("%2f") || path.contains("%5c") {
+        return false;
+    }
+    let segments: Vec<&str> = url.path_segments().into_iter().flatten().collect();
+    allowlist
+        .iter()
+        .filter_map(|allowed| url::Url::parse(allowed).ok())
+        .any(|allowed| {
+            let allowed_segments = allowed
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .filter(|segment| !segment.is_empty());
+            url.scheme() == allowed.scheme()
+                && url.host() == allowed.host()
+                && url.port_or_known_default() == allowed.port_or_known_default()
+                && allowed_segments
+                    .enumerate()
+                    .all(|(i, segment)| segments.get(i) == Some(&segment))
+        })
+}
+
+async fn download(url: &str, config: &AppConfig) -> anyhow::Result<Vec<u8>> {
+    if !is_allowed_url(url, &config.import_url_allowlist) {
+        bail!(
+            "Importing data from {url:?} is not allowed. \
+            Add the URL to import_url_allowlist in the configuration to allow it."
+        );
+    }
+    log::info!("Downloading the data to import from {url}");
+    // Redirects are not followed, since they could lead outside of the allowed URLs
+    let client =
+        make_http_client_without_redirects(config).context("Unable to create an HTTP client")?;
+    let mut response = client
+        .get(url)
+        .timeout(REMOTE_IMPORT_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Unable to download {url}: {e}"))?;
+    let status = response.status();
+    if status.is_redirection() {
+        bail!(
+            "Unable to download {url}: the server redirected to another URL, which is not followed. \
+            Use the final URL of the data instead."
+        );
+    }
+    if !status.is_success() {
+        bail!("Unable to download {url}: the server responded with {status}");
+    }
+    let data = response
+        .body()
+        .limit(config.max_uploaded_file_size)
+        .await
+        .with_context(|| {
+            format!(
+                "Unable to read the data from {url}. \
+                It cannot be larger than max_uploaded_file_size ({} bytes).",
+                config.max_uploaded_file_size
+            )
+        })?;
+    Ok(data.to_vec())
+}
+
 pub(super) async fn run_csv_import(
     db: &mut AnyConnection,
     csv_import: &CsvImport,
     request: &RequestInfo,
-) -> anyhow::Result<()> {
-    let named_temp_file = &request
-        .uploaded_files
-        .get(&csv_import.uploaded_file)
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "The request does not contain a field named {:?} with an uploaded file.\n\
-                Please check that :\n\
-                 - you have selected a file to upload, \n\
-                 - the form field name is correct.",
-                csv_import.uploaded_file
-            )
-        })?
-        .file;
-    let file_path = named_temp_file.path();
-    let file = tokio::fs::File::open(file_path).await.with_context(|| {
-        format!(
-            "The CSV file {} was uploaded correctly, but could not be opened",
-            file_path.display()
-        )
-    })?;
-    let buffered = tokio::io::BufReader::new(file);
-    // private_get_mut is not supposed to be used outside of sqlx, but it is the only way to
-    // access the underlying connection
-    match db.private_get_mut() {
-        AnyConnectionKind::Postgres(pg_connection) => {
-            run_csv_import_postgres(pg_connection, csv_import, buffered).await
+) -> anyhow::Result<Vec<ImportRow>> {
+    let file_path = match import_source(csv_import, request).await? {
+        ImportSource::RequestBody(body) => {
+            return run_import(db, csv_import, body).await.with_context(|| {
+                format!(
+                    "The request body could not be imported into the table {}",
+                    csv_import.table_name
+                )
+            });
         }
-        _ => run_csv_import_insert(db, csv_import, buffered).await,
-    }
-    .with_context(|| {
+        ImportSource::Remote(data) => {
+            return run_import(db, csv_import, data.as_slice())
+                .await
+                .with_context(|| {
+                    format!(
+                        "The data downloaded from {} could not be imported into the table {}",
+                        csv_import.uploaded_file, csv_import.table_name
+                    )
+                });
+        }
+        ImportSource::UploadedFile(path) => Cow::Borrowed(path),
+        ImportSource::LocalFile(path) => Cow::Owned(path),
+    };
+    // Parquet files are not read sequentially, so they are not opened with tokio
+    let result = if csv_import.format == ImportFormat::Parquet {
+        let file = std::fs::File::open(&file_path)
+            .with_context(|| format!("Unable to open {}", file_path.display()))?;
+        run_parquet_import_insert(db, csv_import, file)
+            .await
+            .map(|()| Vec::new())
+    } else {
+        let file = tokio::fs::File::open(&file_path)
+            .await
+            .with_context(|| format!("Unable to open {}", file_path.display()))?;
+        run_import(db, csv_import, tokio::io::BufReader::new(file)).await
+    };
+    result.with_context(|| {
         let table_name = &csv_import.table_name;
         format!(
-            "{} was uploaded correctly, but its records could not be imported into the table {}",
+            "The records of {} could not be imported into the table {}",
             file_path.display(),
             table_name
         )
     })
 }
 
+async fn run_import(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    mut data: impl AsyncRead + Unpin + Send,
+) -> anyhow::Result<Vec<ImportRow>> {
+    match csv_import.format {
+        ImportFormat::Json => run_json_import_insert(db, csv_import, data).await?,
+        ImportFormat::NdJson => run_ndjson_import_insert(db, csv_import, data).await?,
+        ImportFormat::Xlsx => run_xlsx_import_insert(db, csv_import, data).await?,
+        ImportFormat::Parquet => {
+            let mut file = Vec::new();
+            data.read_to_end(&mut file).await?;
+            run_parquet_import_insert(db, csv_import, actix_web::web::Bytes::from(file)).await?;
+        }
+        ImportFormat::Csv => return run_csv_file_import(db, csv_import, data).await,
+    }
+    Ok(Vec::new())
+}
+
+async fn run_csv_file_import(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    data: impl AsyncRead + Unpin + Send,
+) -> anyhow::Result<Vec<ImportRow>> {
+    let mut data = tokio::io::BufReader::new(data);
+    for line in 0..csv_import.skip_lines {
+        if data.read_until(b'\n', &mut Vec::new()).await? == 0 {
+            bail!(
+                "The file has only {line} lines, but {} lines should be skipped",
+                csv_import.skip_lines
+            );
+        }
+    }
+    // private_get_mut is not supposed to be used outside of sqlx, but it is the only way to
+    // access the underlying connection
+    match db.private_get_mut() {
+        // The native COPY does not match the header with the column names, stops at the first invalid row,
+        // and imports all the rows in a single transaction
+        AnyConnectionKind::Postgres(pg_connection)
+            if csv_import.column_headers.is_empty()
+                && csv_import.max_errors.is_none()
+                && csv_import.commit_every.is_none() =>
+        {
+            run_csv_import_postgres(pg_connection, csv_import, data).await?;
+            Ok(Vec::new())
+        }
+        _ => run_csv_import_insert(db, csv_import, data).await,
+    }
+}
+
 /// This function does not parse the CSV file, it only sends it to postgres.
 /// This is the fastest way to import a CSV file into postgres
 async fn run_csv_import_postgres(
@@ -218,16 +588,328 @@ async fn run_csv_import_insert(
     db: &mut AnyConnection,
     csv_import: &CsvImport,
     file: impl AsyncRead + Unpin + Send,
+) -> anyhow::Result<Vec<ImportRow>> {
+    let mut import_rows = Vec::new();
+    let Some(commit_every) = csv_import.commit_every else {
+        insert_csv_records(db, csv_import, file, None, &mut import_rows).await?;
+        return Ok(import_rows);
+    };
+    let mut chunks = ChunkedTransaction {
+        commit_every,
+        uncommitted_rows: 0,
+        committed_rows: 0,
+    };
+    AnyTransactionManager::begin(db).await?;
+    let result =
+        insert_csv_records(db, csv_import, file, Some(&mut chunks), &mut import_rows).await;
+    if let Err(error) = result {
+        if let Err(e) = AnyTransactionManager::rollback(db).await {
+            log::error!("Unable to roll back the last rows of the CSV import: {e}");
+        }
+        return Err(error.context(format!(
+            "The import failed after committing {} rows",
+            chunks.committed_rows
+        )));
+    }
+    Ok(import_rows)
+}
+
+type AnyTransactionManager = <sqlx::Any as sqlx::Database>::TransactionManager;
+
+/// Commits the rows of an import every `commit_every` rows, so that huge imports do not hold a single
+/// giant transaction, and that a failure cancels only the rows imported since the last commit
+struct ChunkedTransaction {
+    commit_every: usize,
+    uncommitted_rows: usize,
+    committed_rows: u64,
+}
+
+impl ChunkedTransaction {
+    async fn row_imported(
+        &mut self,
+        db: &mut AnyConnection,
+        line: u64,
+        import_rows: &mut Vec<ImportRow>,
+    ) -> anyhow::Result<()> {
+        self.uncommitted_rows += 1;
+        if self.uncommitted_rows >= self.commit_every {
+            self.commit(db, line, import_rows).await?;
+            AnyTransactionManager::begin(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Commits the current transaction, and reports the number of rows committed until `line`
+    async fn commit(
+        &mut self,
+        db: &mut AnyConnection,
+        line: u64,
+        import_rows: &mut Vec<ImportRow>,
+    ) -> anyhow::Result<()> {
+        AnyTransactionManager::commit(db)
+            .await
+            .with_context(|| format!("Unable to commit the rows imported until line {line}"))?;
+        if self.uncommitted_rows > 0 {
+            self.committed_rows += self.uncommitted_rows as u64;
+            self.uncommitted_rows = 0;
+            log::debug!("Committed {} imported rows", self.committed_rows);
+            import_rows.push(ImportRow::Committed {
+                line,
+                committed_rows: self.committed_rows,
+            });
+        }
+        Ok(())
+    }
+}
+
+async fn insert_csv_records(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    file: impl AsyncRead + Unpin + Send,
+    mut chunks: Option<&mut ChunkedTransaction>,
+    import_rows: &mut Vec<ImportRow>,
 ) -> anyhow::Result<()> {
     let insert_stmt = create_insert_stmt(db.kind(), csv_import);
     log::debug!("CSV data insert statement: {insert_stmt}");
     let mut reader = make_csv_reader(csv_import, file);
     let col_idxs = compute_column_indices(&mut reader, csv_import).await?;
     let mut records = reader.into_records();
+    // In a transaction, a failed insert must be rolled back alone to continue the import
+    let use_savepoints = chunks.is_some() && csv_import.max_errors.is_some();
+    let mut rejected_rows = 0;
+    let mut last_line = 0;
     while let Some(record) = records.next().await {
-        let r = record.with_context(|| "reading csv record")?;
-        process_csv_record(r, db, &insert_stmt, csv_import, &col_idxs).await?;
+        let (position, result) = match record {
+            Ok(r) if use_savepoints => (
+                r.position().cloned(),
+                process_csv_record_in_savepoint(r, db, &insert_stmt, csv_import, &col_idxs).await,
+            ),
+            Ok(r) => (
+                r.position().cloned(),
+                process_csv_record(r, db, &insert_stmt, csv_import, &col_idxs).await,
+            ),
+            Err(e) => (
+                e.position().cloned(),
+                Err(anyhow::Error::new(e).context("reading csv record")),
+            ),
+        };
+        let line = position.map_or(0, |p| p.line()) + csv_import.skip_lines as u64;
+        last_line = line;
+        match (result, chunks.as_deref_mut()) {
+            (Ok(()), Some(chunks)) => chunks.row_imported(db, line, import_rows).await?,
+            (Ok(()), None) => {}
+            (Err(error), _) => {
+                reject_row(
+                    import_rows,
+                    rejected_rows,
+                    csv_import.max_errors,
+                    line,
+                    error,
+                )?;
+                rejected_rows += 1;
+            }
+        }
+    }
+    if let Some(chunks) = chunks {
+        chunks.commit(db, last_line, import_rows).await?;
+    }
+    Ok(())
+}
+
+/// Records a row that could not be imported, or returns the error if no more invalid rows are allowed
+fn reject_row(
+    import_rows: &mut Vec<ImportRow>,
+    rejected_rows: usize,
+    max_errors: Option<usize>,
+    line: u64,
+    error: anyhow::Error,
+) -> anyhow::Result<()> {
+    let Some(max_errors) = max_errors else {
+        return Err(error);
+    };
+    if rejected_rows >= max_errors {
+        return Err(error.context(format!(
+            "Line {line} could not be imported, and more than {max_errors} rows are invalid"
+        )));
+    }
+    let error = format!("{error:#}");
+    log::warn!("Skipping line {line} of the CSV file: {error}");
+    import_rows.push(ImportRow::Rejected { line, error });
+    Ok(())
+}
+
+/// JSON is not supported by the postgres COPY command, so it is always parsed by `SQLPage`
+async fn run_json_import_insert(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    mut data: impl AsyncRead + Unpin + Send,
+) -> anyhow::Result<()> {
+    if csv_import.columns.is_empty() {
+        bail!(
+            "JSON imports need the list of columns to fill, like: COPY {} (col1, col2) FROM ...",
+            csv_import.table_name
+        );
+    }
+    let insert_stmt = create_insert_stmt(db.kind(), csv_import);
+    log::debug!("JSON data insert statement: {insert_stmt}");
+    let mut json = Vec::new();
+    data.read_to_end(&mut json).await?;
+    let elements: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_slice(&json)
+        .with_context(|| {
+        "The data to import must be a JSON array of objects, like [{\"col1\": \"a\", \"col2\": 1}]"
+    })?;
+    for (i, element) in elements.iter().enumerate() {
+        insert_row(db, &insert_stmt, json_object_values(csv_import, element))
+            .await
+            .with_context(|| format!("inserting the element number {i} of the JSON array"))?;
+    }
+    Ok(())
+}
+
+/// Each line is parsed and inserted as soon as it is read, so the whole file is never kept in memory
+async fn run_ndjson_import_insert(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    data: impl AsyncRead + Unpin + Send,
+) -> anyhow::Result<()> {
+    if csv_import.columns.is_empty() {
+        bail!(
+            "NDJSON imports need the list of columns to fill, like: COPY {} (col1, col2) FROM ...",
+            csv_import.table_name
+        );
     }
+    let insert_stmt = create_insert_stmt(db.kind(), csv_import);
+    log::debug!("NDJSON data insert statement: {insert_stmt}");
+    let mut lines = tokio::io::BufReader::new(data).lines();
+    let mut line_number = 0;
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let element: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&line)
+            .with_context(|| {
+                format!("Line {line_number} is not a JSON object, like {{\"col1\": \"a\", \"col2\": 1}}")
+            })?;
+        insert_row(db, &insert_stmt, json_object_values(csv_import, &element))
+            .await
+            .with_context(|| format!("inserting line {line_number} of the NDJSON data"))?;
+    }
+    Ok(())
+}
+
+/// The values of the imported columns in a JSON object. Strings are inserted without their quotes.
+fn json_object_values<'a>(
+    csv_import: &'a CsvImport,
+    element: &'a serde_json::Map<String, serde_json::Value>,
+) -> impl Iterator<Item = Option<String>> + 'a {
+    csv_import
+        .columns
+        .iter()
+        .map(|column| match element.get(column) {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(s)) => Some(s.clone()),
+            Some(other) => Some(other.to_string()),
+        })
+}
+
+/// Spreadsheets are always parsed by `SQLPage`, and their cells are inserted as text
+async fn run_xlsx_import_insert(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    mut data: impl AsyncRead + Unpin + Send,
+) -> anyhow::Result<()> {
+    if csv_import.columns.is_empty() {
+        bail!(
+            "Spreadsheet imports need the list of columns to fill, like: COPY {} (col1, col2) FROM ...",
+            csv_import.table_name
+        );
+    }
+    let insert_stmt = create_insert_stmt(db.kind(), csv_import);
+    log::debug!("Spreadsheet data insert statement: {insert_stmt}");
+    let mut spreadsheet = Vec::new();
+    data.read_to_end(&mut spreadsheet).await?;
+    let sheet = csv_import.sheet.clone();
+    let columns = csv_import.columns.clone();
+    let header = csv_import.header.unwrap_or(true);
+    // Spreadsheets are compressed XML documents: decoding them is CPU-intensive
+    let rows = tokio::task::spawn_blocking(move || {
+        super::xlsx_import::read_rows(spreadsheet, sheet.as_deref(), &columns, header)
+    })
+    .await??;
+    for (i, row) in rows.into_iter().enumerate() {
+        insert_row(db, &insert_stmt, row)
+            .await
+            .with_context(|| format!("inserting the record number {} of the sheet", i + 1))?;
+    }
+    Ok(())
+}
+
+/// Parquet files are decoded on a blocking thread, and their rows are inserted in batches while they are read
+async fn run_parquet_import_insert(
+    db: &mut AnyConnection,
+    csv_import: &CsvImport,
+    file: impl parquet::file::reader::ChunkReader + 'static,
+) -> anyhow::Result<()> {
+    let requested_columns = csv_import.columns.clone();
+    let (columns, rows) = tokio::task::spawn_blocking(move || {
+        super::parquet_import::read_rows(file, &requested_columns)
+    })
+    .await??;
+    let batch_size = (MAX_BATCH_PARAMETERS / columns.len().max(1)).clamp(1, MAX_BATCH_ROWS);
+    let (sender, mut batches) = tokio::sync::mpsc::channel::<anyhow::Result<Vec<_>>>(2);
+    let reader = tokio::task::spawn_blocking(move || {
+        let mut rows = rows.peekable();
+        while rows.peek().is_some() {
+            let batch = rows.by_ref().take(batch_size).collect();
+            if sender.blocking_send(batch).is_err() {
+                break; // The import failed
+            }
+        }
+    });
+    let kind = db.kind();
+    let full_batch_stmt =
+        create_batch_insert_stmt(kind, &csv_import.table_name, &columns, batch_size);
+    log::debug!("Parquet data insert statement: {full_batch_stmt}");
+    let mut inserted = 0;
+    while let Some(batch) = batches.recv().await {
+        let batch: Vec<Vec<Option<String>>> = batch?;
+        let partial_batch_stmt;
+        let insert_stmt = if batch.len() == batch_size {
+            &full_batch_stmt
+        } else {
+            partial_batch_stmt =
+                create_batch_insert_stmt(kind, &csv_import.table_name, &columns, batch.len());
+            &partial_batch_stmt
+        };
+        let rows_in_batch = batch.len();
+        insert_row(db, insert_stmt, batch.into_iter().flatten())
+            .await
+            .with_context(|| {
+                format!(
+                    "inserting the records {} to {} of the Parquet file",
+                    inserted + 1,
+                    inserted + rows_in_batch
+                )
+            })?;
+        inserted += rows_in_batch;
+    }
+    reader.await?;
+    log::debug!("Imported {inserted} rows from a Parquet file");
+    Ok(())
+}
+
+async fn insert_row(
+    db: &mut AnyConnection,
+    insert_stmt: &str,
+    values: impl IntoIterator<Item = Option<String>>,
+) -> anyhow::Result<()> {
+    let mut arguments = AnyArguments::default();
+    for value in values {
+        log::trace!("Imported value: {value:?}");
+        arguments.add(value);
+    }
+    db.execute((insert_stmt, Some(arguments))).await?;
     Ok(())
 }
 
@@ -245,9 +927,13 @@ async fn compute_column_indices<R: AsyncRead + Unpin + Send>(
             .map(|(i, h)| (h, i))
             .collect::<HashMap<&str, usize>>();
         for column in &csv_import.columns {
+            let header = csv_import
+                .column_headers
+                .get(column)
+                .map_or(column.as_str(), String::as_str);
             let &idx = headers
-                .get(column.as_str())
-                .ok_or_else(|| anyhow::anyhow!("CSV Column not found: {column}"))?;
+                .get(header)
+                .ok_or_else(|| anyhow::anyhow!("CSV Column not found: {header}"))?;
             col_idxs.push(idx);
         }
     } else {
@@ -257,21 +943,28 @@ async fn compute_column_indices<R: AsyncRead + Unpin + Send>(
 }
 
 fn create_insert_stmt(kind: AnyKind, csv_import: &CsvImport) -> String {
-    let columns = csv_import.columns.join(", ");
-    let placeholders = csv_import
-        .columns
-        .iter()
-        .enumerate()
-        .map(|(i, _)| make_placeholder(kind, i + 1))
-        .fold(String::new(), |mut acc, f| {
-            if !acc.is_empty() {
-                acc.push_str(", ");
-            }
-            acc.push_str(&f);
-            acc
-        });
-    let table_name = &csv_import.table_name;
-    format!("INSERT INTO {table_name} ({columns}) VALUES ({placeholders})")
+    create_batch_insert_stmt(kind, &csv_import.table_name, &csv_import.columns, 1)
+}
+
+/// An INSERT statement with placeholders for `rows` rows of values
+fn create_batch_insert_stmt(
+    kind: AnyKind,
+    table_name: &str,
+    columns: &[String],
+    rows: usize,
+) -> String {
+    let column_list = columns.join(", ");
+    let rows = (0..rows)
+        .map(|row| {
+            let placeholders = (1..=columns.len())
+                .map(|i| make_placeholder(kind, row * columns.len() + i))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("({placeholders})")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("INSERT INTO {table_name} ({column_list}) VALUES {rows}")
 }
 
 async fn process_csv_record(
@@ -293,6 +986,24 @@ async fn process_csv_record(
     Ok(())
 }
 
+/// Inserts a record in a savepoint of the current transaction, that is rolled back if the insert fails
+async fn process_csv_record_in_savepoint(
+    record: csv_async::StringRecord,
+    db: &mut AnyConnection,
+    insert_stmt: &str,
+    csv_import: &CsvImport,
+    column_indices: &[usize],
+) -> anyhow::Result<()> {
+    AnyTransactionManager::begin(db).await?;
+    let result = process_csv_record(record, db, insert_stmt, csv_import, column_indices).await;
+    if result.is_ok() {
+        AnyTransactionManager::commit(db).await?;
+    } else {
+        AnyTransactionManager::rollback(db).await?;
+    }
+    result
+}
+
 fn make_csv_reader<R: AsyncRead + Unpin + Send>(
     csv_import: &CsvImport,
     file: R,
@@ -326,7 +1037,13 @@ fn test_make_statement() {
         header: Some(true),
         null_str: None,
         escape: None,
+        format: ImportFormat::Csv,
         uploaded_file: "my_file.csv".into(),
+        sheet: None,
+        skip_lines: 0,
+        column_headers: HashMap::new(),
+        max_errors: None,
+        commit_every: None,
     };
     let insert_stmt = create_insert_stmt(AnyKind::Postgres, &csv_import);
     assert_eq!(
@@ -347,7 +1064,7 @@ async fn test_end_to_end() {
     .into_iter()
     .next()
     .unwrap();
-    let csv_import = extract_csv_copy_statement(&mut copy_stmt).unwrap();
+    let csv_import = extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap();
     assert_eq!(
         csv_import,
         CsvImport {
@@ -359,7 +1076,13 @@ async fn test_end_to_end() {
             header: Some(true),
             null_str: None,
             escape: None,
+            format: ImportFormat::Csv,
             uploaded_file: "my_file.csv".into(),
+            sheet: None,
+            skip_lines: 0,
+            column_headers: HashMap::new(),
+            max_errors: None,
+            commit_every: None,
         }
     );
     let mut conn = "sqlite::memory:"
@@ -385,3 +1108,359 @@ async fn test_end_to_end() {
         vec![("b".into(), "a".into()), ("d".into(), "c".into())]
     );
 }
+
+#[actix_web::test]
+async fn test_csv_file_options() {
+    use sqlx::ConnectOptions;
+
+    let mut copy_stmt = sqlparser::parser::Parser::parse_sql(
+        &sqlparser::dialect::GenericDialect {},
+        "COPY contacts (name, email) FROM 'contacts#skip=2&name=Full%20Name' (DELIMITER ';')",
+    )
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    let csv_import = extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap();
+    assert_eq!(csv_import.uploaded_file, "contacts");
+    assert_eq!(csv_import.skip_lines, 2);
+    assert_eq!(
+        csv_import.column_headers,
+        HashMap::from([("name".into(), "Full Name".into())])
+    );
+    let mut conn = "sqlite::memory:"
+        .parse::<sqlx::any::AnyConnectOptions>()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE contacts (name TEXT, email TEXT)")
+        .await
+        .unwrap();
+    let csv = "Exported on 2024-01-01\n\nemail;Full Name\nada@example.com;Ada Lovelace";
+    run_import(&mut conn, &csv_import, csv.as_bytes())
+        .await
+        .unwrap();
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT * FROM contacts")
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![("Ada Lovelace".into(), "ada@example.com".into())]
+    );
+
+    let mut invalid_stmt = sqlparser::parser::Parser::parse_sql(
+        &sqlparser::dialect::GenericDialect {},
+        "COPY contacts (name, email) FROM 'contacts#phone=Phone'",
+    )
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    assert!(extract_csv_copy_statement(&mut invalid_stmt)
+        .unwrap()
+        .is_err());
+}
+
+#[actix_web::test]
+async fn test_csv_max_errors() {
+    use sqlx::ConnectOptions;
+
+    let parse = |sql| {
+        let mut copy_stmt =
+            sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::GenericDialect {}, sql)
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap();
+        extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap()
+    };
+    let mut conn = "sqlite::memory:"
+        .parse::<sqlx::any::AnyConnectOptions>()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE people (name TEXT, age INTEGER CHECK (typeof(age) = 'integer'))")
+        .await
+        .unwrap();
+    let csv = "Exported people\nname,age\nAda,36\nBob\nCarl,unknown\nDan,40";
+    let csv_import = parse("COPY people (name, age) FROM 'people#skip=1&max_errors=2'");
+    assert_eq!(csv_import.max_errors, Some(2));
+    let rejected_rows = run_import(&mut conn, &csv_import, csv.as_bytes())
+        .await
+        .unwrap();
+    let ImportRow::Rejected { line, error } = &rejected_rows[1] else {
+        panic!("{rejected_rows:?}");
+    };
+    assert_eq!(*line, 5);
+    assert!(error.contains("CHECK constraint failed"), "{error}");
+    assert!(
+        matches!(rejected_rows[0], ImportRow::Rejected { line: 4, .. }),
+        "{rejected_rows:?}"
+    );
+    let names: Vec<(String,)> = sqlx::query_as("SELECT name FROM people")
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(names, [("Ada".into(),), ("Dan".into(),)]);
+
+    let csv_import = parse("COPY people (name, age) FROM 'people#skip=1&max_errors=1'");
+    let error = run_import(&mut conn, &csv_import, csv.as_bytes())
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{error:#}").contains("more than 1 rows are invalid"),
+        "{error:#}"
+    );
+}
+
+#[actix_web::test]
+async fn test_csv_commit_every() {
+    use sqlx::ConnectOptions;
+
+    let parse = |sql| {
+        let mut copy_stmt =
+            sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::GenericDialect {}, sql)
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap();
+        extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap()
+    };
+    let mut conn = "sqlite::memory:"
+        .parse::<sqlx::any::AnyConnectOptions>()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE people (name TEXT, age INTEGER CHECK (typeof(age) = 'integer'))")
+        .await
+        .unwrap();
+    let csv = "name,age\nAda,36\nBob,unknown\nCarl,20\nDan,40\nEve,50\nFay,60";
+    let csv_import = parse("COPY people (name, age) FROM 'people#commit_every=2&max_errors=1'");
+    assert_eq!(csv_import.commit_every, Some(2));
+    let import_rows = run_import(&mut conn, &csv_import, csv.as_bytes())
+        .await
+        .unwrap();
+    assert!(
+        matches!(import_rows[0], ImportRow::Rejected { line: 3, .. }),
+        "{import_rows:?}"
+    );
+    assert_eq!(
+        import_rows[1..]
+            .iter()
+            .map(|row| match row {
+                ImportRow::Committed {
+                    line,
+                    committed_rows,
+                } => (*line, *committed_rows),
+                ImportRow::Rejected { .. } => panic!("{import_rows:?}"),
+            })
+            .collect::<Vec<_>>(),
+        [(4, 2), (6, 4), (7, 5)]
+    );
+    let names: Vec<(String,)> = sqlx::query_as("SELECT name FROM people")
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(names.len(), 5);
+
+    // A late failure keeps the rows of the chunks committed before it
+    conn.execute("DELETE FROM people").await.unwrap();
+    let csv_import = parse("COPY people (name, age) FROM 'people#commit_every=2'");
+    let error = run_import(
+        &mut conn,
+        &csv_import,
+        "name,age\nAda,1\nBob,2\nCarl,3\nDan,x".as_bytes(),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        format!("{error:#}").contains("after committing 2 rows"),
+        "{error:#}"
+    );
+    let names: Vec<(String,)> = sqlx::query_as("SELECT name FROM people")
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(names, [("Ada".into(),), ("Bob".into(),)]);
+    assert!(parse_csv_file_options("commit_every=0", &[]).is_err());
+}
+
+#[test]
+fn test_is_allowed_url() {
+    let allowlist = [
+        "https://partner.example.com/exports/".to_string(),
+        "https://feeds.example.com".to_string(),
+    ];
+    for url in [
+        "https://partner.example.com/exports/nightly.csv",
+        "https://partner.example.com:443/exports/nightly.csv",
+        "https://PARTNER.example.com/exports/nightly.csv",
+        "https://partner.example.com/exports/2024/../nightly.csv",
+        "https://feeds.example.com",
+        "https://feeds.example.com/prices.json",
+        "https://feeds.example.com?day=monday",
+    ] {
+        assert!(is_allowed_url(url, &allowlist), "{url}");
+    }
+    for url in [
+        "https://partner.example.com/private.csv",
+        "https://partner.example.com/exports-private/data.csv",
+        "https://partner.example.com/exports/../private.csv",
+        "https://partner.example.com/exports/%2e%2e/private.csv",
+        "https://partner.example.com/exports/..%2Fprivate.csv",
+        "https://partner.example.com:8443/exports/nightly.csv",
+        "https://partner.example.com@attacker.net/exports/nightly.csv",
+        "https://feeds.example.com.attacker.net/prices.json",
+        "http://feeds.example.com/prices.json",
+        "not a url",
+    ] {
+        assert!(!is_allowed_url(url, &allowlist), "{url}");
+    }
+    assert!(!is_allowed_url("https://feeds.example.com", &[]));
+}
+
+#[actix_web::test]
+async fn test_local_file_path() {
+    let web_root = Path::new("tests/uploads");
+    let path = local_file_path(web_root, "people.parquet").await.unwrap();
+    assert!(path.ends_with("tests/uploads/people.parquet"), "{path:?}");
+    let path = local_file_path(web_root, "./../uploads/people.parquet").await;
+    assert!(path.is_ok(), "{path:?}");
+    let outside = std::fs::canonicalize("Cargo.toml").unwrap();
+    for path in [
+        "../../Cargo.toml",
+        "../mod.rs",
+        outside.to_str().unwrap(),
+        "does_not_exist.csv",
+    ] {
+        assert!(local_file_path(web_root, path).await.is_err(), "{path}");
+    }
+}
+
+#[actix_web::test]
+async fn test_json_import() {
+    use sqlx::ConnectOptions;
+
+    let mut copy_stmt = sqlparser::parser::Parser::parse_sql(
+        &sqlparser::dialect::GenericDialect {},
+        "COPY my_table (col1, col2) FROM 'request_body' (FORMAT json)",
+    )
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    let json_import = extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap();
+    assert_eq!(json_import.format, ImportFormat::Json);
+    assert_eq!(json_import.uploaded_file, REQUEST_BODY_SOURCE);
+    let mut conn = "sqlite::memory:"
+        .parse::<sqlx::any::AnyConnectOptions>()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE my_table (col1 TEXT, col2 TEXT)")
+        .await
+        .unwrap();
+    let json = r#"[{"col2": 1, "col1": "a", "ignored": true}, {"col1": null, "col2": [true]}]"#;
+    run_json_import_insert(&mut conn, &json_import, json.as_bytes())
+        .await
+        .unwrap();
+    let rows: Vec<(Option<String>, String)> = sqlx::query_as("SELECT * FROM my_table")
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![(Some("a".into()), "1".into()), (None, "[true]".into())]
+    );
+    let not_an_array = run_json_import_insert(&mut conn, &json_import, &b"{}"[..]).await;
+    assert!(not_an_array.is_err());
+}
+
+#[actix_web::test]
+async fn test_ndjson_import() {
+    use sqlx::ConnectOptions;
+
+    let mut copy_stmt = sqlparser::parser::Parser::parse_sql(
+        &sqlparser::dialect::GenericDialect {},
+        "COPY logs (level, message) FROM 'logs' (FORMAT ndjson)",
+    )
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    let ndjson_import = extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap();
+    assert_eq!(ndjson_import.format, ImportFormat::NdJson);
+    let mut conn = "sqlite::memory:"
+        .parse::<sqlx::any::AnyConnectOptions>()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE logs (level TEXT, message TEXT)")
+        .await
+        .unwrap();
+    let ndjson = "{\"level\": \"info\", \"message\": \"started\"}\r\n\n{\"level\": 3}";
+    run_ndjson_import_insert(&mut conn, &ndjson_import, ndjson.as_bytes())
+        .await
+        .unwrap();
+    let rows: Vec<(String, Option<String>)> = sqlx::query_as("SELECT * FROM logs")
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![("info".into(), Some("started".into())), ("3".into(), None)]
+    );
+    let invalid_line = run_ndjson_import_insert(&mut conn, &ndjson_import, &b"{}\n[1]"[..]).await;
+    assert!(format!("{:#}", invalid_line.unwrap_err()).contains("Line 2"));
+}
+
+#[actix_web::test]
+async fn test_parquet_import() {
+    use sqlx::ConnectOptions;
+
+    assert_eq!(
+        create_batch_insert_stmt(AnyKind::Postgres, "t", &["a".into(), "b".into()], 2),
+        "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4)"
+    );
+    let mut copy_stmt = sqlparser::parser::Parser::parse_sql(
+        &sqlparser::dialect::GenericDialect {},
+        "COPY people FROM 'people.parquet' (FORMAT parquet)",
+    )
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    let parquet_import = extract_csv_copy_statement(&mut copy_stmt).unwrap().unwrap();
+    assert_eq!(parquet_import.format, ImportFormat::Parquet);
+    let mut conn = "sqlite::memory:"
+        .parse::<sqlx::any::AnyConnectOptions>()
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE people (name TEXT, age INTEGER, birth_date DATE, seen_at TEXT)")
+        .await
+        .unwrap();
+    let file = super::parquet_import::example_file();
+    run_parquet_import_insert(&mut conn, &parquet_import, file)
+        .await
+        .unwrap();
+    let rows: Vec<(String, Option<i64>, String)> =
+        sqlx::query_as("SELECT name, age, birth_date FROM people")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            ("Ophir".into(), Some(29), "1995-07-11".into()),
+            ("Max".into(), None, "1970-01-01".into())
+        ]
+    );
+}
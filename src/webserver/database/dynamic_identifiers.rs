@@ -0,0 +1,270 @@
+//! `sqlpage.identifier(value, 'allowed,names')` lets the name of a table or of a column depend on a variable,
+//! which bound parameters cannot do: `ORDER BY sqlpage.identifier($sort, 'name,created_at,price')`.
+//!
+//! The calls are replaced by markers when the file is parsed, and one version of the query is prepared
+//! for each combination of allowed names. When the query runs, the version matching the values of the variables
+//! is sent to the database, so only the names written in the SQL file can ever appear in the query.
+
+use std::borrow::Cow;
+use std::ops::ControlFlow;
+
+use anyhow::{anyhow, bail, Context};
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, FunctionArgumentList, FunctionArguments, Ident,
+    ObjectName, ObjectNamePart, Statement, TableFactor, Value, ValueWithSpan, VisitMut, VisitorMut,
+};
+
+use super::sql::function_arg_to_stmt_param;
+use super::syntax_tree::StmtParam;
+use crate::webserver::ErrorWithStatus;
+
+const FUNCTION_NAME: &str = "identifier";
+const MARKER_PREFIX: &str = "SQLPAGE_IDENTIFIER_";
+/// Limits the number of versions of a query that are prepared in advance
+const MAX_QUERY_VARIANTS: usize = 1024;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(super) struct DynamicIdentifier {
+    /// The value that selects the name, such as `$sort`
+    pub value: StmtParam,
+    /// The names that can be used in the query. The first one is used when the value is NULL.
+    pub allowed: Vec<String>,
+}
+
+/// Replaces the `sqlpage.identifier(...)` calls of a statement with markers, and returns them in order
+pub(super) fn extract_dynamic_identifiers(
+    stmt: &mut Statement,
+) -> anyhow::Result<Vec<DynamicIdentifier>> {
+    let mut extractor = IdentifierExtractor {
+        identifiers: Vec::new(),
+    };
+    if let ControlFlow::Break(err) = stmt.visit(&mut extractor) {
+        return Err(err);
+    }
+    Ok(extractor.identifiers)
+}
+
+/// All the versions of `query`, with each marker replaced by one of the allowed names of its identifier,
+/// in the order used by [`variant_index`]
+pub(super) fn query_variants(
+    query: &str,
+    identifiers: &[DynamicIdentifier],
+) -> anyhow::Result<Vec<String>> {
+    if identifiers.is_empty() {
+        return Ok(Vec::new());
+    }
+    let count = identifiers
+        .iter()
+        .try_fold(1usize, |count, id| count.checked_mul(id.allowed.len()))
+        .filter(|&count| count <= MAX_QUERY_VARIANTS)
+        .ok_or_else(|| {
+            anyhow!(
+                "Too many combinations of names in the sqlpage.identifier calls of this query. \
+                At most {MAX_QUERY_VARIANTS} versions of a query can be prepared."
+            )
+        })?;
+    Ok((0..count)
+        .map(|index| {
+            let mut variant = query.to_string();
+            let mut rest = index;
+            for (idx, identifier) in identifiers.iter().enumerate().rev() {
+                let name = &identifier.allowed[rest % identifier.allowed.len()];
+                rest /= identifier.allowed.len();
+                variant = variant.replace(&marker(idx), name);
+            }
+            variant
+        })
+        .collect())
+}
+
+/// The position in the output of [`query_variants`] of the query that uses the names given by `values`
+pub(super) fn variant_index(
+    identifiers: &[DynamicIdentifier],
+    values: &[Option<Cow<'_, str>>],
+) -> anyhow::Result<usize> {
+    identifiers
+        .iter()
+        .zip(values)
+        .try_fold(0, |index, (identifier, value)| {
+            let position = match value {
+                None => 0,
+                Some(value) => identifier
+                    .allowed
+                    .iter()
+                    .position(|name| name.eq_ignore_ascii_case(value))
+                    .ok_or_else(|| {
+                        anyhow::Error::new(ErrorWithStatus {
+                            status: actix_web::http::StatusCode::BAD_REQUEST,
+                        })
+                        .context(format!(
+                            "{value:?} is not an allowed name. Expected one of: {}",
+                            identifier.allowed.join(", ")
+                        ))
+                    })?,
+            };
+            Ok(index * identifier.allowed.len() + position)
+        })
+}
+
+fn marker(index: usize) -> String {
+    // The trailing underscore prevents the marker of the first identifier from matching the eleventh
+    format!("{MARKER_PREFIX}{index}_")
+}
+
+struct IdentifierExtractor {
+    identifiers: Vec<DynamicIdentifier>,
+}
+
+impl IdentifierExtractor {
+    fn extract(&mut self, args: &mut [FunctionArg]) -> anyhow::Result<Ident> {
+        let [value, allowed] = args else {
+            bail!(
+                "Expected two arguments: the value, and the comma-separated list of allowed names"
+            );
+        };
+        let value = function_arg_to_stmt_param(value)
+            .with_context(|| format!("Unsupported value: {value}"))?;
+        let allowed = parse_allowed_names(allowed)?;
+        let ident = Ident::new(marker(self.identifiers.len()));
+        self.identifiers.push(DynamicIdentifier { value, allowed });
+        Ok(ident)
+    }
+}
+
+impl VisitorMut for IdentifierExtractor {
+    type Break = anyhow::Error;
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        if let Expr::Function(Function {
+            name: ObjectName(name),
+            args: FunctionArguments::List(FunctionArgumentList { args, .. }),
+            ..
+        }) = expr
+        {
+            if is_identifier_function(name) {
+                match self.extract(args) {
+                    Ok(ident) => *expr = Expr::Identifier(ident),
+                    Err(err) => return ControlFlow::Break(err.context(invalid_call(expr))),
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table: &mut TableFactor) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table { name, args, .. } = table {
+            if args.is_some() && is_identifier_function(&name.0) {
+                let mut arguments = args.take().map(|a| a.args).unwrap_or_default();
+                match self.extract(&mut arguments) {
+                    Ok(ident) => *name = ObjectName(vec![ObjectNamePart::Identifier(ident)]),
+                    Err(err) => {
+                        let args: Vec<String> = arguments.iter().map(ToString::to_string).collect();
+                        let call = format!("{name}({})", args.join(", "));
+                        return ControlFlow::Break(err.context(invalid_call(&call)));
+                    }
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn invalid_call(call: &impl std::fmt::Display) -> String {
+    format!("Invalid call to sqlpage.{FUNCTION_NAME}: {call}")
+}
+
+fn is_identifier_function(name: &[ObjectNamePart]) -> bool {
+    matches!(name, [ObjectNamePart::Identifier(namespace), ObjectNamePart::Identifier(function)]
+        if namespace.value == "sqlpage" && function.value == FUNCTION_NAME)
+}
+
+/// The allowed names must be a literal string, so that they are known when the file is parsed
+fn parse_allowed_names(arg: &FunctionArg) -> anyhow::Result<Vec<String>> {
+    let FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Value(ValueWithSpan {
+        value: Value::SingleQuotedString(names),
+        ..
+    }))) = arg
+    else {
+        bail!("The list of allowed names must be a literal string, such as 'name,created_at', not {arg}");
+    };
+    let names: Vec<String> = names.split(',').map(|n| n.trim().to_string()).collect();
+    for name in &names {
+        if !is_valid_name(name) {
+            bail!("{name:?} is not a valid table or column name. Names can only contain letters, digits and underscores, and can be qualified with a dot, like users.name");
+        }
+    }
+    Ok(names)
+}
+
+pub(super) fn is_valid_name(name: &str) -> bool {
+    name.split('.').all(|part| {
+        part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::SQLiteDialect;
+    use sqlparser::parser::Parser;
+
+    fn parse(sql: &str) -> Statement {
+        Parser::parse_sql(&SQLiteDialect {}, sql).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_query_variants() {
+        let mut stmt = parse(
+            "SELECT * FROM sqlpage.identifier($table, 'users,admins') \
+             ORDER BY sqlpage.identifier($sort, 'name, created_at, users.id')",
+        );
+        let identifiers = extract_dynamic_identifiers(&mut stmt).unwrap();
+        assert_eq!(
+            identifiers,
+            [
+                DynamicIdentifier {
+                    value: StmtParam::PostOrGet("table".into()),
+                    allowed: vec!["users".into(), "admins".into()],
+                },
+                DynamicIdentifier {
+                    value: StmtParam::PostOrGet("sort".into()),
+                    allowed: vec!["name".into(), "created_at".into(), "users.id".into()],
+                },
+            ]
+        );
+        let variants = query_variants(&stmt.to_string(), &identifiers).unwrap();
+        assert_eq!(variants.len(), 6);
+        let index = variant_index(
+            &identifiers,
+            &[
+                Some(Cow::Borrowed("ADMINS")),
+                Some(Cow::Borrowed("users.id")),
+            ],
+        )
+        .unwrap();
+        assert_eq!(variants[index], "SELECT * FROM admins ORDER BY users.id");
+        let index =
+            variant_index(&identifiers, &[None, Some(Cow::Borrowed("created_at"))]).unwrap();
+        assert_eq!(variants[index], "SELECT * FROM users ORDER BY created_at");
+        assert!(variant_index(
+            &identifiers,
+            &[Some(Cow::Borrowed("users; drop table users")), None]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_allowed_names() {
+        for sql in [
+            "SELECT * FROM t ORDER BY sqlpage.identifier($sort, $allowed)",
+            "SELECT * FROM t ORDER BY sqlpage.identifier($sort, 'name, 1; drop table t')",
+            "SELECT * FROM t ORDER BY sqlpage.identifier($sort)",
+        ] {
+            assert!(
+                extract_dynamic_identifiers(&mut parse(sql)).is_err(),
+                "{sql}"
+            );
+        }
+    }
+}
@@ -4,23 +4,27 @@ use futures_util::StreamExt;
 use serde_json::Value;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
 use std::pin::Pin;
+use std::time::{Duration, Instant};
 
 use super::csv_import::run_csv_import;
+use super::dynamic_identifiers::variant_index;
 use super::error_highlighting::display_stmt_db_error;
 use super::sql::{
     DelayedFunctionCall, ParsedSqlFile, ParsedStatement, SimpleSelectValue, StmtWithParams,
 };
+use super::statement_hooks::StatementExecution;
 use crate::dynamic_component::parse_dynamic_rows;
 use crate::utils::add_value_to_map;
-use crate::webserver::database::sql_to_json::row_to_string;
+use crate::webserver::database::sql_to_json::{column_types, row_to_json, row_to_string};
 use crate::webserver::http::SingleOrVec;
 use crate::webserver::http_request_info::RequestInfo;
 
 use super::syntax_tree::{extract_req_param, StmtParam};
 use super::{error_highlighting::display_db_error, Database, DbItem};
-use sqlx::any::{AnyArguments, AnyQueryResult, AnyRow, AnyStatement, AnyTypeInfo};
+use sqlx::any::{AnyArguments, AnyKind, AnyQueryResult, AnyRow, AnyStatement, AnyTypeInfo};
 use sqlx::pool::PoolConnection;
 use sqlx::{
     Any, AnyConnection, Arguments, Column, Either, Executor, Row as _, Statement, ValueRef,
@@ -28,6 +32,9 @@ use sqlx::{
 
 pub type DbConn = Option<PoolConnection<sqlx::Any>>;
 
+/// When this URL parameter is present and `allow_explain_mode` is enabled outside of production, statements are explained instead of being executed.
+pub const EXPLAIN_MODE_PARAMETER: &str = "_sqlpage_explain";
+
 impl Database {
     pub(crate) async fn prepare_with(
         &self,
@@ -47,22 +54,56 @@ pub fn stream_query_results_with_conn<'a>(
     request: &'a mut RequestInfo,
     db_connection: &'a mut DbConn,
 ) -> impl Stream<Item = DbItem> + 'a {
-    let source_file = &sql_file.source_path;
+    stream_statements(
+        &sql_file.statements,
+        &sql_file.source_path,
+        request,
+        db_connection,
+    )
+}
+
+fn stream_statements<'a>(
+    statements: &'a [ParsedStatement],
+    source_file: &'a Path,
+    request: &'a mut RequestInfo,
+    db_connection: &'a mut DbConn,
+) -> impl Stream<Item = DbItem> + 'a {
+    let explain_mode = is_explain_mode(request);
     async_stream::try_stream! {
-        for res in &sql_file.statements {
+        let mut conditional_blocks: Vec<ConditionalBlock> = Vec::new();
+        for res in statements {
+            let active = explain_mode
+                || update_conditional_blocks(&mut conditional_blocks, res, request, db_connection, source_file).await?;
+            if !active {
+                continue;
+            }
             match res {
+                statement if explain_mode => {
+                    for item in Box::pin(explain_parsed_statement(source_file, statement, request, db_connection)).await? {
+                        yield item;
+                    }
+                },
                 ParsedStatement::CsvImport(csv_import) => {
-                    let connection = take_connection(&request.app_state.db, db_connection).await?;
+                    let connection = take_connection(request.database(), db_connection).await?;
                     log::debug!("Executing CSV import: {csv_import:?}");
-                    run_csv_import(connection, csv_import, request).await.with_context(|| format!("Failed to import the CSV file {:?} into the table {:?}", csv_import.uploaded_file, csv_import.table_name))?;
+                    let import_rows = run_csv_import(connection, csv_import, request).await.with_context(|| format!("Failed to import the {} file {:?} into the table {:?}", csv_import.format.name(), csv_import.uploaded_file, csv_import.table_name))?;
+                    for row in import_rows { yield DbItem::Row(row.into_json()); }
                 },
                 ParsedStatement::StmtWithParams(stmt) => {
-                    let query = bind_parameters(stmt, request, db_connection).await?;
-                    let connection = take_connection(&request.app_state.db, db_connection).await?;
+                    let started_at = Instant::now();
+                    let (query, execution) = bind_parameters(stmt, request, db_connection, source_file).await?;
+                    let connection = take_connection(request.database(), db_connection).await?;
                     log::trace!("Executing query {:?}", query.sql);
                     let mut stream = connection.fetch_many(query);
                     let mut error = None;
+                    let mut row_count = 0;
                     while let Some(elem) = stream.next().await {
+                        if let Ok(Either::Right(row)) = &elem {
+                            if row_count == 0 {
+                                yield DbItem::ColumnTypes(column_types(row));
+                            }
+                            row_count += 1;
+                        }
                         let mut query_result = parse_single_sql_result(source_file, stmt, elem);
                         if let DbItem::Error(e) = query_result {
                             error = Some(e);
@@ -75,13 +116,21 @@ pub fn stream_query_results_with_conn<'a>(
                         }
                     }
                     drop(stream);
+                    execution.finish(row_count, error.as_ref());
+                    request.app_state.statistics.record_statement(
+                        source_file,
+                        stmt.query_position.start.line,
+                        &stmt.query,
+                        started_at.elapsed(),
+                        error.is_some(),
+                    );
                     if let Some(error) = error {
                         try_rollback_transaction(connection).await;
                         yield DbItem::Error(error);
                     }
                 },
-                ParsedStatement::SetVariable { variable, value} => {
-                    execute_set_variable_query(db_connection, request, variable, value, source_file).await
+                ParsedStatement::SetVariable { variable, value, all_rows } => {
+                    execute_set_variable_query(db_connection, request, variable, value, *all_rows, source_file).await
                     .with_context(||
                         format!("Failed to set the {variable} variable to {value:?}")
                     )?;
@@ -91,13 +140,384 @@ pub fn stream_query_results_with_conn<'a>(
                         yield i;
                     }
                 }
+                ParsedStatement::ForEach { rows, body } => {
+                    let mut loop_results = stream_loop(rows, body, source_file, request, db_connection);
+                    while let Some(item) = loop_results.next().await {
+                        yield item?;
+                    }
+                }
+                ParsedStatement::Discard(statement) => {
+                    execute_discarded_statement(statement, source_file, request, db_connection).await?;
+                }
                 ParsedStatement::Error(e) => yield DbItem::Error(clone_anyhow_err(source_file, e)),
+                ParsedStatement::If { .. }
+                | ParsedStatement::Else
+                | ParsedStatement::EndIf
+                | ParsedStatement::EndForEach => {}
             }
         }
     }
     .map(|res| res.unwrap_or_else(DbItem::Error))
 }
 
+type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// An `-- @if` block in which statements are being executed
+struct ConditionalBlock {
+    /// Whether the statements around the block are executed
+    parent_active: bool,
+    /// Whether the condition of the block is true. Inverted by `-- @else`.
+    condition_met: bool,
+}
+
+impl ConditionalBlock {
+    fn is_active(blocks: &[Self]) -> bool {
+        blocks
+            .last()
+            .is_none_or(|block| block.parent_active && block.condition_met)
+    }
+}
+
+/// Updates the open `-- @if` blocks, and returns whether `statement` should be executed.
+/// The future is boxed here to keep it out of the stack frame of the recursive query stream.
+fn update_conditional_blocks<'a>(
+    blocks: &'a mut Vec<ConditionalBlock>,
+    statement: &'a ParsedStatement,
+    request: &'a mut RequestInfo,
+    db_connection: &'a mut DbConn,
+    source_file: &'a Path,
+) -> LocalBoxFuture<'a, anyhow::Result<bool>> {
+    Box::pin(async move {
+        match statement {
+            ParsedStatement::If { condition } => {
+                let parent_active = ConditionalBlock::is_active(blocks);
+                let condition_met = parent_active
+                    && evaluate_condition(condition, request, db_connection, source_file).await?;
+                blocks.push(ConditionalBlock {
+                    parent_active,
+                    condition_met,
+                });
+                Ok(false)
+            }
+            ParsedStatement::Else => {
+                if let Some(block) = blocks.last_mut() {
+                    block.condition_met = !block.condition_met;
+                }
+                Ok(false)
+            }
+            ParsedStatement::EndIf => {
+                blocks.pop();
+                Ok(false)
+            }
+            _ => Ok(ConditionalBlock::is_active(blocks)),
+        }
+    })
+}
+
+/// Runs the query that drives a `-- @foreach` loop.
+/// The future is boxed for the same reason as in [`update_conditional_blocks`].
+fn fetch_loop_rows<'a>(
+    rows: &'a StmtWithParams,
+    request: &'a mut RequestInfo,
+    db_connection: &'a mut DbConn,
+    source_file: &'a Path,
+) -> LocalBoxFuture<'a, anyhow::Result<Vec<serde_json::Map<String, Value>>>> {
+    Box::pin(async move {
+        let (query, execution) = bind_parameters(rows, request, db_connection, source_file).await?;
+        let connection = take_connection(request.database(), db_connection).await?;
+        log::debug!("Executing the query of a loop: {:?}", query.sql);
+        let fetched = match connection.fetch_all(query).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                try_rollback_transaction(connection).await;
+                return Err(finish_with_error(
+                    execution,
+                    display_stmt_db_error(source_file, rows, e),
+                ));
+            }
+        };
+        execution.finish(fetched.len(), None);
+        Ok(fetched
+            .iter()
+            .filter_map(|row| {
+                let mut item = DbItem::Row(row_to_json(row));
+                apply_json_columns(&mut item, &rows.json_columns);
+                match item {
+                    DbItem::Row(Value::Object(object)) => Some(object),
+                    _ => None,
+                }
+            })
+            .collect())
+    })
+}
+
+/// The values of the variables that a loop overwrites, to restore them at the end of the loop
+fn save_variables(
+    request: &RequestInfo,
+    first_row: Option<&serde_json::Map<String, Value>>,
+) -> Vec<(String, Option<SingleOrVec>)> {
+    first_row
+        .into_iter()
+        .flat_map(serde_json::Map::keys)
+        .map(|name| (name.clone(), request.set_variables.get(name).cloned()))
+        .collect()
+}
+
+fn set_row_variables(request: &mut RequestInfo, row: serde_json::Map<String, Value>) {
+    for (name, value) in row {
+        if let Some(value) = json_to_fn_param(value) {
+            request
+                .set_variables
+                .insert(name, SingleOrVec::Single(value.into_owned()));
+        } else {
+            request.set_variables.remove(&name);
+        }
+    }
+}
+
+fn restore_variables(request: &mut RequestInfo, values: Vec<(String, Option<SingleOrVec>)>) {
+    for (name, value) in values {
+        if let Some(value) = value {
+            request.set_variables.insert(name, value);
+        } else {
+            request.set_variables.remove(&name);
+        }
+    }
+}
+
+/// Evaluates the condition of an `-- @if` block. NULL, false, 0 and the empty string are false.
+async fn evaluate_condition(
+    condition: &StmtWithParams,
+    request: &mut RequestInfo,
+    db_connection: &mut DbConn,
+    source_file: &Path,
+) -> anyhow::Result<bool> {
+    let (query, execution) =
+        bind_parameters(condition, request, db_connection, source_file).await?;
+    let connection = take_connection(request.database(), db_connection).await?;
+    log::debug!("Evaluating the condition {:?}", query.sql);
+    let value = match connection.fetch_optional(query).await {
+        Ok(row) => {
+            execution.finish(usize::from(row.is_some()), None);
+            row.as_ref().and_then(row_to_string)
+        }
+        Err(e) => {
+            try_rollback_transaction(connection).await;
+            return Err(finish_with_error(
+                execution,
+                display_stmt_db_error(source_file, condition, e),
+            ));
+        }
+    };
+    Ok(value.is_some_and(|v| {
+        !matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "" | "0" | "false" | "f"
+        )
+    }))
+}
+
+/// Explain mode displays the queries and the values of their parameters to anyone, so it has to be enabled explicitly,
+/// and is never available in production
+fn is_explain_mode(request: &RequestInfo) -> bool {
+    let config = &request.app_state.config;
+    config.allow_explain_mode
+        && !config.environment.is_prod()
+        && request.get_variables.contains_key(EXPLAIN_MODE_PARAMETER)
+}
+
+/// Returns the rows describing a statement in explain mode, without executing it.
+/// The statements of `-- @if` blocks are all explained, and the ones of `-- @foreach` loops are explained once.
+async fn explain_parsed_statement(
+    source_file: &Path,
+    statement: &ParsedStatement,
+    request: &RequestInfo,
+    db_connection: &mut DbConn,
+) -> anyhow::Result<Vec<DbItem>> {
+    let explanation = match statement {
+        ParsedStatement::StmtWithParams(stmt) => {
+            explain_statement(stmt, request, db_connection, "Statement").await?
+        }
+        ParsedStatement::SetVariable {
+            variable, value, ..
+        } => explain_statement(value, request, db_connection, &format!("SET {variable}")).await?,
+        ParsedStatement::If { condition } => {
+            explain_statement(condition, request, db_connection, "-- @if").await?
+        }
+        ParsedStatement::ForEach { rows, body } => {
+            let mut items = vec![
+                DbItem::Row(serde_json::json!({ "component": "code" })),
+                DbItem::Row(explain_statement(rows, request, db_connection, "-- @foreach").await?),
+            ];
+            for statement in body {
+                let explanation = Box::pin(explain_parsed_statement(
+                    source_file,
+                    statement,
+                    request,
+                    db_connection,
+                ));
+                items.extend(explanation.await?);
+            }
+            return Ok(items);
+        }
+        ParsedStatement::Else | ParsedStatement::EndIf | ParsedStatement::EndForEach => {
+            return Ok(vec![])
+        }
+        ParsedStatement::Discard(statement) => {
+            return Box::pin(explain_parsed_statement(
+                source_file,
+                statement,
+                request,
+                db_connection,
+            ))
+            .await
+        }
+        ParsedStatement::CsvImport(csv_import) => serde_json::json!({
+            "title": format!("CSV import into {}", csv_import.table_name),
+            "contents": "CSV imports are not executed in explain mode.",
+        }),
+        ParsedStatement::StaticSimpleSelect(columns) => {
+            explain_static_simple_select(columns, request, db_connection).await?
+        }
+        ParsedStatement::Error(e) => {
+            return Ok(vec![DbItem::Error(clone_anyhow_err(source_file, e))])
+        }
+    };
+    Ok(vec![
+        DbItem::Row(serde_json::json!({ "component": "code" })),
+        DbItem::Row(explanation),
+    ])
+}
+
+/// Runs `EXPLAIN` on a statement instead of executing it, and returns a row for the `code` component
+/// containing the query, the values of its parameters, and its execution plan.
+async fn explain_statement(
+    stmt: &StmtWithParams,
+    request: &RequestInfo,
+    db_connection: &mut DbConn,
+    kind: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let mut values = evaluate_parameters_without_functions(stmt, request, db_connection).await?;
+    let query = query_for_identifiers(stmt, &values.split_off(stmt.params.len()))?;
+    let description = format!(
+        "```sql\n{query}\n```\n{}",
+        parameters_description(&stmt.params, &values)
+    );
+    let db_kind = request.database().connection.any_kind();
+    let contents = if let Some(explain_prefix) = explain_prefix(db_kind) {
+        let explain_sql = format!("{explain_prefix} {query}");
+        let query = statement_with_values(&explain_sql, values);
+        let connection = take_connection(request.database(), db_connection).await?;
+        match connection.fetch_all(query).await {
+            Ok(rows) => format_query_plan(&rows),
+            Err(e) => format!("Unable to explain this statement: {e}"),
+        }
+    } else {
+        format!("EXPLAIN is not supported on {db_kind:?}")
+    };
+    Ok(serde_json::json!({
+        "title": format!("Line {}: {kind}", stmt.query_position.start.line),
+        "description_md": description,
+        "contents": contents,
+    }))
+}
+
+/// Displays the row that a static `SELECT` sends to the components instead of rendering it,
+/// since components like `redirect` or `cookie` would change the response.
+async fn explain_static_simple_select(
+    columns: &[(String, SimpleSelectValue)],
+    request: &RequestInfo,
+    db_connection: &mut DbConn,
+) -> anyhow::Result<serde_json::Value> {
+    let mut row = serde_json::Map::with_capacity(columns.len());
+    for (name, value) in columns {
+        let value = match value {
+            SimpleSelectValue::Static(s) => s.clone(),
+            SimpleSelectValue::Dynamic(p) if p.calls_function() => Value::String(p.to_string()),
+            SimpleSelectValue::Dynamic(p) => {
+                extract_req_param_as_json(p, request, db_connection).await?
+            }
+        };
+        row = add_value_to_map(row, (name.clone(), value));
+    }
+    Ok(serde_json::json!({
+        "title": "Row sent to the components",
+        "description_md": "This row does not query the database. Calls to sqlpage functions are not evaluated.",
+        "contents": serde_json::to_string_pretty(&row)?,
+        "language": "json",
+    }))
+}
+
+/// Evaluates the parameters like [`evaluate_parameters`], except the calls to sqlpage functions,
+/// which are replaced by NULL, since functions like `sqlpage.exec` or `sqlpage.fetch` have side effects.
+async fn evaluate_parameters_without_functions<'a>(
+    stmt: &StmtWithParams,
+    request: &'a RequestInfo,
+    db_connection: &mut DbConn,
+) -> anyhow::Result<Vec<Option<Cow<'a, str>>>> {
+    let identifiers = stmt.identifiers.iter().map(|identifier| &identifier.value);
+    let mut values = Vec::with_capacity(stmt.params.len() + stmt.identifiers.len());
+    for param in stmt.params.iter().chain(identifiers) {
+        values.push(if param.calls_function() {
+            None
+        } else {
+            extract_req_param(param, request, db_connection).await?
+        });
+    }
+    Ok(values)
+}
+
+fn parameters_description(params: &[StmtParam], values: &[Option<Cow<'_, str>>]) -> String {
+    use std::fmt::Write;
+    let mut description = String::new();
+    for (idx, (param, value)) in params.iter().zip(values).enumerate() {
+        let idx = idx + 1;
+        let _ = if param.calls_function() {
+            writeln!(description, " - parameter {idx}: `{param}`, not evaluated")
+        } else {
+            let value = value.as_deref().unwrap_or("NULL");
+            writeln!(description, " - parameter {idx}: `{value}`")
+        };
+    }
+    description
+}
+
+fn explain_prefix(db_kind: AnyKind) -> Option<&'static str> {
+    match db_kind {
+        AnyKind::Sqlite => Some("EXPLAIN QUERY PLAN"),
+        AnyKind::Postgres | AnyKind::MySql => Some("EXPLAIN"),
+        AnyKind::Mssql => None,
+    }
+}
+
+fn format_query_plan(rows: &[AnyRow]) -> String {
+    let mut plan = String::new();
+    for row in rows {
+        let Value::Object(columns) = super::sql_to_json::row_to_json(row) else {
+            continue;
+        };
+        let line = if columns.len() == 1 {
+            columns.values().map(json_to_plan_text).collect::<String>()
+        } else {
+            columns
+                .iter()
+                .map(|(k, v)| format!("{k}: {}", json_to_plan_text(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        plan.push_str(&line);
+        plan.push('\n');
+    }
+    plan
+}
+
+fn json_to_plan_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
 /// Transforms a stream of database items to stop processing after encountering the first error.
 /// The error item itself is still emitted before stopping.
 pub fn stop_at_first_error(
@@ -121,6 +541,37 @@ pub fn stop_at_first_error(
         .take_until(error_rx)
 }
 
+/// Ends the stream with an error once `timeout` has elapsed.
+/// Dropping the stream cancels the statement being executed, and everything it was waiting for,
+/// such as `sqlpage.fetch` requests and `sqlpage.run_sql` calls.
+pub fn stop_at_timeout(
+    results_stream: impl Stream<Item = DbItem>,
+    timeout: Duration,
+) -> impl Stream<Item = DbItem> {
+    async_stream::stream! {
+        let deadline = tokio::time::sleep(timeout);
+        tokio::pin!(deadline);
+        let mut results_stream = std::pin::pin!(results_stream);
+        loop {
+            tokio::select! {
+                biased;
+                () = &mut deadline => {
+                    yield DbItem::Error(anyhow!(
+                        "The page took more than {} seconds to generate, and was interrupted. \
+                        Make its queries faster, or increase request_timeout_seconds in the configuration.",
+                        timeout.as_secs_f64()
+                    ));
+                    break;
+                }
+                item = results_stream.next() => match item {
+                    Some(item) => yield item,
+                    None => break,
+                },
+            }
+        }
+    }
+}
+
 /// Executes the sqlpage pseudo-functions contained in a static simple select
 async fn exec_static_simple_select(
     columns: &[(String, SimpleSelectValue)],
@@ -164,6 +615,70 @@ async fn extract_req_param_as_json(
     }
 }
 
+/// Executes a statement preceded by `-- @discard`, ignoring its results but not its errors.
+/// The future is boxed here to keep it out of the stack frame of the recursive query stream.
+fn execute_discarded_statement<'a>(
+    statement: &'a ParsedStatement,
+    source_file: &'a Path,
+    request: &'a mut RequestInfo,
+    db_connection: &'a mut DbConn,
+) -> LocalBoxFuture<'a, anyhow::Result<()>> {
+    Box::pin(async move {
+        let mut results = stream_statements_boxed(
+            std::slice::from_ref(statement),
+            source_file,
+            request,
+            db_connection,
+        );
+        while let Some(item) = results.next().await {
+            if let DbItem::Error(e) = item {
+                return Err(e);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Executes the statements of a `-- @foreach` loop once for each row returned by its query
+fn stream_loop<'a>(
+    rows: &'a StmtWithParams,
+    body: &'a [ParsedStatement],
+    source_file: &'a Path,
+    request: &'a mut RequestInfo,
+    db_connection: &'a mut DbConn,
+) -> Pin<Box<dyn Stream<Item = anyhow::Result<DbItem>> + 'a>> {
+    Box::pin(async_stream::try_stream! {
+        let rows = fetch_loop_rows(rows, request, db_connection, source_file).await?;
+        let previous_values = save_variables(request, rows.first());
+        'rows: for row in rows {
+            set_row_variables(request, row);
+            let mut body_results = stream_statements_boxed(body, source_file, request, db_connection);
+            while let Some(item) = body_results.next().await {
+                let is_error = matches!(item, DbItem::Error(_));
+                yield item;
+                if is_error {
+                    break 'rows;
+                }
+            }
+        }
+        restore_variables(request, previous_values);
+    })
+}
+
+fn stream_statements_boxed<'a>(
+    statements: &'a [ParsedStatement],
+    source_file: &'a Path,
+    request: &'a mut RequestInfo,
+    db_connection: &'a mut DbConn,
+) -> Pin<Box<dyn Stream<Item = DbItem> + 'a>> {
+    Box::pin(stream_statements(
+        statements,
+        source_file,
+        request,
+        db_connection,
+    ))
+}
+
 /// This function is used to create a pinned boxed stream of query results.
 /// This allows recursive calls.
 pub fn stream_query_results_boxed<'a>(
@@ -183,22 +698,41 @@ async fn execute_set_variable_query<'a>(
     request: &'a mut RequestInfo,
     variable: &StmtParam,
     statement: &StmtWithParams,
+    all_rows: bool,
     source_file: &Path,
 ) -> anyhow::Result<()> {
-    let query = bind_parameters(statement, request, db_connection).await?;
-    let connection = take_connection(&request.app_state.db, db_connection).await?;
+    let (query, execution) =
+        bind_parameters(statement, request, db_connection, source_file).await?;
+    let connection = take_connection(request.database(), db_connection).await?;
     log::debug!(
         "Executing query to set the {variable:?} variable: {:?}",
         query.sql
     );
 
-    let value = match connection.fetch_optional(query).await {
-        Ok(Some(row)) => row_to_string(&row),
-        Ok(None) => None,
+    let result = if all_rows {
+        connection.fetch_all(query).await.map(|rows| {
+            (
+                rows.len(),
+                Some(rows_to_json_array(&rows, &statement.json_columns)),
+            )
+        })
+    } else {
+        connection.fetch_optional(query).await.map(|row| {
+            (
+                usize::from(row.is_some()),
+                row.as_ref().and_then(row_to_string),
+            )
+        })
+    };
+    let value = match result {
+        Ok((row_count, value)) => {
+            execution.finish(row_count, None);
+            value
+        }
         Err(e) => {
             try_rollback_transaction(connection).await;
             let err = display_stmt_db_error(source_file, statement, e);
-            return Err(err);
+            return Err(finish_with_error(execution, err));
         }
     };
 
@@ -210,10 +744,36 @@ async fn execute_set_variable_query<'a>(
     } else {
         log::debug!("Removing variable {name}");
         vars.remove(name);
+        if let StmtParam::PostOrGet(name) = variable {
+            // Setting $x to NULL also hides the URL parameter with the same name
+            request.get_variables.remove(name);
+        }
     }
     Ok(())
 }
 
+/// Converts the rows of a `SET $x = ARRAY(SELECT ...)` query to a JSON array.
+/// Rows with a single column are represented by their value, other rows by an object.
+fn rows_to_json_array(rows: &[AnyRow], json_columns: &[String]) -> String {
+    let values: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            let mut item = DbItem::Row(row_to_json(row));
+            apply_json_columns(&mut item, json_columns);
+            match item {
+                DbItem::Row(Value::Object(object)) if object.len() == 1 => object
+                    .into_iter()
+                    .next()
+                    .map(|(_, v)| v)
+                    .unwrap_or_default(),
+                DbItem::Row(value) => value,
+                _ => Value::Null,
+            }
+        })
+        .collect();
+    Value::Array(values).to_string()
+}
+
 fn vars_and_name<'a, 'b>(
     request: &'a mut RequestInfo,
     variable: &'b StmtParam,
@@ -224,7 +784,7 @@ fn vars_and_name<'a, 'b>(
                 log::warn!("Deprecation warning! Setting the value of ${name}, but there is already a form field named :{name}. This will stop working soon. Please rename the variable, or use :{name} directly if you intended to overwrite the posted form field value.");
                 Ok((&mut request.post_variables, name))
             } else {
-                Ok((&mut request.get_variables, name))
+                Ok((&mut request.set_variables, name))
             }
         }
         StmtParam::Get(name) => Ok((&mut request.get_variables, name)),
@@ -235,14 +795,15 @@ fn vars_and_name<'a, 'b>(
     }
 }
 
-async fn take_connection<'a>(
+pub(super) async fn take_connection<'a>(
     db: &'a Database,
     conn: &'a mut DbConn,
 ) -> anyhow::Result<&'a mut PoolConnection<sqlx::Any>> {
     if let Some(c) = conn {
         return Ok(c);
     }
-    match db.connection.acquire().await {
+    // Boxed to keep the large future of the pool out of the recursive query stream
+    match Box::pin(db.connection.acquire()).await {
         Ok(c) => {
             log::debug!("Acquired a database connection");
             *conn = Some(c);
@@ -307,15 +868,55 @@ fn clone_anyhow_err(source_file: &Path, err: &anyhow::Error) -> anyhow::Error {
     e
 }
 
-async fn bind_parameters<'a>(
+/// Evaluates the parameters of the statement, and calls the statement hooks before it is executed.
+/// The future is boxed for the same reason as in [`update_conditional_blocks`].
+fn bind_parameters<'a: 'b, 'b>(
+    stmt: &'a StmtWithParams,
+    request: &'a RequestInfo,
+    db_connection: &'b mut DbConn,
+    source_file: &'a Path,
+) -> LocalBoxFuture<'b, anyhow::Result<(StatementWithParams<'a>, StatementExecution<'a>)>> {
+    Box::pin(async move {
+        let mut values = evaluate_parameters(stmt, request, db_connection).await?;
+        let sql = query_for_identifiers(stmt, &values.split_off(stmt.params.len()))?;
+        log::debug!("Preparing statement: {sql}");
+        let execution = request.app_state.statement_hooks.start(
+            source_file,
+            stmt.query_position.start.line,
+            sql,
+            &values,
+        )?;
+        Ok((statement_with_values(sql, values), execution))
+    })
+}
+
+fn finish_with_error(execution: StatementExecution<'_>, error: anyhow::Error) -> anyhow::Error {
+    execution.finish(0, Some(&error));
+    error
+}
+
+/// The version of the query that uses the names selected by its `sqlpage.identifier` calls
+fn query_for_identifiers<'a>(
     stmt: &'a StmtWithParams,
+    values: &[Option<Cow<'_, str>>],
+) -> anyhow::Result<&'a str> {
+    if stmt.identifiers.is_empty() {
+        return Ok(&stmt.query);
+    }
+    let index = variant_index(&stmt.identifiers, values)
+        .context("Invalid value in a call to sqlpage.identifier")?;
+    Ok(&stmt.query_variants[index])
+}
+
+/// Evaluates the parameters of the statement, followed by the values of its `sqlpage.identifier` calls
+async fn evaluate_parameters<'a>(
+    stmt: &StmtWithParams,
     request: &'a RequestInfo,
     db_connection: &mut DbConn,
-) -> anyhow::Result<StatementWithParams<'a>> {
-    let sql = stmt.query.as_str();
-    log::debug!("Preparing statement: {sql}");
-    let mut arguments = AnyArguments::default();
-    for (param_idx, param) in stmt.params.iter().enumerate() {
+) -> anyhow::Result<Vec<Option<Cow<'a, str>>>> {
+    let identifiers = stmt.identifiers.iter().map(|identifier| &identifier.value);
+    let mut values = Vec::with_capacity(stmt.params.len() + stmt.identifiers.len());
+    for (param_idx, param) in stmt.params.iter().chain(identifiers).enumerate() {
         log::trace!("\tevaluating parameter {}: {}", param_idx + 1, param);
         let argument = extract_req_param(param, request, db_connection).await?;
         log::debug!(
@@ -323,18 +924,29 @@ async fn bind_parameters<'a>(
             param_idx + 1,
             argument.as_ref().unwrap_or(&Cow::Borrowed("NULL"))
         );
+        values.push(argument);
+    }
+    Ok(values)
+}
+
+fn statement_with_values<'a>(
+    sql: &'a str,
+    values: Vec<Option<Cow<'a, str>>>,
+) -> StatementWithParams<'a> {
+    let mut arguments = AnyArguments::default();
+    let has_arguments = !values.is_empty();
+    for argument in values {
         match argument {
             None => arguments.add(None::<String>),
             Some(Cow::Owned(s)) => arguments.add(s),
             Some(Cow::Borrowed(v)) => arguments.add(v),
         }
     }
-    let has_arguments = !stmt.params.is_empty();
-    Ok(StatementWithParams {
+    StatementWithParams {
         sql,
         arguments,
         has_arguments,
-    })
+    }
 }
 
 async fn apply_delayed_functions(
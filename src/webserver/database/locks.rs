@@ -0,0 +1,278 @@
+//! Race-free counters and named locks, for `sqlpage.counter_next` and `sqlpage.with_lock`.
+//!
+//! Counters are stored in a `sqlpage_counters` table, that the application creates in a migration,
+//! and each call increments a counter and reads its new value in a single atomic statement.
+//!
+//! Locks use the advisory locks of the database: `pg_advisory_lock` on `PostgreSQL`, `GET_LOCK` on `MySQL`,
+//! and `sp_getapplock` on SQL Server, so that they exclude the pages executed by all the `SQLPage` servers
+//! that share the database. `SQLite` has no such locks: its locks only exclude the pages executed by the same process.
+//! A lock is held by the connection of the page that acquired it, until the end of the page.
+
+use super::execute_queries::{take_connection, DbConn};
+use super::make_placeholder;
+use super::sql_to_json::row_to_string;
+use crate::webserver::http_request_info::RequestInfo;
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use sqlx::any::AnyKind;
+use sqlx::{AnyConnection, Executor};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const COUNTERS_TABLE: &str = "sqlpage_counters";
+/// The longest lock name that `MySQL` accepts
+const MAX_LOCK_NAME_LENGTH: usize = 64;
+/// `PostgreSQL` has no way to wait for an advisory lock with a timeout, so we try again at increasing intervals
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Returns the next value of the counter named `name`, starting at 1.
+/// Pages that increment the same counter at the same time always get different values.
+pub(crate) async fn counter_next(
+    connection: &mut AnyConnection,
+    name: &str,
+) -> anyhow::Result<String> {
+    let kind = connection.kind();
+    let name_placeholder = make_placeholder(kind, 1);
+    let increment = match kind {
+        AnyKind::Postgres | AnyKind::Sqlite => format!(
+            "INSERT INTO {COUNTERS_TABLE} (name, value) VALUES ({name_placeholder}, 1) \
+            ON CONFLICT (name) DO UPDATE SET value = {COUNTERS_TABLE}.value + 1 RETURNING value"
+        ),
+        // LAST_INSERT_ID(x) returns x, and remembers it for the next call to LAST_INSERT_ID() in this connection
+        AnyKind::MySql => format!(
+            "INSERT INTO {COUNTERS_TABLE} (name, value) VALUES ({name_placeholder}, LAST_INSERT_ID(1)) \
+            ON DUPLICATE KEY UPDATE value = LAST_INSERT_ID(value + 1)"
+        ),
+        AnyKind::Mssql => format!(
+            "MERGE {COUNTERS_TABLE} WITH (HOLDLOCK) AS counter \
+            USING (SELECT {name_placeholder} AS name) AS incremented ON counter.name = incremented.name \
+            WHEN MATCHED THEN UPDATE SET value = counter.value + 1 \
+            WHEN NOT MATCHED THEN INSERT (name, value) VALUES (incremented.name, 1) \
+            OUTPUT inserted.value;"
+        ),
+    };
+    let error_context = || {
+        format!("Unable to increment the counter {name:?}. Create the {COUNTERS_TABLE} table in a migration to use counters")
+    };
+    let row = if kind == AnyKind::MySql {
+        connection
+            .execute(sqlx::query(&increment).bind(name))
+            .await
+            .with_context(error_context)?;
+        connection.fetch_one("SELECT LAST_INSERT_ID()").await
+    } else {
+        connection
+            .fetch_one(sqlx::query(&increment).bind(name))
+            .await
+    }
+    .with_context(error_context)?;
+    row_to_string(&row).with_context(error_context)
+}
+
+/// The locks of `SQLite` databases, shared by the pages that this process executes
+#[derive(Debug, Default)]
+pub(crate) struct LocalLocks(Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>);
+
+impl LocalLocks {
+    fn get(&self, name: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut locks = self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        // Forget the locks that nobody holds or waits for
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        Arc::clone(locks.entry(name.to_string()).or_default())
+    }
+}
+
+#[derive(Debug)]
+enum HeldLock {
+    Database(String),
+    Local {
+        name: String,
+        /// Releases the lock when the request drops it
+        _guard: tokio::sync::OwnedMutexGuard<()>,
+    },
+}
+
+/// The locks acquired by a request, and by the files it includes with `sqlpage.run_sql`
+#[derive(Debug, Default)]
+pub struct HeldLocks(RefCell<Vec<HeldLock>>);
+
+impl HeldLocks {
+    fn contains(&self, name: &str) -> bool {
+        self.0.borrow().iter().any(|lock| match lock {
+            HeldLock::Database(held) | HeldLock::Local { name: held, .. } => held == name,
+        })
+    }
+}
+
+/// Acquires the lock named `name` until the end of the request, waiting at most `timeout` for other pages to release it.
+/// Returns false when the lock was not released in time. Acquiring a lock that the request already holds succeeds immediately.
+pub(crate) async fn acquire(
+    request: &RequestInfo,
+    db_connection: &mut DbConn,
+    name: &str,
+    timeout: Duration,
+) -> anyhow::Result<bool> {
+    anyhow::ensure!(
+        !name.is_empty() && name.len() <= MAX_LOCK_NAME_LENGTH,
+        "Invalid lock name {name:?}: lock names must have between 1 and {MAX_LOCK_NAME_LENGTH} characters"
+    );
+    let held = &request.held_locks;
+    if held.contains(name) {
+        return Ok(true);
+    }
+    let database = request.database();
+    let kind = database.connection.any_kind();
+    if kind == AnyKind::Sqlite {
+        let lock = database.local_locks.get(name);
+        let Ok(guard) = tokio::time::timeout(timeout, lock.lock_owned()).await else {
+            return Ok(false);
+        };
+        held.0.borrow_mut().push(HeldLock::Local {
+            name: name.to_string(),
+            _guard: guard,
+        });
+        return Ok(true);
+    }
+    let connection = take_connection(database, db_connection).await?;
+    let acquired = match kind {
+        AnyKind::Postgres => try_advisory_lock_until(connection, name, timeout).await,
+        AnyKind::MySql => {
+            let acquired = connection
+                .fetch_one(
+                    sqlx::query("SELECT GET_LOCK(?, ?)")
+                        .bind(name)
+                        .bind(timeout.as_secs_f64()),
+                )
+                .await;
+            acquired.map(|row| row_to_string(&row).as_deref() == Some("1"))
+        }
+        AnyKind::Mssql => {
+            let timeout_ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+            let result = connection
+                .fetch_one(
+                    sqlx::query(
+                        "DECLARE @result INT; \
+                        EXEC @result = sp_getapplock @Resource = @p1, @LockMode = 'Exclusive', @LockOwner = 'Session', @LockTimeout = @p2; \
+                        SELECT @result;",
+                    )
+                    .bind(name)
+                    .bind(timeout_ms),
+                )
+                .await;
+            // sp_getapplock returns a negative number when the lock was not acquired
+            result.map(|row| row_to_string(&row).and_then(|r| r.parse::<i32>().ok()) >= Some(0))
+        }
+        AnyKind::Sqlite => unreachable!("SQLite locks are local"),
+    }
+    .with_context(|| format!("Unable to acquire the lock {name:?}"))?;
+    if acquired {
+        held.0
+            .borrow_mut()
+            .push(HeldLock::Database(name.to_string()));
+    }
+    Ok(acquired)
+}
+
+async fn try_advisory_lock_until(
+    connection: &mut AnyConnection,
+    name: &str,
+    timeout: Duration,
+) -> Result<bool, sqlx::Error> {
+    let deadline = Instant::now() + timeout;
+    let key = advisory_lock_key(name);
+    let mut delay = Duration::from_millis(10);
+    loop {
+        let (acquired,): (bool,) = sqlx::query_as("SELECT pg_try_advisory_lock($1)")
+            .bind(key)
+            .fetch_one(&mut *connection)
+            .await?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if acquired || remaining.is_zero() {
+            return Ok(acquired);
+        }
+        tokio::time::sleep(delay.min(remaining)).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// `PostgreSQL` identifies advisory locks by a number
+fn advisory_lock_key(name: &str) -> i64 {
+    let hash = Sha256::digest(name);
+    i64::from_be_bytes(hash[..8].try_into().expect("sha256 hashes have 32 bytes"))
+}
+
+/// Releases the locks acquired by a request. When a lock cannot be released, the connection
+/// that holds it is closed instead of being returned to the pool, which releases it.
+pub(crate) async fn release_all(request: &RequestInfo, db_connection: &mut DbConn) {
+    let locks = std::mem::take(&mut *request.held_locks.0.borrow_mut());
+    // Local locks are released when their guard is dropped
+    let names: Vec<String> = locks
+        .into_iter()
+        .filter_map(|lock| match lock {
+            HeldLock::Database(name) => Some(name),
+            HeldLock::Local { .. } => None,
+        })
+        .collect();
+    if !names.is_empty() {
+        // Boxed to keep the futures of the queries out of the stack frame of every page
+        Box::pin(release_database_locks(names, db_connection)).await;
+    }
+}
+
+async fn release_database_locks(names: Vec<String>, db_connection: &mut DbConn) {
+    for name in names {
+        let Some(connection) = db_connection.as_mut() else {
+            continue;
+        };
+        let kind = connection.kind();
+        let released = match kind {
+            AnyKind::Postgres => {
+                connection
+                    .execute(
+                        sqlx::query("SELECT pg_advisory_unlock($1)").bind(advisory_lock_key(&name)),
+                    )
+                    .await
+            }
+            AnyKind::MySql => {
+                connection
+                    .execute(sqlx::query("SELECT RELEASE_LOCK(?)").bind(&name))
+                    .await
+            }
+            AnyKind::Mssql => {
+                connection
+                    .execute(
+                        sqlx::query(
+                            "EXEC sp_releaseapplock @Resource = @p1, @LockOwner = 'Session'",
+                        )
+                        .bind(&name),
+                    )
+                    .await
+            }
+            AnyKind::Sqlite => continue,
+        };
+        if let Err(e) = released {
+            log::error!(
+                "Unable to release the lock {name:?}, closing its database connection: {e}"
+            );
+            if let Some(connection) = db_connection.take() {
+                drop(connection.detach());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_local_locks() {
+    let locks = LocalLocks::default();
+    let first = locks.get("invoices");
+    assert!(Arc::ptr_eq(&first, &locks.get("invoices")));
+    assert!(!Arc::ptr_eq(&first, &locks.get("other")));
+    drop(first);
+    locks.get("invoices");
+    assert_eq!(locks.0.lock().unwrap().len(), 1);
+}
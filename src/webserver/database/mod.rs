@@ -1,20 +1,34 @@
 mod connect;
 mod csv_import;
+mod dynamic_identifiers;
 pub mod execute_queries;
+pub(crate) mod locks;
 pub mod migrations;
+mod page_docs;
+mod parquet_import;
+pub mod previews;
 mod sql;
 mod sqlpage_functions;
+pub mod statement_hooks;
+pub mod statistics;
 mod syntax_tree;
+mod temporal_tables;
+mod xlsx_import;
 
 mod error_highlighting;
-mod sql_to_json;
+pub(crate) mod sql_to_json;
 
+pub(crate) use connect::connect_options;
+pub use page_docs::{PageParameter, ParameterSource};
 pub use sql::ParsedSqlFile;
 use sql::{DbPlaceHolder, DB_PLACEHOLDERS};
 use sqlx::any::AnyKind;
 
+#[derive(Debug)]
 pub struct Database {
     pub connection: sqlx::AnyPool,
+    /// The locks of `sqlpage.with_lock`, when the database has no locks of its own
+    pub(crate) local_locks: locks::LocalLocks,
 }
 impl Database {
     pub async fn close(&self) -> anyhow::Result<()> {
@@ -26,6 +40,8 @@ impl Database {
 
 #[derive(Debug)]
 pub enum DbItem {
+    /// Sent before the first row of a query: the kind of value in each of its columns
+    ColumnTypes(serde_json::Value),
     Row(serde_json::Value),
     FinishedQuery,
     Error(anyhow::Error),
@@ -0,0 +1,256 @@
+//! What a page expects from its visitors, and its title, found in its parsed SQL file
+//! to document the pages of a site with the `sqlpage docs` command.
+
+use super::csv_import::CsvImport;
+use super::sql::{ParsedStatement, SimpleSelectValue};
+use super::sqlpage_functions::functions::SqlPageFunctionName;
+use super::syntax_tree::StmtParam;
+use super::ParsedSqlFile;
+use serde::Serialize;
+
+/// Where the value of a parameter comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterSource {
+    /// The query string of the URL
+    Url,
+    /// `:name`: a field of a submitted form
+    Form,
+    /// `$name`: the URL, or a form field
+    UrlOrForm,
+    /// A file uploaded with a form
+    UploadedFile,
+    Cookie,
+    Header,
+}
+
+impl std::fmt::Display for ParameterSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Url => "URL",
+            Self::Form => "form",
+            Self::UrlOrForm => "URL or form",
+            Self::UploadedFile => "uploaded file",
+            Self::Cookie => "cookie",
+            Self::Header => "header",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageParameter {
+    pub name: String,
+    pub source: ParameterSource,
+}
+
+impl ParsedSqlFile {
+    /// The parameters used by the page, in the order in which they first appear.
+    /// Variables that the page defines with `SET` before using them are not parameters.
+    #[must_use]
+    pub fn parameters(&self) -> Vec<PageParameter> {
+        let mut collector = ParameterCollector::default();
+        for statement in &self.statements {
+            collector.statement(statement);
+        }
+        collector.parameters
+    }
+
+    /// The title of the `shell` component of the page, or else the first title of another component,
+    /// when it is a literal string
+    #[must_use]
+    pub fn title(&self) -> Option<String> {
+        let mut titles = self.statements.iter().filter_map(|statement| {
+            let ParsedStatement::StaticSimpleSelect(properties) = statement else {
+                return None;
+            };
+            let get = |name: &str| {
+                properties.iter().find_map(|(key, value)| match value {
+                    SimpleSelectValue::Static(serde_json::Value::String(s)) if key == name => {
+                        Some(s.as_str())
+                    }
+                    _ => None,
+                })
+            };
+            Some((get("component")?, get("title")?.to_string()))
+        });
+        let first = titles.next()?;
+        if first.0 == "shell" {
+            return Some(first.1);
+        }
+        titles
+            .find(|(component, _)| *component == "shell")
+            .map_or(Some(first.1), |(_, title)| Some(title))
+    }
+
+    /// The error that prevents the page from running, if its file could not be parsed
+    #[must_use]
+    pub fn parse_error(&self) -> Option<&anyhow::Error> {
+        self.statements
+            .iter()
+            .find_map(|statement| match statement {
+                ParsedStatement::Error(e) => Some(e),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Default)]
+struct ParameterCollector {
+    parameters: Vec<PageParameter>,
+    /// Variables set by the page
+    defined: Vec<String>,
+}
+
+impl ParameterCollector {
+    fn statement(&mut self, statement: &ParsedStatement) {
+        match statement {
+            ParsedStatement::StmtWithParams(stmt) | ParsedStatement::If { condition: stmt } => {
+                for param in &stmt.params {
+                    self.param(param);
+                }
+                for identifier in &stmt.identifiers {
+                    self.param(&identifier.value);
+                }
+            }
+            ParsedStatement::StaticSimpleSelect(properties) => {
+                for (_, value) in properties {
+                    if let SimpleSelectValue::Dynamic(param) = value {
+                        self.param(param);
+                    }
+                }
+            }
+            ParsedStatement::SetVariable {
+                variable, value, ..
+            } => {
+                for param in &value.params {
+                    self.param(param);
+                }
+                if let StmtParam::PostOrGet(name) | StmtParam::Get(name) = variable {
+                    self.defined.push(name.clone());
+                }
+            }
+            ParsedStatement::CsvImport(CsvImport { uploaded_file, .. }) => {
+                if !uploaded_file.contains("://") {
+                    self.add(uploaded_file, ParameterSource::UploadedFile);
+                }
+            }
+            ParsedStatement::ForEach { rows, body } => {
+                for param in &rows.params {
+                    self.param(param);
+                }
+                for statement in body {
+                    self.statement(statement);
+                }
+            }
+            ParsedStatement::Discard(statement) => self.statement(statement),
+            ParsedStatement::Else
+            | ParsedStatement::EndIf
+            | ParsedStatement::EndForEach
+            | ParsedStatement::Error(_) => {}
+        }
+    }
+
+    fn param(&mut self, param: &StmtParam) {
+        match param {
+            StmtParam::Get(name) => self.add(name, ParameterSource::Url),
+            StmtParam::Post(name) => self.add(name, ParameterSource::Form),
+            StmtParam::PostOrGet(name) => self.add(name, ParameterSource::UrlOrForm),
+            StmtParam::Concat(items)
+            | StmtParam::Coalesce(items)
+            | StmtParam::JsonObject(items)
+            | StmtParam::JsonArray(items) => {
+                for item in items {
+                    self.param(item);
+                }
+            }
+            StmtParam::FunctionCall(call) => {
+                let source = match call.function {
+                    SqlPageFunctionName::uploaded_file_path
+                    | SqlPageFunctionName::uploaded_file_name
+                    | SqlPageFunctionName::uploaded_file_mime_type
+                    | SqlPageFunctionName::uploaded_file_size
+                    | SqlPageFunctionName::persist_uploaded_file => {
+                        Some(ParameterSource::UploadedFile)
+                    }
+                    SqlPageFunctionName::cookie => Some(ParameterSource::Cookie),
+                    SqlPageFunctionName::header => Some(ParameterSource::Header),
+                    _ => None,
+                };
+                match (source, call.arguments.first()) {
+                    (Some(source), Some(StmtParam::Literal(name))) => self.add(name, source),
+                    _ => {
+                        for argument in &call.arguments {
+                            self.param(argument);
+                        }
+                    }
+                }
+            }
+            StmtParam::Error(_) | StmtParam::Literal(_) | StmtParam::Null => {}
+        }
+    }
+
+    fn add(&mut self, name: &str, source: ParameterSource) {
+        let is_variable = matches!(source, ParameterSource::Url | ParameterSource::UrlOrForm);
+        if name.is_empty() || is_variable && self.defined.iter().any(|d| d == name) {
+            return;
+        }
+        let is_request_value = |s| {
+            matches!(
+                s,
+                ParameterSource::Url | ParameterSource::Form | ParameterSource::UrlOrForm
+            )
+        };
+        match self.parameters.iter_mut().find(|p| p.name == name) {
+            Some(existing) if existing.source == source => {}
+            // `$x` and `:x` in the same page: the value can come from both places
+            Some(existing) if is_request_value(existing.source) && is_request_value(source) => {
+                existing.source = ParameterSource::UrlOrForm;
+            }
+            _ => self.parameters.push(PageParameter {
+                name: name.to_string(),
+                source,
+            }),
+        }
+    }
+}
+
+#[test]
+fn test_page_parameters() {
+    use sqlx::any::AnyKind;
+    let sql = "select 'shell' as component, 'Sales report' as title;
+        set year = coalesce($year, '2024');
+        select * from sales where year = $year and region = $region and product like :search;
+        select 'text' as component, sqlpage.cookie('session') as contents;
+        select sqlpage.uploaded_file_path('invoice') as path, :region as r;";
+    let file = ParsedSqlFile::parse(AnyKind::Sqlite, sql, std::path::Path::new("sales.sql"));
+    assert_eq!(file.title().as_deref(), Some("Sales report"));
+    let parameters = file.parameters();
+    let parameters: Vec<(&str, ParameterSource)> = parameters
+        .iter()
+        .map(|p| (p.name.as_str(), p.source))
+        .collect();
+    assert_eq!(
+        parameters,
+        [
+            ("year", ParameterSource::UrlOrForm),
+            ("region", ParameterSource::UrlOrForm),
+            ("search", ParameterSource::Form),
+            ("session", ParameterSource::Cookie),
+            ("invoice", ParameterSource::UploadedFile),
+        ]
+    );
+    assert!(file.parse_error().is_none());
+}
+
+#[test]
+fn test_page_title() {
+    use sqlx::any::AnyKind;
+    let parse = |sql| ParsedSqlFile::parse(AnyKind::Sqlite, sql, std::path::Path::new("x.sql"));
+    let file = parse("select 'title' as component, 'Users' as title; select 'shell' as component, 'Admin' as title;");
+    assert_eq!(file.title().as_deref(), Some("Admin"));
+    let file = parse("select 'hero' as component, 'Welcome' as title;");
+    assert_eq!(file.title().as_deref(), Some("Welcome"));
+    let file = parse("select 'shell' as component, $title as title;");
+    assert_eq!(file.title(), None);
+    assert!(parse("select from where").parse_error().is_some());
+}
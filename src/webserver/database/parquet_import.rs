@@ -0,0 +1,148 @@
+//! Reading of Parquet files for `COPY ... (FORMAT parquet)` statements.
+//!
+//! Values are converted to text that all databases can cast to the type of the target column:
+//! dates become `YYYY-MM-DD`, timestamps `YYYY-MM-DD HH:MM:SS` in UTC, decimals keep their exact digits,
+//! and nested values (lists, maps and structs) become JSON.
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use chrono::{DateTime, NaiveDate};
+use parquet::file::reader::{ChunkReader, FileReader, SerializedFileReader};
+use parquet::record::{reader::RowIter, Field};
+
+/// The values of the imported columns in a row, converted to text
+type Record = Vec<Option<String>>;
+
+/// Opens a Parquet file, and returns the names of the columns to import with an iterator over its rows.
+/// When `columns` is empty, all the columns of the file are imported.
+pub(super) fn read_rows(
+    reader: impl ChunkReader + 'static,
+    columns: &[String],
+) -> anyhow::Result<(Vec<String>, impl Iterator<Item = anyhow::Result<Record>>)> {
+    let reader =
+        SerializedFileReader::new(reader).context("The file is not a valid Parquet file")?;
+    let file_columns: Vec<String> = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .root_schema()
+        .get_fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+    let columns = if columns.is_empty() {
+        file_columns.clone()
+    } else {
+        columns.to_vec()
+    };
+    let indices = columns
+        .iter()
+        .map(|column| {
+            file_columns
+                .iter()
+                .position(|c| c == column)
+                .with_context(|| {
+                    format!(
+                        "The Parquet file does not have a column named {column:?}. Its columns are: {}",
+                        file_columns.join(", ")
+                    )
+                })
+        })
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+    let rows = RowIter::from_file_into(Box::new(reader)).map(move |row| {
+        let mut fields = row
+            .context("Unable to read a row of the Parquet file")?
+            .into_columns();
+        indices
+            .iter()
+            .map(|&i| field_to_string(std::mem::replace(&mut fields[i].1, Field::Null)))
+            .collect()
+    });
+    Ok((columns, rows))
+}
+
+fn field_to_string(field: Field) -> anyhow::Result<Option<String>> {
+    Ok(Some(match field {
+        Field::Null => return Ok(None),
+        Field::Str(s) => s,
+        Field::Bytes(bytes) => match String::from_utf8(bytes.data().to_vec()) {
+            Ok(text) => text,
+            Err(_) => base64::engine::general_purpose::STANDARD.encode(bytes.data()),
+        },
+        Field::Date(days) => NaiveDate::default()
+            .checked_add_signed(chrono::Duration::days(days.into()))
+            .context("Invalid date")?
+            .format("%Y-%m-%d")
+            .to_string(),
+        Field::TimestampMillis(millis) => {
+            format_timestamp(DateTime::from_timestamp_millis(millis))?
+        }
+        Field::TimestampMicros(micros) => {
+            format_timestamp(DateTime::from_timestamp_micros(micros))?
+        }
+        Field::Bool(b) => b.to_string(),
+        Field::Byte(n) => n.to_string(),
+        Field::Short(n) => n.to_string(),
+        Field::Int(n) => n.to_string(),
+        Field::Long(n) => n.to_string(),
+        Field::UByte(n) => n.to_string(),
+        Field::UShort(n) => n.to_string(),
+        Field::UInt(n) => n.to_string(),
+        Field::ULong(n) => n.to_string(),
+        Field::Float16(n) => n.to_string(),
+        Field::Float(n) => n.to_string(),
+        Field::Double(n) => n.to_string(),
+        Field::Decimal(_)
+        | Field::TimeMillis(_)
+        | Field::TimeMicros(_)
+        | Field::Group(_)
+        | Field::ListInternal(_)
+        | Field::MapInternal(_) => match field.to_json_value() {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Null => bail!("Unsupported Parquet value: {field}"),
+            json => json.to_string(),
+        },
+    }))
+}
+
+fn format_timestamp(timestamp: Option<DateTime<chrono::Utc>>) -> anyhow::Result<String> {
+    Ok(timestamp
+        .context("Invalid timestamp")?
+        .format("%Y-%m-%d %H:%M:%S%.f")
+        .to_string())
+}
+
+#[cfg(test)]
+pub(super) fn example_file() -> actix_web::web::Bytes {
+    // name: string, age: optional int32, birth_date: date, seen_at: timestamp in milliseconds
+    actix_web::web::Bytes::from_static(include_bytes!("../../../tests/uploads/people.parquet"))
+}
+
+#[test]
+fn test_read_rows() {
+    let (columns, rows) = read_rows(example_file(), &[]).unwrap();
+    assert_eq!(columns, ["name", "age", "birth_date", "seen_at"]);
+    let rows: Vec<_> = rows.collect::<anyhow::Result<_>>().unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                Some("Ophir".into()),
+                Some("29".into()),
+                Some("1995-07-11".into()),
+                Some("2023-11-14 22:13:20".into())
+            ],
+            vec![
+                Some("Max".into()),
+                None,
+                Some("1970-01-01".into()),
+                Some("2023-11-14 22:13:20.250".into())
+            ],
+        ]
+    );
+    let (_, rows) = read_rows(example_file(), &["age".into(), "name".into()]).unwrap();
+    let first_row = rows.into_iter().next().unwrap().unwrap();
+    assert_eq!(first_row, vec![Some("29".into()), Some("Ophir".into())]);
+    assert!(read_rows(example_file(), &["missing".into()]).is_err());
+    assert!(read_rows(actix_web::web::Bytes::from_static(b"name,age"), &[]).is_err());
+}
@@ -0,0 +1,310 @@
+//! Preview databases: isolated copies of the database, to review the branches of an application safely.
+//!
+//! When `preview_databases_directory` is set, a request that names a preview, in the `X-SQLPage-Preview` header
+//! or in a subdomain of `preview_domain`, uses its own `SQLite` database instead of the main one.
+//! The database of a preview is created on its first request: the migrations are applied, then the SQL files
+//! in `sqlpage/preview_fixtures/` are executed in alphabetical order to seed it with test data.
+//! In production, requests can only use existing previews: new ones are created with the administration API,
+//! so that anonymous clients cannot create databases.
+//! At most `max_preview_databases` previews are open at the same time.
+//! Previews are deleted with the administration API.
+
+use super::{migrations, Database};
+use crate::app_config::AppConfig;
+use crate::webserver::ErrorWithStatus;
+use crate::PREVIEW_FIXTURES_DIR;
+use actix_web::http::header::HeaderMap;
+use actix_web::http::StatusCode;
+use anyhow::Context;
+use sqlx::Executor;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Header that selects the preview to use
+pub const PREVIEW_HEADER: &str = "x-sqlpage-preview";
+const MAX_PREVIEW_NAME_LENGTH: usize = 63;
+const DATABASE_EXTENSION: &str = "sqlite";
+
+/// The database of a preview, that is set once it is opened and seeded
+type PreviewSlot = Arc<OnceCell<Arc<Database>>>;
+
+/// The databases of the previews opened since the server started
+#[derive(Default)]
+pub struct PreviewDatabases(Mutex<HashMap<String, PreviewSlot>>);
+
+impl PreviewDatabases {
+    /// The database of a preview. It is created and seeded if it does not exist yet, and `create` is true.
+    pub async fn get(
+        &self,
+        config: &AppConfig,
+        name: &str,
+        create: bool,
+    ) -> anyhow::Result<Arc<Database>> {
+        let (slot, unused) = self.slot(config, name)?;
+        for database in unused {
+            database.close().await?;
+        }
+        // Only the requests to this preview wait while it is created, and it is only seeded once
+        let opened = slot
+            .get_or_try_init(|| open(config, name, create))
+            .await
+            .map(Arc::clone);
+        if opened.is_err() {
+            let mut databases = self.0.lock().expect("previews lock poisoned");
+            if databases.get(name).is_some_and(|s| Arc::ptr_eq(s, &slot)) && !slot.initialized() {
+                databases.remove(name);
+            }
+        }
+        opened
+    }
+
+    /// The slot of the preview, and the databases of the unused previews closed to make room for it
+    fn slot(
+        &self,
+        config: &AppConfig,
+        name: &str,
+    ) -> anyhow::Result<(PreviewSlot, Vec<Arc<Database>>)> {
+        let mut databases = self.0.lock().expect("previews lock poisoned");
+        if let Some(slot) = databases.get(name) {
+            return Ok((Arc::clone(slot), Vec::new()));
+        }
+        let mut unused = Vec::new();
+        while databases.len() >= config.max_preview_databases {
+            // A preview is unused when no request holds its slot or its database
+            let unused_name = databases.iter().find_map(|(name, slot)| {
+                let database = slot.get()?;
+                (Arc::strong_count(slot) == 1 && Arc::strong_count(database) == 1)
+                    .then(|| name.clone())
+            });
+            let Some(unused_name) = unused_name else {
+                return Err(anyhow::anyhow!(ErrorWithStatus {
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                })
+                .context(format!(
+                    "Unable to open the preview {name:?}: {} previews are already in use. Increase max_preview_databases in the configuration",
+                    databases.len()
+                )));
+            };
+            let slot = databases.remove(&unused_name).expect("found above");
+            log::info!("Closing the database of the unused preview {unused_name:?}");
+            unused.extend(slot.get().map(Arc::clone));
+        }
+        let slot = PreviewSlot::default();
+        databases.insert(name.to_string(), Arc::clone(&slot));
+        Ok((slot, unused))
+    }
+
+    /// The names of the previews that have a database
+    pub async fn list(&self, config: &AppConfig) -> anyhow::Result<Vec<String>> {
+        let directory = previews_directory(config)?;
+        let mut names = Vec::new();
+        let Ok(mut entries) = tokio::fs::read_dir(&directory).await else {
+            return Ok(names);
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext == DATABASE_EXTENSION)
+            {
+                if let Some(name) = path.file_stem() {
+                    names.push(name.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Closes the database of a preview and deletes it. Returns false if the preview has no database.
+    pub async fn delete(&self, config: &AppConfig, name: &str) -> anyhow::Result<bool> {
+        let slot = self.0.lock().expect("previews lock poisoned").remove(name);
+        if let Some(database) = slot.as_ref().and_then(|slot| slot.get()) {
+            database.close().await?;
+        }
+        let path = database_path(&previews_directory(config)?, name);
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(false);
+        }
+        remove_database_files(&path).await;
+        log::info!("Deleted the database of the preview {name:?}");
+        Ok(true)
+    }
+}
+
+/// Opens the database of a preview, creating and seeding it if needed
+async fn open(config: &AppConfig, name: &str, create: bool) -> anyhow::Result<Arc<Database>> {
+    let directory = previews_directory(config)?;
+    let path = database_path(&directory, name);
+    let is_new = !tokio::fs::try_exists(&path).await.unwrap_or(false);
+    if is_new && !create {
+        return Err(anyhow::anyhow!(ErrorWithStatus {
+            status: StatusCode::NOT_FOUND,
+        })
+        .context(format!(
+            "The preview {name:?} does not exist. In production, previews are created with the administration API"
+        )));
+    }
+    tokio::fs::create_dir_all(&directory)
+        .await
+        .with_context(|| format!("Unable to create {}", directory.display()))?;
+    let mut preview_config = config.clone();
+    preview_config.database_url =
+        format!("sqlite://{}?mode=rwc", crate::app_config::encode_uri(&path));
+    preview_config.database_password = None;
+    let database = Database::init(&preview_config)
+        .await
+        .with_context(|| format!("Unable to open the database of the preview {name:?}"))?;
+    let prepared = async {
+        migrations::apply(&preview_config, &database).await?;
+        if is_new {
+            seed(config, &database).await?;
+        }
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = prepared {
+        database.close().await?;
+        if is_new {
+            remove_database_files(&path).await;
+        }
+        return Err(e.context(format!(
+            "Unable to prepare the database of the preview {name:?}"
+        )));
+    }
+    log::info!(
+        "Opened the database of the preview {name:?} in {}",
+        path.display()
+    );
+    Ok(Arc::new(database))
+}
+
+/// The name of the preview requested in the preview header, or in a subdomain of `preview_domain`
+pub fn requested_preview(
+    config: &AppConfig,
+    headers: &HeaderMap,
+    host: &str,
+) -> Option<anyhow::Result<String>> {
+    config.preview_databases_directory.as_ref()?;
+    if let Some(header) = headers.get(PREVIEW_HEADER) {
+        return Some(
+            header
+                .to_str()
+                .context("Invalid preview header")
+                .and_then(preview_name),
+        );
+    }
+    let domain = config.preview_domain.as_deref()?;
+    let host = host.rsplit_once(':').map_or(host, |(host, _port)| host);
+    let subdomain = host.strip_suffix(domain)?.strip_suffix('.')?;
+    Some(preview_name(subdomain))
+}
+
+/// Turns a branch name like `feature/New-Login` into a name that can be used in file names: `feature-new-login`
+pub fn preview_name(branch: &str) -> anyhow::Result<String> {
+    let name: String = branch
+        .trim()
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('a'..='z' | '0'..='9' | '_' | '-') => c,
+            _ => '-',
+        })
+        .collect();
+    let name = name.trim_matches('-');
+    anyhow::ensure!(
+        !name.is_empty() && name.len() <= MAX_PREVIEW_NAME_LENGTH,
+        "Invalid preview name {branch:?}. It must contain between 1 and {MAX_PREVIEW_NAME_LENGTH} letters, digits, dashes or underscores"
+    );
+    Ok(name.to_string())
+}
+
+fn previews_directory(config: &AppConfig) -> anyhow::Result<PathBuf> {
+    let directory = config
+        .preview_databases_directory
+        .as_ref()
+        .context("Preview databases are disabled. Set preview_databases_directory in the configuration to enable them")?;
+    Ok(config.configuration_directory.join(directory))
+}
+
+fn database_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(name).with_extension(DATABASE_EXTENSION)
+}
+
+/// Executes the SQL files of the fixtures directory, in alphabetical order
+async fn seed(config: &AppConfig, database: &Database) -> anyhow::Result<()> {
+    let fixtures_dir = config.configuration_directory.join(PREVIEW_FIXTURES_DIR);
+    let Ok(mut entries) = tokio::fs::read_dir(&fixtures_dir).await else {
+        log::debug!(
+            "Not seeding the preview database because {} does not exist",
+            fixtures_dir.display()
+        );
+        return Ok(());
+    };
+    let mut fixtures = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "sql") {
+            fixtures.push(path);
+        }
+    }
+    fixtures.sort();
+    for fixture in fixtures {
+        log::debug!("Seeding the preview database with {}", fixture.display());
+        let sql = tokio::fs::read_to_string(&fixture)
+            .await
+            .with_context(|| format!("Unable to read {}", fixture.display()))?;
+        database
+            .connection
+            .execute(sql.as_str())
+            .await
+            .with_context(|| format!("Unable to execute the fixture {}", fixture.display()))?;
+    }
+    Ok(())
+}
+
+async fn remove_database_files(path: &Path) {
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        if let Err(e) = tokio::fs::remove_file(&file).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::error!("Unable to delete {}: {e}", Path::new(&file).display());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_preview_name() {
+    assert_eq!(
+        preview_name("feature/New-Login").unwrap(),
+        "feature-new-login"
+    );
+    assert_eq!(preview_name(" pr_42 ").unwrap(), "pr_42");
+    assert!(preview_name("../..").is_err());
+    assert!(preview_name(&"x".repeat(64)).is_err());
+}
+
+#[test]
+fn test_requested_preview() {
+    let mut config = crate::app_config::tests::test_config();
+    let mut headers = HeaderMap::new();
+    assert!(requested_preview(&config, &headers, "feature.preview.example.com").is_none());
+    config.preview_databases_directory = Some("previews".into());
+    config.preview_domain = Some("preview.example.com".into());
+    let preview =
+        |headers: &HeaderMap, host| requested_preview(&config, headers, host).map(Result::unwrap);
+    assert_eq!(
+        preview(&headers, "pr-7.preview.example.com:8080").as_deref(),
+        Some("pr-7")
+    );
+    assert_eq!(preview(&headers, "preview.example.com"), None);
+    assert_eq!(preview(&headers, "example.com"), None);
+    headers.insert(PREVIEW_HEADER.parse().unwrap(), "fix/typo".parse().unwrap());
+    assert_eq!(
+        preview(&headers, "example.com").as_deref(),
+        Some("fix-typo")
+    );
+}
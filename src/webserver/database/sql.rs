@@ -1,7 +1,9 @@
 use super::csv_import::{extract_csv_copy_statement, CsvImport};
+use super::dynamic_identifiers::{extract_dynamic_identifiers, query_variants, DynamicIdentifier};
 use super::sqlpage_functions::functions::SqlPageFunctionName;
 use super::sqlpage_functions::{are_params_extractable, func_call_to_param};
 use super::syntax_tree::StmtParam;
+use super::temporal_tables::rewrite_as_of_tables;
 use crate::file_cache::AsyncFromStrWithState;
 use crate::webserver::database::error_highlighting::quote_source_with_highlight;
 use crate::{AppState, Database};
@@ -16,7 +18,7 @@ use sqlparser::ast::{
 use sqlparser::dialect::{Dialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
 use sqlparser::parser::{Parser, ParserError};
 use sqlparser::tokenizer::Token::{self, SemiColon, EOF};
-use sqlparser::tokenizer::{TokenWithSpan, Tokenizer};
+use sqlparser::tokenizer::{Span, TokenWithSpan, Tokenizer, Whitespace};
 use sqlx::any::AnyKind;
 use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
@@ -25,19 +27,36 @@ use std::str::FromStr;
 #[derive(Default)]
 pub struct ParsedSqlFile {
     pub(super) statements: Vec<ParsedStatement>,
-    pub(super) source_path: PathBuf,
+    pub(crate) source_path: PathBuf,
 }
 
 impl ParsedSqlFile {
     #[must_use]
     pub fn new(db: &Database, sql: &str, source_path: &Path) -> ParsedSqlFile {
-        let dialect = dialect_for_db(db.connection.any_kind());
+        Self::parse(db.connection.any_kind(), sql, source_path)
+    }
+
+    /// Parses a file with the SQL dialect of a kind of database, without connecting to it
+    #[must_use]
+    pub fn parse(db_kind: AnyKind, sql: &str, source_path: &Path) -> ParsedSqlFile {
+        let dialect = dialect_for_db(db_kind);
         log::debug!("Parsing SQL file {}", source_path.display());
         let parsed_statements = match parse_sql(dialect.as_ref(), sql) {
             Ok(parsed) => parsed,
             Err(err) => return Self::from_err(err, source_path),
         };
-        let statements = parsed_statements.collect();
+        let mut statements: Vec<ParsedStatement> = parsed_statements.collect();
+        let has_error = statements
+            .iter()
+            .any(|s| matches!(s, ParsedStatement::Error(_)));
+        if !has_error {
+            let grouped = group_loop_bodies(statements)
+                .and_then(|grouped| validate_conditional_blocks(&grouped).map(|()| grouped));
+            statements = match grouped {
+                Ok(grouped) => grouped,
+                Err(err) => return Self::from_err(err, source_path),
+            };
+        }
         ParsedSqlFile {
             statements,
             source_path: source_path.to_path_buf(),
@@ -83,6 +102,10 @@ pub(super) struct StmtWithParams {
     /// Columns that are JSON columns, and which should be converted to JSON objects after the query is executed.
     /// Only relevant for databases that do not have a native JSON type, and which return JSON values as text.
     pub json_columns: Vec<String>,
+    /// The `sqlpage.identifier` calls of the query, whose values select one of the `query_variants`.
+    pub identifiers: Vec<DynamicIdentifier>,
+    /// The versions of the query for each combination of allowed identifiers. Empty when there are no identifiers.
+    pub query_variants: Vec<String>,
 }
 
 /// A location in the source code.
@@ -106,8 +129,27 @@ pub(super) enum ParsedStatement {
     SetVariable {
         variable: StmtParam,
         value: StmtWithParams,
+        /// `SET $x = ARRAY(SELECT ...)` stores all the rows of the query as a JSON array instead of a single value
+        all_rows: bool,
     },
     CsvImport(CsvImport),
+    /// `-- @if <condition>`: the following statements, until the matching `-- @else` or `-- @endif`,
+    /// are only executed if the condition is true
+    If {
+        condition: StmtWithParams,
+    },
+    Else,
+    EndIf,
+    /// `-- @foreach`: the statements until the matching `-- @endforeach` are executed once for each row
+    /// returned by the query that follows the directive, with the columns of the row as variables
+    ForEach {
+        rows: StmtWithParams,
+        body: Vec<ParsedStatement>,
+    },
+    /// Only present while parsing. The statements before it are moved to the body of the matching loop.
+    EndForEach,
+    /// `-- @discard`: the statement is executed, but its results are not rendered
+    Discard(Box<ParsedStatement>),
     Error(anyhow::Error),
 }
 
@@ -169,6 +211,20 @@ fn parse_single_statement(
     db_kind: AnyKind,
     source_sql: &str,
 ) -> Option<ParsedStatement> {
+    parse_single_statement_with_options(parser, db_kind, source_sql, true)
+}
+
+/// Selects of constant values are normally evaluated by `SQLPage` without sending them to the database,
+/// unless `optimize_static_selects` is false.
+fn parse_single_statement_with_options(
+    parser: &mut Parser<'_>,
+    db_kind: AnyKind,
+    source_sql: &str,
+    optimize_static_selects: bool,
+) -> Option<ParsedStatement> {
+    if let Some(directive) = parse_directive(parser, db_kind, source_sql) {
+        return Some(directive);
+    }
     if parser.peek_token() == EOF {
         return None;
     }
@@ -181,14 +237,24 @@ fn parse_single_statement(
     while parser.consume_token(&SemiColon) {
         semicolon = true;
     }
+    if let Err(err) = rewrite_as_of_tables(&mut stmt, db_kind) {
+        return Some(ParsedStatement::Error(err));
+    }
+    let mut identifiers = match extract_dynamic_identifiers(&mut stmt) {
+        Ok(identifiers) => identifiers,
+        Err(err) => return Some(ParsedStatement::Error(err)),
+    };
     let mut params = ParameterExtractor::extract_parameters(&mut stmt, db_kind);
-    if let Some(parsed) = extract_set_variable(&mut stmt, &mut params, db_kind) {
+    if let Some(parsed) = extract_set_variable(&mut stmt, &mut params, &mut identifiers, db_kind) {
         return Some(parsed);
     }
     if let Some(csv_import) = extract_csv_copy_statement(&mut stmt) {
-        return Some(ParsedStatement::CsvImport(csv_import));
+        return Some(csv_import.map_or_else(ParsedStatement::Error, ParsedStatement::CsvImport));
     }
-    if let Some(static_statement) = extract_static_simple_select(&stmt, &params) {
+    if let Some(static_statement) = (optimize_static_selects && identifiers.is_empty())
+        .then(|| extract_static_simple_select(&stmt, &params))
+        .flatten()
+    {
         log::debug!("Optimised a static simple select to avoid a trivial database query: {stmt} optimized to {static_statement:?}");
         return Some(ParsedStatement::StaticSimpleSelect(static_statement));
     }
@@ -209,14 +275,241 @@ fn parse_single_statement(
         params,
         delayed_functions,
         json_columns,
+        identifiers: Vec::new(),
+        query_variants: Vec::new(),
     };
     transform_to_positional_placeholders(&mut stmt_with_params, db_kind);
+    if let Err(err) = add_query_variants(&mut stmt_with_params, identifiers) {
+        return Some(ParsedStatement::Error(err));
+    }
     log::debug!("Final transformed statement: {}", stmt_with_params.query);
     Some(ParsedStatement::StmtWithParams(stmt_with_params))
 }
 
+/// Directives are comments between statements, starting with `-- @`:
+/// conditional blocks (`-- @if <condition>`, `-- @else` and `-- @endif`), loops (`-- @foreach` and `-- @endforeach`),
+/// and the destination of the results of the next statement (`-- @into $var`, `-- @render <component>`, `-- @discard`).
+/// Other comments are skipped.
+fn parse_directive(
+    parser: &mut Parser<'_>,
+    db_kind: AnyKind,
+    source_sql: &str,
+) -> Option<ParsedStatement> {
+    loop {
+        let TokenWithSpan {
+            token: Token::Whitespace(whitespace),
+            span,
+        } = parser.peek_token_no_skip()
+        else {
+            return None;
+        };
+        parser.next_token_no_skip();
+        let Whitespace::SingleLineComment { comment, prefix } = whitespace else {
+            continue;
+        };
+        let Some(directive) = comment.trim().strip_prefix('@').filter(|_| prefix == "--") else {
+            continue;
+        };
+        let (keyword, condition) = directive
+            .split_once(char::is_whitespace)
+            .unwrap_or((directive, ""));
+        match keyword {
+            "if" => return Some(parse_if_condition(condition.trim(), span, db_kind)),
+            "else" => return Some(ParsedStatement::Else),
+            "endif" => return Some(ParsedStatement::EndIf),
+            "foreach" => return Some(parse_for_each(parser, db_kind, source_sql, span)),
+            "endforeach" => return Some(ParsedStatement::EndForEach),
+            "into" => {
+                return Some(parse_into(
+                    parser,
+                    db_kind,
+                    source_sql,
+                    condition.trim(),
+                    span,
+                ))
+            }
+            "render" => return Some(parse_render(condition.trim(), span)),
+            "discard" => return Some(parse_discard(parser, db_kind, source_sql, span)),
+            _ => log::debug!("Ignoring unknown directive in SQL comment: {comment}"),
+        }
+    }
+}
+
+fn parse_if_condition(condition: &str, span: Span, db_kind: AnyKind) -> ParsedStatement {
+    let dialect = dialect_for_db(db_kind);
+    let parsed = Parser::new(dialect.as_ref())
+        .try_with_sql(condition)
+        .and_then(|mut parser| {
+            let expr = parser.parse_expr()?;
+            parser.expect_token(&EOF)?;
+            Ok(expr)
+        });
+    let expr = match parsed {
+        Ok(expr) => expr,
+        Err(err) => {
+            return ParsedStatement::Error(anyhow::Error::from(err).context(format!(
+                "Invalid condition on line {}: -- @if {condition}",
+                span.start.line
+            )))
+        }
+    };
+    let mut select_stmt = expr_to_statement(expr);
+    let identifiers = match extract_dynamic_identifiers(&mut select_stmt) {
+        Ok(identifiers) => identifiers,
+        Err(err) => return ParsedStatement::Error(err),
+    };
+    let params = ParameterExtractor::extract_parameters(&mut select_stmt, db_kind);
+    match select_to_stmt_with_params(select_stmt, params, identifiers, db_kind) {
+        Ok(mut condition) => {
+            condition.query_position = source_span(span);
+            ParsedStatement::If { condition }
+        }
+        Err(err) => ParsedStatement::Error(err),
+    }
+}
+
+/// The statement following `-- @foreach` is the query that returns the rows to iterate over
+fn parse_for_each(
+    parser: &mut Parser<'_>,
+    db_kind: AnyKind,
+    source_sql: &str,
+    span: Span,
+) -> ParsedStatement {
+    match parse_single_statement(parser, db_kind, source_sql) {
+        Some(ParsedStatement::StmtWithParams(rows)) => ParsedStatement::ForEach {
+            rows,
+            body: Vec::new(),
+        },
+        Some(ParsedStatement::Error(err)) => ParsedStatement::Error(err),
+        _ => ParsedStatement::Error(anyhow::anyhow!(
+            "The -- @foreach directive on line {} must be followed by a SELECT query that returns the rows to iterate over. A SELECT of constant values, without FROM, returns a single row and cannot be used as a loop.",
+            span.start.line
+        )),
+    }
+}
+
+/// `-- @into $var` stores all the rows of the next statement in a variable, like `SET $var = ARRAY(...)`
+fn parse_into(
+    parser: &mut Parser<'_>,
+    db_kind: AnyKind,
+    source_sql: &str,
+    variable: &str,
+    span: Span,
+) -> ParsedStatement {
+    if !(variable.starts_with('$') || variable.starts_with(':')) || variable.len() < 2 {
+        return ParsedStatement::Error(anyhow::anyhow!(
+            "The -- @into directive on line {} must be followed by the name of a variable, like -- @into $rows. Found: {variable:?}",
+            span.start.line
+        ));
+    }
+    match parse_single_statement_with_options(parser, db_kind, source_sql, false) {
+        Some(ParsedStatement::StmtWithParams(value)) => ParsedStatement::SetVariable {
+            variable: map_param(variable.to_string()),
+            value,
+            all_rows: true,
+        },
+        Some(ParsedStatement::Error(err)) => ParsedStatement::Error(err),
+        _ => ParsedStatement::Error(anyhow::anyhow!(
+            "The -- @into directive on line {} must be followed by a SQL query",
+            span.start.line
+        )),
+    }
+}
+
+/// `-- @render <component>` selects the component of the next statement, like `SELECT '<component>' AS component`
+fn parse_render(component: &str, span: Span) -> ParsedStatement {
+    if component.is_empty() || component.contains(char::is_whitespace) {
+        return ParsedStatement::Error(anyhow::anyhow!(
+            "The -- @render directive on line {} must be followed by the name of a component, like -- @render table",
+            span.start.line
+        ));
+    }
+    ParsedStatement::StaticSimpleSelect(vec![(
+        "component".to_string(),
+        SimpleSelectValue::Static(component.into()),
+    )])
+}
+
+/// `-- @discard` executes the next statement without rendering its results
+fn parse_discard(
+    parser: &mut Parser<'_>,
+    db_kind: AnyKind,
+    source_sql: &str,
+    span: Span,
+) -> ParsedStatement {
+    match parse_single_statement(parser, db_kind, source_sql) {
+        Some(
+            statement @ (ParsedStatement::StmtWithParams(_)
+            | ParsedStatement::StaticSimpleSelect(_)),
+        ) => ParsedStatement::Discard(Box::new(statement)),
+        Some(ParsedStatement::Error(err)) => ParsedStatement::Error(err),
+        _ => ParsedStatement::Error(anyhow::anyhow!(
+            "The -- @discard directive on line {} must be followed by a SQL query",
+            span.start.line
+        )),
+    }
+}
+
+/// Moves the statements between `-- @foreach` and `-- @endforeach` to the body of the loop
+fn group_loop_bodies(statements: Vec<ParsedStatement>) -> anyhow::Result<Vec<ParsedStatement>> {
+    // The loops being parsed, with the statements that precede them
+    let mut open_loops: Vec<(StmtWithParams, Vec<ParsedStatement>)> = Vec::new();
+    let mut current = Vec::new();
+    for statement in statements {
+        match statement {
+            ParsedStatement::ForEach { rows, .. } => {
+                open_loops.push((rows, std::mem::take(&mut current)));
+            }
+            ParsedStatement::EndForEach => {
+                let (rows, preceding) = open_loops.pop().ok_or_else(|| {
+                    anyhow::anyhow!("Found an -- @endforeach without a matching -- @foreach")
+                })?;
+                let body = std::mem::replace(&mut current, preceding);
+                current.push(ParsedStatement::ForEach { rows, body });
+            }
+            statement => current.push(statement),
+        }
+    }
+    if !open_loops.is_empty() {
+        anyhow::bail!(
+            "A -- @foreach loop is not closed. Add -- @endforeach after its last statement."
+        );
+    }
+    Ok(current)
+}
+
+/// Checks that `-- @if` blocks are balanced. Blocks cannot start inside a loop and end outside of it.
+fn validate_conditional_blocks(statements: &[ParsedStatement]) -> anyhow::Result<()> {
+    // For each open block, whether it already has an else branch
+    let mut open_blocks: Vec<bool> = Vec::new();
+    for statement in statements {
+        match statement {
+            ParsedStatement::If { .. } => open_blocks.push(false),
+            ParsedStatement::ForEach { body, .. } => validate_conditional_blocks(body)?,
+            ParsedStatement::Else => match open_blocks.last_mut() {
+                Some(has_else) if !*has_else => *has_else = true,
+                Some(_) => anyhow::bail!("An -- @if block cannot have more than one -- @else"),
+                None => anyhow::bail!("Found an -- @else without a matching -- @if"),
+            },
+            ParsedStatement::EndIf => {
+                open_blocks.pop().ok_or_else(|| {
+                    anyhow::anyhow!("Found an -- @endif without a matching -- @if")
+                })?;
+            }
+            _ => {}
+        }
+    }
+    if !open_blocks.is_empty() {
+        anyhow::bail!("An -- @if block is not closed. Add -- @endif after its last statement.");
+    }
+    Ok(())
+}
+
 fn extract_query_start(stmt: &impl Spanned) -> SourceSpan {
-    let location = stmt.span();
+    source_span(stmt.span())
+}
+
+fn source_span(location: Span) -> SourceSpan {
     SourceSpan {
         start: SourceLocation {
             line: usize::try_from(location.start.line).unwrap_or(0),
@@ -237,7 +530,7 @@ fn syntax_error(err: ParserError, parser: &Parser, sql: &str) -> ParsedStatement
     )))
 }
 
-fn dialect_for_db(db_kind: AnyKind) -> Box<dyn Dialect> {
+pub(super) fn dialect_for_db(db_kind: AnyKind) -> Box<dyn Dialect> {
     match db_kind {
         AnyKind::Postgres => Box::new(PostgreSqlDialect {}),
         AnyKind::Mssql => Box::new(MsSqlDialect {}),
@@ -440,8 +733,12 @@ fn extract_static_simple_select(
                 }
             }
             other => {
-                log::trace!("Cancelling simple select optimization because of expr: {other:?}");
-                return None;
+                if let Some(p) = simple_select_expression(other, &mut params_iter) {
+                    Dynamic(p)
+                } else {
+                    log::trace!("Cancelling simple select optimization because of expr: {other:?}");
+                    return None;
+                }
             }
         };
         let key = alias.value.clone();
@@ -454,6 +751,64 @@ fn extract_static_simple_select(
     Some(items)
 }
 
+/// Expressions that `SQLPage` evaluates itself in selects without `FROM`, without sending them to the database:
+/// string concatenations with `||` and `COALESCE`, of literals, variables and sqlpage functions.
+fn simple_select_expression(
+    expr: &Expr,
+    params: &mut impl Iterator<Item = StmtParam>,
+) -> Option<StmtParam> {
+    match expr {
+        e if is_simple_select_placeholder(e) => params.next(),
+        Expr::Value(ValueWithSpan {
+            value: Value::SingleQuotedString(s),
+            ..
+        }) => Some(StmtParam::Literal(s.clone())),
+        Expr::Value(ValueWithSpan {
+            value: Value::Number(n, _),
+            ..
+        }) => Some(StmtParam::Literal(n.clone())),
+        Expr::Value(ValueWithSpan {
+            value: Value::Null, ..
+        }) => Some(StmtParam::Null),
+        Expr::Nested(inner) => simple_select_expression(inner, params),
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::StringConcat,
+            right,
+        } => {
+            let left = simple_select_expression(left, params)?;
+            let right = simple_select_expression(right, params)?;
+            Some(StmtParam::Concat(vec![left, right]))
+        }
+        Expr::Function(Function {
+            name: ObjectName(name),
+            args:
+                FunctionArguments::List(FunctionArgumentList {
+                    args,
+                    duplicate_treatment: None,
+                    clauses,
+                }),
+            filter: None,
+            over: None,
+            ..
+        }) if clauses.is_empty()
+            && matches!(name.as_slice(), [ObjectNamePart::Identifier(ident)] if ident.value.eq_ignore_ascii_case("coalesce")) =>
+        {
+            let args = args
+                .iter()
+                .map(|arg| match arg {
+                    FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => {
+                        simple_select_expression(e, params)
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(StmtParam::Coalesce(args))
+        }
+        _ => None,
+    }
+}
+
 fn is_simple_select_placeholder(e: &Expr) -> bool {
     match e {
         Expr::Value(ValueWithSpan {
@@ -473,6 +828,7 @@ fn is_simple_select_placeholder(e: &Expr) -> bool {
 fn extract_set_variable(
     stmt: &mut Statement,
     params: &mut Vec<StmtParam>,
+    identifiers: &mut Vec<DynamicIdentifier>,
     db_kind: AnyKind,
 ) -> Option<ParsedStatement> {
     if let Statement::Set(Set::SingleAssignment {
@@ -491,26 +847,71 @@ fn extract_set_variable(
                 StmtParam::PostOrGet(std::mem::take(&mut ident.value))
             };
             let owned_expr = std::mem::replace(value, Expr::value(Value::Null));
-            let mut select_stmt: Statement = expr_to_statement(owned_expr);
-            let delayed_functions = extract_toplevel_functions(&mut select_stmt);
-            if let Err(err) = validate_function_calls(&select_stmt) {
-                return Some(ParsedStatement::Error(err));
-            }
-            let json_columns = extract_json_columns(&select_stmt, db_kind);
-            let mut value = StmtWithParams {
-                query: select_stmt.to_string(),
-                query_position: extract_query_start(&select_stmt),
-                params: std::mem::take(params),
-                delayed_functions,
-                json_columns,
+            let (select_stmt, all_rows) = match owned_expr {
+                Expr::Function(Function {
+                    name: ObjectName(name),
+                    args: FunctionArguments::Subquery(query),
+                    ..
+                }) if is_array_function(&name) => (Statement::Query(query), true),
+                expr => (expr_to_statement(expr), false),
             };
-            transform_to_positional_placeholders(&mut value, db_kind);
-            return Some(ParsedStatement::SetVariable { variable, value });
+            let value = match select_to_stmt_with_params(
+                select_stmt,
+                std::mem::take(params),
+                std::mem::take(identifiers),
+                db_kind,
+            ) {
+                Ok(value) => value,
+                Err(err) => return Some(ParsedStatement::Error(err)),
+            };
+            return Some(ParsedStatement::SetVariable {
+                variable,
+                value,
+                all_rows,
+            });
         }
     }
     None
 }
 
+fn is_array_function(name: &[ObjectNamePart]) -> bool {
+    matches!(name, [ObjectNamePart::Identifier(ident)] if ident.value.eq_ignore_ascii_case("array"))
+}
+
+/// Prepares a query generated by `SQLPage`, whose parameters have already been extracted, for execution.
+fn select_to_stmt_with_params(
+    mut select_stmt: Statement,
+    params: Vec<StmtParam>,
+    identifiers: Vec<DynamicIdentifier>,
+    db_kind: AnyKind,
+) -> anyhow::Result<StmtWithParams> {
+    let delayed_functions = extract_toplevel_functions(&mut select_stmt);
+    validate_function_calls(&select_stmt)?;
+    let json_columns = extract_json_columns(&select_stmt, db_kind);
+    let mut value = StmtWithParams {
+        query: select_stmt.to_string(),
+        query_position: extract_query_start(&select_stmt),
+        params,
+        delayed_functions,
+        json_columns,
+        identifiers: Vec::new(),
+        query_variants: Vec::new(),
+    };
+    transform_to_positional_placeholders(&mut value, db_kind);
+    add_query_variants(&mut value, identifiers)?;
+    Ok(value)
+}
+
+/// Prepares the versions of the query for all the names that its `sqlpage.identifier` calls can take
+fn add_query_variants(
+    stmt: &mut StmtWithParams,
+    identifiers: Vec<DynamicIdentifier>,
+) -> anyhow::Result<()> {
+    stmt.query_variants = query_variants(&stmt.query, &identifiers)?;
+    stmt.identifiers = identifiers;
+    Ok(())
+}
+
 struct ParameterExtractor {
     db_kind: AnyKind,
     parameters: Vec<StmtParam>,
@@ -734,6 +1135,10 @@ fn expr_to_stmt_param(arg: &mut Expr) -> Option<StmtParam> {
             value: Value::Number(param_value, _is_long),
             ..
         }) => Some(StmtParam::Literal(param_value.clone())),
+        Expr::Value(ValueWithSpan {
+            value: Value::Boolean(b),
+            ..
+        }) => Some(StmtParam::Literal(b.to_string())),
         Expr::Value(ValueWithSpan {
             value: Value::Null, ..
         }) => Some(StmtParam::Null),
@@ -1238,6 +1643,7 @@ mod test {
             if let Some(ParsedStatement::SetVariable {
                 variable,
                 value: StmtWithParams { query, params, .. },
+                ..
             }) = stmt
             {
                 assert_eq!(
@@ -1248,11 +1654,33 @@ mod test {
                 assert!(query.starts_with("SELECT "));
                 assert_eq!(params, [StmtParam::PostOrGet("y".to_string())]);
             } else {
-                panic!("Failed for dialect {dialect:?}: {stmt:#?}",);
+                panic!("Failed for dialect {dialect:?}: {stmt:#?}");
             }
         }
     }
 
+    #[test]
+    fn test_set_variable_to_all_rows() {
+        let sql = "set x = ARRAY(select a, b from t where c = $y)";
+        for &(dialect, db_kind) in ALL_DIALECTS {
+            let mut parser = Parser::new(dialect).try_with_sql(sql).unwrap();
+            let stmt = parse_single_statement(&mut parser, db_kind, sql);
+            let Some(ParsedStatement::SetVariable {
+                value: StmtWithParams { query, params, .. },
+                all_rows: true,
+                ..
+            }) = stmt
+            else {
+                panic!("Failed for dialect {dialect:?}: {stmt:#?}");
+            };
+            assert!(
+                query.starts_with("SELECT a, b FROM t WHERE c = "),
+                "{dialect:?}: {query}"
+            );
+            assert_eq!(params, [StmtParam::PostOrGet("y".to_string())]);
+        }
+    }
+
     #[test]
     fn is_own_placeholder() {
         assert!(ParameterExtractor {
@@ -1348,6 +1776,59 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_simple_select_with_expressions() {
+        let sql = "select 'link' as component, '/page.sql?id=' || $id || '&tab=' || 2 as link, coalesce($title, 'Untitled') as title, ('q=' || sqlpage.url_encode($q)) as query";
+        for &(dialect, db_kind) in ALL_DIALECTS {
+            use StmtParam::{Coalesce, Concat, Literal, PostOrGet};
+
+            let parsed: Vec<ParsedStatement> = parse_sql(dialect, sql).unwrap().collect();
+            if db_kind == AnyKind::Mssql {
+                // || is rewritten to CONCAT, which ignores NULL values on SQL Server
+                assert!(
+                    matches!(parsed.as_slice(), [ParsedStatement::StmtWithParams(_)]),
+                    "{parsed:#?}"
+                );
+                continue;
+            }
+            let [ParsedStatement::StaticSimpleSelect(q)] = parsed.as_slice() else {
+                panic!("failed to extract simple select in {dialect:?}: {parsed:#?}");
+            };
+            assert_eq!(
+                q[1],
+                (
+                    "link".into(),
+                    SimpleSelectValue::Dynamic(Concat(vec![
+                        Concat(vec![
+                            Concat(vec![
+                                Literal("/page.sql?id=".into()),
+                                PostOrGet("id".into())
+                            ]),
+                            Literal("&tab=".into())
+                        ]),
+                        Literal("2".into())
+                    ]))
+                ),
+                "{dialect:?}"
+            );
+            assert_eq!(
+                q[2],
+                (
+                    "title".into(),
+                    SimpleSelectValue::Dynamic(Coalesce(vec![
+                        PostOrGet("title".into()),
+                        Literal("Untitled".into())
+                    ]))
+                ),
+                "{dialect:?}"
+            );
+            assert!(
+                matches!(&q[3].1, SimpleSelectValue::Dynamic(Concat(args)) if matches!(args[1], StmtParam::FunctionCall(_))),
+                "{dialect:?}: {q:?}"
+            );
+        }
+    }
+
     #[test]
     fn test_simple_select_only_extraction() {
         use SimpleSelectValue::{Dynamic, Static};
@@ -1373,6 +1854,7 @@ mod test {
             if let Some(ParsedStatement::SetVariable {
                 variable,
                 value: StmtWithParams { query, params, .. },
+                ..
             }) = stmt
             {
                 assert_eq!(
@@ -1383,13 +1865,20 @@ mod test {
                 assert_eq!(query, "SELECT 42 AS sqlpage_set_expr");
                 assert!(params.is_empty());
             } else {
-                panic!("Failed for dialect {dialect:?}: {stmt:#?}",);
+                panic!("Failed for dialect {dialect:?}: {stmt:#?}");
             }
         }
     }
 
     #[test]
     fn test_static_extract_doesnt_match() {
+        assert_eq!(
+            extract_static_simple_select(
+                &parse_postgres_stmt("select 'hello' || upper('world') as hello"),
+                &[]
+            ),
+            None
+        );
         assert_eq!(
             extract_static_simple_select(
                 &parse_postgres_stmt("select 'hello' as hello, 42 as answer limit 0"),
@@ -1489,6 +1978,7 @@ mod test {
                         json_columns,
                         ..
                     },
+                ..
             }) = stmt
             else {
                 panic!("for dialect {dialect:?}: {stmt:#?} instead of SetVariable");
@@ -1563,6 +2053,8 @@ mod test {
             ],
             delayed_functions: vec![],
             json_columns: vec![],
+            identifiers: vec![],
+            query_variants: vec![],
         };
         transform_to_positional_placeholders(&mut stmt, AnyKind::MySql);
         assert_eq!(
@@ -1615,4 +2107,107 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_conditional_directives() {
+        let sql = "select 1;\n-- @if $x = 'a'\nselect 2;\n-- some comment\n-- @else\nselect 3;\n-- @endif\n";
+        for &(dialect, db_kind) in ALL_DIALECTS {
+            let statements: Vec<_> = parse_sql(dialect, sql).unwrap().collect();
+            let [ParsedStatement::StmtWithParams(_), ParsedStatement::If { condition }, ParsedStatement::StmtWithParams(_), ParsedStatement::Else, ParsedStatement::StmtWithParams(_), ParsedStatement::EndIf] =
+                statements.as_slice()
+            else {
+                panic!("Failed for dialect {dialect:?}: {statements:#?}");
+            };
+            assert!(
+                condition.query.starts_with("SELECT "),
+                "{db_kind:?}: {}",
+                condition.query
+            );
+            assert_eq!(condition.params, [StmtParam::PostOrGet("x".to_string())]);
+            assert_eq!(condition.query_position.start.line, 2);
+            validate_conditional_blocks(&statements).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_unbalanced_conditional_blocks() {
+        for sql in [
+            "-- @if 1 = 1\nselect 1;",
+            "select 1;\n-- @endif\n",
+            "-- @else\nselect 1;",
+            "-- @if 1 = 1\n-- @else\n-- @else\n-- @endif\n",
+        ] {
+            let statements: Vec<_> = parse_sql(&SQLiteDialect {}, sql).unwrap().collect();
+            assert!(
+                validate_conditional_blocks(&statements).is_err(),
+                "{sql:?} should be rejected: {statements:#?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_foreach_loop() {
+        let sql = "-- @foreach\nselect id from t;\nselect $id as x;\n-- @foreach\nselect 1 as y union all select 2 as y;\nselect $y as y;\n-- @endforeach\n-- @endforeach\nselect 3 as z;";
+        let statements: Vec<_> = parse_sql(&SQLiteDialect {}, sql).unwrap().collect();
+        let statements = group_loop_bodies(statements).unwrap();
+        let [ParsedStatement::ForEach { rows, body }, ParsedStatement::StaticSimpleSelect(_)] =
+            statements.as_slice()
+        else {
+            panic!("{statements:#?}");
+        };
+        assert_eq!(rows.query, "SELECT id FROM t;");
+        assert!(
+            matches!(body.as_slice(), [ParsedStatement::StaticSimpleSelect(_), ParsedStatement::ForEach { body, .. }] if body.len() == 1),
+            "{body:#?}"
+        );
+
+        for sql in [
+            "-- @foreach\nselect x from t;",
+            "select 1;\n-- @endforeach\n",
+        ] {
+            let statements: Vec<_> = parse_sql(&SQLiteDialect {}, sql).unwrap().collect();
+            assert!(group_loop_bodies(statements).is_err(), "{sql:?}");
+        }
+    }
+
+    #[test]
+    fn test_result_destination_directives() {
+        let sql = "-- @into $rows\nselect 1 as x;\n-- @render table\nselect * from t;\n-- @discard\nselect * from t;";
+        let statements: Vec<_> = parse_sql(&SQLiteDialect {}, sql).unwrap().collect();
+        let [ParsedStatement::SetVariable {
+            variable,
+            value,
+            all_rows: true,
+        }, ParsedStatement::StaticSimpleSelect(component), ParsedStatement::StmtWithParams(_), ParsedStatement::Discard(discarded)] =
+            statements.as_slice()
+        else {
+            panic!("{statements:#?}");
+        };
+        assert_eq!(variable, &StmtParam::PostOrGet("rows".to_string()));
+        assert_eq!(value.query, "SELECT 1 AS x;");
+        assert_eq!(
+            component,
+            &[(
+                "component".to_string(),
+                SimpleSelectValue::Static("table".into())
+            )]
+        );
+        assert!(
+            matches!(discarded.as_ref(), ParsedStatement::StmtWithParams(_)),
+            "{discarded:#?}"
+        );
+
+        for sql in [
+            "-- @into rows\nselect 1 as x;",
+            "-- @into $rows\nset x = 1;",
+            "-- @render\nselect 1 as x;",
+            "-- @discard\n",
+        ] {
+            let statements: Vec<_> = parse_sql(&SQLiteDialect {}, sql).unwrap().collect();
+            assert!(
+                matches!(statements.as_slice(), [ParsedStatement::Error(_), ..]),
+                "{sql:?} should be rejected: {statements:#?}"
+            );
+        }
+    }
 }
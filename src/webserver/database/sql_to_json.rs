@@ -52,6 +52,19 @@ fn decode_raw<'a, T: Decode<'a, sqlx::any::Any> + Default>(
     }
 }
 
+/// Text stored in a binary column is returned as a string, and other binary data as a data URL,
+/// that can be displayed in an image or served by the `download` component.
+fn binary_to_json(bytes: Vec<u8>) -> Value {
+    use base64::Engine;
+    match String::from_utf8(bytes) {
+        Ok(text) => Value::String(text),
+        Err(err) => {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(err.as_bytes());
+            Value::String(format!("data:application/octet-stream;base64,{encoded}"))
+        }
+    }
+}
+
 pub fn sql_nonnull_to_json<'r>(mut get_ref: impl FnMut() -> sqlx::any::AnyValueRef<'r>) -> Value {
     let raw_value = get_ref();
     let type_info = raw_value.type_info();
@@ -94,11 +107,54 @@ pub fn sql_nonnull_to_json<'r>(mut get_ref: impl FnMut() -> sqlx::any::AnyValueR
             .to_string()
             .into(),
         "JSON" | "JSON[]" | "JSONB" | "JSONB[]" => decode_raw::<Value>(raw_value),
+        "BLOB" | "BYTEA" | "BINARY" | "VARBINARY" | "IMAGE" => {
+            binary_to_json(decode_raw::<Vec<u8>>(raw_value))
+        }
         // Deserialize as a string by default
         _ => decode_raw::<String>(raw_value).into(),
     }
 }
 
+/// The kind of value stored in each column of a row, made available to templates as `@column_types`.
+/// The type of the value in the row is used when the database does not declare a type for the column,
+/// as `SQLite` does for expressions.
+pub fn column_types(row: &AnyRow) -> Value {
+    let mut map = Map::new();
+    for col in row.columns() {
+        let declared = col.type_info();
+        let type_info = match row.try_get_raw(col.ordinal()) {
+            Ok(value) if declared.is_null() && !value.is_null() => value.type_info().into_owned(),
+            _ => declared.clone(),
+        };
+        map.entry(col.name())
+            .or_insert_with(|| type_category(&type_info).into());
+    }
+    Value::Object(map)
+}
+
+fn type_category(type_info: &AnyTypeInfo) -> &'static str {
+    match type_info.name() {
+        "BOOL" | "BOOLEAN" => "boolean",
+        "BIT"
+            if matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::Mssql(_)))
+                || matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::MySql(mysql_type)) if mysql_type.max_size() == Some(1)) =>
+        {
+            "boolean"
+        }
+        "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" | "NUMERIC" | "DECIMAL" | "MONEY"
+        | "SMALLMONEY" | "INT8" | "BIGINT" | "SERIAL8" | "BIGSERIAL" | "IDENTITY" | "INT64"
+        | "INTEGER8" | "BIGINT SIGNED" | "INT" | "INT4" | "INTEGER" | "MEDIUMINT" | "YEAR"
+        | "INT2" | "SMALLINT" | "TINYINT" | "BIGINT UNSIGNED" | "INT UNSIGNED"
+        | "MEDIUMINT UNSIGNED" | "SMALLINT UNSIGNED" | "TINYINT UNSIGNED" | "BIT" => "number",
+        "DATE" => "date",
+        "TIME" | "TIMETZ" => "time",
+        "DATETIMEOFFSET" | "TIMESTAMP" | "TIMESTAMPTZ" | "DATETIME" | "DATETIME2" => "datetime",
+        "JSON" | "JSON[]" | "JSONB" | "JSONB[]" => "json",
+        "BLOB" | "BYTEA" | "BINARY" | "VARBINARY" | "IMAGE" => "binary",
+        _ => "text",
+    }
+}
+
 /// Takes the first column of a row and converts it to a string.
 pub fn row_to_string(row: &AnyRow) -> Option<String> {
     let col = row.columns().first()?;
@@ -159,6 +215,28 @@ mod tests {
                 "three_values": ["x","y","z"],
             })
         );
+        let Value::Object(columns) = row_to_json(&row) else {
+            unreachable!()
+        };
+        // Objects compare equal regardless of the order of their keys
+        assert_eq!(
+            columns.keys().collect::<Vec<_>>(),
+            ["one_value", "two_values", "three_values"]
+        );
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn test_column_types() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let row = sqlx::query("SELECT 42 as n, 'x' as s, 'y' as s")
+            .fetch_one(&mut c)
+            .await?;
+        assert_eq!(
+            column_types(&row),
+            serde_json::json!({ "n": "number", "s": "text" })
+        );
         Ok(())
     }
 
@@ -326,7 +404,8 @@ mod tests {
                 42 as integer,
                 42.25 as real,
                 'xxx' as string,
-                x'68656c6c6f20776f726c64' as blob",
+                x'68656c6c6f20776f726c64' as blob,
+                x'ff00' as binary_blob",
         )
         .fetch_one(&mut c)
         .await?;
@@ -338,6 +417,7 @@ mod tests {
                 "real": 42.25,
                 "string": "xxx",
                 "blob": "hello world",
+                "binary_blob": "data:application/octet-stream;base64,/wA=",
             }),
         );
         Ok(())
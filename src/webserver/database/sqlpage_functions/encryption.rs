@@ -0,0 +1,96 @@
+//! Authenticated encryption of values that are sent to users and read back later,
+//! such as password reset links or remember-me cookies.
+//!
+//! Values are encrypted with AES-256-GCM, using a key derived from `encryption_key` in the configuration.
+//! The encryption time is stored with the value, so that old tokens can be rejected.
+//! Tokens are encoded in unpadded URL-safe base64, so they can be used in URLs and cookies without escaping.
+
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use sha2::{Digest, Sha256};
+
+/// Prevents tokens created by other applications with the same key from being accepted
+const ASSOCIATED_DATA: &[u8] = b"sqlpage.encrypt";
+const TIMESTAMP_LEN: usize = 8;
+
+fn cipher(secret: &str) -> LessSafeKey {
+    let key = Sha256::digest(secret.as_bytes());
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).expect("AES-256 keys have 32 bytes"))
+}
+
+/// Encrypts `value` at the unix time `now`
+pub(super) fn encrypt(secret: &str, value: &str, now: u64) -> String {
+    let nonce_bytes = rand::random::<[u8; NONCE_LEN]>();
+    let mut in_out = Vec::with_capacity(TIMESTAMP_LEN + value.len() + AES_256_GCM.tag_len());
+    in_out.extend_from_slice(&now.to_be_bytes());
+    in_out.extend_from_slice(value.as_bytes());
+    cipher(secret)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce_bytes),
+            Aad::from(ASSOCIATED_DATA),
+            &mut in_out,
+        )
+        .expect("the value is too large to be encrypted");
+    let mut token = nonce_bytes.to_vec();
+    token.extend_from_slice(&in_out);
+    URL_SAFE_NO_PAD.encode(token)
+}
+
+/// The value encrypted in `token`, or None if the token was not created with this secret,
+/// was modified, or is older than `max_age_seconds`
+pub(super) fn decrypt(
+    secret: &str,
+    token: &str,
+    max_age_seconds: Option<u64>,
+    now: u64,
+) -> Option<String> {
+    let mut token = URL_SAFE_NO_PAD.decode(token.trim()).ok()?;
+    if token.len() < NONCE_LEN {
+        return None;
+    }
+    let mut in_out = token.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&token).ok()?;
+    let plaintext = cipher(secret)
+        .open_in_place(nonce, Aad::from(ASSOCIATED_DATA), &mut in_out)
+        .ok()?;
+    let (timestamp, value) = plaintext.split_first_chunk::<TIMESTAMP_LEN>()?;
+    let encrypted_at = u64::from_be_bytes(*timestamp);
+    if max_age_seconds.is_some_and(|max_age| now.saturating_sub(encrypted_at) > max_age) {
+        return None;
+    }
+    String::from_utf8(value.to_vec()).ok()
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let secret = "a secret key of at least 32 characters";
+    let token = encrypt(secret, "user 42", 1_000);
+    assert!(!token.contains("42"), "{token}");
+    assert_eq!(
+        decrypt(secret, &token, None, 1_000_000).as_deref(),
+        Some("user 42")
+    );
+    assert_eq!(
+        decrypt(secret, &token, Some(60), 1_060).as_deref(),
+        Some("user 42")
+    );
+    assert_eq!(decrypt(secret, &token, Some(60), 1_061), None);
+    assert_eq!(decrypt("another secret", &token, None, 1_000), None);
+    assert_ne!(token, encrypt(secret, "user 42", 1_000));
+}
+
+#[test]
+fn test_decrypt_tampered_token() {
+    let secret = "a secret key of at least 32 characters";
+    let mut token = URL_SAFE_NO_PAD
+        .decode(encrypt(secret, "admin=false", 0))
+        .unwrap();
+    let last = token.len() - 1;
+    token[last] ^= 1;
+    assert_eq!(
+        decrypt(secret, &URL_SAFE_NO_PAD.encode(&token), None, 0),
+        None
+    );
+    assert_eq!(decrypt(secret, "not a token", None, 0), None);
+    assert_eq!(decrypt(secret, "", None, 0), None);
+}
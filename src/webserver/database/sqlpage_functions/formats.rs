@@ -0,0 +1,139 @@
+//! Validation and normalization of common data formats entered in forms: email addresses, phone numbers and URLs.
+//!
+//! Each function returns the canonical form of its input, or `None` if the input is not valid.
+
+use openidconnect::url::Url;
+
+/// Trims the address and lowercases its domain. The local part is case-sensitive and kept as is.
+pub(super) fn normalize_email(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().rsplit_once('@')?;
+    let is_valid_local = (1..=64).contains(&local.len())
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+    let domain = domain.to_lowercase();
+    (is_valid_local && is_valid_domain(&domain)).then(|| format!("{local}@{domain}"))
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+    domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && !tld.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Converts a phone number to the international [E.164](https://en.wikipedia.org/wiki/E.164) format: `+14155552671`.
+/// Numbers without an international prefix (`+` or `00`) are considered national numbers in the country
+/// with the given calling code, and their leading trunk prefix (`0`) is removed.
+pub(super) fn normalize_phone(phone: &str, default_country_code: Option<&str>) -> Option<String> {
+    let phone = phone.trim();
+    let mut digits = String::with_capacity(phone.len());
+    for c in phone.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '(' | ')' | '/' => {}
+            '+' if digits.is_empty() => {}
+            _ => return None,
+        }
+    }
+    let international = if phone.starts_with('+') {
+        digits
+    } else if let Some(number) = digits.strip_prefix("00") {
+        number.to_string()
+    } else {
+        let country_code = default_country_code?.trim().trim_start_matches('+');
+        if country_code.is_empty() || !country_code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        format!("{country_code}{}", digits.trim_start_matches('0'))
+    };
+    let is_valid = (8..=15).contains(&international.len()) && !international.starts_with('0');
+    is_valid.then(|| format!("+{international}"))
+}
+
+/// Parses an http or https URL, and returns it in its canonical form. URLs without a scheme are considered https URLs.
+pub(super) fn normalize_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(openidconnect::url::ParseError::RelativeUrlWithoutBase) => {
+            Url::parse(&format!("https://{url}")).ok()?
+        }
+        Err(_) => return None,
+    };
+    let has_valid_host = match parsed.host() {
+        Some(openidconnect::url::Host::Domain(domain)) => is_valid_domain(domain),
+        Some(_) => true, // IP addresses
+        None => false,
+    };
+    (matches!(parsed.scheme(), "http" | "https") && has_valid_host).then(|| parsed.into())
+}
+
+#[test]
+fn test_normalize_email() {
+    assert_eq!(
+        normalize_email(" John.Doe@Example.COM ").as_deref(),
+        Some("John.Doe@example.com")
+    );
+    assert_eq!(
+        normalize_email("a+tag@sub.example.org").as_deref(),
+        Some("a+tag@sub.example.org")
+    );
+    for invalid in [
+        "",
+        "john",
+        "john@",
+        "@example.com",
+        "john@localhost",
+        "jo..hn@example.com",
+        "john@exa_mple.com",
+        "john doe@example.com",
+    ] {
+        assert_eq!(normalize_email(invalid), None, "{invalid}");
+    }
+}
+
+#[test]
+fn test_normalize_phone() {
+    assert_eq!(
+        normalize_phone("+1 (415) 555-2671", None).as_deref(),
+        Some("+14155552671")
+    );
+    assert_eq!(
+        normalize_phone("0033 6 12 34 56 78", None).as_deref(),
+        Some("+33612345678")
+    );
+    assert_eq!(
+        normalize_phone("06.12.34.56.78", Some("33")).as_deref(),
+        Some("+33612345678")
+    );
+    assert_eq!(normalize_phone("06 12 34 56 78", None), None);
+    assert_eq!(normalize_phone("+1 555", None), None);
+    assert_eq!(normalize_phone("+33 6 12 34 56 78 ext. 2", None), None);
+}
+
+#[test]
+fn test_normalize_url() {
+    assert_eq!(
+        normalize_url("HTTPS://Example.com").as_deref(),
+        Some("https://example.com/")
+    );
+    assert_eq!(
+        normalize_url("sql-page.com/functions.sql?x=1").as_deref(),
+        Some("https://sql-page.com/functions.sql?x=1")
+    );
+    assert_eq!(normalize_url("javascript:alert(1)"), None);
+    assert_eq!(normalize_url("http://"), None);
+    assert_eq!(normalize_url("not a url"), None);
+}
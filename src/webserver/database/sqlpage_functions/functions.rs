@@ -17,25 +17,44 @@ super::function_definition_macro::sqlpage_functions! {
     basic_auth_password((&RequestInfo));
     basic_auth_username((&RequestInfo));
 
+    client_certificate_fingerprint((&RequestInfo));
+    client_certificate_subject((&RequestInfo));
     client_ip((&RequestInfo));
     cookie((&RequestInfo), name: Cow<str>);
+    counter_next((&RequestInfo, &mut DbConn), name: Cow<str>);
     current_working_directory();
 
-    environment_variable(name: Cow<str>);
+    decrypt((&RequestInfo), token: Option<Cow<str>>, max_age_seconds: Option<Cow<str>>);
+
+    encrypt((&RequestInfo), value: Option<Cow<str>>);
+    environment_variable((&RequestInfo), name: Cow<str>);
     exec((&RequestInfo), program_name: Cow<str>, args: Vec<Cow<str>>);
 
     fetch((&RequestInfo), http_request: SqlPageFunctionParam<super::http_fetch_request::HttpFetchRequest<'_>>);
     fetch_with_meta((&RequestInfo), http_request: SqlPageFunctionParam<super::http_fetch_request::HttpFetchRequest<'_>>);
 
+    get_bool((&RequestInfo), name: Cow<str>, default_value: Option<Cow<str>>);
+    get_int((&RequestInfo), name: Cow<str>, default_value: Option<Cow<str>>, min: Option<Cow<str>>, max: Option<Cow<str>>);
+
     hash_password(password: Option<String>);
     header((&RequestInfo), name: Cow<str>);
     headers((&RequestInfo));
+    host((&RequestInfo));
 
+    json_get(json: Option<Cow<str>>, path: Cow<str>);
     user_info_token((&RequestInfo));
+    jwt_claim((&RequestInfo), token: Option<Cow<str>>, claim: Cow<str>);
+    jwt_claims((&RequestInfo), token: Option<Cow<str>>);
     link(file: Cow<str>, parameters: Option<Cow<str>>, hash: Option<Cow<str>>);
+    link_with_current_parameters((&RequestInfo), path: Option<Cow<str>>, overrides: Option<Cow<str>>);
+
+    normalize_email(email: Option<Cow<str>>);
+    normalize_phone(phone: Option<Cow<str>>, default_country_code: Option<Cow<str>>);
+    normalize_url(url: Option<Cow<str>>);
 
     path((&RequestInfo));
-    persist_uploaded_file((&RequestInfo), field_name: Cow<str>, folder: Option<Cow<str>>, allowed_extensions: Option<Cow<str>>);
+    performance_statistics((&RequestInfo), section: Cow<str>);
+    persist_uploaded_file((&RequestInfo), field_name: Cow<str>, folder: Option<Cow<str>>, allowed_extensions: Option<Cow<str>>, max_size: Option<Cow<str>>);
     protocol((&RequestInfo));
 
     random_string(string_length: SqlPageFunctionParam<usize>);
@@ -44,14 +63,29 @@ super::function_definition_macro::sqlpage_functions! {
     request_method((&RequestInfo));
     run_sql((&RequestInfo, &mut DbConn), sql_file_path: Option<Cow<str>>, variables: Option<Cow<str>>);
 
+    shorten((&RequestInfo, &mut DbConn), url: Option<Cow<str>>);
+    similar_rows((&RequestInfo, &mut DbConn), table: Cow<str>, values: Cow<str>, min_similarity: Option<Cow<str>>, key: Option<Cow<str>>);
+    state_decode((&RequestInfo), token: Option<Cow<str>>);
+    state_encode((&RequestInfo), state: Option<Cow<str>>);
+
+    totp_secret();
+    totp_verify(secret: Option<Cow<str>>, code: Option<Cow<str>>);
+
     uploaded_file_mime_type((&RequestInfo), upload_name: Cow<str>);
     uploaded_file_path((&RequestInfo), upload_name: Cow<str>);
     uploaded_file_name((&RequestInfo), upload_name: Cow<str>);
+    uploaded_file_size((&RequestInfo), upload_name: Cow<str>);
+    url((&RequestInfo));
+    url_decode(encoded_text: Option<Cow<str>>);
     url_encode(raw_text: Option<Cow<str>>);
     user_info((&RequestInfo), claim: Cow<str>);
+    uuid();
+
+    validate((&RequestInfo), rules: SqlPageFunctionParam<super::validation::ValidationRules>);
 
     variables((&RequestInfo), get_or_post: Option<Cow<str>>);
     version();
+    with_lock((&RequestInfo, &mut DbConn), name: Cow<str>, timeout_seconds: Option<Cow<str>>);
     request_body((&RequestInfo));
     request_body_base64((&RequestInfo));
 }
@@ -97,7 +131,24 @@ async fn current_working_directory() -> anyhow::Result<String> {
 }
 
 /// Returns the value of an environment variable.
-async fn environment_variable(name: Cow<'_, str>) -> anyhow::Result<Option<Cow<'_, str>>> {
+async fn environment_variable<'a>(
+    request: &'a RequestInfo,
+    name: Cow<'a, str>,
+) -> anyhow::Result<Option<Cow<'a, str>>> {
+    if let Some(allowed) = &request.app_state.config.allowed_environment_variables {
+        let is_allowed = allowed
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => *pattern == name,
+            });
+        if !is_allowed {
+            return Err(anyhow!(ErrorWithStatus {
+                status: actix_web::http::StatusCode::FORBIDDEN,
+            }))
+            .with_context(|| format!("The environment variable {name:?} is not listed in the allowed_environment_variables configuration option"));
+        }
+    }
     match std::env::var(&*name) {
         Ok(value) => Ok(Some(Cow::Owned(value))),
         Err(std::env::VarError::NotPresent) if name.contains(['=', '\0']) => anyhow::bail!("Invalid environment variable name: {name:?}. Environment variable names cannot contain an equals sign or a null character."),
@@ -315,6 +366,83 @@ async fn fetch_with_meta(
     Ok(return_value)
 }
 
+/// The last value of a URL parameter. When a parameter is given several times,
+/// like with a hidden field followed by a checkbox of the same name, the last value is used.
+fn last_get_variable<'a>(request: &'a RequestInfo, name: &str) -> Option<&'a str> {
+    match request.get_variables.get(name)? {
+        SingleOrVec::Single(value) => Some(value),
+        SingleOrVec::Vec(values) => values.last().map(String::as_str),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    const TRUE: [&str; 6] = ["1", "true", "t", "on", "yes", "y"];
+    const FALSE: [&str; 7] = ["0", "false", "f", "off", "no", "n", ""];
+    let value = value.trim();
+    if TRUE.iter().any(|t| value.eq_ignore_ascii_case(t)) {
+        Some(true)
+    } else if FALSE.iter().any(|f| value.eq_ignore_ascii_case(f)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Returns 1 or 0 depending on the value of a URL parameter, like a checkbox in a filter form.
+/// Missing or unrecognized values return the default value.
+async fn get_bool<'a>(
+    request: &'a RequestInfo,
+    name: Cow<'a, str>,
+    default_value: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<&'static str>> {
+    let default_value = default_value
+        .map(|default| {
+            parse_bool(&default).with_context(|| {
+                format!(
+                    "The default value of sqlpage.get_bool must be true or false, not {default:?}"
+                )
+            })
+        })
+        .transpose()?;
+    let value = last_get_variable(request, &name)
+        .and_then(parse_bool)
+        .or(default_value);
+    Ok(value.map(|value| if value { "1" } else { "0" }))
+}
+
+/// Returns the integer value of a URL parameter, like a page number, limited to the range between min and max.
+/// Missing or invalid values return the default value.
+async fn get_int<'a>(
+    request: &'a RequestInfo,
+    name: Cow<'a, str>,
+    default_value: Option<Cow<'a, str>>,
+    min: Option<Cow<'a, str>>,
+    max: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let parse_argument = |argument: Option<Cow<'a, str>>, argument_name: &str| {
+        argument
+            .map(|value| {
+                value.trim().parse::<i64>().with_context(|| {
+                    format!(
+                        "The {argument_name} of sqlpage.get_int must be an integer, not {value:?}"
+                    )
+                })
+            })
+            .transpose()
+    };
+    let default_value = parse_argument(default_value, "default value")?;
+    let min = parse_argument(min, "minimum")?.unwrap_or(i64::MIN);
+    let max = parse_argument(max, "maximum")?.unwrap_or(i64::MAX);
+    anyhow::ensure!(
+        min <= max,
+        "The minimum of sqlpage.get_int ({min}) is greater than its maximum ({max})"
+    );
+    let value = last_get_variable(request, &name)
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .or(default_value);
+    Ok(value.map(|value| value.clamp(min, max).to_string()))
+}
+
 pub(crate) async fn hash_password(password: Option<String>) -> anyhow::Result<Option<String>> {
     let Some(password) = password else {
         return Ok(None);
@@ -339,6 +467,82 @@ async fn header<'a>(request: &'a RequestInfo, name: Cow<'a, str>) -> Option<Cow<
         .map(SingleOrVec::as_json_str)
 }
 
+/// Returns a claim of a JSON Web Token, after verifying its signature.
+/// Returns NULL if the token is NULL, invalid or expired, or if it does not contain the claim.
+async fn jwt_claim<'a>(
+    request: &'a RequestInfo,
+    token: Option<Cow<'a, str>>,
+    claim: Cow<'a, str>,
+) -> anyhow::Result<Option<String>> {
+    let Some(mut claims) = verified_jwt_claims(request, token.as_deref())? else {
+        return Ok(None);
+    };
+    Ok(match claims.remove(claim.as_ref()) {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+/// Returns the value at the given path in a JSON document, like `$.items[0].name`.
+/// Strings are returned without quotes, other values as JSON. Returns NULL if there is no value at this path.
+async fn json_get<'a>(
+    json: Option<Cow<'a, str>>,
+    path: Cow<'a, str>,
+) -> anyhow::Result<Option<String>> {
+    let Some(json) = json else {
+        return Ok(None);
+    };
+    let document: serde_json::Value = serde_json::from_str(&json)
+        .with_context(|| format!("sqlpage.json_get expects a JSON document, got {json:?}"))?;
+    Ok(
+        match super::json_path::get(&document, &path)
+            .with_context(|| format!("Invalid JSON path: {path}"))?
+        {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(s)) => Some(s.clone()),
+            Some(value) => Some(value.to_string()),
+        },
+    )
+}
+
+/// Returns all the claims of a JSON Web Token as a JSON object, after verifying its signature.
+/// Returns NULL if the token is NULL, invalid or expired.
+async fn jwt_claims<'a>(
+    request: &'a RequestInfo,
+    token: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let Some(claims) = verified_jwt_claims(request, token.as_deref())? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::to_string(&claims)?))
+}
+
+/// Tokens can be passed directly from the `Authorization: Bearer <token>` header
+fn verified_jwt_claims(
+    request: &RequestInfo,
+    token: Option<&str>,
+) -> anyhow::Result<Option<serde_json::Map<String, serde_json::Value>>> {
+    let keys = request.app_state.jwt_keys.as_ref().context(
+        "Verifying JSON Web Tokens requires setting jwt_secret or jwt_jwks_url in the configuration",
+    )?;
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let token = token.trim();
+    let token = token
+        .get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("bearer "))
+        .map_or(token, |_| token[7..].trim_start());
+    match keys.verify(token) {
+        Ok(claims) => Ok(Some(claims)),
+        Err(e) => {
+            log::debug!("Rejected JSON Web Token: {e:#}");
+            Ok(None)
+        }
+    }
+}
+
 /// Builds a URL from a file name and a JSON object conatining URL parameters.
 /// For instance, if the file is "index.sql" and the parameters are {"x": "hello world"},
 /// the result will be "index.sql?x=hello%20world".
@@ -362,6 +566,106 @@ async fn link<'a>(
     Ok(url)
 }
 
+/// Builds a URL to `path` (by default, the current page) that keeps the parameters of the current URL,
+/// except the ones in the `overrides` JSON object, which are replaced, or removed when they are null.
+/// For instance, on "products.sql?category=tools&page=3", `link_with_current_parameters(NULL, '{"page": 4}')`
+/// returns "/products.sql?category=tools&page=4".
+async fn link_with_current_parameters<'a>(
+    request: &'a RequestInfo,
+    path: Option<Cow<'a, str>>,
+    overrides: Option<Cow<'a, str>>,
+) -> anyhow::Result<String> {
+    let overrides: serde_json::Map<String, serde_json::Value> = match overrides {
+        Some(overrides) => serde_json::from_str(&overrides).with_context(|| {
+            format!(
+                "link_with_current_parameters: invalid URL parameters: not a valid json object:\n{overrides}"
+            )
+        })?,
+        None => serde_json::Map::new(),
+    };
+    let is_overridden = |key: &str| {
+        let name = key.strip_suffix("[]").unwrap_or(key);
+        overrides
+            .keys()
+            .any(|k| k.strip_suffix("[]").unwrap_or(k) == name)
+    };
+    let current_parameters =
+        actix_web::web::Query::<Vec<(String, String)>>::from_query(&request.query_string)
+            .map(actix_web::web::Query::into_inner)
+            .unwrap_or_default();
+    let mut parameters = URLParameters::default();
+    for (key, value) in &current_parameters {
+        if !is_overridden(key) {
+            parameters.push_kv(key, value);
+        }
+    }
+    for (key, value) in &overrides {
+        parameters.push_json_value(key, value);
+    }
+    let mut url = path.map_or_else(|| request.path.clone(), Cow::into_owned);
+    if !parameters.get().is_empty() {
+        url.push('?');
+        url.push_str(parameters.get());
+    }
+    Ok(url)
+}
+
+/// Returns the email address with its domain in lowercase, or NULL if it is not a valid email address.
+async fn normalize_email(email: Option<Cow<'_, str>>) -> Option<String> {
+    super::formats::normalize_email(&email?)
+}
+
+/// Returns the phone number in the international E.164 format, or NULL if it is not a valid phone number.
+async fn normalize_phone(
+    phone: Option<Cow<'_, str>>,
+    default_country_code: Option<Cow<'_, str>>,
+) -> Option<String> {
+    super::formats::normalize_phone(&phone?, default_country_code.as_deref())
+}
+
+/// Returns the canonical form of an http(s) URL, or NULL if it is not a valid URL.
+async fn normalize_url(url: Option<Cow<'_, str>>) -> Option<String> {
+    super::formats::normalize_url(&url?)
+}
+
+/// Returns performance statistics collected since the server started, as a JSON array of rows.
+async fn performance_statistics(
+    request: &RequestInfo,
+    section: Cow<'_, str>,
+) -> anyhow::Result<String> {
+    let app_state = &request.app_state;
+    let cache_row = |name: &str, (hits, misses): (u64, u64)| {
+        #[allow(clippy::cast_precision_loss)]
+        let hit_rate = if hits + misses == 0 {
+            serde_json::Value::Null
+        } else {
+            (hits as f64 / (hits + misses) as f64).into()
+        };
+        serde_json::json!({ "cache": name, "hits": hits, "misses": misses, "hit_rate": hit_rate })
+    };
+    let rows = match section.as_ref() {
+        "statements" => app_state.statistics.statements(),
+        "pages" => app_state.statistics.pages(),
+        "caches" => vec![
+            cache_row("sql files", app_state.sql_file_cache.hits_and_misses()),
+            cache_row("templates", app_state.all_templates.cache_hits_and_misses()),
+        ],
+        "database" => {
+            let pool = &app_state.db.connection;
+            vec![serde_json::json!({
+                "connections": pool.size(),
+                "idle_connections": pool.num_idle(),
+                "busy_connections": pool.size().saturating_sub(u32::try_from(pool.num_idle()).unwrap_or(u32::MAX)),
+            })]
+        }
+        _ => anyhow::bail!(
+            "Invalid section {section:?} for sqlpage.performance_statistics. \
+            Expected one of 'statements', 'pages', 'caches' or 'database'."
+        ),
+    };
+    Ok(serde_json::to_string(&rows)?)
+}
+
 /// Returns the path component of the URL of the current request.
 async fn path(request: &RequestInfo) -> &str {
     &request.path
@@ -375,8 +679,34 @@ async fn persist_uploaded_file<'a>(
     field_name: Cow<'a, str>,
     folder: Option<Cow<'a, str>>,
     allowed_extensions: Option<Cow<'a, str>>,
+    max_size: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    // Boxed to keep the futures of all the other sqlpage functions small
+    Box::pin(copy_uploaded_file(
+        request,
+        field_name,
+        folder,
+        allowed_extensions,
+        max_size,
+    ))
+    .await
+}
+
+async fn copy_uploaded_file<'a>(
+    request: &'a RequestInfo,
+    field_name: Cow<'a, str>,
+    folder: Option<Cow<'a, str>>,
+    allowed_extensions: Option<Cow<'a, str>>,
+    max_size: Option<Cow<'a, str>>,
 ) -> anyhow::Result<Option<String>> {
     let folder = folder.unwrap_or(Cow::Borrowed("uploads"));
+    let max_size: Option<usize> = max_size
+        .as_deref()
+        .map(|s| s.trim().parse())
+        .transpose()
+        .with_context(|| {
+            format!("persist_uploaded_file: invalid maximum file size {max_size:?}. Expected a number of bytes.")
+        })?;
     let allowed_extensions_str =
         allowed_extensions.unwrap_or(Cow::Borrowed(DEFAULT_ALLOWED_EXTENSIONS));
     let allowed_extensions = allowed_extensions_str.split(',');
@@ -392,6 +722,12 @@ async fn persist_uploaded_file<'a>(
         let exts = allowed_extensions.collect::<Vec<_>>().join(", ");
         anyhow::bail!("file extension {extension} is not allowed. Allowed extensions: {exts}");
     }
+    if let Some(max_size) = max_size.filter(|&max| uploaded_file.size > max) {
+        anyhow::bail!(
+            "the uploaded file {file_name:?} is too large: {} bytes. The maximum size is {max_size} bytes.",
+            uploaded_file.size
+        );
+    }
     // resolve the folder path relative to the web root
     let web_root = &request.app_state.config.web_root;
     let target_folder = web_root.join(&*folder);
@@ -430,6 +766,21 @@ async fn protocol(request: &RequestInfo) -> &str {
     &request.protocol
 }
 
+/// Returns the host name of the current request, with the port if it is not the default one.
+async fn host(request: &RequestInfo) -> &str {
+    &request.host
+}
+
+/// Returns the absolute URL of the current request, including its query string.
+async fn url(request: &RequestInfo) -> String {
+    let mut url = format!("{}://{}{}", request.protocol, request.host, request.path);
+    if !request.query_string.is_empty() {
+        url.push('?');
+        url.push_str(&request.query_string);
+    }
+    url
+}
+
 /// Returns a random string of the specified length.
 pub(crate) async fn random_string(len: usize) -> anyhow::Result<String> {
     // OsRng can block on Linux, so we run this on a blocking thread.
@@ -452,6 +803,127 @@ async fn test_random_string() {
     assert_eq!(s.len(), 10);
 }
 
+/// Returns a random (version 4) UUID, formatted as `xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx`.
+async fn uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    let mut uuid = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        std::fmt::Write::write_fmt(&mut uuid, format_args!("{byte:02x}")).unwrap();
+    }
+    uuid
+}
+
+fn encryption_key(request: &RequestInfo) -> anyhow::Result<&str> {
+    request.app_state.config.encryption_key.as_deref().context(
+        "sqlpage.encrypt, sqlpage.decrypt, sqlpage.state_encode and sqlpage.state_decode require setting encryption_key in the configuration",
+    )
+}
+
+fn unix_time_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Returns an encrypted and signed token containing the value, that only `sqlpage.decrypt` can read.
+async fn encrypt<'a>(
+    request: &'a RequestInfo,
+    value: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let key = encryption_key(request)?;
+    Ok(value.map(|value| super::encryption::encrypt(key, &value, unix_time_now())))
+}
+
+/// Returns the value contained in a token created by `sqlpage.encrypt`.
+/// Returns NULL if the token is NULL, was modified, or is older than `max_age_seconds`.
+async fn decrypt<'a>(
+    request: &'a RequestInfo,
+    token: Option<Cow<'a, str>>,
+    max_age_seconds: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let key = encryption_key(request)?;
+    let max_age_seconds = max_age_seconds
+        .map(|max_age| {
+            max_age.trim().parse::<u64>().with_context(|| {
+                format!("max_age_seconds must be a positive number of seconds, not {max_age:?}")
+            })
+        })
+        .transpose()?;
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let value = super::encryption::decrypt(key, &token, max_age_seconds, unix_time_now());
+    if value.is_none() {
+        log::debug!("Rejected an invalid or expired encrypted token");
+    }
+    Ok(value)
+}
+
+/// Returns a compact signed token containing a JSON state, like the values of the filters of a page,
+/// that can be shared in a URL and read back with `sqlpage.state_decode`.
+async fn state_encode<'a>(
+    request: &'a RequestInfo,
+    state: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let key = encryption_key(request)?;
+    let Some(state) = state else {
+        return Ok(None);
+    };
+    let state: serde_json::Value = serde_json::from_str(&state)
+        .with_context(|| format!("sqlpage.state_encode expects a JSON value, got {state:?}"))?;
+    Ok(Some(super::state_token::encode(key, &state)))
+}
+
+/// Returns the JSON state contained in a token created by `sqlpage.state_encode`.
+/// Returns NULL if the token is NULL or was modified.
+async fn state_decode<'a>(
+    request: &'a RequestInfo,
+    token: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let key = encryption_key(request)?;
+    let Some(token) = token else {
+        return Ok(None);
+    };
+    let state = super::state_token::decode(key, &token);
+    if state.is_none() {
+        log::debug!("Rejected an invalid state token");
+    }
+    Ok(state.map(|state| state.to_string()))
+}
+
+/// Returns a new random secret for time-based one-time passwords, encoded in base32.
+async fn totp_secret() -> String {
+    super::totp::generate_secret()
+}
+
+/// Returns 'true' if the code is the current one-time password for the secret, and 'false' otherwise.
+async fn totp_verify(secret: Option<Cow<'_, str>>, code: Option<Cow<'_, str>>) -> &'static str {
+    let now = unix_time_now();
+    let is_valid = match (secret, code) {
+        (Some(secret), Some(code)) => super::totp::verify(&secret, &code, now),
+        _ => false,
+    };
+    if is_valid {
+        "true"
+    } else {
+        "false"
+    }
+}
+
+#[tokio::test]
+async fn test_uuid() {
+    let id = uuid().await;
+    assert_eq!(id.len(), 36);
+    assert_eq!(&id[14..15], "4");
+    assert!("89ab".contains(&id[19..20]), "{id}");
+    assert_ne!(id, uuid().await);
+}
+
 async fn read_file_bytes(request: &RequestInfo, path_str: &str) -> Result<Vec<u8>, anyhow::Error> {
     let path = std::path::Path::new(path_str);
     // If the path is relative, it's relative to the web root, not the current working directory,
@@ -531,6 +1003,22 @@ async fn run_sql<'a>(
     db_connection: &mut DbConn,
     sql_file_path: Option<Cow<'a, str>>,
     variables: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<Cow<'a, str>>> {
+    // Boxed to keep the futures of all the other sqlpage functions small
+    Box::pin(run_sql_file(
+        request,
+        db_connection,
+        sql_file_path,
+        variables,
+    ))
+    .await
+}
+
+async fn run_sql_file<'a>(
+    request: &'a RequestInfo,
+    db_connection: &mut DbConn,
+    sql_file_path: Option<Cow<'a, str>>,
+    variables: Option<Cow<'a, str>>,
 ) -> anyhow::Result<Option<Cow<'a, str>>> {
     use serde::ser::{SerializeSeq, Serializer};
     let Some(sql_file_path) = sql_file_path else {
@@ -574,12 +1062,13 @@ async fn run_sql<'a>(
     let mut json_encoder = serde_json::Serializer::new(&mut json_results_bytes);
     let mut seq = json_encoder.serialize_seq(None)?;
     while let Some(db_item) = results_stream.next().await {
-        use crate::webserver::database::DbItem::{Error, FinishedQuery, Row};
+        use crate::webserver::database::DbItem::{ColumnTypes, Error, FinishedQuery, Row};
         match db_item {
             Row(row) => {
                 log::debug!("run_sql: row: {row:?}");
                 seq.serialize_element(&row)?;
             }
+            ColumnTypes(_) => {}
             FinishedQuery => log::trace!("run_sql: Finished query"),
             Error(err) => {
                 return Err(err.context(format!("run_sql: unable to run {sql_file_path:?}")))
@@ -590,6 +1079,201 @@ async fn run_sql<'a>(
     Ok(Some(Cow::Owned(String::from_utf8(json_results_bytes)?)))
 }
 
+/// Maximum number of rows returned by `sqlpage.similar_rows`
+const MAX_SIMILAR_ROWS: usize = 10;
+
+/// Returns the path of a short link that redirects to the given URL, and counts its clicks
+async fn shorten<'a>(
+    request: &'a RequestInfo,
+    db_connection: &mut DbConn,
+    url: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    // Boxed to keep the futures of all the other sqlpage functions small
+    Box::pin(create_short_link(request, db_connection, url)).await
+}
+
+/// Increments the counter named `name`, and returns its new value.
+async fn counter_next<'a>(
+    request: &'a RequestInfo,
+    db_connection: &mut DbConn,
+    name: Cow<'a, str>,
+) -> anyhow::Result<String> {
+    // Boxed to keep the futures of all the other sqlpage functions small
+    Box::pin(async move {
+        let connection = crate::webserver::database::execute_queries::take_connection(
+            request.database(),
+            db_connection,
+        )
+        .await?;
+        crate::webserver::database::locks::counter_next(connection, &name).await
+    })
+    .await
+}
+
+/// Acquires the lock named `name` until the end of the page, waiting at most `timeout_seconds` (10 by default)
+/// for other pages to release it. Returns the name of the lock.
+async fn with_lock<'a>(
+    request: &'a RequestInfo,
+    db_connection: &mut DbConn,
+    name: Cow<'a, str>,
+    timeout_seconds: Option<Cow<'a, str>>,
+) -> anyhow::Result<Cow<'a, str>> {
+    let timeout = match timeout_seconds {
+        Some(t) => t
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(|t| std::time::Duration::try_from_secs_f64(t).ok())
+            .with_context(|| {
+                format!("with_lock: invalid timeout {t:?}, expected a number of seconds")
+            })?,
+        None => std::time::Duration::from_secs(10),
+    };
+    let acquired = Box::pin(crate::webserver::database::locks::acquire(
+        request,
+        db_connection,
+        &name,
+        timeout,
+    ))
+    .await?;
+    if !acquired {
+        return Err(anyhow::Error::new(ErrorWithStatus {
+            status: actix_web::http::StatusCode::SERVICE_UNAVAILABLE,
+        })
+        .context(format!(
+            "with_lock: the lock {name:?} is held by another page, and was not released after {} seconds",
+            timeout.as_secs_f64()
+        )));
+    }
+    Ok(name)
+}
+
+async fn create_short_link(
+    request: &RequestInfo,
+    db_connection: &mut DbConn,
+    url: Cow<'_, str>,
+) -> anyhow::Result<Option<String>> {
+    let connection = crate::webserver::database::execute_queries::take_connection(
+        request.database(),
+        db_connection,
+    )
+    .await?;
+    let code = crate::webserver::short_links::shorten(connection, &url).await?;
+    Ok(Some(format!(
+        "{}{}/{code}",
+        request.app_state.config.site_prefix,
+        crate::webserver::short_links::SHORT_LINKS_PATH
+    )))
+}
+
+/// Returns the rows of `table` that look like duplicates of the given values, as a JSON array sorted by similarity.
+/// Returns NULL if there is no similar row.
+async fn similar_rows<'a>(
+    request: &'a RequestInfo,
+    db_connection: &mut DbConn,
+    table: Cow<'a, str>,
+    values: Cow<'a, str>,
+    min_similarity: Option<Cow<'a, str>>,
+    key: Option<Cow<'a, str>>,
+) -> anyhow::Result<Option<String>> {
+    // Boxed to keep the futures of all the other sqlpage functions small
+    Box::pin(find_similar_rows(
+        request,
+        db_connection,
+        table,
+        values,
+        min_similarity,
+        key,
+    ))
+    .await
+}
+
+async fn find_similar_rows(
+    request: &RequestInfo,
+    db_connection: &mut DbConn,
+    table: Cow<'_, str>,
+    values: Cow<'_, str>,
+    min_similarity: Option<Cow<'_, str>>,
+    key: Option<Cow<'_, str>>,
+) -> anyhow::Result<Option<String>> {
+    use super::similarity::{candidates_query, is_safe_identifier, rank_similar_rows};
+    use sqlx::Executor;
+    anyhow::ensure!(
+        is_safe_identifier(&table),
+        "similar_rows: invalid table name {table:?}"
+    );
+    if let Some(key) = key.as_deref().filter(|key| !is_safe_identifier(key)) {
+        anyhow::bail!("similar_rows: invalid key column {key:?}");
+    }
+    let values: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&values)
+        .with_context(|| {
+            format!("similar_rows: expected a json object associating column names to values, got {values:?}")
+        })?;
+    let min_similarity: f64 = match min_similarity.as_deref() {
+        None => 0.5,
+        Some(min) => min
+            .trim()
+            .parse()
+            .ok()
+            .filter(|min| (0. ..=1.).contains(min))
+            .with_context(|| {
+                format!("similar_rows: invalid minimum similarity {min:?}, expected a number between 0 and 1")
+            })?,
+    };
+    let values: Vec<(&str, String)> = values
+        .iter()
+        .filter_map(|(column, value)| match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) if s.trim().is_empty() => None,
+            serde_json::Value::String(s) => Some((column.as_str(), s.clone())),
+            other => Some((column.as_str(), other.to_string())),
+        })
+        .collect();
+    if let Some((column, _)) = values.iter().find(|(c, _)| !is_safe_identifier(c)) {
+        anyhow::bail!("similar_rows: invalid column name {column:?}");
+    }
+    if values.is_empty() {
+        return Ok(None);
+    }
+    let connection = crate::webserver::database::execute_queries::take_connection(
+        request.database(),
+        db_connection,
+    )
+    .await?;
+    let kind = connection.kind();
+    let pg_trgm = kind == sqlx::any::AnyKind::Postgres
+        && connection
+            .fetch_optional("SELECT 1 FROM pg_extension WHERE extname = 'pg_trgm'")
+            .await?
+            .is_some();
+    let (sql, parameters) = candidates_query(
+        kind,
+        &table,
+        key.as_deref(),
+        &values,
+        min_similarity,
+        pg_trgm,
+    );
+    let query = parameters
+        .iter()
+        .fold(sqlx::query(&sql), sqlx::query::Query::bind);
+    let rows = connection
+        .fetch_all(query)
+        .await
+        .with_context(|| format!("similar_rows: unable to read the {table} table"))?;
+    let rows = rows
+        .iter()
+        .map(crate::webserver::database::sql_to_json::row_to_json);
+    let similar = rank_similar_rows(rows, &values, min_similarity, MAX_SIMILAR_ROWS);
+    if similar.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_string(&similar)?))
+}
+
 #[tokio::test]
 async fn test_hash_password() {
     let s = hash_password(Some("password".to_string()))
@@ -631,6 +1315,15 @@ async fn uploaded_file_name<'a>(
     Some(Cow::Borrowed(fname.as_str()))
 }
 
+/// Returns the size of an uploaded file, in bytes
+async fn uploaded_file_size<'a>(
+    request: &'a RequestInfo,
+    upload_name: Cow<'a, str>,
+) -> Option<String> {
+    let uploaded_file = request.uploaded_files.get(&*upload_name)?;
+    Some(uploaded_file.size.to_string())
+}
+
 /// escapes a string for use in a URL using percent encoding
 /// for example, spaces are replaced with %20, '/' with %2F, etc.
 /// This is useful for constructing URLs in SQL queries.
@@ -655,6 +1348,22 @@ async fn url_encode(raw_text: Option<Cow<'_, str>>) -> Option<Cow<'_, str>> {
     })
 }
 
+/// Decodes a percent-encoded string, such as a query string value: `hello%20world` becomes `hello world`.
+/// `+` is decoded as a space, as in HTML form submissions.
+/// Returns NULL when passed NULL, and an error if the decoded text is not valid UTF-8.
+async fn url_decode(encoded_text: Option<Cow<'_, str>>) -> anyhow::Result<Option<String>> {
+    let Some(encoded_text) = encoded_text else {
+        return Ok(None);
+    };
+    let with_spaces = encoded_text.replace('+', " ");
+    let decoded = percent_encoding::percent_decode_str(&with_spaces)
+        .decode_utf8()
+        .with_context(|| {
+            format!("sqlpage.url_decode: {encoded_text:?} does not decode to valid UTF-8 text")
+        })?;
+    Ok(Some(decoded.into_owned()))
+}
+
 /// Returns all variables in the request as a JSON object.
 async fn variables<'a>(
     request: &'a RequestInfo,
@@ -665,18 +1374,26 @@ async fn variables<'a>(
             serde_json::to_string(&request.get_variables)?
         } else if get_or_post.eq_ignore_ascii_case("post") {
             serde_json::to_string(&request.post_variables)?
+        } else if get_or_post.eq_ignore_ascii_case("set") {
+            serde_json::to_string(&request.set_variables)?
         } else {
             return Err(anyhow!(
-                "Expected 'get' or 'post' as the argument to sqlpage.all_variables"
+                "Expected 'get', 'post' or 'set' as the argument to sqlpage.all_variables"
             ));
         }
     } else {
         use serde::{ser::SerializeMap, Serializer};
         let mut res = Vec::new();
         let mut serializer = serde_json::Serializer::new(&mut res);
-        let len = request.get_variables.len() + request.post_variables.len();
+        let len = request.get_variables.len()
+            + request.post_variables.len()
+            + request.set_variables.len();
         let mut ser = serializer.serialize_map(Some(len))?;
-        let iter = request.get_variables.iter().chain(&request.post_variables);
+        let iter = request
+            .get_variables
+            .iter()
+            .chain(&request.post_variables)
+            .chain(&request.set_variables);
         for (k, v) in iter {
             ser.serialize_entry(k, v)?;
         }
@@ -685,6 +1402,24 @@ async fn variables<'a>(
     })
 }
 
+/// Checks the submitted form fields against declarative validation rules.
+/// Returns NULL if all the fields are valid, and a JSON object associating invalid field names to error messages otherwise.
+async fn validate(
+    request: &RequestInfo,
+    rules: super::validation::ValidationRules,
+) -> anyhow::Result<Option<String>> {
+    let errors = rules.validate(|field| {
+        request
+            .post_variables
+            .get(field)
+            .or_else(|| request.get_variables.get(field))
+    });
+    if errors.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_string(&errors)?))
+}
+
 /// Returns the version of the sqlpage that is running.
 async fn version() -> &'static str {
     env!("CARGO_PKG_VERSION")
@@ -717,6 +1452,16 @@ async fn headers(request: &RequestInfo) -> String {
     serde_json::to_string(&request.headers).unwrap_or_default()
 }
 
+/// Returns the SHA-256 fingerprint of the certificate the client authenticated with, if any.
+async fn client_certificate_fingerprint(request: &RequestInfo) -> Option<&str> {
+    Some(&request.client_certificate.as_ref()?.fingerprint)
+}
+
+/// Returns the distinguished name of the subject of the client certificate, if any.
+async fn client_certificate_subject(request: &RequestInfo) -> Option<&str> {
+    Some(&request.client_certificate.as_ref()?.subject)
+}
+
 async fn client_ip(request: &RequestInfo) -> Option<String> {
     Some(request.client_ip?.to_string())
 }
@@ -747,7 +1492,7 @@ async fn user_info<'a>(
         "iat" => Some(claims.issue_time().timestamp().to_string()),
         "sub" => Some(claims.subject().to_string()),
         "auth_time" => claims.auth_time().map(|t| t.timestamp().to_string()),
-        "nonce" => claims.nonce().map(|n| n.secret().to_string()), // Assuming Nonce has secret()
+        "nonce" => claims.nonce().map(|n| n.secret().clone()), // Assuming Nonce has secret()
         "acr" => claims.auth_context_ref().map(|acr| acr.to_string()),
         // amr requires serialization: handled separately if needed
         "azp" => claims.authorized_party().map(|azp| azp.to_string()),
@@ -0,0 +1,131 @@
+//! Extraction of values from JSON documents with simple paths like `$.items[0].name`,
+//! evaluated by `SQLPage` so that it works the same way on all databases.
+//!
+//! Paths start with `$`, the whole document, followed by any number of:
+//!  - `.key`, `."key"` or `["key"]`, the value of a key in an object,
+//!  - `[index]`, an element of an array, counting from the end when the index is negative.
+
+use anyhow::{bail, Context};
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+enum PathElement {
+    Key(String),
+    Index(i64),
+}
+
+fn parse_path(path: &str) -> anyhow::Result<Vec<PathElement>> {
+    let Some(mut rest) = path.trim().strip_prefix('$') else {
+        bail!("JSON paths must start with $, like '$.items[0].name'");
+    };
+    let mut elements = Vec::new();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('.').filter(|r| r.starts_with('"')) {
+            let mut keys = serde_json::Deserializer::from_str(quoted).into_iter::<String>();
+            let key = keys
+                .next()
+                .transpose()
+                .ok()
+                .flatten()
+                .with_context(|| format!("Invalid quoted key in {path:?}"))?;
+            elements.push(PathElement::Key(key));
+            rest = &quoted[keys.byte_offset()..];
+        } else if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let key = &after_dot[..end];
+            if key.is_empty() {
+                bail!("Missing key name after '.' in {path:?}");
+            }
+            elements.push(PathElement::Key(key.to_string()));
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = closing_bracket(after_bracket)
+                .with_context(|| format!("Missing ']' in {path:?}"))?;
+            let inside = after_bracket[..end].trim();
+            elements.push(if inside.starts_with('"') {
+                PathElement::Key(
+                    serde_json::from_str(inside)
+                        .with_context(|| format!("Invalid quoted key {inside} in {path:?}"))?,
+                )
+            } else {
+                PathElement::Index(inside.parse().with_context(|| {
+                    format!("Invalid array index {inside:?} in {path:?}. Quote keys like [\"key\"]")
+                })?)
+            });
+            rest = &after_bracket[end + 1..];
+        } else {
+            bail!("Unexpected {rest:?} in {path:?}. Use .key or [index]");
+        }
+    }
+    Ok(elements)
+}
+
+/// The position of the `]` that closes a bracket, ignoring the brackets in quoted keys
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ']' if !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value at `path` in `json`, or `None` when there is no such value
+pub(super) fn get<'a>(json: &'a Value, path: &str) -> anyhow::Result<Option<&'a Value>> {
+    let elements = parse_path(path)?;
+    Ok(elements
+        .iter()
+        .try_fold(json, |value, element| match (element, value) {
+            (PathElement::Key(key), Value::Object(object)) => object.get(key),
+            (PathElement::Index(index), Value::Array(array)) => {
+                let position = if *index < 0 {
+                    usize::try_from(index.unsigned_abs())
+                        .ok()
+                        .and_then(|from_end| array.len().checked_sub(from_end))
+                } else {
+                    usize::try_from(*index).ok()
+                };
+                position.and_then(|position| array.get(position))
+            }
+            _ => None,
+        }))
+}
+
+#[test]
+fn test_json_path() {
+    let json = serde_json::json!({
+        "user": {"name": "Ada", "tags": ["admin", "dev"], "a.b": {"c]": 1}},
+        "items": [{"id": 1}, {"id": 2}]
+    });
+    let value_at = |path| get(&json, path).unwrap().cloned();
+    assert_eq!(value_at("$.user.name"), Some("Ada".into()));
+    assert_eq!(value_at("$.user.tags[1]"), Some("dev".into()));
+    assert_eq!(value_at("$.user.tags[-2]"), Some("admin".into()));
+    assert_eq!(value_at("$.user.tags[-3]"), None);
+    assert_eq!(value_at(r#"$."user".x"#), None);
+    assert_eq!(
+        value_at(r#"$."user"."a.b""#),
+        Some(serde_json::json!({"c]": 1}))
+    );
+    assert_eq!(value_at(r#"$.user["a.b"]["c]"]"#), Some(1.into()));
+    assert_eq!(value_at("$.items[1].id"), Some(2.into()));
+    assert_eq!(value_at("$.items.id"), None);
+    assert_eq!(value_at("$"), Some(json.clone()));
+    for invalid in [
+        "user.name",
+        "$.",
+        "$[0",
+        "$[x]",
+        "$['user']",
+        "$x",
+        r#"$."user"#,
+    ] {
+        assert!(get(&json, invalid).is_err(), "{invalid}");
+    }
+}
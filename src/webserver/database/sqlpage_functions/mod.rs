@@ -1,8 +1,15 @@
+mod encryption;
+mod formats;
 mod function_definition_macro;
 mod function_traits;
 pub(super) mod functions;
 mod http_fetch_request;
+mod json_path;
+mod similarity;
+mod state_token;
+mod totp;
 mod url_parameter_deserializer;
+mod validation;
 
 use sqlparser::ast::FunctionArg;
 
@@ -0,0 +1,214 @@
+//! Fuzzy text comparison used by `sqlpage.similar_rows` to detect probable duplicates.
+//!
+//! Strings are compared using the same trigram similarity as postgres' `pg_trgm` extension,
+//! computed by `SQLPage` so that it works identically on all databases.
+//! The database only returns a bounded number of candidate rows, selected with `pg_trgm` when it is installed,
+//! and with `LIKE` on the first letters of the words of the values otherwise.
+
+use crate::webserver::database::make_placeholder;
+use sqlx::any::AnyKind;
+use std::collections::HashSet;
+
+/// Maximum number of candidate rows read from the database
+const MAX_CANDIDATES: usize = 1000;
+/// Maximum number of words of each value used to select the candidate rows with `LIKE`
+const MAX_PREFILTER_WORDS: usize = 8;
+
+/// The set of three-letter sequences in the words of a string, ignoring case and punctuation.
+/// Each word is padded with two spaces at the beginning and one space at the end.
+fn trigrams(s: &str) -> HashSet<[char; 3]> {
+    let mut result = HashSet::new();
+    for word in words(s) {
+        let chars: Vec<char> = "  "
+            .chars()
+            .chain(word.chars())
+            .chain(std::iter::once(' '))
+            .collect();
+        for window in chars.windows(3) {
+            result.insert([window[0], window[1], window[2]]);
+        }
+    }
+    result
+}
+
+/// The lowercase words of a string, without punctuation
+fn words(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Similarity between two strings, between 0 (nothing in common) and 1 (same words).
+fn trigram_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let similarity = a.intersection(&b).count() as f64 / union as f64;
+    similarity
+}
+
+/// The rows whose values are the most similar to `values`, at least `min_similarity` on average,
+/// from the most similar to the least similar, with their similarity in a `_similarity` column.
+pub(super) fn rank_similar_rows(
+    rows: impl Iterator<Item = serde_json::Value>,
+    values: &[(&str, String)],
+    min_similarity: f64,
+    max_rows: usize,
+) -> Vec<serde_json::Value> {
+    let mut similar: Vec<(f64, serde_json::Value)> = rows
+        .filter_map(|row| {
+            let columns = row.as_object()?;
+            let mut total = 0.;
+            for (column, value) in values {
+                let existing = columns
+                    .iter()
+                    .find(|(c, _)| c.eq_ignore_ascii_case(column))
+                    .map(|(_, v)| v)?;
+                total += match existing {
+                    serde_json::Value::String(s) => trigram_similarity(value, s),
+                    serde_json::Value::Null => 0.,
+                    other => trigram_similarity(value, &other.to_string()),
+                };
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let similarity = total / values.len() as f64;
+            (similarity >= min_similarity).then_some((similarity, row))
+        })
+        .collect();
+    similar.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    similar
+        .into_iter()
+        .take(max_rows)
+        .map(|(similarity, mut row)| {
+            if let Some(columns) = row.as_object_mut() {
+                columns.insert("_similarity".into(), similarity.into());
+            }
+            row
+        })
+        .collect()
+}
+
+/// The query that selects the candidate rows of `table`, with the columns to compare and the `key` column,
+/// and its parameters. With `pg_trgm`, the similarity is computed by the database.
+pub(super) fn candidates_query(
+    kind: AnyKind,
+    table: &str,
+    key: Option<&str>,
+    values: &[(&str, String)],
+    min_similarity: f64,
+    pg_trgm: bool,
+) -> (String, Vec<String>) {
+    let text = |column: &str| match kind {
+        AnyKind::MySql => format!("CAST({column} AS CHAR)"),
+        AnyKind::Mssql => format!("CAST({column} AS NVARCHAR(MAX))"),
+        AnyKind::Postgres | AnyKind::Sqlite => format!("CAST({column} AS TEXT)"),
+    };
+    let mut parameters = Vec::new();
+    let mut placeholder = |parameter: String| {
+        parameters.push(parameter);
+        make_placeholder(kind, parameters.len())
+    };
+    let condition = if min_similarity <= 0. {
+        None
+    } else if pg_trgm {
+        let similarities: Vec<String> = values
+            .iter()
+            .map(|(column, value)| {
+                let value = placeholder(value.clone());
+                format!("COALESCE(similarity({}, {value}), 0)", text(column))
+            })
+            .collect();
+        Some(format!(
+            "({}) / {} >= {min_similarity}",
+            similarities.join(" + "),
+            values.len()
+        ))
+    } else {
+        // A value can only be similar to a row that shares some of its trigrams
+        let mut likes = Vec::new();
+        for (column, value) in values {
+            let mut prefixes: Vec<String> = words(value)
+                .map(|word| word.chars().take(2).collect())
+                .collect();
+            prefixes.sort_unstable();
+            prefixes.dedup();
+            for prefix in prefixes.into_iter().take(MAX_PREFILTER_WORDS) {
+                let pattern = placeholder(format!("%{prefix}%"));
+                likes.push(format!("LOWER({}) LIKE {pattern}", text(column)));
+            }
+        }
+        Some(if likes.is_empty() {
+            "1 = 0".to_string()
+        } else {
+            likes.join(" OR ")
+        })
+    };
+    let columns: Vec<&str> = key
+        .into_iter()
+        .chain(values.iter().map(|(column, _)| *column))
+        .collect();
+    let columns = columns.join(", ");
+    let condition = condition.map(|c| format!(" WHERE {c}")).unwrap_or_default();
+    let sql = if kind == AnyKind::Mssql {
+        format!("SELECT TOP {MAX_CANDIDATES} {columns} FROM {table}{condition}")
+    } else {
+        format!("SELECT {columns} FROM {table}{condition} LIMIT {MAX_CANDIDATES}")
+    };
+    (sql, parameters)
+}
+
+/// Table and column names cannot be passed as query parameters, so only plain identifiers are accepted.
+pub(super) fn is_safe_identifier(name: &str) -> bool {
+    name.split('.').all(|part| {
+        part.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[test]
+fn test_trigram_similarity() {
+    assert!((trigram_similarity("John Smith", "john smith") - 1.).abs() < f64::EPSILON);
+    assert!(trigram_similarity("Jon Smith", "John Smith") > 0.5);
+    assert!(trigram_similarity("Alice Martin", "John Smith") < 0.1);
+    assert!(trigram_similarity("", "") < f64::EPSILON);
+    // pg_trgm: select similarity('word', 'two words') = 0.363636
+    assert!((trigram_similarity("word", "two words") - 4. / 11.).abs() < 1e-9);
+}
+
+#[test]
+fn test_candidates_query() {
+    let values = [
+        ("name", "Jon Smith".to_string()),
+        ("email", "jo@x.io".into()),
+    ];
+    let (sql, parameters) =
+        candidates_query(AnyKind::Sqlite, "people", Some("id"), &values, 0.5, false);
+    assert_eq!(
+        sql,
+        "SELECT id, name, email FROM people WHERE LOWER(CAST(name AS TEXT)) LIKE ?1 \
+        OR LOWER(CAST(name AS TEXT)) LIKE ?2 OR LOWER(CAST(email AS TEXT)) LIKE ?3 \
+        OR LOWER(CAST(email AS TEXT)) LIKE ?4 OR LOWER(CAST(email AS TEXT)) LIKE ?5 LIMIT 1000"
+    );
+    assert_eq!(parameters, ["%jo%", "%sm%", "%io%", "%jo%", "%x%"]);
+    let (sql, parameters) =
+        candidates_query(AnyKind::Postgres, "people", None, &values[..1], 0.5, true);
+    assert_eq!(
+        sql,
+        "SELECT name FROM people WHERE (COALESCE(similarity(CAST(name AS TEXT), $1), 0)) / 1 >= 0.5 LIMIT 1000"
+    );
+    assert_eq!(parameters, ["Jon Smith"]);
+}
+
+#[test]
+fn test_is_safe_identifier() {
+    assert!(is_safe_identifier("customers"));
+    assert!(is_safe_identifier("crm.customers_2024"));
+    assert!(!is_safe_identifier("customers; drop table x"));
+    assert!(!is_safe_identifier("1table"));
+    assert!(!is_safe_identifier(""));
+}
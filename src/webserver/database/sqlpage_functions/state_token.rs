@@ -0,0 +1,85 @@
+//! Compact signed tokens that contain the state of a page, like the values of its filters,
+//! created by `sqlpage.state_encode` and read by `sqlpage.state_decode`.
+//!
+//! A token is the compressed JSON state followed by a truncated HMAC-SHA256 signature,
+//! computed with `encryption_key` from the configuration, and encoded in unpadded URL-safe base64.
+//! The state is not encrypted: anyone can read it, but nobody can modify it without the key.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::{Read, Write};
+
+/// Prevents signatures created by other features with the same key from being accepted
+const SIGNATURE_CONTEXT: &[u8] = b"sqlpage.state_encode";
+/// 128 bits of signature are enough and keep the links short
+const SIGNATURE_LEN: usize = 16;
+/// Limits the memory used to decompress a token
+const MAX_STATE_LEN: u64 = 1024 * 1024;
+
+fn mac(secret: &str, data: &[u8]) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts any key size")
+        .chain_update(SIGNATURE_CONTEXT)
+        .chain_update(data)
+}
+
+/// Returns a token containing the JSON state
+pub(super) fn encode(secret: &str, state: &serde_json::Value) -> String {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(state.to_string().as_bytes())
+        .and_then(|()| encoder.finish())
+        .map(|mut token| {
+            let signature = mac(secret, &token).finalize().into_bytes();
+            token.extend_from_slice(&signature[..SIGNATURE_LEN]);
+            URL_SAFE_NO_PAD.encode(token)
+        })
+        .expect("compressing in memory cannot fail")
+}
+
+/// The JSON state contained in `token`, or None if the token was not created with this secret, or was modified
+pub(super) fn decode(secret: &str, token: &str) -> Option<serde_json::Value> {
+    let token = URL_SAFE_NO_PAD.decode(token.trim()).ok()?;
+    let (compressed, signature) = token.split_at(token.len().checked_sub(SIGNATURE_LEN)?);
+    mac(secret, compressed)
+        .verify_truncated_left(signature)
+        .ok()?;
+    let mut state = Vec::new();
+    DeflateDecoder::new(compressed)
+        .take(MAX_STATE_LEN)
+        .read_to_end(&mut state)
+        .ok()?;
+    serde_json::from_slice(&state).ok()
+}
+
+#[test]
+fn test_encode_decode() {
+    let secret = "a secret key of at least 32 characters";
+    let state = serde_json::json!({
+        "search": "blue shoes",
+        "selected_products": (1000..1050).collect::<Vec<_>>(),
+        "colors": ["blue", "navy blue", "light blue"],
+        "sort": {"column": "price", "descending": false}
+    });
+    let token = encode(secret, &state);
+    assert!(
+        token.len() < state.to_string().len(),
+        "the token should be compressed: {token}"
+    );
+    assert!(!token.contains(['+', '/', '=']), "{token}");
+    assert_eq!(decode(secret, &token), Some(state));
+}
+
+#[test]
+fn test_decode_invalid_token() {
+    let secret = "a secret key of at least 32 characters";
+    let token = encode(secret, &serde_json::json!({"admin": false}));
+    assert_eq!(decode("another secret", &token), None);
+    let mut bytes = URL_SAFE_NO_PAD.decode(&token).unwrap();
+    bytes[0] ^= 1;
+    assert_eq!(decode(secret, &URL_SAFE_NO_PAD.encode(&bytes)), None);
+    assert_eq!(decode(secret, "not a token"), None);
+    assert_eq!(decode(secret, ""), None);
+}
@@ -0,0 +1,93 @@
+//! Time-based one-time passwords (RFC 6238), as used by authenticator apps for two-factor authentication.
+//!
+//! Secrets are encoded in base32, the format expected by authenticator apps. Codes have 6 digits, change every
+//! 30 seconds, and are computed with HMAC-SHA1, which are the defaults of all common authenticator apps.
+
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+const SECRET_BYTES: usize = 20;
+const TIME_STEP_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+/// Codes from the previous and next time steps are also accepted, to tolerate clock drift
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+
+/// A new random secret, encoded in base32
+pub(super) fn generate_secret() -> String {
+    BASE32_NOPAD.encode(&rand::random::<[u8; SECRET_BYTES]>())
+}
+
+/// Decodes a base32 secret, ignoring case, spaces and padding
+fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    let normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=' && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    BASE32_NOPAD
+        .decode(normalized.as_bytes())
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+fn code_at_step(key: &[u8], step: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let truncated = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    truncated % 10u32.pow(DIGITS)
+}
+
+/// Whether `code` is the one-time password of `secret` at the unix time `now`, give or take one time step
+pub(super) fn verify(secret: &str, code: &str, now: u64) -> bool {
+    let Some(key) = decode_secret(secret) else {
+        return false;
+    };
+    let code = code.trim();
+    let Some(code) = code
+        .parse::<u32>()
+        .ok()
+        .filter(|_| code.len() == DIGITS as usize)
+    else {
+        return false;
+    };
+    let current_step = now / TIME_STEP_SECONDS;
+    (current_step.saturating_sub(ALLOWED_DRIFT_STEPS)..=current_step + ALLOWED_DRIFT_STEPS)
+        .any(|step| code_at_step(&key, step) == code)
+}
+
+#[test]
+fn test_rfc6238_vectors() {
+    // Test vectors from RFC 6238, appendix B, truncated to 6 digits
+    let secret = BASE32_NOPAD.encode(b"12345678901234567890");
+    for (time, code) in [
+        (59, "287082"),
+        (1_111_111_109, "081804"),
+        (1_234_567_890, "005924"),
+        (2_000_000_000, "279037"),
+    ] {
+        assert!(verify(&secret, code, time), "{time}: {code}");
+    }
+    assert!(verify(&secret.to_lowercase(), "287082", 59 + 30));
+    assert!(!verify(&secret, "287082", 59 + 90));
+    assert!(!verify(&secret, "287083", 59));
+    assert!(!verify(&secret, "87082", 59));
+    assert!(!verify("not base32!", "287082", 59));
+}
+
+#[test]
+fn test_generate_secret() {
+    let secret = generate_secret();
+    assert_eq!(secret.len(), 32);
+    assert_eq!(
+        decode_secret(&secret).map(|key| key.len()),
+        Some(SECRET_BYTES)
+    );
+}
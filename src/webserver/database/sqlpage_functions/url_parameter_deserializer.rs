@@ -4,6 +4,7 @@ use serde_json::Value;
 use std::borrow::Cow;
 use std::fmt;
 
+#[derive(Default)]
 pub struct URLParameters(String);
 
 impl URLParameters {
@@ -11,14 +12,38 @@ impl URLParameters {
         let val: Cow<str> = percent_encode(v.as_bytes(), NON_ALPHANUMERIC).into();
         self.0.push_str(&val);
     }
-    fn push_kv(&mut self, key: &str, value: &str) {
+    /// Appends a parameter. The `[]` at the end of the names of array parameters is not encoded.
+    pub fn push_kv(&mut self, key: &str, value: &str) {
         if !self.0.is_empty() {
             self.0.push('&');
         }
-        self.encode_and_push(key);
+        if let Some(array_name) = key.strip_suffix("[]") {
+            self.encode_and_push(array_name);
+            self.0.push_str("[]");
+        } else {
+            self.encode_and_push(key);
+        }
         self.0.push('=');
         self.encode_and_push(value);
     }
+    /// Appends a parameter with a JSON value: arrays become several `key[]=value` parameters,
+    /// and null values are skipped.
+    pub fn push_json_value(&mut self, key: &str, value: &Value) {
+        match value {
+            Value::Null => {}
+            Value::String(s) => self.push_kv(key, s),
+            Value::Array(values) => {
+                let key = format!("{}[]", key.strip_suffix("[]").unwrap_or(key));
+                for value in values {
+                    match value {
+                        Value::String(s) => self.push_kv(&key, s),
+                        other => self.push_kv(&key, &other.to_string()),
+                    }
+                }
+            }
+            other => self.push_kv(key, &other.to_string()),
+        }
+    }
     pub fn get(&self) -> &str {
         &self.0
     }
@@ -122,3 +147,14 @@ fn test_url_parameters_deserializer_issue_879() {
         "name=John%20Doe%20%26%20Son%27s&items[]=1&items[]=item%202%20%26%203&items[]=true&special%5Fchar=%25%26%3D%2B%20"
     );
 }
+
+#[test]
+fn test_url_parameters_push_json_value() {
+    use serde_json::json;
+    let mut url_parameters = URLParameters::default();
+    url_parameters.push_kv("tags[]", "a b");
+    url_parameters.push_json_value("page", &json!(2));
+    url_parameters.push_json_value("removed", &json!(null));
+    url_parameters.push_json_value("ids", &json!([1, "x&y"]));
+    assert_eq!(url_parameters.0, "tags[]=a%20b&page=2&ids[]=1&ids[]=x%26y");
+}
@@ -0,0 +1,221 @@
+//! Declarative validation of form submissions, used by `sqlpage.validate`.
+//!
+//! Rules are given as a JSON object associating field names with constraints:
+//! ```json
+//! { "age": { "required": true, "min": 18 }, "end_date": { "check": false, "message": "Must be after the start date" } }
+//! ```
+//! Cross-field checks are written as SQL expressions, evaluated by the database before the rules are passed to
+//! `SQLPage`: the `check` property contains the result of the expression.
+
+use super::formats::{normalize_email, normalize_phone, normalize_url};
+use super::function_traits::BorrowFromStr;
+use crate::webserver::http::SingleOrVec;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(super) struct FieldRules {
+    #[serde(default, deserialize_with = "deserialize_truthy")]
+    pub required: bool,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub one_of: Option<Vec<String>>,
+    pub format: Option<Format>,
+    /// Result of a SQL expression. NULL means that the expression could not be evaluated, and is not an error.
+    #[serde(default)]
+    pub check: serde_json::Value,
+    /// Error message displayed instead of the default one when any of the rules fails
+    pub message: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Format {
+    Email,
+    Phone,
+    Url,
+}
+
+#[derive(Debug)]
+pub(super) struct ValidationRules(pub Vec<(String, FieldRules)>);
+
+impl<'a> BorrowFromStr<'a> for ValidationRules {
+    fn borrow_from_str(s: Cow<'a, str>) -> anyhow::Result<Self> {
+        let rules: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&s)
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid validation rules: {e}. Expected a json object associating field names to rules, \
+                    e.g. '{{\"age\": {{\"required\": true, \"min\": 18}}}}'"
+                )
+            })?;
+        rules
+            .into_iter()
+            .map(|(field, rules)| {
+                let rules = parse_field_rules(rules)
+                    .map_err(|e| anyhow::anyhow!("Invalid validation rules for {field:?}: {e}"))?;
+                Ok((field, rules))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(ValidationRules)
+    }
+}
+
+/// SQL databases often return JSON booleans as strings or numbers.
+fn parse_field_rules(mut rules: serde_json::Value) -> serde_json::Result<FieldRules> {
+    if let Some(obj) = rules.as_object_mut() {
+        for key in ["min_length", "max_length", "min", "max"] {
+            if let Some(serde_json::Value::String(s)) = obj.get(key) {
+                if let Ok(n) = s.trim().parse::<f64>() {
+                    obj.insert(key.into(), n.into());
+                }
+            }
+        }
+        for key in ["min_length", "max_length"] {
+            if let Some(n) = obj.get(key).and_then(serde_json::Value::as_f64) {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                obj.insert(key.into(), (n.max(0.) as u64).into());
+            }
+        }
+    }
+    serde_json::from_value(rules)
+}
+
+fn deserialize_truthy<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value: serde_json::Value = serde::Deserialize::deserialize(deserializer)?;
+    Ok(is_truthy(&value) == Some(true))
+}
+
+/// Interprets booleans as returned by the different databases: `true`, `1`, `'t'`, `'true'`...
+/// Returns `None` for NULL.
+fn is_truthy(value: &serde_json::Value) -> Option<bool> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Bool(b) => Some(*b),
+        serde_json::Value::Number(n) => Some(n.as_f64() != Some(0.)),
+        serde_json::Value::String(s) => Some(!matches!(
+            s.trim().to_ascii_lowercase().as_str(),
+            "" | "0" | "f" | "false"
+        )),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => Some(true),
+    }
+}
+
+impl ValidationRules {
+    /// Returns the error message of each invalid field. `field_value` returns the submitted value of a field.
+    pub fn validate<'a>(
+        &self,
+        field_value: impl Fn(&str) -> Option<&'a SingleOrVec>,
+    ) -> BTreeMap<&str, String> {
+        let mut errors = BTreeMap::new();
+        for (field, rules) in &self.0 {
+            let values: Vec<&str> = match field_value(field) {
+                Some(SingleOrVec::Single(v)) => vec![v.as_str()],
+                Some(SingleOrVec::Vec(v)) => v.iter().map(String::as_str).collect(),
+                None => vec![],
+            }
+            .into_iter()
+            .filter(|v| !v.trim().is_empty())
+            .collect();
+            let error = if values.is_empty() {
+                rules
+                    .required
+                    .then(|| "This field is required.".to_string())
+            } else {
+                values.iter().find_map(|v| rules.check_value(v))
+            }
+            .or_else(|| {
+                (is_truthy(&rules.check) == Some(false))
+                    .then(|| "This value is invalid.".to_string())
+            });
+            if let Some(error) = error {
+                errors.insert(field.as_str(), rules.message.clone().unwrap_or(error));
+            }
+        }
+        errors
+    }
+}
+
+impl FieldRules {
+    fn check_value(&self, value: &str) -> Option<String> {
+        let length = value.chars().count();
+        if let Some(min_length) = self.min_length.filter(|&m| length < m) {
+            return Some(format!("Must be at least {min_length} characters long."));
+        }
+        if let Some(max_length) = self.max_length.filter(|&m| length > m) {
+            return Some(format!("Must be at most {max_length} characters long."));
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Ok(number) = value.trim().parse::<f64>() else {
+                return Some("Must be a number.".to_string());
+            };
+            if let Some(min) = self.min.filter(|&m| number < m) {
+                return Some(format!("Must be greater than or equal to {min}."));
+            }
+            if let Some(max) = self.max.filter(|&m| number > m) {
+                return Some(format!("Must be less than or equal to {max}."));
+            }
+        }
+        let format_error = match self.format {
+            Some(Format::Email) if normalize_email(value).is_none() => {
+                Some("Must be a valid email address.")
+            }
+            Some(Format::Phone) if normalize_phone(value, None).is_none() => {
+                Some("Must be a phone number in international format, starting with +.")
+            }
+            Some(Format::Url) if normalize_url(value).is_none() => Some("Must be a valid URL."),
+            _ => None,
+        };
+        if let Some(format_error) = format_error {
+            return Some(format_error.to_string());
+        }
+        if let Some(one_of) = &self.one_of {
+            if !one_of.iter().any(|o| o == value) {
+                return Some(format!("Must be one of: {}.", one_of.join(", ")));
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn test_validate() {
+    let rules = ValidationRules::borrow_from_str(Cow::Borrowed(
+        r#"{
+            "name": {"required": 1, "min_length": "3"},
+            "age": {"min": 18, "max": 150},
+            "color": {"one_of": ["red", "blue"]},
+            "end": {"check": 0, "message": "The end must be after the start."},
+            "optional": {"max_length": 2},
+            "email": {"format": "email"}
+        }"#,
+    ))
+    .unwrap();
+    let values: std::collections::HashMap<&str, SingleOrVec> = [
+        ("name", SingleOrVec::Single("Al".into())),
+        ("age", SingleOrVec::Single("abc".into())),
+        (
+            "color",
+            SingleOrVec::Vec(vec!["red".into(), "green".into()]),
+        ),
+        ("email", SingleOrVec::Single("not an email".into())),
+    ]
+    .into_iter()
+    .collect();
+    let errors = rules.validate(|f| values.get(f));
+    assert_eq!(
+        errors,
+        BTreeMap::from([
+            ("age", "Must be a number.".to_string()),
+            ("color", "Must be one of: red, blue.".to_string()),
+            ("email", "Must be a valid email address.".to_string()),
+            ("end", "The end must be after the start.".to_string()),
+            ("name", "Must be at least 3 characters long.".to_string()),
+        ])
+    );
+    let empty = std::collections::HashMap::<&str, SingleOrVec>::new();
+    let errors = rules.validate(|f| empty.get(f));
+    assert_eq!(errors.keys().copied().collect::<Vec<_>>(), ["end", "name"]);
+}
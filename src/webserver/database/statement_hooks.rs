@@ -0,0 +1,135 @@
+//! Extension points around the execution of SQL statements, for applications that use `SQLPage` as a library.
+//!
+//! Hooks are registered with [`crate::AppState::add_statement_hook`] before the server is started.
+//! They are called for every statement sent to the database: the statements that display data,
+//! and the queries of `SET` statements, `-- @if` conditions and `-- @foreach` loops.
+//! They can be used to audit the queries, collect metrics, or reject statements that break a policy.
+//!
+//! ```
+//! use sqlpage::webserver::database::statement_hooks::{StatementHook, StatementInfo, StatementOutcome};
+//!
+//! struct SlowQueryLogger;
+//!
+//! impl StatementHook for SlowQueryLogger {
+//!     fn after_statement(&self, statement: &StatementInfo<'_>, outcome: &StatementOutcome<'_>) {
+//!         if outcome.duration.as_secs() >= 1 {
+//!             log::warn!("Slow query in {}: {}", statement.source_file.display(), statement.sql);
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A statement that is about to be executed, or that was just executed
+#[derive(Debug)]
+pub struct StatementInfo<'a> {
+    /// The SQL file that contains the statement
+    pub source_file: &'a Path,
+    /// The line where the statement starts in the file
+    pub line: usize,
+    /// The SQL sent to the database, with placeholders for the parameters
+    pub sql: &'a str,
+    /// The values of the parameters, in the order of the placeholders
+    pub parameters: &'a [Option<String>],
+}
+
+/// The result of the execution of a statement
+#[derive(Debug)]
+pub struct StatementOutcome<'a> {
+    /// Time spent executing the statement and reading its results
+    pub duration: Duration,
+    /// Number of rows returned by the statement
+    pub rows: usize,
+    /// The error returned by the database, if the statement failed
+    pub error: Option<&'a anyhow::Error>,
+}
+
+pub trait StatementHook: Send + Sync {
+    /// Called before the statement is sent to the database.
+    /// Returning an error prevents the statement from being executed, and displays the error on the page.
+    fn before_statement(&self, statement: &StatementInfo<'_>) -> anyhow::Result<()> {
+        let _ = statement;
+        Ok(())
+    }
+
+    /// Called once all the results of the statement have been read, or when it failed
+    fn after_statement(&self, statement: &StatementInfo<'_>, outcome: &StatementOutcome<'_>) {
+        let _ = (statement, outcome);
+    }
+}
+
+/// Shared by the servers started when the configuration is reloaded
+#[derive(Default, Clone)]
+pub(crate) struct StatementHooks(Vec<Arc<dyn StatementHook>>);
+
+impl StatementHooks {
+    pub(crate) fn add(&mut self, hook: Box<dyn StatementHook>) {
+        self.0.push(Arc::from(hook));
+    }
+
+    /// Calls the `before_statement` hooks, and starts timing the statement
+    pub(super) fn start<'a>(
+        &'a self,
+        source_file: &'a Path,
+        line: usize,
+        sql: &'a str,
+        values: &[Option<Cow<'_, str>>],
+    ) -> anyhow::Result<StatementExecution<'a>> {
+        let execution = StatementExecution {
+            hooks: &self.0,
+            source_file,
+            line,
+            sql,
+            // The values are only copied when they are needed
+            parameters: if self.0.is_empty() {
+                Vec::new()
+            } else {
+                values
+                    .iter()
+                    .map(|v| v.as_ref().map(ToString::to_string))
+                    .collect()
+            },
+            started_at: Instant::now(),
+        };
+        for hook in execution.hooks {
+            hook.before_statement(&execution.info())?;
+        }
+        Ok(execution)
+    }
+}
+
+/// A statement being executed. [`StatementExecution::finish`] calls the `after_statement` hooks.
+pub(super) struct StatementExecution<'a> {
+    hooks: &'a [Arc<dyn StatementHook>],
+    source_file: &'a Path,
+    line: usize,
+    sql: &'a str,
+    parameters: Vec<Option<String>>,
+    started_at: Instant,
+}
+
+impl StatementExecution<'_> {
+    fn info(&self) -> StatementInfo<'_> {
+        StatementInfo {
+            source_file: self.source_file,
+            line: self.line,
+            sql: self.sql,
+            parameters: &self.parameters,
+        }
+    }
+
+    pub(super) fn finish(self, rows: usize, error: Option<&anyhow::Error>) {
+        let outcome = StatementOutcome {
+            duration: self.started_at.elapsed(),
+            rows,
+            error,
+        };
+        for hook in self.hooks {
+            hook.after_statement(&self.info(), &outcome);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! In-memory performance statistics, collected since the server started.
+//!
+//! Every executed statement and every rendered page is timed. The statistics are exposed to SQL through
+//! `sqlpage.performance_statistics`, which powers the built-in performance page in development mode.
+//! Durations include the time spent sending the results to the client, since rows are streamed while the query runs.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Path of the built-in page that displays the statistics, available only in development
+pub const PERFORMANCE_PAGE: &str = "_sqlpage/performance.sql";
+
+/// Maximum number of statements and pages returned by [`PerformanceStatistics::statements`] and [`PerformanceStatistics::pages`]
+const MAX_ROWS: usize = 50;
+
+#[derive(Default, Debug, Clone, Copy)]
+struct Timings {
+    count: u64,
+    errors: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Timings {
+    fn record(&mut self, duration: Duration, is_error: bool) {
+        self.count += 1;
+        self.errors += u64::from(is_error);
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    fn to_json(self, mut row: serde_json::Map<String, Value>) -> Value {
+        let average = self
+            .total
+            .checked_div(u32::try_from(self.count).unwrap_or(u32::MAX))
+            .unwrap_or_default();
+        row.insert("executions".into(), self.count.into());
+        row.insert("errors".into(), self.errors.into());
+        row.insert("total_ms".into(), millis(self.total));
+        row.insert("average_ms".into(), millis(average));
+        row.insert("max_ms".into(), millis(self.max));
+        Value::Object(row)
+    }
+}
+
+fn millis(duration: Duration) -> Value {
+    json!((duration.as_secs_f64() * 1e6).round() / 1e3)
+}
+
+#[derive(Default)]
+pub struct PerformanceStatistics {
+    /// Statements are identified by the file and line where they are defined
+    statements: Mutex<HashMap<(PathBuf, usize), (String, Timings)>>,
+    pages: Mutex<HashMap<PathBuf, Timings>>,
+}
+
+impl PerformanceStatistics {
+    pub(crate) fn record_statement(
+        &self,
+        source_file: &Path,
+        line: usize,
+        query: &str,
+        duration: Duration,
+        is_error: bool,
+    ) {
+        let mut statements = self.statements.lock().expect("statistics lock poisoned");
+        if let Some((_, timings)) = statements.get_mut(&(source_file.to_path_buf(), line)) {
+            timings.record(duration, is_error);
+        } else {
+            let mut timings = Timings::default();
+            timings.record(duration, is_error);
+            statements.insert(
+                (source_file.to_path_buf(), line),
+                (query.to_string(), timings),
+            );
+        }
+    }
+
+    pub(crate) fn record_page(&self, path: &Path, duration: Duration, is_error: bool) {
+        let mut pages = self.pages.lock().expect("statistics lock poisoned");
+        if let Some(timings) = pages.get_mut(path) {
+            timings.record(duration, is_error);
+        } else {
+            let mut timings = Timings::default();
+            timings.record(duration, is_error);
+            pages.insert(path.to_path_buf(), timings);
+        }
+    }
+
+    /// The statements that took the most time in total, slowest first
+    pub(crate) fn statements(&self) -> Vec<Value> {
+        let statements = self.statements.lock().expect("statistics lock poisoned");
+        let mut rows: Vec<_> = statements.iter().collect();
+        rows.sort_by_key(|(_, (_, timings))| std::cmp::Reverse(timings.total));
+        rows.into_iter()
+            .take(MAX_ROWS)
+            .map(|((file, line), (query, timings))| {
+                let mut row = serde_json::Map::new();
+                row.insert("file".into(), file.to_string_lossy().into());
+                row.insert("line".into(), (*line).into());
+                row.insert("query".into(), query.as_str().into());
+                timings.to_json(row)
+            })
+            .collect()
+    }
+
+    /// The most requested pages, most frequent first
+    pub(crate) fn pages(&self) -> Vec<Value> {
+        let pages = self.pages.lock().expect("statistics lock poisoned");
+        let mut rows: Vec<_> = pages.iter().collect();
+        rows.sort_by_key(|(_, timings)| std::cmp::Reverse(timings.count));
+        rows.into_iter()
+            .take(MAX_ROWS)
+            .map(|(path, timings)| {
+                let mut row = serde_json::Map::new();
+                row.insert("page".into(), path.to_string_lossy().into());
+                timings.to_json(row)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_statistics() {
+    let stats = PerformanceStatistics::default();
+    let file = Path::new("index.sql");
+    stats.record_statement(file, 1, "select 1", Duration::from_millis(1), false);
+    stats.record_statement(file, 3, "select 2", Duration::from_millis(5), false);
+    stats.record_statement(file, 3, "select 2", Duration::from_millis(15), true);
+    let statements = stats.statements();
+    assert_eq!(
+        statements[0],
+        json!({"file": "index.sql", "line": 3, "query": "select 2", "executions": 2, "errors": 1,
+               "total_ms": 20.0, "average_ms": 10.0, "max_ms": 15.0})
+    );
+    assert_eq!(statements[1]["line"], 1);
+
+    stats.record_page(file, Duration::from_millis(30), false);
+    stats.record_page(Path::new("other.sql"), Duration::from_millis(2), false);
+    stats.record_page(Path::new("other.sql"), Duration::from_millis(4), false);
+    let pages = stats.pages();
+    assert_eq!(pages[0]["page"], "other.sql");
+    assert_eq!(pages[0]["average_ms"], 3.0);
+    assert_eq!(pages[1]["executions"], 1);
+}
@@ -44,6 +44,20 @@ pub(crate) enum StmtParam {
     FunctionCall(SqlPageFunctionCall),
 }
 
+impl StmtParam {
+    /// Whether evaluating the parameter calls a sqlpage function
+    pub(crate) fn calls_function(&self) -> bool {
+        match self {
+            StmtParam::FunctionCall(_) => true,
+            StmtParam::Concat(items)
+            | StmtParam::Coalesce(items)
+            | StmtParam::JsonObject(items)
+            | StmtParam::JsonArray(items) => items.iter().any(StmtParam::calls_function),
+            _ => false,
+        }
+    }
+}
+
 impl std::fmt::Display for StmtParam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -158,7 +172,9 @@ pub(super) async fn extract_req_param<'a>(
         // sync functions
         StmtParam::Get(x) => request.get_variables.get(x).map(SingleOrVec::as_json_str),
         StmtParam::Post(x) => request.post_variables.get(x).map(SingleOrVec::as_json_str),
-        StmtParam::PostOrGet(x) => if let Some(v) = request.post_variables.get(x) {
+        StmtParam::PostOrGet(x) => if let Some(v) = request.set_variables.get(x) {
+            Some(v)
+        } else if let Some(v) = request.post_variables.get(x) {
             log::warn!("Deprecation warning! ${x} was used to reference a form field value (a POST variable) instead of a URL parameter. This will stop working soon. Please use :{x} instead.");
             Some(v)
         } else {
@@ -166,7 +182,7 @@ pub(super) async fn extract_req_param<'a>(
         }
         .map(SingleOrVec::as_json_str),
         StmtParam::Error(x) => anyhow::bail!("{}", x),
-        StmtParam::Literal(x) => Some(Cow::Owned(x.to_string())),
+        StmtParam::Literal(x) => Some(Cow::Owned(x.clone())),
         StmtParam::Null => None,
         StmtParam::Concat(args) => concat_params(&args[..], request, db_connection).await?,
         StmtParam::JsonObject(args) => json_object_params(&args[..], request, db_connection).await?,
@@ -0,0 +1,198 @@
+//! `sqlpage.as_of(table, timestamp)` reads a table as it was at a given time,
+//! in place of a table name: `SELECT * FROM sqlpage.as_of('products', $as_of) WHERE price > 10`.
+//!
+//! On SQL Server, the call becomes a query on a system-versioned temporal table: `products FOR SYSTEM_TIME AS OF $as_of`,
+//! and the timestamp must not be NULL.
+//! Other databases have no temporal tables, so the call becomes a subquery on the table and on its history table
+//! (`products_history` by default), that have the same columns, including `valid_from` and `valid_to`.
+//! The table contains the current version of the rows, and the history table their old versions.
+//! When the timestamp is NULL, the current rows are returned.
+
+use std::ops::ControlFlow;
+
+use anyhow::{bail, Context};
+use sqlparser::ast::{
+    Expr, FunctionArg, FunctionArgExpr, Ident, ObjectNamePart, Statement, TableAlias, TableFactor,
+    Value, ValueWithSpan, VisitMut, VisitorMut,
+};
+use sqlparser::parser::Parser;
+use sqlx::any::AnyKind;
+
+use super::dynamic_identifiers::is_valid_name;
+use super::sql::dialect_for_db;
+
+const FUNCTION_NAME: &str = "as_of";
+const HISTORY_TABLE_SUFFIX: &str = "_history";
+const VALID_FROM: &str = "valid_from";
+const VALID_TO: &str = "valid_to";
+
+/// Replaces the `sqlpage.as_of(...)` calls in the FROM clauses of a statement with queries on the versions of the table
+pub(super) fn rewrite_as_of_tables(stmt: &mut Statement, db_kind: AnyKind) -> anyhow::Result<()> {
+    match stmt.visit(&mut AsOfRewriter { db_kind }) {
+        ControlFlow::Break(err) => Err(err),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+struct AsOfRewriter {
+    db_kind: AnyKind,
+}
+
+impl AsOfRewriter {
+    fn rewrite(&self, table: &mut TableFactor) -> anyhow::Result<()> {
+        let TableFactor::Table { args, alias, .. } = table else {
+            return Ok(());
+        };
+        let arguments = args.take().map(|a| a.args).unwrap_or_default();
+        let (table_name, timestamp, history_table) = match arguments.as_slice() {
+            [table_name, timestamp] => (table_name, timestamp, None),
+            [table_name, timestamp, history_table] => {
+                (table_name, timestamp, Some(history_table))
+            }
+            _ => bail!("Expected the name of the table, the timestamp, and optionally the name of the history table"),
+        };
+        let table_name = literal_name(table_name)?;
+        let FunctionArg::Unnamed(FunctionArgExpr::Expr(timestamp)) = timestamp else {
+            bail!("Invalid timestamp: {timestamp}");
+        };
+        let query = if self.db_kind == AnyKind::Mssql {
+            if history_table.is_some() {
+                bail!("SQL Server finds the history of temporal tables by itself: remove the name of the history table");
+            }
+            // In a subquery, because SQL Server expects the alias of the table after FOR SYSTEM_TIME
+            format!("SELECT * FROM {table_name} FOR SYSTEM_TIME AS OF {timestamp}")
+        } else {
+            let history_table = match history_table {
+                Some(history_table) => literal_name(history_table)?,
+                None => format!("{table_name}{HISTORY_TABLE_SUFFIX}"),
+            };
+            format!(
+                "SELECT * FROM {table_name} WHERE {timestamp} IS NULL OR {VALID_FROM} <= {timestamp} \
+                UNION ALL \
+                SELECT * FROM {history_table} WHERE {VALID_FROM} <= {timestamp} AND {VALID_TO} > {timestamp}"
+            )
+        };
+        let subquery = Parser::new(&*dialect_for_db(self.db_kind))
+            .try_with_sql(&query)
+            .and_then(|mut parser| parser.parse_query())
+            .with_context(|| format!("Unable to build the query on the history of {table_name}"))?;
+        // Without an alias, the columns of the subquery are referred to with the name of the table
+        let alias = alias.take().unwrap_or_else(|| TableAlias {
+            name: Ident::new(table_name.rsplit('.').next().unwrap_or(&table_name)),
+            columns: Vec::new(),
+        });
+        *table = TableFactor::Derived {
+            lateral: false,
+            subquery,
+            alias: Some(alias),
+        };
+        Ok(())
+    }
+}
+
+impl VisitorMut for AsOfRewriter {
+    type Break = anyhow::Error;
+
+    fn pre_visit_table_factor(&mut self, table: &mut TableFactor) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table {
+            name,
+            args: Some(_),
+            ..
+        } = table
+        {
+            if is_as_of_function(&name.0) {
+                let call = table.to_string();
+                if let Err(err) = self.rewrite(table) {
+                    return ControlFlow::Break(
+                        err.context(format!("Invalid call to sqlpage.{FUNCTION_NAME}: {call}")),
+                    );
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn is_as_of_function(name: &[ObjectNamePart]) -> bool {
+    matches!(name, [ObjectNamePart::Identifier(namespace), ObjectNamePart::Identifier(function)]
+        if namespace.value == "sqlpage" && function.value == FUNCTION_NAME)
+}
+
+/// Table names must be literal strings, so that they are known when the file is parsed
+fn literal_name(arg: &FunctionArg) -> anyhow::Result<String> {
+    let FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Value(ValueWithSpan {
+        value: Value::SingleQuotedString(name),
+        ..
+    }))) = arg
+    else {
+        bail!("The name of the table must be a literal string, such as 'products', not {arg}");
+    };
+    if !is_valid_name(name) {
+        bail!("{name:?} is not a valid table name. Names can only contain letters, digits and underscores, and can be qualified with a dot, like sales.products");
+    }
+    Ok(name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(sql: &str, db_kind: AnyKind) -> anyhow::Result<String> {
+        let mut stmt = Parser::parse_sql(&*dialect_for_db(db_kind), sql)
+            .unwrap()
+            .remove(0);
+        rewrite_as_of_tables(&mut stmt, db_kind)?;
+        Ok(stmt.to_string())
+    }
+
+    #[test]
+    fn test_history_table() {
+        assert_eq!(
+            rewrite(
+                "SELECT products.name FROM sqlpage.as_of('products', $as_of) WHERE price > 10",
+                AnyKind::Sqlite
+            )
+            .unwrap(),
+            "SELECT products.name FROM (\
+                SELECT * FROM products WHERE $as_of IS NULL OR valid_from <= $as_of \
+                UNION ALL \
+                SELECT * FROM products_history WHERE valid_from <= $as_of AND valid_to > $as_of\
+            ) AS products WHERE price > 10"
+        );
+        assert_eq!(
+            rewrite(
+                "SELECT p.name FROM sqlpage.as_of('shop.products', '2024-01-01', 'audit.products_versions') AS p",
+                AnyKind::Postgres
+            )
+            .unwrap(),
+            "SELECT p.name FROM (\
+                SELECT * FROM shop.products WHERE '2024-01-01' IS NULL OR valid_from <= '2024-01-01' \
+                UNION ALL \
+                SELECT * FROM audit.products_versions WHERE valid_from <= '2024-01-01' AND valid_to > '2024-01-01'\
+            ) AS p"
+        );
+    }
+
+    #[test]
+    fn test_system_versioned_table() {
+        assert_eq!(
+            rewrite(
+                "SELECT * FROM sqlpage.as_of('dbo.products', $as_of) AS p",
+                AnyKind::Mssql
+            )
+            .unwrap(),
+            "SELECT * FROM (SELECT * FROM dbo.products FOR SYSTEM_TIME AS OF $as_of) AS p"
+        );
+    }
+
+    #[test]
+    fn test_invalid_calls() {
+        for sql in [
+            "SELECT * FROM sqlpage.as_of($table, $as_of)",
+            "SELECT * FROM sqlpage.as_of('products; drop table products', $as_of)",
+            "SELECT * FROM sqlpage.as_of('products')",
+        ] {
+            assert!(rewrite(sql, AnyKind::Sqlite).is_err(), "{sql}");
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Reading of spreadsheets (xlsx, xls, xlsb and ods files) for `COPY ... (FORMAT xlsx)` statements.
+//!
+//! The cells are converted to text, like the fields of a CSV file:
+//! dates become `YYYY-MM-DD` (followed by `HH:MM:SS` when they have a time),
+//! and numbers that are integers are written without a decimal part.
+
+use std::io::Cursor;
+
+use anyhow::{bail, Context};
+use calamine::{open_workbook_auto_from_rs, Data, ExcelDateTime, Range, Reader};
+
+/// Number of rows at the top of a sheet in which the header row is searched for
+const MAX_HEADER_ROW: usize = 100;
+
+/// Reads the rows of a sheet, with the values of `columns` in order.
+/// When `header` is true, the values are taken from the columns that have the right names in the header row,
+/// which is the first row that contains all the column names.
+/// Otherwise, the columns of the sheet are used in order. Empty rows are skipped.
+pub(super) fn read_rows(
+    spreadsheet: Vec<u8>,
+    sheet: Option<&str>,
+    columns: &[String],
+    header: bool,
+) -> anyhow::Result<Vec<Vec<Option<String>>>> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(spreadsheet))
+        .context("The file is not a valid spreadsheet")?;
+    let range = if let Some(sheet) = sheet {
+        workbook.worksheet_range(sheet).with_context(|| {
+            format!(
+                "Unable to read the sheet {sheet:?}. The spreadsheet contains the sheets {:?}",
+                workbook.sheet_names()
+            )
+        })?
+    } else {
+        workbook
+            .worksheet_range_at(0)
+            .context("The spreadsheet does not contain any sheet")?
+            .context("Unable to read the first sheet of the spreadsheet")?
+    };
+    rows_from_range(&range, columns, header)
+}
+
+fn rows_from_range(
+    range: &Range<Data>,
+    columns: &[String],
+    header: bool,
+) -> anyhow::Result<Vec<Vec<Option<String>>>> {
+    let first_row = range.start().map_or(0, |(row, _)| row as usize);
+    let mut rows = range.rows().enumerate();
+    let column_indices: Vec<usize> = if header {
+        find_header(&mut rows, columns)?
+    } else {
+        (0..columns.len()).collect()
+    };
+    let mut records = Vec::new();
+    for (row_index, row) in rows {
+        let record = column_indices
+            .iter()
+            .zip(columns)
+            .map(|(&i, column)| {
+                row.get(i)
+                    .map_or(Ok(None), cell_to_string)
+                    .with_context(|| {
+                        format!(
+                            "Invalid value for {column} in row {} of the sheet",
+                            first_row + row_index + 1
+                        )
+                    })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if record.iter().any(Option::is_some) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// Consumes the rows up to the header row, and returns the position of each column in it
+fn find_header<'a>(
+    rows: &mut impl Iterator<Item = (usize, &'a [Data])>,
+    columns: &[String],
+) -> anyhow::Result<Vec<usize>> {
+    for (_, row) in rows.take(MAX_HEADER_ROW) {
+        let positions: Option<Vec<usize>> = columns
+            .iter()
+            .map(|column| {
+                row.iter().position(
+                    |cell| matches!(cell, Data::String(name) if name.trim() == column.as_str()),
+                )
+            })
+            .collect();
+        if let Some(positions) = positions {
+            return Ok(positions);
+        }
+    }
+    bail!(
+        "No header row with all the columns {} was found in the first {MAX_HEADER_ROW} rows of the sheet",
+        columns.join(", ")
+    )
+}
+
+fn cell_to_string(cell: &Data) -> anyhow::Result<Option<String>> {
+    Ok(match cell {
+        Data::Empty => None,
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => Some(s.clone()),
+        Data::Int(i) => Some(i.to_string()),
+        Data::Float(f) => Some(f.to_string()),
+        Data::Bool(b) => Some(b.to_string()),
+        Data::DateTime(date) => Some(format_date(date)),
+        Data::Error(e) => bail!("The cell contains the error {e}"),
+    })
+}
+
+fn format_date(date: &ExcelDateTime) -> String {
+    if date.is_duration() {
+        #[allow(clippy::cast_possible_truncation)]
+        let seconds = (date.as_f64() * 86400.0).round() as i64;
+        return format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+    }
+    let (year, month, day, hour, minute, second, _) = date.to_ymd_hms_milli();
+    if date.as_f64() < 1.0 {
+        format!("{hour:02}:{minute:02}:{second:02}")
+    } else if (hour, minute, second) == (0, 0, 0) {
+        format!("{year:04}-{month:02}-{day:02}")
+    } else {
+        format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}")
+    }
+}
+
+#[test]
+fn test_read_rows() {
+    let spreadsheet = include_bytes!("../../../tests/uploads/people.xlsx").to_vec();
+    let columns = ["birth_date".to_string(), "name".to_string()];
+    let rows = read_rows(spreadsheet.clone(), Some("People"), &columns, true).unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            vec![Some("1995-07-11".into()), Some("Ophir".into())],
+            vec![None, Some("Max".into())],
+        ]
+    );
+    let first_sheet = read_rows(spreadsheet.clone(), None, &columns[1..], false).unwrap();
+    assert_eq!(
+        first_sheet,
+        vec![vec![Some("This workbook lists people".into())]]
+    );
+    assert!(read_rows(spreadsheet.clone(), Some("Missing"), &columns, true).is_err());
+    assert!(read_rows(spreadsheet, None, &columns, true).is_err());
+    assert!(read_rows(b"name,age".to_vec(), None, &columns, true).is_err());
+}
+
+#[test]
+fn test_cell_to_string() {
+    use calamine::ExcelDateTimeType::{DateTime, TimeDelta};
+    let date = |value, kind| Data::DateTime(ExcelDateTime::new(value, kind, false));
+    assert_eq!(cell_to_string(&Data::Float(29.0)).unwrap().unwrap(), "29");
+    assert_eq!(cell_to_string(&Data::Float(2.5)).unwrap().unwrap(), "2.5");
+    assert_eq!(
+        cell_to_string(&date(45000.75, DateTime)).unwrap().unwrap(),
+        "2023-03-15 18:00:00"
+    );
+    assert_eq!(
+        cell_to_string(&date(0.5, DateTime)).unwrap().unwrap(),
+        "12:00:00"
+    );
+    assert_eq!(
+        cell_to_string(&date(1.5, TimeDelta)).unwrap().unwrap(),
+        "36:00:00"
+    );
+    assert!(cell_to_string(&Data::Error(calamine::CellErrorType::Div0)).is_err());
+}
@@ -0,0 +1,247 @@
+//! Server-sent events streams created by the `event_stream` component.
+//!
+//! The response is kept open after the page is executed, and the page is executed again
+//! after an interval, or when a notification is sent on a `PostgreSQL` channel with `NOTIFY`.
+//! Each row is sent to the browser as an event.
+//!
+//! Each channel is listened to on a single connection, opened with the same options as the main
+//! database connection, and shared by all the streams that wait for notifications on it.
+
+use crate::app_config::AppConfig;
+use crate::webserver::database::connect_options;
+use crate::AppState;
+use anyhow::{bail, Context};
+use serde_json::Value as JsonValue;
+use sqlx::any::AnyKind;
+use sqlx::pool::PoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgListener, Postgres};
+use sqlx::Pool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Maximum number of `PostgreSQL` channels listened to at the same time, each on its own connection
+const MAX_LISTENED_CHANNELS: u32 = 32;
+
+/// How often a listener checks whether the streams that waited for its channel are still open
+const UNUSED_LISTENER_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Set by the `event_stream` component on the response of the page
+#[derive(Debug, Clone)]
+pub struct EventStreamDirective {
+    /// Execute the page again after this delay
+    pub interval: Option<Duration>,
+    /// Execute the page again when a notification is received on this `PostgreSQL` channel
+    pub channel: Option<String>,
+}
+
+impl EventStreamDirective {
+    pub(crate) fn from_properties(data: &JsonValue) -> anyhow::Result<Self> {
+        let interval = match data.get("interval") {
+            None | Some(JsonValue::Null) => None,
+            Some(JsonValue::Number(n)) => Some(n.as_f64().unwrap_or(-1.)),
+            Some(JsonValue::String(s)) => Some(s.trim().parse().unwrap_or(-1.)),
+            Some(other) => bail!("event_stream: interval must be a number of seconds, not {other}"),
+        };
+        let interval = interval
+            .map(|seconds| {
+                Duration::try_from_secs_f64(seconds)
+                    .ok()
+                    .filter(|interval| !interval.is_zero())
+                    .with_context(|| format!("event_stream: invalid interval: {seconds}"))
+            })
+            .transpose()?;
+        let channel = data
+            .get("channel")
+            .and_then(JsonValue::as_str)
+            .map(ToString::to_string);
+        if interval.is_none() && channel.is_none() {
+            bail!("The event_stream component requires an 'interval' or a 'channel' property");
+        }
+        Ok(Self { interval, channel })
+    }
+}
+
+type Channels = Arc<Mutex<HashMap<String, broadcast::Sender<()>>>>;
+
+/// The `PostgreSQL` channels listened to by the open event streams
+#[derive(Default)]
+pub struct ChannelListeners {
+    pool: OnceLock<Pool<Postgres>>,
+    channels: Channels,
+}
+
+impl ChannelListeners {
+    /// Returns a receiver that gets a message each time a notification is sent on the channel
+    async fn subscribe(
+        &self,
+        config: &AppConfig,
+        channel: &str,
+    ) -> anyhow::Result<broadcast::Receiver<()>> {
+        let (sender, receiver) = {
+            let mut channels = self.channels.lock().expect("channels lock poisoned");
+            if let Some(sender) = channels.get(channel) {
+                return Ok(sender.subscribe());
+            }
+            channels.retain(|_, sender| sender.receiver_count() > 0);
+            if channels.len() >= MAX_LISTENED_CHANNELS as usize {
+                bail!("event_stream: cannot listen to more than {MAX_LISTENED_CHANNELS} channels at the same time");
+            }
+            let (sender, receiver) = broadcast::channel(1);
+            channels.insert(channel.to_string(), sender.clone());
+            (sender, receiver)
+        };
+        match self.listen(config, channel).await {
+            Ok(listener) => {
+                let channels = Arc::clone(&self.channels);
+                let channel = channel.to_string();
+                actix_web::rt::spawn(forward_notifications(listener, sender, channels, channel));
+                Ok(receiver)
+            }
+            Err(e) => {
+                remove_channel(&self.channels, channel, &sender);
+                Err(e)
+            }
+        }
+    }
+
+    async fn listen(&self, config: &AppConfig, channel: &str) -> anyhow::Result<PgListener> {
+        let pool = if let Some(pool) = self.pool.get() {
+            pool
+        } else {
+            let options = PgConnectOptions::try_from(connect_options(config)?)?;
+            // Listeners do not use the timeouts of the main pool: their connections stay open with the streams
+            let pool = PoolOptions::<Postgres>::new()
+                .max_connections(MAX_LISTENED_CHANNELS)
+                .max_lifetime(None)
+                .idle_timeout(None)
+                .connect_lazy_with(options);
+            self.pool.get_or_init(|| pool)
+        };
+        let mut listener = PgListener::connect_with(pool)
+            .await
+            .context("event_stream: unable to listen to notifications")?;
+        listener.ignore_pool_close_event(true);
+        listener
+            .listen(channel)
+            .await
+            .with_context(|| format!("event_stream: unable to listen to {channel:?}"))?;
+        Ok(listener)
+    }
+}
+
+/// Sends the notifications received by the listener to the streams, until they are all closed
+async fn forward_notifications(
+    mut listener: PgListener,
+    sender: broadcast::Sender<()>,
+    channels: Channels,
+    channel: String,
+) {
+    'listen: loop {
+        let notification = listener.recv();
+        tokio::pin!(notification);
+        let received = loop {
+            tokio::select! {
+                received = &mut notification => break received,
+                () = tokio::time::sleep(UNUSED_LISTENER_CHECK_INTERVAL) => {
+                    if sender.receiver_count() == 0 {
+                        break 'listen;
+                    }
+                }
+            }
+        };
+        match received {
+            Ok(notification) => {
+                log::debug!("Received a notification on {}", notification.channel());
+                if sender.send(()).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                log::error!("event_stream: stopped listening to {channel:?}: {e}");
+                break;
+            }
+        }
+    }
+    log::debug!("Stopped listening to {channel:?}");
+    remove_channel(&channels, &channel, &sender);
+}
+
+/// Forgets the sender of a channel, unless it was already replaced by a new listener
+fn remove_channel(channels: &Channels, channel: &str, sender: &broadcast::Sender<()>) {
+    let mut channels = channels.lock().expect("channels lock poisoned");
+    if channels
+        .get(channel)
+        .is_some_and(|current| current.same_channel(sender))
+    {
+        channels.remove(channel);
+    }
+}
+
+/// Waits until the page has to be executed again
+pub(crate) struct Trigger {
+    interval: Option<Duration>,
+    notifications: Option<broadcast::Receiver<()>>,
+}
+
+impl Trigger {
+    pub(crate) async fn new(
+        directive: &EventStreamDirective,
+        app_state: &AppState,
+    ) -> anyhow::Result<Self> {
+        let notifications = match &directive.channel {
+            None => None,
+            Some(channel) => {
+                if app_state.db.connection.any_kind() != AnyKind::Postgres {
+                    bail!("event_stream: the channel property can only be used with PostgreSQL");
+                }
+                let listeners = &app_state.channel_listeners;
+                Some(listeners.subscribe(&app_state.config, channel).await?)
+            }
+        };
+        Ok(Self {
+            interval: directive.interval,
+            notifications,
+        })
+    }
+
+    pub(crate) async fn wait(&mut self) -> anyhow::Result<()> {
+        let (interval, notifications) = (self.interval, &mut self.notifications);
+        let interval = async {
+            match interval {
+                Some(interval) => tokio::time::sleep(interval).await,
+                None => std::future::pending().await,
+            }
+        };
+        let notification = async {
+            match notifications {
+                // Notifications received while the page was executing are merged into one
+                Some(notifications) => match notifications.recv().await {
+                    Ok(()) | Err(broadcast::error::RecvError::Lagged(_)) => Ok(()),
+                    Err(broadcast::error::RecvError::Closed) => Err(anyhow::anyhow!(
+                        "event_stream: stopped listening to notifications"
+                    )),
+                },
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            () = interval => Ok(()),
+            received = notification => received,
+        }
+    }
+}
+
+#[test]
+fn test_event_stream_properties() {
+    let directive =
+        EventStreamDirective::from_properties(&serde_json::json!({"interval": "2.5"})).unwrap();
+    assert_eq!(directive.interval, Some(Duration::from_millis(2500)));
+    assert_eq!(directive.channel, None);
+    let directive =
+        EventStreamDirective::from_properties(&serde_json::json!({"channel": "orders"})).unwrap();
+    assert_eq!(directive.channel.as_deref(), Some("orders"));
+    assert!(EventStreamDirective::from_properties(&serde_json::json!({})).is_err());
+    assert!(EventStreamDirective::from_properties(&serde_json::json!({"interval": 0})).is_err());
+}
@@ -4,14 +4,16 @@
 
 use crate::render::{AnyRenderBodyContext, HeaderContext, PageContext};
 use crate::webserver::content_security_policy::ContentSecurityPolicy;
-use crate::webserver::database::execute_queries::stop_at_first_error;
-use crate::webserver::database::{execute_queries::stream_query_results_with_conn, DbItem};
-use crate::webserver::http_request_info::extract_request_info;
+use crate::webserver::database::execute_queries::{stop_at_first_error, stop_at_timeout, DbConn};
+use crate::webserver::database::{execute_queries::stream_query_results_with_conn, locks, DbItem};
+use crate::webserver::http_request_info::{extract_request_info, RequestInfo};
 use crate::webserver::ErrorWithStatus;
 use crate::{app_config, AppConfig, AppState, ParsedSqlFile, DEFAULT_404_FILE};
 use actix_web::dev::{fn_service, ServiceFactory, ServiceRequest};
 use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
-use actix_web::http::header::{ContentType, Header, HttpDate, IfModifiedSince, LastModified};
+use actix_web::http::header::{
+    ContentType, Header, HttpDate, IfModifiedSince, IfRange, LastModified, Range,
+};
 use actix_web::http::{header, StatusCode};
 use actix_web::web::PayloadConfig;
 use actix_web::{
@@ -19,11 +21,19 @@ use actix_web::{
 };
 use actix_web::{HttpResponseBuilder, ResponseError};
 
+use super::admin_api;
+use super::byte_ranges::{ranged_file_response, ranged_response};
+use super::event_stream::{EventStreamDirective, Trigger};
 use super::http_client::make_http_client;
 use super::https::make_auto_rustls_config;
 use super::oidc::OidcMiddleware;
+use super::page_etags::{not_modified, ConditionalGet, EtagDirective, RowsHasher};
+use super::post_redirect;
+use super::rate_limit::RateLimitMiddleware;
 use super::response_writer::ResponseWriter;
+use super::short_links;
 use super::static_content;
+use super::websocket::{is_websocket_upgrade, serve_websocket};
 use crate::webserver::routing::RoutingAction::{
     CustomNotFound, Execute, NotFound, Redirect, Serve,
 };
@@ -35,7 +45,7 @@ use futures_util::stream::Stream;
 use futures_util::StreamExt;
 use std::borrow::Cow;
 use std::mem;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -44,25 +54,63 @@ use tokio::sync::mpsc;
 #[derive(Clone)]
 pub struct RequestContext {
     pub is_embedded: bool,
+    /// The page was requested with `_sqlpage_embed=json`: return the rows as JSON instead of rendering them.
+    pub is_embedded_data: bool,
+    /// The page was requested with `_sqlpage_embed=iframe`: render it without header and footer,
+    /// to be displayed inside an iframe on another website.
+    pub is_iframe: bool,
+    /// The page was requested with `_sqlpage_data=N`: return the data rows of the component
+    /// whose `@component_index` is N, as JSON, instead of rendering the page.
+    pub component_data_index: Option<usize>,
     pub content_security_policy: ContentSecurityPolicy,
+    /// The path of the page, to which its forms are submitted by default
+    pub path: String,
+    /// The part of the file that the `download` component should send
+    pub range: Option<Range>,
+}
+
+async fn stream_response(
+    stream: impl Stream<Item = DbItem>,
+    renderer: AnyRenderBodyContext,
+    source_path: &Path,
+) {
+    let Some(renderer) = render_stream(stream, renderer, source_path).await else {
+        return;
+    };
+    if let Err(e) = &renderer.close().await.async_flush().await {
+        log::error!("Unable to flush data to client after rendering the page end: {e}");
+        return;
+    }
+    log::debug!("Successfully finished rendering the page");
 }
 
-async fn stream_response(stream: impl Stream<Item = DbItem>, mut renderer: AnyRenderBodyContext) {
+/// Renders all the items of the stream, and returns the renderer,
+/// or None if the rendering had to stop early
+async fn render_stream(
+    stream: impl Stream<Item = DbItem>,
+    mut renderer: AnyRenderBodyContext,
+    source_path: &Path,
+) -> Option<AnyRenderBodyContext> {
     let mut stream = Box::pin(stream);
 
     if let Err(e) = &renderer.flush().await {
         log::error!("Unable to flush initial data to client: {e}");
-        return;
+        return None;
     }
 
     while let Some(item) = stream.next().await {
         log::trace!("Received item from database: {item:?}");
         let render_result = match item {
+            DbItem::ColumnTypes(column_types) => {
+                renderer.set_column_types(column_types);
+                Ok(())
+            }
             DbItem::FinishedQuery => renderer.finish_query().await,
             DbItem::Row(row) => renderer.handle_row(&row).await,
             DbItem::Error(e) => renderer.handle_error(&e).await,
         };
         if let Err(e) = render_result {
+            log::error!("Error while rendering {}: {e:#}", source_path.display());
             if let Err(nested_err) = renderer.handle_error(&e).await {
                 renderer
                     .close()
@@ -74,7 +122,7 @@ async fn stream_response(stream: impl Stream<Item = DbItem>, mut renderer: AnyRe
                     \nRoot error: {e}\n
                     \nNested error: {nested_err}"
                 );
-                return;
+                return None;
             }
         }
         if let Err(e) = &renderer.flush().await {
@@ -83,14 +131,72 @@ async fn stream_response(stream: impl Stream<Item = DbItem>, mut renderer: AnyRe
                 The user has probably closed the connection before we finished rendering the page: {e:#}"
             );
             // If we cannot write to the client anymore, there is nothing we can do, so we just stop rendering
-            return;
+            return None;
         }
     }
+    Some(renderer)
+}
+
+/// Executes the page again each time the trigger of the `event_stream` component fires,
+/// and sends the new rows as server-sent events, until the client disconnects
+async fn stream_events(
+    mut renderer: AnyRenderBodyContext,
+    sql_file: &ParsedSqlFile,
+    req_param: &mut RequestInfo,
+    conn: &mut DbConn,
+    app_state: &AppState,
+    directive: &EventStreamDirective,
+) {
+    let source_path = &sql_file.source_path;
+    let mut trigger = match Trigger::new(directive, app_state).await {
+        Ok(trigger) => Some(trigger),
+        Err(e) => {
+            let _ = renderer.handle_error(&e).await;
+            None
+        }
+    };
+    while let Some(trigger) = &mut trigger {
+        // Do not hold a database connection while waiting
+        locks::release_all(req_param, conn).await;
+        *conn = None;
+        let triggered = tokio::select! {
+            () = renderer.closed() => {
+                log::debug!("The client closed the event stream of {}", source_path.display());
+                return;
+            }
+            triggered = trigger.wait() => triggered,
+        };
+        if let Err(e) = triggered {
+            let _ = renderer.handle_error(&e).await;
+            break;
+        }
+        req_param.set_variables.clear();
+        let mut is_after_component = false;
+        let rows = stream_query_results_with_conn(sql_file, req_param, conn).filter(move |item| {
+            // The rows before the event_stream component, and the component itself, are not events
+            let is_event = is_after_component || matches!(item, DbItem::Error(_));
+            if let DbItem::Row(row) = item {
+                is_after_component |=
+                    row.get("component").and_then(|c| c.as_str()) == Some("event_stream");
+            }
+            std::future::ready(is_event)
+        });
+        let rows = match app_state.config.request_timeout_seconds {
+            Some(timeout) => {
+                stop_at_timeout(rows, std::time::Duration::from_secs_f64(timeout)).boxed_local()
+            }
+            None => rows.boxed_local(),
+        };
+        let Some(next_renderer) =
+            render_stream(stop_at_first_error(rows), renderer, source_path).await
+        else {
+            return;
+        };
+        renderer = next_renderer;
+    }
     if let Err(e) = &renderer.close().await.async_flush().await {
-        log::error!("Unable to flush data to client after rendering the page end: {e}");
-        return;
+        log::error!("Unable to flush the end of the event stream: {e}");
     }
-    log::debug!("Successfully finished rendering the page");
 }
 
 async fn build_response_header_and_stream<S: Stream<Item = DbItem>>(
@@ -106,8 +212,13 @@ async fn build_response_header_and_stream<S: Stream<Item = DbItem>>(
     while let Some(item) = stream.next().await {
         let page_context = match item {
             DbItem::Row(data) => head_context.handle_row(data).await?,
+            DbItem::ColumnTypes(column_types) => {
+                head_context.set_column_types(column_types);
+                continue;
+            }
             DbItem::FinishedQuery => {
                 log::debug!("finished query");
+                head_context.set_column_types(serde_json::Value::Null);
                 continue;
             }
             DbItem::Error(source_err)
@@ -133,7 +244,7 @@ async fn build_response_header_and_stream<S: Stream<Item = DbItem>>(
                 let http_response = http_response.streaming(result_stream);
                 return Ok(ResponseWithWriter::RenderStream {
                     http_response,
-                    renderer,
+                    renderer: Box::new(renderer),
                     database_entries_stream: stream,
                 });
             }
@@ -150,7 +261,7 @@ async fn build_response_header_and_stream<S: Stream<Item = DbItem>>(
 enum ResponseWithWriter<S> {
     RenderStream {
         http_response: HttpResponse,
-        renderer: AnyRenderBodyContext,
+        renderer: Box<AnyRenderBodyContext>,
         database_entries_stream: Pin<Box<S>>,
     },
     FinishedResponse {
@@ -168,49 +279,149 @@ async fn render_sql(
         .clone() // Cheap reference count increase
         .into_inner();
 
+    let conditional_get = ConditionalGet::from_request(srv_req);
+    if let Some(etag) = conditional_get
+        .as_ref()
+        .and_then(|conditional_get| app_state.page_etags.fresh_match(conditional_get))
+    {
+        log::debug!("The browser already has the latest version of the page, not executing it");
+        return Ok(not_modified(etag));
+    }
+
     let mut req_param = extract_request_info(srv_req, Arc::clone(&app_state))
         .await
         .map_err(|e| anyhow_err_to_actix(e, app_state.config.environment))?;
     log::debug!("Received a request with the following parameters: {req_param:?}");
+    let embed_param = match req_param.get_variables.get(EMBED_PARAMETER) {
+        Some(SingleOrVec::Single(s)) => Some(s.as_str()),
+        Some(SingleOrVec::Vec(_)) => Some(""),
+        None => None,
+    };
+    let is_iframe = embed_param == Some("iframe");
+    let is_embedded = embed_param.is_some() && !is_iframe;
+    let is_embedded_data = embed_param == Some("json");
+    let component_data_index = match req_param.get_variables.get(COMPONENT_DATA_PARAMETER) {
+        Some(SingleOrVec::Single(s)) => Some(s.parse::<usize>().map_err(|_| {
+            ErrorBadRequest(format!(
+                "{COMPONENT_DATA_PARAMETER} should be the index of a component, not {s:?}"
+            ))
+        })?),
+        Some(SingleOrVec::Vec(_)) => {
+            return Err(ErrorBadRequest(format!(
+                "{COMPONENT_DATA_PARAMETER} can only be given once"
+            )))
+        }
+        None => None,
+    };
+    let embed_origin = allowed_embed_origin(srv_req, &app_state.config).filter(|_| is_embedded);
+    let frame_ancestors = is_iframe.then(|| frame_ancestors(&app_state.config));
+    // The contents of a download can change between two requests, so ranges that depend on a previous version are ignored
+    let range = Range::parse(srv_req)
+        .ok()
+        .filter(|_| !srv_req.headers().contains_key(header::IF_RANGE));
 
     let (resp_send, resp_recv) = tokio::sync::oneshot::channel::<HttpResponse>();
     actix_web::rt::spawn(async move {
+        let started_at = std::time::Instant::now();
         let request_context = RequestContext {
-            is_embedded: req_param.get_variables.contains_key("_sqlpage_embed"),
+            is_embedded,
+            is_embedded_data,
+            is_iframe,
+            component_data_index,
             content_security_policy: ContentSecurityPolicy::with_random_nonce(),
+            path: req_param.path.clone(),
+            range,
         };
         let mut conn = None;
-        let database_entries_stream =
-            stream_query_results_with_conn(&sql_file, &mut req_param, &mut conn);
-        let database_entries_stream = stop_at_first_error(database_entries_stream);
-        let response_with_writer = build_response_header_and_stream(
-            Arc::clone(&app_state),
-            database_entries_stream,
+        let is_error = execute_and_respond(
+            &sql_file,
+            &mut req_param,
+            &mut conn,
+            &app_state,
             request_context,
+            conditional_get.as_ref(),
+            resp_send,
         )
         .await;
-        match response_with_writer {
-            Ok(ResponseWithWriter::RenderStream {
-                http_response,
-                renderer,
-                database_entries_stream,
-            }) => {
-                resp_send
-                    .send(http_response)
-                    .unwrap_or_else(|e| log::error!("could not send headers {e:?}"));
-                stream_response(database_entries_stream, renderer).await;
-            }
-            Ok(ResponseWithWriter::FinishedResponse { http_response }) => {
-                resp_send
-                    .send(http_response)
-                    .unwrap_or_else(|e| log::error!("could not send headers {e:?}"));
-            }
-            Err(err) => {
-                send_anyhow_error(&err, resp_send, app_state.config.environment);
-            }
-        }
+        locks::release_all(&req_param, &mut conn).await;
+        app_state
+            .statistics
+            .record_page(&sql_file.source_path, started_at.elapsed(), is_error);
     });
-    resp_recv.await.map_err(ErrorInternalServerError)
+    let mut http_response = resp_recv.await.map_err(ErrorInternalServerError)?;
+    if let Some(origin) = embed_origin {
+        allow_embedding_origin(&mut http_response, &origin)?;
+    }
+    if let Some(frame_ancestors) = frame_ancestors.flatten() {
+        allow_framing(&mut http_response, &frame_ancestors)?;
+    }
+    Ok(http_response)
+}
+
+/// URL parameter that makes sqlpage render only the components of a page, without the shell
+const EMBED_PARAMETER: &str = "_sqlpage_embed";
+/// URL parameter that makes sqlpage return the data rows of a single component as JSON
+const COMPONENT_DATA_PARAMETER: &str = "_sqlpage_data";
+
+/// Returns the origin of the request if it is allowed to embed pages from this site.
+fn allowed_embed_origin(srv_req: &ServiceRequest, config: &AppConfig) -> Option<String> {
+    let origin = srv_req.headers().get(header::ORIGIN)?.to_str().ok()?;
+    let allowed = &config.embed_allowed_origins;
+    if allowed.iter().any(|allowed| allowed == origin) {
+        Some(origin.to_string())
+    } else if allowed.iter().any(|allowed| allowed == "*") {
+        // Any website can read the page, but only without the cookies of the visitor
+        Some("*".to_string())
+    } else {
+        None
+    }
+}
+
+/// Lets the embedding website read the response of a cross-origin request
+fn allow_embedding_origin(response: &mut HttpResponse, origin: &str) -> actix_web::Result<()> {
+    let headers = response.headers_mut();
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        header::HeaderValue::from_str(origin).map_err(ErrorBadRequest)?,
+    );
+    if origin != "*" {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            header::HeaderValue::from_static("true"),
+        );
+    }
+    headers.append(header::VARY, header::HeaderValue::from_static("Origin"));
+    Ok(())
+}
+
+/// The `frame-ancestors` CSP directive for pages displayed in an iframe, if framing is restricted
+fn frame_ancestors(config: &AppConfig) -> Option<String> {
+    if config.embed_allowed_origins.is_empty() {
+        return None;
+    }
+    let mut directive = String::from("frame-ancestors 'self'");
+    for origin in &config.embed_allowed_origins {
+        directive.push(' ');
+        directive.push_str(origin);
+    }
+    Some(directive)
+}
+
+/// Restricts the websites that can display the page in an iframe to the allowed embedding origins
+fn allow_framing(response: &mut HttpResponse, frame_ancestors: &str) -> actix_web::Result<()> {
+    let headers = response.headers_mut();
+    let csp = match headers.get(header::CONTENT_SECURITY_POLICY) {
+        Some(existing) => format!(
+            "{}; {frame_ancestors}",
+            existing.to_str().map_err(ErrorInternalServerError)?
+        ),
+        None => frame_ancestors.to_string(),
+    };
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        header::HeaderValue::from_str(&csp).map_err(ErrorInternalServerError)?,
+    );
+    Ok(())
 }
 
 fn anyhow_err_to_actix_resp(e: &anyhow::Error, env: app_config::DevOrProd) -> HttpResponse {
@@ -251,6 +462,244 @@ fn anyhow_err_to_actix_resp(e: &anyhow::Error, env: app_config::DevOrProd) -> Ht
     }
 }
 
+/// Executes the page, and sends its response. Returns whether the page failed before its response was sent.
+/// The future is boxed here to keep it out of the stack frame of the task that runs the page.
+fn execute_and_respond<'a>(
+    sql_file: &'a ParsedSqlFile,
+    req_param: &'a mut RequestInfo,
+    conn: &'a mut DbConn,
+    app_state: &'a Arc<AppState>,
+    request_context: RequestContext,
+    conditional_get: Option<&'a ConditionalGet>,
+    resp_send: tokio::sync::oneshot::Sender<HttpResponse>,
+) -> futures_util::future::LocalBoxFuture<'a, bool> {
+    Box::pin(async move {
+        let post_redirect_target = post_redirect::redirect_target(&app_state.config, req_param);
+        let hasher = app_state.page_etags.hasher();
+        // The rows of the first execution borrow the request and the connection until the end of this block
+        let event_stream = {
+            let database_entries_stream = limit_page_rows(
+                stream_query_results_with_conn(sql_file, req_param, conn),
+                &app_state.config,
+                &hasher,
+            );
+            let response_with_writer = build_response_header_and_stream(
+                Arc::clone(app_state),
+                database_entries_stream,
+                request_context,
+            )
+            .await;
+            stop_hashing_without_etag(&response_with_writer, &hasher);
+            match response_with_writer {
+                Ok(ResponseWithWriter::RenderStream {
+                    http_response,
+                    renderer,
+                    database_entries_stream,
+                }) if post_redirect_target.is_some()
+                    && post_redirect::displays_page(&http_response) =>
+                {
+                    let http_response = Box::pin(render_then_redirect(
+                        http_response,
+                        database_entries_stream,
+                        *renderer,
+                        &sql_file.source_path,
+                        app_state,
+                        post_redirect_target.as_deref().expect("checked above"),
+                    ))
+                    .await;
+                    send_headers(resp_send, http_response);
+                    return false;
+                }
+                Ok(ResponseWithWriter::RenderStream {
+                    http_response,
+                    renderer,
+                    database_entries_stream,
+                }) if http_response.extensions().contains::<EtagDirective>() => {
+                    let http_response = Box::pin(render_with_etag(
+                        http_response,
+                        database_entries_stream,
+                        *renderer,
+                        &sql_file.source_path,
+                        app_state,
+                        conditional_get,
+                        &hasher,
+                    ))
+                    .await;
+                    send_headers(resp_send, http_response);
+                    return false;
+                }
+                Ok(ResponseWithWriter::RenderStream {
+                    http_response,
+                    renderer,
+                    database_entries_stream,
+                }) => {
+                    let directive = http_response
+                        .extensions()
+                        .get::<EventStreamDirective>()
+                        .cloned();
+                    send_headers(resp_send, http_response);
+                    let Some(directive) = directive else {
+                        let rendering = stream_response(
+                            database_entries_stream,
+                            *renderer,
+                            &sql_file.source_path,
+                        );
+                        Box::pin(rendering).await;
+                        return false;
+                    };
+                    let first_run =
+                        render_stream(database_entries_stream, *renderer, &sql_file.source_path);
+                    let Some(renderer) = Box::pin(first_run).await else {
+                        return false;
+                    };
+                    (renderer, directive)
+                }
+                Ok(ResponseWithWriter::FinishedResponse { http_response }) => {
+                    send_headers(resp_send, http_response);
+                    return false;
+                }
+                Err(err) => {
+                    send_anyhow_error(&err, resp_send, app_state.config.environment);
+                    return true;
+                }
+            }
+        };
+        let (renderer, directive) = event_stream;
+        Box::pin(stream_events(
+            renderer, sql_file, req_param, conn, app_state, &directive,
+        ))
+        .await;
+        false
+    })
+}
+
+/// Stops the rows of a page at the first error, and after the request timeout.
+/// All the rows are hashed, including the ones read before the body, in case the page has an `etag` component.
+fn limit_page_rows<'a>(
+    rows: impl Stream<Item = DbItem> + 'a,
+    config: &AppConfig,
+    hasher: &'a RowsHasher,
+) -> impl Stream<Item = DbItem> + 'a {
+    let rows = match config.request_timeout_seconds {
+        Some(timeout) => {
+            stop_at_timeout(rows, std::time::Duration::from_secs_f64(timeout)).boxed_local()
+        }
+        None => rows.boxed_local(),
+    };
+    stop_at_first_error(rows).inspect(|item| hasher.update(item))
+}
+
+/// Only the pages with an `etag` component need the hash of their rows
+fn stop_hashing_without_etag<S>(
+    response: &anyhow::Result<ResponseWithWriter<S>>,
+    hasher: &RowsHasher,
+) {
+    let has_etag = matches!(response, Ok(ResponseWithWriter::RenderStream { http_response, .. })
+        if http_response.extensions().contains::<EtagDirective>());
+    if !has_etag {
+        hasher.stop();
+    }
+}
+
+/// Renders the whole page in memory, to send it with its `ETag`, computed by the hasher of its rows,
+/// or to answer `304 Not Modified` when the browser already has the same version of the page.
+async fn render_with_etag(
+    http_response: HttpResponse,
+    database_entries_stream: impl Stream<Item = DbItem>,
+    renderer: AnyRenderBodyContext,
+    source_path: &Path,
+    app_state: &AppState,
+    conditional_get: Option<&ConditionalGet>,
+    hasher: &RowsHasher,
+) -> HttpResponse {
+    let max_age = http_response
+        .extensions()
+        .get::<EtagDirective>()
+        .map(|directive| directive.max_age)
+        .unwrap_or_default();
+    let (response, body) = http_response.into_parts();
+    let ((), body) = futures_util::join!(
+        stream_response(database_entries_stream, renderer, source_path),
+        actix_web::body::to_bytes(body)
+    );
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            let err = anyhow::anyhow!("Unable to render {}: {e}", source_path.display());
+            return anyhow_err_to_actix_resp(&err, app_state.config.environment);
+        }
+    };
+    let mut response = response.set_body(body).map_into_boxed_body();
+    let etag = hasher
+        .finish()
+        .filter(|_| response.status() == StatusCode::OK);
+    let Some(etag) = etag else {
+        return response;
+    };
+    if let Some(conditional_get) = conditional_get {
+        app_state
+            .page_etags
+            .remember(conditional_get, etag.clone(), max_age);
+        if conditional_get.matches(&etag) {
+            return not_modified(etag);
+        }
+    }
+    let headers = response.headers_mut();
+    if let Ok(etag) = header::HeaderValue::from_str(&etag.to_string()) {
+        headers.insert(header::ETAG, etag);
+    }
+    if !headers.contains_key(header::CACHE_CONTROL) {
+        // Browsers check that the page has not changed before displaying it from their cache
+        headers.insert(
+            header::CACHE_CONTROL,
+            header::HeaderValue::from_static("no-cache"),
+        );
+    }
+    response
+}
+
+/// Executes the whole page of a POST request, and redirects the browser if there was no error.
+/// Pages with errors are sent, to display the errors.
+async fn render_then_redirect(
+    http_response: HttpResponse,
+    database_entries_stream: impl Stream<Item = DbItem>,
+    renderer: AnyRenderBodyContext,
+    source_path: &Path,
+    app_state: &AppState,
+    target: &str,
+) -> HttpResponse {
+    let has_error = std::cell::Cell::new(false);
+    let (response, body) = http_response.into_parts();
+    let rows = database_entries_stream.inspect(|item| {
+        if matches!(item, DbItem::Error(_)) {
+            has_error.set(true);
+        }
+    });
+    let ((), body) = futures_util::join!(
+        stream_response(rows, renderer, source_path),
+        actix_web::body::to_bytes(body)
+    );
+    match body {
+        Ok(_) if !has_error.get() => {
+            post_redirect::redirect_response(&app_state.config, target, &response)
+        }
+        Ok(body) => response.set_body(body).map_into_boxed_body(),
+        Err(e) => {
+            let err = anyhow::anyhow!("Unable to render {}: {e}", source_path.display());
+            anyhow_err_to_actix_resp(&err, app_state.config.environment)
+        }
+    }
+}
+
+fn send_headers(
+    resp_send: tokio::sync::oneshot::Sender<HttpResponse>,
+    http_response: HttpResponse,
+) {
+    resp_send
+        .send(http_response)
+        .unwrap_or_else(|e| log::error!("could not send headers {e:?}"));
+}
+
 fn send_anyhow_error(
     e: &anyhow::Error,
     resp_send: tokio::sync::oneshot::Sender<HttpResponse>,
@@ -313,6 +762,9 @@ async fn process_sql_request(
         .await
         .with_context(|| format!("Unable to get SQL file \"{}\"", sql_path.display()))
         .map_err(|e| anyhow_err_to_actix(e, app_state.config.environment))?;
+    if is_websocket_upgrade(req) {
+        return serve_websocket(req, sql_file).await;
+    }
     render_sql(req, sql_file).await
 }
 
@@ -320,36 +772,58 @@ async fn serve_file(
     path: &str,
     state: &AppState,
     if_modified_since: Option<IfModifiedSince>,
+    range: Option<Range>,
+    if_range: Option<IfRange>,
 ) -> actix_web::Result<HttpResponse> {
     let path = strip_site_prefix(path, state);
-    if let Some(IfModifiedSince(date)) = if_modified_since {
+    let modified_since = |date: HttpDate| async move {
         let since = DateTime::<Utc>::from(SystemTime::from(date));
-        let modified = state
+        state
             .file_system
             .modified_since(state, path.as_ref(), since, false)
             .await
             .with_context(|| format!("Unable to get modification time of file {path:?}"))
-            .map_err(|e| anyhow_err_to_actix(e, state.config.environment))?;
-        if !modified {
+            .map_err(|e| anyhow_err_to_actix(e, state.config.environment))
+    };
+    if let Some(IfModifiedSince(date)) = if_modified_since {
+        if !modified_since(date).await? {
             return Ok(HttpResponse::NotModified().finish());
         }
     }
-    state
+    // The range is only valid if the file did not change since the client downloaded the start of it
+    let range = match if_range {
+        None => range,
+        Some(IfRange::Date(date)) if !modified_since(date).await? => range,
+        Some(_) => None,
+    };
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(
+            mime_guess::from_path(path)
+                .first()
+                .map_or_else(ContentType::octet_stream, ContentType),
+        )
+        .insert_header(LastModified(HttpDate::from(SystemTime::now())));
+    let local_file = state
+        .file_system
+        .open_local_file(state, path.as_ref())
+        .await
+        .with_context(|| format!("Unable to open file {path:?}"))
+        .map_err(|e| anyhow_err_to_actix(e, state.config.environment))?;
+    if let Some((file, length)) = local_file {
+        return ranged_file_response(response, range.as_ref(), file, length)
+            .await
+            .with_context(|| format!("Unable to read file {path:?}"))
+            .map_err(|e| anyhow_err_to_actix(e, state.config.environment));
+    }
+    // Files stored in the database are loaded entirely
+    let contents = state
         .file_system
         .read_file(state, path.as_ref(), false)
         .await
         .with_context(|| format!("Unable to read file {path:?}"))
-        .map_err(|e| anyhow_err_to_actix(e, state.config.environment))
-        .map(|b| {
-            HttpResponse::Ok()
-                .insert_header(
-                    mime_guess::from_path(path)
-                        .first()
-                        .map_or_else(ContentType::octet_stream, ContentType),
-                )
-                .insert_header(LastModified(HttpDate::from(SystemTime::now())))
-                .body(b)
-        })
+        .map_err(|e| anyhow_err_to_actix(e, state.config.environment))?;
+    Ok(ranged_response(response, range.as_ref(), contents))
 }
 
 /// Strips the site prefix from a path
@@ -403,11 +877,15 @@ pub async fn main_handler(
             .finish()),
         Serve(path) => {
             let if_modified_since = IfModifiedSince::parse(&service_request).ok();
+            let range = Range::parse(&service_request).ok();
+            let if_range = IfRange::parse(&service_request).ok();
             let app_state: &web::Data<AppState> = service_request.app_data().expect("app_state");
             serve_file(
                 path.as_os_str().to_str().unwrap(),
                 app_state,
                 if_modified_since,
+                range,
+                if_range,
             )
             .await
         }
@@ -457,11 +935,15 @@ pub fn create_app(
                 .service(static_content::css())
                 .service(static_content::icons())
                 .service(static_content::favicon())
+                .service(static_content::embed_js(&app_state.config))
+                .configure(|cfg| admin_api::configure(cfg, &app_state))
+                .configure(short_links::configure)
                 .default_service(fn_service(main_handler)),
         )
         // when receiving a request outside of the prefix, redirect to the prefix
         .default_service(fn_service(default_prefix_redirect))
         .wrap(OidcMiddleware::new(&app_state))
+        .wrap(RateLimitMiddleware::new(&app_state))
         .wrap(Logger::default())
         .wrap(default_headers())
         .wrap(middleware::Condition::new(
@@ -507,19 +989,64 @@ fn default_headers() -> middleware::DefaultHeaders {
 }
 
 pub async fn run_server(config: &AppConfig, state: AppState) -> anyhow::Result<()> {
-    let listen_on = config.listen_on();
-    let state = web::Data::new(state);
-    let final_state = web::Data::clone(&state);
-    let factory = move || create_app(web::Data::clone(&state));
+    let mut state = web::Data::new(state);
 
     #[cfg(feature = "lambda-web")]
     if lambda_web::is_running_on_lambda() {
+        let state = web::Data::clone(&state);
+        let factory = move || create_app(web::Data::clone(&state));
         lambda_web::run_actix_on_lambda(factory)
             .await
             .map_err(|e| anyhow::anyhow!("Unable to start the lambda: {e}"))?;
         return Ok(());
     }
-    let mut server = HttpServer::new(factory);
+    let listeners = bind_listeners(config)?;
+    log_welcome_message(config);
+    let mut server = start_server(&listeners, &state)?;
+    loop {
+        let mut reload_requests = state
+            .config_reloads
+            .take_requests()
+            .context("The server state is already used by another server")?;
+        let (new_state, new_server) = loop {
+            tokio::select! {
+                result = &mut server => {
+                    result.with_context(|| "Unable to start the application")?;
+                    // We are done, we can close the database connection
+                    state.db.close().await?;
+                    return Ok(());
+                }
+                Some(reply) = reload_requests.recv() => {
+                    match reload_server(&listeners, &state).await {
+                        Ok(reloaded) => {
+                            let _ = reply.send(Ok(()));
+                            break reloaded;
+                        }
+                        Err(e) => {
+                            log::error!("Unable to reload the configuration: {e:#}");
+                            let _ = reply.send(Err(e));
+                        }
+                    }
+                }
+            }
+        };
+        let old_state = mem::replace(&mut state, new_state);
+        let old_server = mem::replace(&mut server, new_server);
+        stop_server(old_server, old_state);
+    }
+}
+
+/// A socket opened at startup, that is shared by the servers started when the configuration is reloaded
+enum Listener {
+    Http(std::net::TcpListener),
+    Https(std::net::TcpListener, Box<rustls::ServerConfig>),
+    #[cfg(target_family = "unix")]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+fn bind_listeners(config: &AppConfig) -> anyhow::Result<Vec<Listener>> {
+    let listen_on = config.listen_on();
+    let mut listeners = Vec::new();
     if let Some(unix_socket) = &config.unix_socket {
         log::info!(
             "Will start HTTP server on UNIX socket: \"{}\"",
@@ -527,9 +1054,9 @@ pub async fn run_server(config: &AppConfig, state: AppState) -> anyhow::Result<(
         );
         #[cfg(target_family = "unix")]
         {
-            server = server
-                .bind_uds(unix_socket)
-                .map_err(|e| bind_unix_socket_err(e, unix_socket))?;
+            let listener =
+                bind_unix_socket(unix_socket).map_err(|e| bind_unix_socket_err(e, unix_socket))?;
+            listeners.push(Listener::Unix(listener));
         }
         #[cfg(not(target_family = "unix"))]
         anyhow::bail!("Unix sockets are not supported on your operating system. Use listen_on instead of unix_socket.");
@@ -538,30 +1065,79 @@ pub async fn run_server(config: &AppConfig, state: AppState) -> anyhow::Result<(
             let mut listen_on_https = listen_on;
             listen_on_https.set_port(443);
             log::debug!("Will start HTTPS server on {listen_on_https}");
-            let config = make_auto_rustls_config(domain, config);
-            server = server
-                .bind_rustls_0_23(listen_on_https, config)
+            let rustls_config = make_auto_rustls_config(domain, config)?;
+            let listener = std::net::TcpListener::bind(listen_on_https)
                 .map_err(|e| bind_error(e, listen_on_https))?;
+            listeners.push(Listener::Https(listener, Box::new(rustls_config)));
         } else if listen_on.port() == 443 {
             bail!("Please specify a value for https_domain in the configuration file. This is required when using HTTPS (port 443)");
         }
         if listen_on.port() != 443 {
             log::debug!("Will start HTTP server on {listen_on}");
-            server = server
-                .bind(listen_on)
-                .map_err(|e| bind_error(e, listen_on))?;
+            let listener =
+                std::net::TcpListener::bind(listen_on).map_err(|e| bind_error(e, listen_on))?;
+            listeners.push(Listener::Http(listener));
         }
     }
+    Ok(listeners)
+}
 
-    log_welcome_message(config);
-    server
-        .run()
-        .await
-        .with_context(|| "Unable to start the application")?;
+/// Binds the socket, removing the file left by a previous server, like [`HttpServer::bind_uds`]
+#[cfg(target_family = "unix")]
+fn bind_unix_socket(path: &Path) -> std::io::Result<std::os::unix::net::UnixListener> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    std::os::unix::net::UnixListener::bind(path)
+}
 
-    // We are done, we can close the database connection
-    final_state.db.close().await?;
-    Ok(())
+/// Starts serving the application on copies of the sockets, and evaluating its alerts
+fn start_server(
+    listeners: &[Listener],
+    state: &web::Data<AppState>,
+) -> anyhow::Result<actix_web::dev::Server> {
+    let app_state = web::Data::clone(state);
+    let factory = move || create_app(web::Data::clone(&app_state));
+    let mut server = HttpServer::new(factory).on_connect(super::https::on_connect);
+    for listener in listeners {
+        server = match listener {
+            Listener::Http(listener) => server.listen(listener.try_clone()?)?,
+            Listener::Https(listener, rustls_config) => server.listen_rustls_0_23(
+                listener.try_clone()?,
+                rustls::ServerConfig::clone(rustls_config),
+            )?,
+            #[cfg(target_family = "unix")]
+            Listener::Unix(listener) => server.listen_uds(listener.try_clone()?)?,
+        };
+    }
+    super::alerts::start(state);
+    Ok(server.run())
+}
+
+/// Starts a server with a new configuration, alongside the current one
+async fn reload_server(
+    listeners: &[Listener],
+    state: &AppState,
+) -> anyhow::Result<(web::Data<AppState>, actix_web::dev::Server)> {
+    let new_state = web::Data::new(super::config_reload::load_new_state(state).await?);
+    let new_server = start_server(listeners, &new_state)?;
+    Ok((new_state, new_server))
+}
+
+/// Lets the old server finish the requests it is handling, then closes its database connections
+fn stop_server(server: actix_web::dev::Server, state: web::Data<AppState>) {
+    state.alert_triggers.stop();
+    let stopped = server.handle().stop(true);
+    actix_web::rt::spawn(async move {
+        if let Err(e) = server.await {
+            log::error!("Error while stopping the previous server: {e}");
+        }
+        stopped.await;
+        if let Err(e) = state.db.close().await {
+            log::error!("Unable to close the previous database connections: {e:#}");
+        }
+    });
 }
 
 fn log_welcome_message(config: &AppConfig) {
@@ -5,7 +5,24 @@ use std::sync::OnceLock;
 
 static NATIVE_CERTS: OnceLock<anyhow::Result<rustls::RootCertStore>> = OnceLock::new();
 
+/// The number of redirects followed by default, like awc does
+const DEFAULT_MAX_REDIRECTS: u8 = 10;
+
 pub fn make_http_client(config: &crate::app_config::AppConfig) -> anyhow::Result<awc::Client> {
+    build_http_client(config, DEFAULT_MAX_REDIRECTS)
+}
+
+/// A client that does not follow redirects, for requests whose URL has been checked against an allowlist
+pub fn make_http_client_without_redirects(
+    config: &crate::app_config::AppConfig,
+) -> anyhow::Result<awc::Client> {
+    build_http_client(config, 0)
+}
+
+fn build_http_client(
+    config: &crate::app_config::AppConfig,
+    max_redirects: u8,
+) -> anyhow::Result<awc::Client> {
     let connector = if config.system_root_ca_certificates {
         let roots = NATIVE_CERTS
             .get_or_init(|| {
@@ -42,6 +59,7 @@ pub fn make_http_client(config: &crate::app_config::AppConfig) -> anyhow::Result
     };
     let client = awc::Client::builder()
         .connector(connector)
+        .max_redirects(max_redirects)
         .add_default_header((awc::http::header::USER_AGENT, env!("CARGO_PKG_NAME")))
         .finish();
     log::debug!("Created HTTP client");
@@ -22,6 +22,9 @@ use std::rc::Rc;
 use std::sync::Arc;
 use tokio_stream::StreamExt;
 
+use super::database::locks::HeldLocks;
+use super::database::{previews, Database};
+use super::https::ClientCertificate;
 use super::oidc::OidcClaims;
 use super::request_variables::param_map;
 use super::request_variables::ParamMap;
@@ -31,17 +34,28 @@ pub struct RequestInfo {
     pub method: actix_web::http::Method,
     pub path: String,
     pub protocol: String,
+    /// The host name from the `Host` header, with the port if it is not the default one
+    pub host: String,
+    /// The raw query string of the URL, without the leading `?`
+    pub query_string: String,
     pub get_variables: ParamMap,
     pub post_variables: ParamMap,
+    /// Variables defined in SQL with `SET $name = ...`. They are not URL parameters nor form fields.
+    pub set_variables: ParamMap,
     pub uploaded_files: Rc<HashMap<String, TempFile>>,
     pub headers: ParamMap,
     pub client_ip: Option<IpAddr>,
+    pub client_certificate: Option<ClientCertificate>,
     pub cookies: ParamMap,
     pub basic_auth: Option<Basic>,
     pub app_state: Arc<AppState>,
     pub clone_depth: u8,
     pub raw_body: Option<Vec<u8>>,
     pub oidc_claims: Option<OidcClaims>,
+    /// The database of the preview selected by the request, if any
+    pub preview_database: Option<Arc<Database>>,
+    /// The locks acquired with `sqlpage.with_lock`, released at the end of the request
+    pub held_locks: Rc<HeldLocks>,
 }
 
 impl RequestInfo {
@@ -51,19 +65,33 @@ impl RequestInfo {
             method: self.method.clone(),
             path: self.path.clone(),
             protocol: self.protocol.clone(),
+            host: self.host.clone(),
+            query_string: self.query_string.clone(),
             get_variables: ParamMap::new(),
             post_variables: ParamMap::new(),
+            set_variables: ParamMap::new(),
             uploaded_files: self.uploaded_files.clone(),
             headers: self.headers.clone(),
             client_ip: self.client_ip,
+            client_certificate: self.client_certificate.clone(),
             cookies: self.cookies.clone(),
             basic_auth: self.basic_auth.clone(),
             app_state: self.app_state.clone(),
             clone_depth: self.clone_depth + 1,
             raw_body: self.raw_body.clone(),
             oidc_claims: self.oidc_claims.clone(),
+            preview_database: self.preview_database.clone(),
+            held_locks: Rc::clone(&self.held_locks),
         }
     }
+
+    /// The database that the queries of the request use
+    #[must_use]
+    pub fn database(&self) -> &Database {
+        self.preview_database
+            .as_deref()
+            .unwrap_or(&self.app_state.db)
+    }
 }
 
 impl Clone for RequestInfo {
@@ -71,6 +99,7 @@ impl Clone for RequestInfo {
         let mut clone = self.clone_without_variables();
         clone.get_variables.clone_from(&self.get_variables);
         clone.post_variables.clone_from(&self.post_variables);
+        clone.set_variables.clone_from(&self.set_variables);
         clone
     }
 }
@@ -82,9 +111,19 @@ pub(crate) async fn extract_request_info(
     let (http_req, payload) = req.parts_mut();
     let method = http_req.method().clone();
     let protocol = http_req.connection_info().scheme().to_string();
+    let host = http_req.connection_info().host().to_string();
     let config = &app_state.config;
-    let (post_variables, uploaded_files, raw_body) =
+    let (mut post_variables, uploaded_files, raw_body) =
         extract_post_data(http_req, payload, config).await?;
+    let client_ip = super::client_ip::resolve_client_ip(
+        req.peer_addr().map(|addr| addr.ip()),
+        req.headers(),
+        &config.trusted_proxies,
+    );
+    app_state
+        .spam_protection
+        .check_form_submission(&mut post_variables, req.path(), config, client_ip)
+        .await?;
     let headers = req.headers().iter().map(|(name, value)| {
         (
             name.to_string(),
@@ -94,7 +133,6 @@ pub(crate) async fn extract_request_info(
     let get_variables = web::Query::<Vec<(String, String)>>::from_query(req.query_string())
         .map(web::Query::into_inner)
         .unwrap_or_default();
-    let client_ip = req.peer_addr().map(|addr| addr.ip());
 
     let raw_cookies = req.cookies();
     let cookies = raw_cookies
@@ -107,6 +145,16 @@ pub(crate) async fn extract_request_info(
         .map(Authorization::into_scheme);
 
     let oidc_claims: Option<OidcClaims> = req.extensions().get::<OidcClaims>().cloned();
+    let client_certificate = req.conn_data::<ClientCertificate>().cloned();
+    let preview_database = match previews::requested_preview(config, req.headers(), &host) {
+        // Boxed because opening a database is a large future, that most requests do not need
+        Some(name) => {
+            // Anonymous clients must not be able to create databases on a production server
+            let create = !config.environment.is_prod();
+            Some(Box::pin(app_state.previews.get(config, &name?, create)).await?)
+        }
+        None => None,
+    };
 
     Ok(RequestInfo {
         method,
@@ -114,15 +162,21 @@ pub(crate) async fn extract_request_info(
         headers: param_map(headers),
         get_variables: param_map(get_variables),
         post_variables: param_map(post_variables),
+        set_variables: ParamMap::new(),
         uploaded_files: Rc::new(HashMap::from_iter(uploaded_files)),
         client_ip,
+        client_certificate,
         cookies: param_map(cookies),
         basic_auth,
         app_state,
         protocol,
+        host,
+        query_string: req.query_string().to_string(),
         clone_depth: 0,
         raw_body,
         oidc_claims,
+        preview_database,
+        held_locks: Rc::default(),
     })
 }
 
@@ -149,9 +203,17 @@ async fn extract_post_data(
     } else {
         let body = actix_web::web::Bytes::from_request(http_req, payload)
             .await
-            .map(|bytes| bytes.to_vec())
-            .unwrap_or_default();
-        Ok((Vec::new(), Vec::new(), Some(body)))
+            .map_err(|e| {
+                let status = e.as_response_error().status_code();
+                let hint = if status == actix_web::http::StatusCode::PAYLOAD_TOO_LARGE {
+                    " You can increase this limit by setting max_uploaded_file_size in the configuration file."
+                } else {
+                    ""
+                };
+                anyhow!(super::ErrorWithStatus { status })
+                    .context(format!("could not read the request body: {e}.{hint}"))
+            })?;
+        Ok((Vec::new(), Vec::new(), Some(body.to_vec())))
     }
 }
 
@@ -265,8 +327,7 @@ async fn is_file_field_empty(
             && uploaded_file
                 .file_name
                 .as_ref()
-                .filter(|x| !x.is_empty())
-                .is_none()
+                .is_none_or(String::is_empty)
             && tokio::fs::metadata(&uploaded_file.file.path()).await?.len() == 0,
     )
 }
@@ -299,6 +360,7 @@ mod test {
         let mut service_request = TestRequest::get()
             .uri("/?my_array[]=5")
             .insert_header(ContentType::form_url_encoded())
+            .insert_header(("host", "example.com:8080"))
             .set_payload("my_array[]=3&my_array[]=Hello%20World&repeated=1&repeated=2")
             .to_srv_request();
         let app_data = Arc::new(AppState::init(&config).await.unwrap());
@@ -327,6 +389,8 @@ mod test {
             .into_iter()
             .collect::<ParamMap>()
         );
+        assert_eq!(request_info.host, "example.com:8080");
+        assert_eq!(request_info.query_string, "my_array[]=5");
     }
 
     #[actix_web::test]
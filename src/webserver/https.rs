@@ -1,9 +1,26 @@
-use rustls_acme::{caches::DirCache, futures_rustls::rustls::ServerConfig, AcmeConfig};
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use anyhow::Context;
+use rustls_acme::{
+    caches::DirCache,
+    futures_rustls::rustls::{
+        pki_types::{pem::PemObject, CertificateDer},
+        server::WebPkiClientVerifier,
+        RootCertStore, ServerConfig,
+    },
+    AcmeConfig,
+};
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
 use tokio_stream::StreamExt;
 
 use crate::app_config::AppConfig;
 
-pub fn make_auto_rustls_config(domain: &str, config: &AppConfig) -> ServerConfig {
+pub fn make_auto_rustls_config(domain: &str, config: &AppConfig) -> anyhow::Result<ServerConfig> {
     log::info!("Starting HTTPS configuration for {domain}");
     let mut state = AcmeConfig::new([domain])
         .contact([if let Some(email) = &config.https_certificate_email {
@@ -16,7 +33,11 @@ pub fn make_auto_rustls_config(domain: &str, config: &AppConfig) -> ServerConfig
         )))
         .directory(&config.https_acme_directory_url)
         .state();
-    let rustls_config = state.challenge_rustls_config();
+    let challenge_config = state.challenge_rustls_config();
+    let rustls_config = match &config.https_client_ca_certificate {
+        Some(ca_path) => with_client_authentication(&challenge_config, ca_path)?,
+        None => ServerConfig::clone(&challenge_config),
+    };
 
     tokio::spawn(async move {
         while let Some(event) = state.next().await {
@@ -28,5 +49,132 @@ pub fn make_auto_rustls_config(domain: &str, config: &AppConfig) -> ServerConfig
         log::error!("ACME configuration stream ended. This should never happen.");
     });
 
-    ServerConfig::clone(&rustls_config)
+    Ok(rustls_config)
+}
+
+/// Builds a configuration identical to `challenge_config`, that also verifies client certificates
+fn with_client_authentication(
+    challenge_config: &ServerConfig,
+    ca_path: &Path,
+) -> anyhow::Result<ServerConfig> {
+    let ca_pem = std::fs::read(ca_path).with_context(|| {
+        format!(
+            "Unable to read the client certificate authorities from {}",
+            ca_path.display()
+        )
+    })?;
+    let roots = client_ca_roots(&ca_pem)
+        .with_context(|| format!("Invalid https_client_ca_certificate {}", ca_path.display()))?;
+    let provider = Arc::clone(challenge_config.crypto_provider());
+    let verifier =
+        WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::clone(&provider))
+            // The ACME challenge is answered without a client certificate.
+            // Pages restrict access by checking sqlpage.client_certificate_fingerprint().
+            .allow_unauthenticated()
+            .build()?;
+    let mut rustls_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_client_cert_verifier(verifier)
+        .with_cert_resolver(Arc::clone(&challenge_config.cert_resolver));
+    rustls_config
+        .alpn_protocols
+        .clone_from(&challenge_config.alpn_protocols);
+    log::info!(
+        "HTTPS clients can authenticate with a certificate signed by {}",
+        ca_path.display()
+    );
+    Ok(rustls_config)
+}
+
+fn client_ca_roots(ca_pem: &[u8]) -> anyhow::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for certificate in CertificateDer::pem_slice_iter(ca_pem) {
+        roots.add(certificate?)?;
+    }
+    if roots.is_empty() {
+        anyhow::bail!("The file does not contain any PEM certificate");
+    }
+    Ok(roots)
+}
+
+/// A client certificate, verified during the TLS handshake
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Distinguished name of the subject of the certificate, like `CN=build-server, O=Example Corp`
+    pub subject: String,
+    /// SHA-256 hash of the certificate, in lowercase hexadecimal
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    fn from_der(der: &[u8]) -> anyhow::Result<Self> {
+        let (_, certificate) = x509_parser::parse_x509_certificate(der)
+            .map_err(|e| anyhow::anyhow!("Invalid client certificate: {e}"))?;
+        let mut fingerprint = String::with_capacity(64);
+        for byte in Sha256::digest(der) {
+            write!(fingerprint, "{byte:02x}")?;
+        }
+        Ok(Self {
+            subject: certificate.subject().to_string(),
+            fingerprint,
+        })
+    }
+}
+
+/// Stores the certificate presented by the client in the data of new HTTPS connections
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    let Some(tls_stream) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    let Some(certificate) = tls_stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(<[_]>::first)
+    else {
+        return;
+    };
+    match ClientCertificate::from_der(certificate) {
+        Ok(certificate) => {
+            log::debug!("Client authenticated with the certificate {certificate:?}");
+            data.insert(certificate);
+        }
+        Err(e) => log::error!("{e:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBsTCCAVegAwIBAgIUNUidDGVoQx8yxh/TmjRr9sn11nswCgYIKoZIzj0EAwIw
+LjEVMBMGA1UEAwwMYnVpbGQtc2VydmVyMRUwEwYDVQQKDAxFeGFtcGxlIENvcnAw
+HhcNMjYxMDE0MTQ1ODI5WhcNMzYxMDExMTQ1ODI5WjAuMRUwEwYDVQQDDAxidWls
+ZC1zZXJ2ZXIxFTATBgNVBAoMDEV4YW1wbGUgQ29ycDBZMBMGByqGSM49AgEGCCqG
+SM49AwEHA0IABHacR6EyGJkGVFRQx5DFUHMzmSQDNoWK0CFlmf756lwfHTaIhlMi
+qBW5r0J0IBWDpbex+gk/mFx2Aj5Y5oaDJvCjUzBRMB0GA1UdDgQWBBR/oBVX5MMV
+NTrRqKeOk5uL3ovLTTAfBgNVHSMEGDAWgBR/oBVX5MMVNTrRqKeOk5uL3ovLTTAP
+BgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIQDuT/8Y1HjOTgGPRTNR
+nD/BnjUWcaDs/yjlHt4EetSxjAIgO2t8nzVTCI4JQQgNBwX83ztva7n4KrWLhqmk
+2gn5gpM=
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_client_certificate() {
+        let der = CertificateDer::from_pem_slice(CERTIFICATE.as_bytes()).unwrap();
+        let certificate = ClientCertificate::from_der(&der).unwrap();
+        assert_eq!(certificate.subject, "CN=build-server, O=Example Corp");
+        assert_eq!(
+            certificate.fingerprint,
+            "dfbffe137c51d40e99e73615b51e99d3a18f24fbf16b9c5aab27877867294c10"
+        );
+    }
+
+    #[test]
+    fn test_client_ca_roots() {
+        assert_eq!(client_ca_roots(CERTIFICATE.as_bytes()).unwrap().len(), 1);
+        assert!(client_ca_roots(b"not a certificate").is_err());
+    }
 }
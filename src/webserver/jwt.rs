@@ -0,0 +1,153 @@
+//! Verification of JSON Web Tokens issued by external identity providers, used by `sqlpage.jwt_claims`.
+//!
+//! Tokens signed with HMAC are verified with the shared `jwt_secret`, and tokens signed with public key algorithms
+//! with the keys published at `jwt_jwks_url`, downloaded when the server starts.
+//! Only the signature and the validity period (`exp` and `nbf` claims) are checked: the issuer and the audience
+//! of the token are claims like the others, that pages can check in SQL.
+
+use crate::app_config::AppConfig;
+use crate::webserver::http_client::make_http_client;
+use crate::webserver::oidc::AwcHttpClient;
+use anyhow::{ensure, Context};
+use base64::Engine;
+use openidconnect::core::{CoreJsonWebKey, CoreJsonWebKeySet, CoreJwsSigningAlgorithm};
+use openidconnect::{JsonWebKey, JsonWebKeyId, JsonWebKeySetUrl};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The keys that are trusted to sign tokens
+pub struct JwtKeys {
+    keys: Vec<CoreJsonWebKey>,
+}
+
+/// Loads the keys configured with `jwt_secret` and `jwt_jwks_url`, if any
+pub async fn load_jwt_keys(config: &AppConfig) -> anyhow::Result<Option<JwtKeys>> {
+    let mut keys = Vec::new();
+    if let Some(secret) = &config.jwt_secret {
+        keys.push(CoreJsonWebKey::new_symmetric(secret.as_bytes().to_vec()));
+    }
+    if let Some(url) = &config.jwt_jwks_url {
+        let url = JsonWebKeySetUrl::new(url.clone())
+            .with_context(|| format!("Invalid jwt_jwks_url: {url:?}"))?;
+        let http_client = make_http_client(config)?;
+        let key_set =
+            CoreJsonWebKeySet::fetch_async(&url, &AwcHttpClient::from_client(&http_client))
+                .await
+                .with_context(|| {
+                    format!(
+                        "Unable to download the JSON Web Key Set from {}",
+                        url.as_str()
+                    )
+                })?;
+        log::info!(
+            "Loaded {} keys to verify JSON Web Tokens from {}",
+            key_set.keys().len(),
+            url.as_str()
+        );
+        keys.extend(key_set.keys().iter().cloned());
+    }
+    Ok((!keys.is_empty()).then_some(JwtKeys { keys }))
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: CoreJwsSigningAlgorithm,
+    kid: Option<JsonWebKeyId>,
+}
+
+fn decode_part(part: &str) -> anyhow::Result<Vec<u8>> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(part)
+        .context("the token is not valid base64url")
+}
+
+impl JwtKeys {
+    /// Returns the claims of a token, after checking its signature and its validity period
+    pub fn verify(&self, token: &str) -> anyhow::Result<Map<String, Value>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0., |d| d.as_secs_f64());
+        self.verify_at(token, now)
+    }
+
+    fn verify_at(&self, token: &str, now: f64) -> anyhow::Result<Map<String, Value>> {
+        let (signed, signature) = token
+            .rsplit_once('.')
+            .context("the token does not have a signature")?;
+        let (header, payload) = signed
+            .split_once('.')
+            .context("the token does not have a payload")?;
+        let header: JwtHeader = serde_json::from_slice(&decode_part(header)?)
+            .context("the header of the token is invalid")?;
+        ensure!(
+            header.alg != CoreJwsSigningAlgorithm::None,
+            "unsigned tokens are not accepted"
+        );
+        let signature = decode_part(signature)?;
+        let is_signed = self
+            .keys
+            .iter()
+            .filter(|key| {
+                header.kid.is_none()
+                    || key.key_id().is_none()
+                    || key.key_id() == header.kid.as_ref()
+            })
+            .any(|key| {
+                key.verify_signature(&header.alg, signed.as_bytes(), &signature)
+                    .is_ok()
+            });
+        ensure!(is_signed, "the signature of the token is invalid");
+        let claims: Map<String, Value> = serde_json::from_slice(&decode_part(payload)?)
+            .context("the payload of the token is not a JSON object")?;
+        if let Some(exp) = claims.get("exp").and_then(Value::as_f64) {
+            ensure!(now < exp, "the token has expired");
+        }
+        if let Some(nbf) = claims.get("nbf").and_then(Value::as_f64) {
+            ensure!(now >= nbf, "the token is not valid yet");
+        }
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    fn hs256_token(secret: &[u8], header: &str, claims: &str) -> String {
+        let encode = |s: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(s);
+        let signed = format!(
+            "{}.{}",
+            encode(header.as_bytes()),
+            encode(claims.as_bytes())
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(signed.as_bytes());
+        format!("{signed}.{}", encode(&mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_hs256() {
+        let keys = JwtKeys {
+            keys: vec![CoreJsonWebKey::new_symmetric(b"secret".to_vec())],
+        };
+        let header = r#"{"alg":"HS256","typ":"JWT"}"#;
+        let token = hs256_token(b"secret", header, r#"{"sub":"42","exp":2000}"#);
+        let claims = keys.verify_at(&token, 1000.).unwrap();
+        assert_eq!(claims["sub"], "42");
+
+        let err = keys.verify_at(&token, 3000.).unwrap_err();
+        assert_eq!(err.to_string(), "the token has expired");
+
+        let forged = hs256_token(b"other secret", header, r#"{"sub":"42"}"#);
+        assert!(keys.verify_at(&forged, 1000.).is_err());
+
+        let unsigned = hs256_token(b"secret", r#"{"alg":"none"}"#, r#"{"sub":"42"}"#);
+        assert!(keys.verify_at(&unsigned, 1000.).is_err());
+
+        let not_yet_valid = hs256_token(b"secret", header, r#"{"nbf":2000}"#);
+        assert!(keys.verify_at(&not_yet_valid, 1000.).is_err());
+    }
+}
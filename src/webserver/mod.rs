@@ -29,13 +29,17 @@
 //! - [`static_content`]: Static asset handling (JS, CSS, icons)
 //!
 
+pub mod alerts;
+pub mod client_ip;
 pub mod content_security_policy;
 pub mod database;
 pub mod error_with_status;
+pub mod event_stream;
 pub mod http;
 pub mod http_client;
 pub mod http_request_info;
-mod https;
+pub mod https;
+pub mod jwt;
 pub mod request_variables;
 
 pub use database::Database;
@@ -43,7 +47,18 @@ pub use error_with_status::ErrorWithStatus;
 
 pub use database::make_placeholder;
 pub use database::migrations::apply;
+mod admin_api;
+pub(crate) mod byte_ranges;
+pub(crate) mod config_reload;
 pub mod oidc;
+pub mod page_etags;
+pub mod post_redirect;
+pub mod rate_limit;
 pub mod response_writer;
 pub mod routing;
+pub mod short_links;
+pub mod site_docs;
+pub mod spam_protection;
 mod static_content;
+pub mod static_export;
+mod websocket;
@@ -0,0 +1,218 @@
+//! `ETag`s for dynamic pages, enabled by the `etag` component.
+//!
+//! The rendered HTML contains a random nonce, so the `ETag` of a page is a weak hash of all the rows returned by its queries,
+//! from the first one, including the rows of the header components and the shell.
+//! Changes to the templates only change the `ETag`s when the server restarts.
+//! Pages that use the component are rendered in memory, then sent with their `ETag`, or with a
+//! `304 Not Modified` status when the browser already has the same version of the page.
+//!
+//! With a `max_age`, the `ETag` of the page is remembered for that long, and requests that
+//! send it back in their `If-None-Match` header get a `304 Not Modified` without executing the page.
+//! The remembered `ETag`s are specific to the cookies and `Authorization` header of the request,
+//! so that a user who logs out, or another user, does not get the version of the page of someone else.
+
+use super::database::DbItem;
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{self, EntityTag, Header, IfNoneMatch};
+use actix_web::HttpResponse;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The number of pages whose `ETag` is remembered. New pages are not remembered when it is reached.
+const MAX_REMEMBERED_PAGES: usize = 10_000;
+
+/// Set by the `etag` component on the response of the page
+#[derive(Debug, Clone, Copy)]
+pub struct EtagDirective {
+    /// How long the page can be assumed not to change, without executing it again
+    pub max_age: Duration,
+}
+
+/// The `ETag`s of the pages that can answer conditional requests without being executed
+#[derive(Debug)]
+pub struct PageEtags {
+    pages: Mutex<HashMap<String, RememberedEtag>>,
+    /// Changes the `ETag`s of all pages when the server restarts, since its templates may have changed
+    salt: [u8; 16],
+}
+
+#[derive(Debug)]
+struct RememberedEtag {
+    etag: EntityTag,
+    fresh_until: Instant,
+}
+
+impl Default for PageEtags {
+    fn default() -> Self {
+        Self {
+            pages: Mutex::default(),
+            salt: rand::random(),
+        }
+    }
+}
+
+impl PageEtags {
+    /// Returns the remembered `ETag` of the page, if the browser has the same version of it,
+    /// and the page has not expired
+    pub(crate) fn fresh_match(&self, request: &ConditionalGet) -> Option<EntityTag> {
+        let pages = self
+            .pages
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let remembered = pages.get(&request.key)?;
+        (remembered.fresh_until > Instant::now() && request.matches(&remembered.etag))
+            .then(|| remembered.etag.clone())
+    }
+
+    pub(crate) fn remember(&self, request: &ConditionalGet, etag: EntityTag, max_age: Duration) {
+        let page = request.page.as_str();
+        if max_age.is_zero() {
+            return;
+        }
+        let now = Instant::now();
+        let mut pages = self
+            .pages
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if pages.len() >= MAX_REMEMBERED_PAGES && !pages.contains_key(&request.key) {
+            pages.retain(|_, remembered| remembered.fresh_until > now);
+            if pages.len() >= MAX_REMEMBERED_PAGES {
+                log::debug!("Not remembering the ETag of {page}: too many pages have an ETag");
+                return;
+            }
+        }
+        let fresh_until = now + max_age;
+        pages.insert(request.key.clone(), RememberedEtag { etag, fresh_until });
+    }
+
+    pub(crate) fn hasher(&self) -> RowsHasher {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt);
+        RowsHasher(RefCell::new(Some(hasher)))
+    }
+}
+
+/// A GET request, and the versions of the page that the browser already has
+#[derive(Debug)]
+pub(crate) struct ConditionalGet {
+    /// The path and query string of the page
+    page: String,
+    /// Identifies the page and the user who requests it, to remember its `ETag`
+    key: String,
+    if_none_match: Option<IfNoneMatch>,
+}
+
+impl ConditionalGet {
+    pub(crate) fn from_request(request: &ServiceRequest) -> Option<Self> {
+        let method = request.method();
+        if method != actix_web::http::Method::GET && method != actix_web::http::Method::HEAD {
+            return None;
+        }
+        let page = request
+            .uri()
+            .path_and_query()
+            .map_or_else(|| request.path().to_string(), ToString::to_string);
+        // The page may depend on who requests it: the credentials are part of the key, hashed to keep them out of memory
+        let mut credentials = Sha256::new();
+        for name in [header::COOKIE, header::AUTHORIZATION] {
+            for value in request.headers().get_all(name) {
+                credentials.update(value.as_bytes());
+                credentials.update(b"\n");
+            }
+        }
+        let credentials =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(credentials.finalize());
+        Some(Self {
+            key: format!("{page}#{credentials}"),
+            page,
+            if_none_match: IfNoneMatch::parse(request).ok(),
+        })
+    }
+
+    /// Whether the browser already has the version of the page with this `ETag`
+    pub(crate) fn matches(&self, etag: &EntityTag) -> bool {
+        match &self.if_none_match {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(etags)) => etags.iter().any(|e| e.weak_eq(etag)),
+            None => false,
+        }
+    }
+}
+
+/// Hashes the rows of a page while it is rendered
+pub(crate) struct RowsHasher(RefCell<Option<Sha256>>);
+
+impl RowsHasher {
+    pub(crate) fn update(&self, item: &DbItem) {
+        let mut hasher = self.0.borrow_mut();
+        let Some(hasher_ref) = hasher.as_mut() else {
+            return;
+        };
+        match item {
+            DbItem::Row(row) => {
+                hasher_ref.update(serde_json::to_vec(row).unwrap_or_default());
+                hasher_ref.update(b"\n");
+            }
+            DbItem::FinishedQuery => hasher_ref.update(b"\0"),
+            DbItem::ColumnTypes(_) => {}
+            // Pages that display an error must not be cached
+            DbItem::Error(_) => *hasher = None,
+        }
+    }
+
+    /// Stops hashing the rows, when the page does not use the `etag` component
+    pub(crate) fn stop(&self) {
+        self.0.borrow_mut().take();
+    }
+
+    /// The `ETag` of the page, or None if the page failed.
+    /// It is weak: the HTML of two versions with the same `ETag` differs by its nonce.
+    pub(crate) fn finish(&self) -> Option<EntityTag> {
+        let hash = self.0.borrow_mut().take()?.finalize();
+        let tag = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&hash[..18]);
+        Some(EntityTag::new_weak(tag))
+    }
+}
+
+pub(crate) fn not_modified(etag: EntityTag) -> HttpResponse {
+    HttpResponse::NotModified()
+        .insert_header(header::ETag(etag))
+        .finish()
+}
+
+#[test]
+fn test_conditional_get_matches() {
+    let etag = EntityTag::new_strong("abc".to_string());
+    let request = actix_web::test::TestRequest::get()
+        .uri("/report.sql?year=2024")
+        .insert_header((header::IF_NONE_MATCH, "\"xyz\", W/\"abc\""))
+        .to_srv_request();
+    let conditional = ConditionalGet::from_request(&request).unwrap();
+    assert_eq!(conditional.page, "/report.sql?year=2024");
+    assert!(conditional.matches(&etag));
+    assert!(!conditional.matches(&EntityTag::new_strong("other".to_string())));
+
+    let etags = PageEtags::default();
+    assert!(etags.fresh_match(&conditional).is_none());
+    etags.remember(&conditional, etag.clone(), Duration::from_secs(60));
+    assert_eq!(etags.fresh_match(&conditional), Some(etag.clone()));
+
+    // Another user does not get the version of the page remembered for the first one
+    let other_user = actix_web::test::TestRequest::get()
+        .uri("/report.sql?year=2024")
+        .insert_header((header::IF_NONE_MATCH, "\"abc\""))
+        .insert_header((header::COOKIE, "session=other"))
+        .to_srv_request();
+    let other_user = ConditionalGet::from_request(&other_user).unwrap();
+    assert!(other_user.matches(&etag));
+    assert!(etags.fresh_match(&other_user).is_none());
+
+    let post = actix_web::test::TestRequest::post()
+        .uri("/report.sql")
+        .to_srv_request();
+    assert!(ConditionalGet::from_request(&post).is_none());
+}
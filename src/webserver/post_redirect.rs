@@ -0,0 +1,86 @@
+//! The post/redirect/get pattern, enabled with the `post_redirect_get` configuration option.
+//!
+//! When a form is submitted with a POST request, and the page executes without errors,
+//! the browser is redirected with `303 See Other` instead of receiving the page,
+//! so that reloading the page it lands on does not submit the form again.
+//! A flash message can be displayed once on that page: it is stored in a short-lived cookie,
+//! that `sqlpage.js` reads, displays, and removes.
+
+use super::http::SingleOrVec;
+use super::http_request_info::RequestInfo;
+use crate::app_config::AppConfig;
+use actix_web::cookie::{time::Duration, Cookie, SameSite};
+use actix_web::http::{header, Method, StatusCode, Uri};
+use actix_web::HttpResponse;
+
+/// Name of the cookie that contains the message to display on the next page
+pub const FLASH_COOKIE: &str = "sqlpage_flash";
+
+/// Where to redirect the browser after this request, if it is a POST request that should be redirected.
+/// By default, the browser goes back to the page that submitted the form.
+pub(crate) fn redirect_target(config: &AppConfig, request: &RequestInfo) -> Option<String> {
+    if !config.post_redirect_get || request.method != Method::POST {
+        return None;
+    }
+    if let Some(target) = &config.post_redirect_target {
+        return Some(target.clone());
+    }
+    let referer = match request.headers.get("referer") {
+        Some(SingleOrVec::Single(referer)) => referer.parse::<Uri>().ok(),
+        _ => None,
+    };
+    // Only follow the referer to a page of this website
+    let same_site_page = referer
+        .filter(|referer| {
+            referer
+                .authority()
+                .map(actix_web::http::uri::Authority::as_str)
+                == Some(request.host.as_str())
+        })
+        .and_then(|referer| referer.path_and_query().map(ToString::to_string));
+    Some(
+        same_site_page.unwrap_or_else(|| match request.query_string.as_str() {
+            "" => request.path.clone(),
+            query => format!("{}?{query}", request.path),
+        }),
+    )
+}
+
+/// Whether the response displays a page, that can be replaced by a redirect.
+/// Redirects, errors, downloads and API responses are sent unchanged.
+pub(crate) fn displays_page<B>(response: &HttpResponse<B>) -> bool {
+    response.status() == StatusCode::OK
+        && response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/html"))
+}
+
+/// The redirect that replaces the page, with the cookies the page has set
+pub(crate) fn redirect_response<B>(
+    config: &AppConfig,
+    target: &str,
+    page: &HttpResponse<B>,
+) -> HttpResponse {
+    let mut response = HttpResponse::SeeOther();
+    response.insert_header((header::LOCATION, target));
+    for cookie in page.headers().get_all(header::SET_COOKIE) {
+        response.append_header((header::SET_COOKIE, cookie.clone()));
+    }
+    if let Some(message) = &config.post_redirect_message {
+        response.cookie(flash_cookie(message));
+    }
+    response.finish()
+}
+
+/// A cookie that makes `sqlpage.js` display the message once, on the next page
+#[must_use]
+pub fn flash_cookie(message: &str) -> Cookie<'static> {
+    let value = percent_encoding::utf8_percent_encode(message, percent_encoding::NON_ALPHANUMERIC);
+    Cookie::build(FLASH_COOKIE, value.to_string())
+        .path("/")
+        .max_age(Duration::minutes(1))
+        .same_site(SameSite::Lax)
+        .finish()
+}
@@ -0,0 +1,359 @@
+//! Limits the number of requests each client can make, to protect public websites from scraping and brute force.
+//!
+//! Each client IP address can make `rate_limit_requests` requests in every window of `rate_limit_window_seconds`.
+//! The `rate_limits` configuration option sets stricter limits on some paths, like `/login.sql`,
+//! that are counted separately. Requests over the limit get a `429 Too Many Requests` response,
+//! with a `Retry-After` header, without being executed.
+//!
+//! Paths are compared by the page they execute, so that `/login`, `/login.sql` and `/login.sql/` share the same limit.
+//! Static files are not counted in the global limit, so that the assets of a page do not use up its budget.
+
+use super::client_ip::resolve_client_ip;
+use crate::app_config::AppConfig;
+use crate::AppState;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::middleware::Condition;
+use actix_web::{web, Error, HttpResponse};
+use futures_util::future::{ready, Either, LocalBoxFuture, Ready};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The counters of the clients whose window has ended are removed when there are more than this number of them
+const MIN_CLEANUP_SIZE: usize = 1024;
+
+/// A limit on the requests to some paths, that applies in addition to the global limit
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PathRateLimit {
+    /// The path of the page, relative to the site prefix. A trailing `*` matches any suffix (`/api/*`).
+    pub path: String,
+    /// The number of requests each client can make to the matching paths in each window
+    pub requests: u32,
+    #[serde(default = "default_window_seconds")]
+    pub window_seconds: u64,
+}
+
+#[must_use]
+pub fn default_window_seconds() -> u64 {
+    60
+}
+
+/// The paths to which a [`PathRateLimit`] applies, in the form returned by [`page_route`]
+pub(crate) enum PathPattern {
+    Exact(String),
+    Prefix(String),
+}
+
+impl PathPattern {
+    pub(crate) fn new(path: &str) -> Self {
+        match path.strip_suffix('*') {
+            Some(prefix) => {
+                let mut prefix = normalize_segments(prefix);
+                if prefix.len() > 1 && path.ends_with("/*") {
+                    prefix.push('/');
+                }
+                Self::Prefix(prefix)
+            }
+            None => Self::Exact(page_route(path)),
+        }
+    }
+
+    pub(crate) fn matches(&self, route: &str) -> bool {
+        match self {
+            Self::Prefix(prefix) => route.starts_with(prefix.as_str()),
+            Self::Exact(path) => route == path,
+        }
+    }
+}
+
+/// Decodes the path, and resolves its `.` and `..` segments and repeated slashes, like the router does
+fn normalize_segments(path: &str) -> String {
+    let decoded = percent_encoding::percent_decode_str(path).decode_utf8_lossy();
+    let mut segments = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
+
+/// The page that a path executes, without its `.sql` extension:
+/// `/login`, `/login.sql` and `/login.sql/` all return `/login`, and `/admin/` returns `/admin/index`.
+/// Static files keep their extension.
+pub(crate) fn page_route(path: &str) -> String {
+    let mut route = normalize_segments(path);
+    let last_segment = route.rsplit('/').next().unwrap_or_default();
+    if path.ends_with('/') && !last_segment.contains('.') {
+        if route.len() > 1 {
+            route.push('/');
+        }
+        route.push_str("index");
+    } else if let Some(page) = route.strip_suffix(".sql") {
+        route.truncate(page.len());
+    }
+    route
+}
+
+/// Whether the route executes a SQL file rather than serving a static file
+fn is_page(route: &str) -> bool {
+    !route.rsplit('/').next().unwrap_or_default().contains('.')
+}
+
+struct Limit {
+    requests: u32,
+    window: Duration,
+}
+
+/// The number of requests made by a client in the current window of a limit
+struct Window {
+    started_at: Instant,
+    count: u32,
+}
+
+/// The request counters of all the clients
+pub struct RateLimiter {
+    /// The global limit, followed by the limits of the paths
+    limits: Vec<Limit>,
+    has_global_limit: bool,
+    path_patterns: Vec<PathPattern>,
+    site_prefix: String,
+    /// Indexed by the position of the limit in `limits`, and the client address
+    windows: Mutex<HashMap<(usize, IpAddr), Window>>,
+    next_cleanup: Mutex<usize>,
+}
+
+impl RateLimiter {
+    #[must_use]
+    pub fn new(config: &AppConfig) -> Self {
+        let global = config.rate_limit_requests.map(|requests| Limit {
+            requests,
+            window: Duration::from_secs(config.rate_limit_window_seconds),
+        });
+        let paths = config.rate_limits.iter().map(|limit| Limit {
+            requests: limit.requests,
+            window: Duration::from_secs(limit.window_seconds),
+        });
+        Self {
+            has_global_limit: global.is_some(),
+            limits: global.into_iter().chain(paths).collect(),
+            path_patterns: config
+                .rate_limits
+                .iter()
+                .map(|limit| PathPattern::new(&limit.path))
+                .collect(),
+            site_prefix: config.site_prefix.trim_end_matches('/').to_string(),
+            windows: Mutex::default(),
+            next_cleanup: Mutex::new(MIN_CLEANUP_SIZE),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.limits.is_empty()
+    }
+
+    /// Counts the request, and returns how long the client has to wait if it is over one of its limits
+    pub(crate) fn check(&self, client: IpAddr, path: &str, now: Instant) -> Option<Duration> {
+        let path = path.strip_prefix(&self.site_prefix).unwrap_or(path);
+        let route = page_route(path);
+        let first_path_limit = usize::from(self.has_global_limit);
+        let applicable = (0..self.limits.len()).filter(|&index| {
+            if index < first_path_limit {
+                is_page(&route)
+            } else {
+                self.path_patterns[index - first_path_limit].matches(&route)
+            }
+        });
+        let mut windows = self.windows.lock().expect("rate limiter lock poisoned");
+        let mut retry_after = None;
+        for index in applicable {
+            let limit = &self.limits[index];
+            let window = windows.entry((index, client)).or_insert(Window {
+                started_at: now,
+                count: 0,
+            });
+            let elapsed = now.duration_since(window.started_at);
+            if elapsed >= limit.window {
+                window.started_at = now;
+                window.count = 0;
+            }
+            window.count = window.count.saturating_add(1);
+            if window.count > limit.requests {
+                let wait = limit
+                    .window
+                    .saturating_sub(now.duration_since(window.started_at));
+                retry_after = retry_after.max(Some(wait));
+            }
+        }
+        self.cleanup(&mut windows, now);
+        retry_after
+    }
+
+    /// Forgets the clients whose windows have ended, so that the counters do not grow forever
+    fn cleanup(&self, windows: &mut HashMap<(usize, IpAddr), Window>, now: Instant) {
+        let mut next_cleanup = self
+            .next_cleanup
+            .lock()
+            .expect("rate limiter lock poisoned");
+        if windows.len() < *next_cleanup {
+            return;
+        }
+        windows.retain(|(index, _), window| {
+            now.duration_since(window.started_at) < self.limits[*index].window
+        });
+        *next_cleanup = (windows.len() * 2).max(MIN_CLEANUP_SIZE);
+    }
+}
+
+pub struct RateLimitMiddleware {
+    app_state: web::Data<AppState>,
+}
+
+impl RateLimitMiddleware {
+    #[must_use]
+    pub fn new(app_state: &web::Data<AppState>) -> Condition<Self> {
+        let is_enabled = app_state.rate_limiter.is_enabled();
+        Condition::new(
+            is_enabled,
+            Self {
+                app_state: web::Data::clone(app_state),
+            },
+        )
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitService {
+            service,
+            app_state: web::Data::clone(&self.app_state),
+        }))
+    }
+}
+
+pub struct RateLimitService<S> {
+    service: S,
+    app_state: web::Data<AppState>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let client = resolve_client_ip(
+            request.peer_addr().map(|addr| addr.ip()),
+            request.headers(),
+            &self.app_state.config.trusted_proxies,
+        );
+        // Requests received on a unix socket have no address
+        let retry_after = client.and_then(|client| {
+            let limiter = &self.app_state.rate_limiter;
+            limiter.check(client, request.path(), Instant::now())
+        });
+        if let (Some(client), Some(retry_after)) = (client, retry_after) {
+            log::warn!("Rate limit exceeded by {client} on {}", request.path());
+            let seconds = (retry_after + Duration::from_millis(999)).as_secs().max(1);
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, seconds.to_string()))
+                .body(format!(
+                    "Too many requests. Please try again in {seconds} seconds.\n"
+                ));
+            return Either::Right(ready(Ok(request
+                .into_response(response)
+                .map_into_right_body())));
+        }
+        let response = self.service.call(request);
+        Either::Left(Box::pin(
+            async move { Ok(response.await?.map_into_left_body()) },
+        ))
+    }
+}
+
+#[test]
+fn test_rate_limits() {
+    let config: AppConfig = serde_json::from_str(
+        r#"{
+            "rate_limit_requests": 3,
+            "site_prefix": "/app/",
+            "rate_limits": [{"path": "/login.sql", "requests": 1, "window_seconds": 10}]
+        }"#,
+    )
+    .unwrap();
+    let limiter = RateLimiter::new(&config);
+    let client: IpAddr = "192.0.2.1".parse().unwrap();
+    let other: IpAddr = "192.0.2.2".parse().unwrap();
+    let start = Instant::now();
+    assert_eq!(limiter.check(client, "/app/login.sql", start), None);
+    let retry_after = limiter.check(client, "/app/login.sql", start + Duration::from_secs(4));
+    assert_eq!(retry_after, Some(Duration::from_secs(6)));
+    assert_eq!(limiter.check(other, "/app/login.sql", start), None);
+    let later = start + Duration::from_secs(5);
+    assert_eq!(limiter.check(client, "/app/index.sql", later), None);
+    let retry_after = limiter.check(client, "/app/index.sql", later);
+    assert_eq!(retry_after, Some(Duration::from_secs(55)));
+    let next_minute = start + Duration::from_secs(60);
+    assert_eq!(limiter.check(client, "/app/login.sql", next_minute), None);
+    for path in [
+        "/app/login",
+        "/app/login.sql/",
+        "/app/./login.sql",
+        "/app/login%2Esql",
+    ] {
+        let retry_after = limiter.check(client, path, next_minute);
+        assert!(retry_after.is_some(), "{path} bypassed the limit");
+    }
+    // Static files are not counted in the global limit
+    for _ in 0..10 {
+        assert_eq!(limiter.check(other, "/app/style.css", start), None);
+    }
+}
+
+#[test]
+fn test_page_route() {
+    for (path, route) in [
+        ("/", "/index"),
+        ("/index.sql", "/index"),
+        ("/login", "/login"),
+        ("/login.sql/", "/login"),
+        ("//admin/../login.sql", "/login"),
+        ("/admin/", "/admin/index"),
+        ("/style.css", "/style.css"),
+    ] {
+        assert_eq!(page_route(path), route, "{path}");
+    }
+    assert!(PathPattern::new("/api/*").matches(&page_route("/api/users.sql")));
+    assert!(!PathPattern::new("/api/*").matches(&page_route("/apis.sql")));
+    assert!(PathPattern::new("/admin/").matches(&page_route("/admin/index.sql")));
+}
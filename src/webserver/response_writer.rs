@@ -37,6 +37,11 @@ impl ResponseWriter {
         }
     }
 
+    /// Resolves when the client stops receiving the response
+    pub async fn closed(&self) {
+        self.response_bytes.closed().await;
+    }
+
     pub async fn async_flush(&mut self) -> std::io::Result<()> {
         if self.buffer.is_empty() {
             return Ok(());
@@ -0,0 +1,116 @@
+//! Short links to long URLs, created with `sqlpage.shorten(url)`.
+//!
+//! The links are stored in a `sqlpage_short_links` table, that the application creates in a migration.
+//! Visiting `/_sqlpage/s/<code>` increments the number of clicks of the link, and redirects to its URL.
+
+use crate::AppState;
+use actix_web::http::header;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use sqlx::any::AnyKind;
+use sqlx::{AnyConnection, Executor};
+
+use super::database::make_placeholder;
+
+/// Path of the short links, relative to the site prefix
+pub const SHORT_LINKS_PATH: &str = "_sqlpage/s";
+const SHORT_LINKS_TABLE: &str = "sqlpage_short_links";
+/// Codes are made longer only when two URLs would get the same code
+const CODE_LENGTHS: [usize; 4] = [8, 12, 16, 43];
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        &format!("/{SHORT_LINKS_PATH}/{{code}}"),
+        web::get().to(follow_short_link),
+    );
+}
+
+/// Returns the code of the short link to `url`, and creates it if it does not exist.
+/// Shortening the same URL several times returns the same code.
+pub(crate) async fn shorten(connection: &mut AnyConnection, url: &str) -> anyhow::Result<String> {
+    let kind = connection.kind();
+    let hash = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(url));
+    for length in CODE_LENGTHS {
+        let code = &hash[..length];
+        match link_url(connection, kind, code).await? {
+            Some(existing) if existing == url => return Ok(code.to_string()),
+            Some(_) => continue,
+            None => {}
+        }
+        let insert = format!(
+            "INSERT INTO {SHORT_LINKS_TABLE} (code, url) VALUES ({}, {})",
+            make_placeholder(kind, 1),
+            make_placeholder(kind, 2)
+        );
+        connection
+            .execute(sqlx::query(&insert).bind(code).bind(url))
+            .await
+            .with_context(|| {
+                format!("Unable to create a short link in the {SHORT_LINKS_TABLE} table")
+            })?;
+        return Ok(code.to_string());
+    }
+    anyhow::bail!(
+        "Unable to create a short link for {url:?}: all its codes are used by other links"
+    )
+}
+
+async fn link_url(
+    connection: &mut AnyConnection,
+    kind: AnyKind,
+    code: &str,
+) -> anyhow::Result<Option<String>> {
+    let select = format!(
+        "SELECT url FROM {SHORT_LINKS_TABLE} WHERE code = {}",
+        make_placeholder(kind, 1)
+    );
+    let url: Option<(String,)> = sqlx::query_as(&select)
+        .bind(code)
+        .fetch_optional(connection)
+        .await
+        .with_context(|| {
+            format!("Unable to read the {SHORT_LINKS_TABLE} table. Create it in a migration to use short links")
+        })?;
+    Ok(url.map(|(url,)| url))
+}
+
+/// Counts the click, and returns the URL of the link
+async fn click(connection: &mut AnyConnection, code: &str) -> anyhow::Result<Option<String>> {
+    let kind = connection.kind();
+    let Some(url) = link_url(connection, kind, code).await? else {
+        return Ok(None);
+    };
+    let update = format!(
+        "UPDATE {SHORT_LINKS_TABLE} SET clicks = clicks + 1 WHERE code = {}",
+        make_placeholder(kind, 1)
+    );
+    connection
+        .execute(sqlx::query(&update).bind(code))
+        .await
+        .with_context(|| format!("Unable to count the click on the short link {code:?}"))?;
+    Ok(Some(url))
+}
+
+async fn follow_short_link(state: web::Data<AppState>, code: web::Path<String>) -> HttpResponse {
+    let result = match state.db.connection.acquire().await {
+        Ok(mut connection) => click(&mut connection, &code).await,
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        // A temporary redirect, so that browsers do not skip the click count on the next visits
+        Ok(Some(url)) => HttpResponse::Found()
+            .insert_header((header::LOCATION, url))
+            .finish(),
+        Ok(None) => HttpResponse::NotFound().body("This short link does not exist"),
+        Err(e) => {
+            log::error!("Unable to follow the short link {code:?}: {e:#}");
+            if state.config.environment.is_prod() {
+                HttpResponse::InternalServerError().body("Unable to follow this short link")
+            } else {
+                HttpResponse::InternalServerError().body(format!("{e:#}"))
+            }
+        }
+    }
+}
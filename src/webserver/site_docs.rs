@@ -0,0 +1,224 @@
+//! Documentation of the pages of a site, generated by the `sqlpage docs` command.
+//!
+//! Each .sql file of the web root is parsed, without connecting to the database, to find its title,
+//! the parameters it reads from the request, and the comments at the start of the file, that describe it.
+//! The documentation is written as a page of the site, made of static queries that do not use the database,
+//! or as JSON for other tools.
+
+use super::database::{PageParameter, ParsedSqlFile};
+use super::static_export::list_files;
+use crate::app_config::AppConfig;
+use anyhow::Context;
+use serde::Serialize;
+use sqlx::any::AnyKind;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Name of the generated page, in the web root
+const DEFAULT_DOCS_PAGE: &str = "sqlpage_docs.sql";
+/// First line of the generated page, that allows overwriting it
+const GENERATED_HEADER: &str =
+    "-- Documentation of the pages of this site, generated by the `sqlpage docs` command.";
+
+#[derive(Debug, Serialize)]
+pub struct PageDocs {
+    /// Path of the file, relative to the web root
+    pub path: String,
+    pub url: String,
+    pub title: Option<String>,
+    /// The comments at the start of the file
+    pub description: String,
+    pub parameters: Vec<PageParameter>,
+    /// Why the file cannot be parsed
+    pub error: Option<String>,
+}
+
+/// Documents all the pages of the web root, as a page of the site, or as JSON.
+/// The documentation is written to `out`, or by default to a page in the web root, or to the standard output for JSON.
+pub fn generate_docs(config: &AppConfig, out: Option<&Path>, json: bool) -> anyhow::Result<()> {
+    let web_root = std::fs::canonicalize(&config.web_root)
+        .with_context(|| format!("Unable to open the web root {}", config.web_root.display()))?;
+    let out: Option<PathBuf> = match out {
+        Some(out) => Some(out.to_path_buf()),
+        None if json => None,
+        None => Some(web_root.join(DEFAULT_DOCS_PAGE)),
+    };
+    let mut excluded = vec![std::fs::canonicalize(&config.configuration_directory)?];
+    if let Some(out) = out.as_ref().and_then(|out| std::fs::canonicalize(out).ok()) {
+        excluded.push(out);
+    }
+    let db_kind = AnyKind::from_str(&config.database_url).unwrap_or(AnyKind::Sqlite);
+    let pages = document_pages(&web_root, &excluded, db_kind, &config.site_prefix)?;
+
+    let contents = if json {
+        serde_json::to_string_pretty(&pages)? + "\n"
+    } else {
+        docs_page(&pages)
+    };
+    let Some(out) = out else {
+        print!("{contents}");
+        return Ok(());
+    };
+    if !json {
+        if let Ok(existing) = std::fs::read_to_string(&out) {
+            anyhow::ensure!(
+                existing.starts_with(GENERATED_HEADER),
+                "{} already exists, and was not generated by sqlpage docs. Choose another file with --out",
+                out.display()
+            );
+        }
+    }
+    std::fs::write(&out, contents).with_context(|| format!("Unable to write {}", out.display()))?;
+    log::info!("Documented {} pages in {}", pages.len(), out.display());
+    Ok(())
+}
+
+fn document_pages(
+    web_root: &Path,
+    excluded: &[PathBuf],
+    db_kind: AnyKind,
+    site_prefix: &str,
+) -> anyhow::Result<Vec<PageDocs>> {
+    let mut files = Vec::new();
+    list_files(web_root, excluded, &mut files)?;
+    files.retain(|f| f.extension().is_some_and(|e| e == "sql"));
+    files.sort();
+    files
+        .iter()
+        .map(|file| {
+            let sql = std::fs::read_to_string(file)
+                .with_context(|| format!("Unable to read {}", file.display()))?;
+            let path = file
+                .strip_prefix(web_root)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let parsed = ParsedSqlFile::parse(db_kind, &sql, Path::new(&path));
+            Ok(PageDocs {
+                url: format!("{site_prefix}{path}"),
+                title: parsed.title(),
+                description: leading_comment(&sql),
+                parameters: parsed.parameters(),
+                error: parsed.parse_error().map(|e| format!("{e:#}")),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// The comments at the start of a file, without their `--` and `/* */` markers.
+/// `-- @if` and the other directives are not comments.
+fn leading_comment(sql: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut rest = sql.trim_start_matches('\u{feff}');
+    loop {
+        rest = rest.trim_start();
+        if let Some(comment) = rest.strip_prefix("--") {
+            let (line, next) = comment.split_once('\n').unwrap_or((comment, ""));
+            let line = line.trim();
+            if line.starts_with('@') {
+                break;
+            }
+            lines.push(line);
+            rest = next;
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let (block, next) = comment.split_once("*/").unwrap_or((comment, ""));
+            lines.extend(
+                block
+                    .lines()
+                    .map(|l| l.trim().trim_start_matches('*').trim()),
+            );
+            rest = next;
+        } else {
+            break;
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// A page of the site that lists the documented pages in a table
+fn docs_page(pages: &[PageDocs]) -> String {
+    let mut sql = format!(
+        "{GENERATED_HEADER}\n-- Run the command again to update it: the changes made to this file are lost.\n\n\
+        select 'shell' as component, 'Documentation' as title, 'book' as icon;\n\
+        select 'text' as component, {} as contents_md;\n\
+        select 'table' as component, true as sort, true as search, \
+        'page' as markdown, 'description' as markdown, 'parameters' as markdown;\n",
+        sql_string(&format!(
+            "This site has **{}** pages. Describe a page in comments at the start of its file.",
+            pages.len()
+        ))
+    );
+    for page in pages {
+        let title = page.title.as_deref().unwrap_or(&page.path);
+        let mut description = page.description.clone();
+        if let Some(error) = &page.error {
+            // The last line says what the parser did not understand
+            let cause = error.lines().last().unwrap_or_default();
+            let cause = cause.trim_start_matches(": ");
+            let _ = write!(description, "\n\n**This page contains an error**: {cause}");
+        }
+        let parameters: Vec<String> = page
+            .parameters
+            .iter()
+            .map(|p| format!("`{}` ({})", p.name, p.source))
+            .collect();
+        let _ = writeln!(
+            sql,
+            "select {} as page, {} as file, {} as description, {} as parameters;",
+            sql_string(&format!("[{}]({})", escape_markdown(title), page.url)),
+            sql_string(&page.path),
+            sql_string(description.trim()),
+            sql_string(&parameters.join(", "))
+        );
+    }
+    sql
+}
+
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+#[test]
+fn test_leading_comment() {
+    assert_eq!(
+        leading_comment(
+            "-- Sales report\n--   by region\n\n/* Used by\n * the sales team */\nselect 1; -- not this"
+        ),
+        "Sales report\nby region\nUsed by\nthe sales team"
+    );
+    assert_eq!(leading_comment("-- @if $x\nselect 1;"), "");
+    assert_eq!(leading_comment("select 1;"), "");
+}
+
+#[test]
+fn test_docs_page() {
+    let pages = [PageDocs {
+        path: "reports/sales.sql".into(),
+        url: "/reports/sales.sql".into(),
+        title: Some("Bob's [sales]".into()),
+        description: "Sales by region".into(),
+        parameters: vec![PageParameter {
+            name: "year".into(),
+            source: super::database::ParameterSource::UrlOrForm,
+        }],
+        error: None,
+    }];
+    let sql = docs_page(&pages);
+    assert!(sql.starts_with(GENERATED_HEADER));
+    assert!(
+        sql.contains(
+            r"select '[Bob''s \[sales\]](/reports/sales.sql)' as page, 'reports/sales.sql' as file, 'Sales by region' as description, '`year` (URL or form)' as parameters;"
+        ),
+        "{sql}"
+    );
+    let parsed = ParsedSqlFile::parse(AnyKind::Postgres, &sql, Path::new(DEFAULT_DOCS_PAGE));
+    assert!(parsed.parse_error().is_none());
+    assert_eq!(parsed.parameters(), []);
+}
@@ -0,0 +1,462 @@
+//! Spam protection for public forms.
+//!
+//! Forms rendered with the `spam_protection` property contain a signed token, generated when the form is displayed,
+//! that is only valid for the page the form is submitted to. Once such a form has been displayed,
+//! submissions to its target page are checked before their variables are made available to SQL queries:
+//!  - the token must be present, and must not have been used by another submission,
+//!  - the honeypot field, invisible to humans, must be empty,
+//!  - the form must not have been submitted too quickly after being displayed,
+//!  - the browser must have solved a proof-of-work challenge derived from the token,
+//!  - if the form displayed a captcha, the captcha response must be accepted by the captcha provider.
+//!
+//! Tokens are signed with a key derived from the `encryption_key` configuration option,
+//! so that they remain valid across restarts and between the servers that share the same configuration.
+//! The pages listed in `spam_protected_paths` require a token even before their form has been displayed,
+//! which is needed for the protection to survive restarts.
+//! Paths are compared by the page they execute, so that `/contact` and `/contact.sql` are protected together.
+//! Rejected submissions get an HTTP 403 response.
+
+use crate::app_config::{AppConfig, CaptchaProvider};
+use crate::webserver::http_client::make_http_client;
+use crate::webserver::rate_limit::{page_route, PathPattern};
+use crate::webserver::ErrorWithStatus;
+use actix_web::http::StatusCode;
+use anyhow::{anyhow, Context};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Signed token added to protected forms. It is kept in the post variables of accepted submissions.
+pub const TOKEN_FIELD: &str = "_sqlpage_form_token";
+/// Hidden text field that robots fill in
+pub const HONEYPOT_FIELD: &str = "_sqlpage_website";
+/// Solution of the proof-of-work challenge, computed by sqlpage.js
+pub const PROOF_OF_WORK_FIELD: &str = "_sqlpage_pow";
+
+/// Forms must be submitted within this delay after being displayed
+const MAX_TOKEN_AGE_SECONDS: u64 = 24 * 60 * 60;
+/// The used tokens that have expired are forgotten when there are more than this number of them
+const MIN_CLEANUP_SIZE: usize = 1024;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The path to which a form displayed on `page_path` is submitted, given its `action` property
+#[must_use]
+pub fn form_action(page_path: &str, action: Option<&str>) -> String {
+    let Ok(page) = url::Url::parse("http://localhost").and_then(|base| base.join(page_path)) else {
+        return page_path.to_string();
+    };
+    match action.map(|action| page.join(action)) {
+        Some(Ok(target)) => target.path().to_string(),
+        _ => page.path().to_string(),
+    }
+}
+
+struct FormToken<'a> {
+    issued_at: u64,
+    proof_of_work_difficulty: u8,
+    captcha: bool,
+    nonce: &'a str,
+    action: String,
+}
+
+/// The tokens of the forms displayed by this server, and the submissions that used them
+#[derive(Default)]
+pub struct SpamProtection {
+    key: Option<[u8; 32]>,
+    /// The nonces of the accepted tokens, with the time at which the tokens expire
+    used_nonces: Mutex<HashMap<String, u64>>,
+    next_cleanup: Mutex<usize>,
+    /// The pages to which protected forms are submitted, that only accept submissions with a token,
+    /// in the form returned by [`page_route`]
+    protected_actions: Mutex<HashSet<String>>,
+    /// The pages of `spam_protected_paths`, relative to the site prefix
+    protected_paths: Vec<PathPattern>,
+    site_prefix: String,
+}
+
+impl SpamProtection {
+    #[must_use]
+    pub fn new(config: &AppConfig) -> Self {
+        let key = config.encryption_key.as_ref().map(|key| {
+            let mut hasher = Sha256::new();
+            hasher.update(b"sqlpage form tokens:");
+            hasher.update(key.as_bytes());
+            hasher.finalize().into()
+        });
+        Self {
+            key,
+            next_cleanup: Mutex::new(MIN_CLEANUP_SIZE),
+            protected_paths: config
+                .spam_protected_paths
+                .iter()
+                .map(|path| PathPattern::new(path))
+                .collect(),
+            site_prefix: config.site_prefix.trim_end_matches('/').to_string(),
+            ..Self::default()
+        }
+    }
+
+    fn sign(&self, payload: &str) -> anyhow::Result<String> {
+        let key = self.key.context(
+            "Forms with spam_protection or captcha require setting encryption_key in the configuration",
+        )?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts any key size");
+        mac.update(payload.as_bytes());
+        Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    /// Creates the token of a form that is being displayed, and that will be submitted to `action`:
+    /// `<issued at>.<proof of work difficulty>.<captcha>.<nonce>.<action>.<signature>`
+    pub fn new_form_token(
+        &self,
+        action: &str,
+        proof_of_work_difficulty: u8,
+        captcha: bool,
+    ) -> anyhow::Result<String> {
+        let base64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let nonce = base64.encode(rand::random::<[u8; 16]>());
+        let payload = format!(
+            "{}.{proof_of_work_difficulty}.{}.{nonce}.{}",
+            now(),
+            u8::from(captcha),
+            base64.encode(action)
+        );
+        let signature = self.sign(&payload)?;
+        self.protected_actions
+            .lock()
+            .expect("spam protection lock poisoned")
+            .insert(page_route(action));
+        Ok(format!("{payload}.{signature}"))
+    }
+
+    fn parse_token<'a>(&self, token: &'a str) -> Option<FormToken<'a>> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        if self.sign(payload).ok()? != signature {
+            return None;
+        }
+        let mut parts = payload.split('.');
+        let issued_at = parts.next()?.parse().ok()?;
+        let proof_of_work_difficulty = parts.next()?.parse().ok()?;
+        let captcha = parts.next()? == "1";
+        let nonce = parts.next()?;
+        let action = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(parts.next()?)
+            .ok()?;
+        Some(FormToken {
+            issued_at,
+            proof_of_work_difficulty,
+            captcha,
+            nonce,
+            action: String::from_utf8(action).ok()?,
+        })
+    }
+
+    fn is_protected(&self, path: &str) -> bool {
+        let relative_path = path.strip_prefix(&self.site_prefix).unwrap_or(path);
+        let relative_route = page_route(relative_path);
+        self.protected_paths
+            .iter()
+            .any(|pattern| pattern.matches(&relative_route))
+            || self
+                .protected_actions
+                .lock()
+                .expect("spam protection lock poisoned")
+                .contains(&page_route(path))
+    }
+
+    /// Records that the token was used, and returns false if it had already been used
+    fn use_nonce(&self, nonce: &str, issued_at: u64) -> bool {
+        let mut used = self
+            .used_nonces
+            .lock()
+            .expect("spam protection lock poisoned");
+        let mut next_cleanup = self
+            .next_cleanup
+            .lock()
+            .expect("spam protection lock poisoned");
+        let now = now();
+        if used.len() >= *next_cleanup {
+            used.retain(|_, expires_at| *expires_at >= now);
+            *next_cleanup = (used.len() * 2).max(MIN_CLEANUP_SIZE);
+        }
+        used.insert(nonce.to_string(), issued_at + MAX_TOKEN_AGE_SECONDS)
+            .is_none()
+    }
+
+    /// Checks a form submission to `path`, and removes the spam protection fields
+    /// from the post variables, except for the token itself.
+    /// Submissions without a token are only accepted on the pages to which no protected form is submitted,
+    /// and that are not in `spam_protected_paths`.
+    pub(crate) async fn check_form_submission(
+        &self,
+        post_variables: &mut Vec<(String, String)>,
+        path: &str,
+        config: &AppConfig,
+        client_ip: Option<IpAddr>,
+    ) -> anyhow::Result<()> {
+        let honeypot = take_field(post_variables, HONEYPOT_FIELD);
+        let proof_of_work = take_field(post_variables, PROOF_OF_WORK_FIELD);
+        let captcha_response = config
+            .captcha_provider
+            .and_then(|provider| take_field(post_variables, response_field(provider)));
+        let Some((_, token)) = post_variables.iter().find(|(k, _)| k == TOKEN_FIELD) else {
+            if !post_variables.is_empty() && self.is_protected(path) {
+                return Err(rejected("the form token is missing"));
+            }
+            return Ok(());
+        };
+        let form_token = self
+            .parse_token(token)
+            .ok_or_else(|| rejected("invalid form token"))?;
+        if page_route(&form_token.action) != page_route(path) {
+            return Err(rejected("the form token was issued for another page"));
+        }
+        if honeypot.is_some_and(|v| !v.is_empty()) {
+            return Err(rejected("the honeypot field was filled"));
+        }
+        let age = now().saturating_sub(form_token.issued_at);
+        if age < config.form_min_fill_seconds {
+            return Err(rejected("the form was submitted too quickly"));
+        }
+        if age > MAX_TOKEN_AGE_SECONDS {
+            return Err(rejected("the form has expired. Please reload the page"));
+        }
+        let difficulty = form_token.proof_of_work_difficulty;
+        if difficulty > 0
+            && !proof_of_work.is_some_and(|s| is_valid_proof_of_work(token, &s, difficulty))
+        {
+            return Err(rejected("missing or invalid proof of work"));
+        }
+        if form_token.captcha {
+            let response = captcha_response.ok_or_else(|| rejected("missing captcha response"))?;
+            if !verify_captcha(config, &response, client_ip).await? {
+                return Err(rejected("the captcha was not solved"));
+            }
+        }
+        if !self.use_nonce(form_token.nonce, form_token.issued_at) {
+            return Err(rejected(
+                "the form was already submitted. Please reload the page",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// The solution of the challenge is a number such that `sha256("<token>:<solution>")` starts with `difficulty` zero bits
+fn is_valid_proof_of_work(token: &str, solution: &str, difficulty: u8) -> bool {
+    let hash = Sha256::digest(format!("{token}:{solution}").as_bytes());
+    leading_zero_bits(&hash) >= u32::from(difficulty)
+}
+
+fn rejected(reason: &str) -> anyhow::Error {
+    anyhow!(ErrorWithStatus {
+        status: StatusCode::FORBIDDEN,
+    })
+    .context(format!(
+        "This form submission was rejected by the spam protection: {reason}"
+    ))
+}
+
+fn take_field(post_variables: &mut Vec<(String, String)>, name: &str) -> Option<String> {
+    let idx = post_variables.iter().position(|(k, _)| k == name)?;
+    Some(post_variables.remove(idx).1)
+}
+
+/// Name of the form field in which the captcha widget stores its response
+fn response_field(provider: CaptchaProvider) -> &'static str {
+    match provider {
+        CaptchaProvider::Turnstile => "cf-turnstile-response",
+        CaptchaProvider::Hcaptcha => "h-captcha-response",
+    }
+}
+
+fn verification_url(provider: CaptchaProvider) -> &'static str {
+    match provider {
+        CaptchaProvider::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+        CaptchaProvider::Hcaptcha => "https://api.hcaptcha.com/siteverify",
+    }
+}
+
+async fn verify_captcha(
+    config: &AppConfig,
+    response: &str,
+    client_ip: Option<IpAddr>,
+) -> anyhow::Result<bool> {
+    let provider = config
+        .captcha_provider
+        .context("A form with a captcha was submitted, but captcha_provider is not configured")?;
+    let secret = config
+        .captcha_secret_key
+        .as_deref()
+        .context("A form with a captcha was submitted, but captcha_secret_key is not configured")?;
+    let remote_ip = client_ip.map(|ip| ip.to_string()).unwrap_or_default();
+    let client = make_http_client(config)?;
+    let mut resp = client
+        .post(verification_url(provider))
+        .send_form(&[
+            ("secret", secret),
+            ("response", response),
+            ("remoteip", &remote_ip),
+        ])
+        .await
+        .map_err(|e| anyhow!("Unable to contact the captcha provider: {e}"))?;
+    let verification: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| anyhow!("Invalid response from the captcha provider: {e}"))?;
+    log::debug!("Captcha verification result: {verification}");
+    Ok(verification.get("success") == Some(&serde_json::Value::Bool(true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(token: &str, difficulty: u8) -> String {
+        (0..u64::MAX)
+            .map(|n| n.to_string())
+            .find(|n| is_valid_proof_of_work(token, n, difficulty))
+            .unwrap()
+    }
+
+    fn submission(token: &str, fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        std::iter::once((TOKEN_FIELD, token))
+            .chain(fields.iter().copied())
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_check_form_submission() {
+        let mut config = crate::app_config::tests::test_config();
+        config.form_min_fill_seconds = 0;
+        config.encryption_key = Some("0123456789abcdef0123456789abcdef".into());
+        let protection = SpamProtection::new(&config);
+        let token = protection.new_form_token("/contact.sql", 8, false).unwrap();
+        let solution = solve(&token, 8);
+        let check = |mut variables: Vec<(String, String)>, path: &'static str| {
+            let (protection, config) = (&protection, &config);
+            async move {
+                protection
+                    .check_form_submission(&mut variables, path, config, None)
+                    .await
+                    .map(|()| variables)
+            }
+        };
+
+        let honeypot = submission(
+            &token,
+            &[(HONEYPOT_FIELD, "spam"), (PROOF_OF_WORK_FIELD, &solution)],
+        );
+        assert!(check(honeypot, "/contact.sql").await.is_err());
+        assert!(check(submission(&token, &[]), "/contact.sql")
+            .await
+            .is_err());
+        let forged = submission(&token.replace(".8.", ".0."), &[]);
+        assert!(check(forged, "/contact.sql").await.is_err());
+        let other_page = submission(&token, &[(PROOF_OF_WORK_FIELD, &solution)]);
+        assert!(check(other_page, "/other.sql").await.is_err());
+
+        let accepted = submission(
+            &token,
+            &[
+                ("x", "1"),
+                (HONEYPOT_FIELD, ""),
+                (PROOF_OF_WORK_FIELD, &solution),
+            ],
+        );
+        let accepted = check(accepted, "/contact.sql").await.unwrap();
+        assert_eq!(accepted, submission(&token, &[("x", "1")]));
+        let replayed = submission(&token, &[(PROOF_OF_WORK_FIELD, &solution)]);
+        assert!(check(replayed, "/contact.sql").await.is_err());
+
+        // Tokens remain valid on another server with the same configuration
+        let other_server = SpamProtection::new(&config);
+        assert!(other_server.parse_token(&token).is_some());
+
+        let unprotected = vec![("x".to_string(), "1".to_string())];
+        for path in [
+            "/contact.sql",
+            "/contact",
+            "/contact%2Esql",
+            "//contact.sql",
+        ] {
+            assert!(check(unprotected.clone(), path).await.is_err(), "{path}");
+        }
+        check(unprotected.clone(), "/other.sql").await.unwrap();
+
+        // Configured pages are protected before their form is displayed, for instance after a restart
+        config.spam_protected_paths = vec!["/forms/*".into()];
+        let restarted = SpamProtection::new(&config);
+        for path in [
+            "/forms/contact.sql",
+            "/forms/contact",
+            "/forms//contact.sql",
+        ] {
+            let result = restarted
+                .check_form_submission(&mut unprotected.clone(), path, &config, None)
+                .await;
+            assert!(result.is_err(), "{path}");
+        }
+        restarted
+            .check_form_submission(&mut unprotected.clone(), "/contact.sql", &config, None)
+            .await
+            .unwrap();
+
+        config.form_min_fill_seconds = 60;
+        let token = protection.new_form_token("/contact.sql", 8, false).unwrap();
+        let solution = solve(&token, 8);
+        let mut too_fast = submission(&token, &[(PROOF_OF_WORK_FIELD, &solution)]);
+        assert!(protection
+            .check_form_submission(&mut too_fast, "/contact.sql", &config, None)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_form_action() {
+        assert_eq!(
+            form_action("/forms/contact.sql", None),
+            "/forms/contact.sql"
+        );
+        assert_eq!(
+            form_action("/forms/contact.sql", Some("#contact")),
+            "/forms/contact.sql"
+        );
+        assert_eq!(
+            form_action("/forms/contact.sql", Some("send.sql?x=1")),
+            "/forms/send.sql"
+        );
+        assert_eq!(
+            form_action("/forms/contact.sql", Some("/send.sql")),
+            "/send.sql"
+        );
+        assert_eq!(form_action("/forms/", Some("../send")), "/send");
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0, 0b0001_0000, 0xff]), 11);
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::app_config::AppConfig;
 use crate::utils::static_filename;
 use actix_web::{
     http::header::{
@@ -53,3 +54,33 @@ pub fn icons() -> Resource {
 pub fn favicon() -> Resource {
     static_file_endpoint!("favicon", "svg", "image/svg+xml")
 }
+
+/// Script that lets other websites embed sqlpage components as custom elements.
+/// It is served at a stable url, so that it can be referenced from external sites.
+/// Only the websites listed explicitly in `embed_allowed_origins` send the cookies of the visitor.
+pub fn embed_js(config: &AppConfig) -> Resource {
+    let credentialed_origins: Vec<&String> = config
+        .embed_allowed_origins
+        .iter()
+        .filter(|origin| *origin != "*")
+        .collect();
+    let js = format!(
+        "{{\nconst SQLPAGE_STYLESHEET = {:?};\nconst SQLPAGE_CREDENTIALED_ORIGINS = {};\n{}\n}}",
+        format!("{}{}", config.site_prefix, static_filename!("sqlpage.css")),
+        serde_json::to_string(&credentialed_origins).unwrap_or_else(|_| "[]".into()),
+        include_str!("../../sqlpage/sqlpage_embed.js")
+    );
+    web::resource("sqlpage_embed.js").to(move || {
+        let js = js.clone();
+        async move {
+            HttpResponse::Ok()
+                .content_type("application/javascript;charset=UTF-8")
+                .insert_header(CacheControl(vec![
+                    CacheDirective::Public,
+                    CacheDirective::MaxAge(3600),
+                ]))
+                .insert_header(("Access-Control-Allow-Origin", "*"))
+                .body(js)
+        }
+    })
+}
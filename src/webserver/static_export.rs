@@ -0,0 +1,437 @@
+//! Static site export: renders pages to files that can be hosted without a running `SQLPage` server.
+//!
+//! This powers the `sqlpage render --out <dir>` command. Each page is executed exactly like it would
+//! be when receiving an HTTP request, and the response body is written to the output directory,
+//! together with the builtin assets (javascript, css, icons) and the static files from the web root.
+//! Links between pages, like `href="report.sql?year=2024"`, are rewritten to point to the exported files.
+
+use super::http::create_app;
+use crate::utils::static_filename;
+use crate::xlsx_export::XLSX_CONTENT_TYPE;
+use crate::AppState;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Decompress, ServiceResponse};
+use actix_web::http::header;
+use actix_web::test::TestRequest;
+use actix_web::web;
+use anyhow::Context;
+use futures_util::StreamExt;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// Characters that are percent-encoded in the file names of the rewritten links
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Builtin assets referenced by the default templates
+const BUILTIN_ASSETS: &[&str] = &[
+    static_filename!("sqlpage.js"),
+    static_filename!("sqlpage.css"),
+    static_filename!("apexcharts.js"),
+    static_filename!("tomselect.js"),
+    static_filename!("tabler-icons.svg"),
+    static_filename!("favicon.svg"),
+];
+
+/// Renders the given pages (or all the .sql files in the web root if `pages` is empty) to `out_dir`.
+/// Pages are given as paths relative to the web root, with optional query parameters: `report.sql?year=2024`.
+pub async fn export_static_site(
+    app_state: AppState,
+    out_dir: &Path,
+    pages: &[String],
+) -> anyhow::Result<()> {
+    let config = app_state.config.clone();
+    let web_root = std::fs::canonicalize(&config.web_root)
+        .with_context(|| format!("Unable to open the web root {}", config.web_root.display()))?;
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Unable to create {}", out_dir.display()))?;
+    let out_dir = std::fs::canonicalize(out_dir)?;
+    let excluded = [
+        std::fs::canonicalize(&config.configuration_directory)?,
+        out_dir.clone(),
+    ];
+    let mut files = Vec::new();
+    list_files(&web_root, &excluded, &mut files)?;
+
+    let pages: Vec<String> = if pages.is_empty() {
+        files
+            .iter()
+            .filter(|f| f.extension().is_some_and(|e| e == "sql"))
+            .filter_map(|f| f.strip_prefix(&web_root).ok())
+            .map(|f| f.to_string_lossy().replace('\\', "/"))
+            .collect()
+    } else {
+        pages
+            .iter()
+            .map(|p| p.trim_start_matches('/').into())
+            .collect()
+    };
+
+    let app = actix_web::test::init_service(create_app(web::Data::new(app_state))).await;
+    let mut failures = 0;
+    let mut rendered = Vec::with_capacity(pages.len());
+    for page in &pages {
+        let url = format!("{}{page}", config.site_prefix);
+        let req = TestRequest::get().uri(&url).to_request();
+        let resp = actix_web::test::try_call_service(&app, req).await;
+        match read_response(&url, resp).await {
+            Ok((content_type, body)) => {
+                let output = page_output_path(page, &content_type);
+                rendered.push((page, output, content_type, body));
+            }
+            Err(e) => {
+                failures += 1;
+                log::error!("Unable to render {page}: {e:#}");
+            }
+        }
+    }
+    let links = PageLinks {
+        site_prefix: &config.site_prefix,
+        sql_files: files
+            .iter()
+            .filter(|f| f.extension().is_some_and(|e| e == "sql"))
+            .filter_map(|f| f.strip_prefix(&web_root).ok())
+            .map(|f| f.to_string_lossy().replace('\\', "/"))
+            .collect(),
+        exported: rendered
+            .iter()
+            .map(|(_, output, _, _)| (output.with_extension(""), output.clone()))
+            .collect(),
+    };
+    let mut missing = BTreeSet::new();
+    for (page, output, content_type, mut body) in rendered {
+        if content_type.starts_with("text/html") {
+            let html = String::from_utf8_lossy(&body);
+            body = links
+                .rewrite(page, &output, &html, &mut missing)
+                .into_bytes();
+        }
+        let path = out_dir.join(&output);
+        write_file(&path, &body)?;
+        log::info!("Rendered {page} to {}", path.display());
+    }
+    for page in missing {
+        log::warn!("Some pages link to {page}, which was not exported. Add it to the list of pages to render.");
+    }
+    for asset in BUILTIN_ASSETS {
+        let url = format!("{}{asset}", config.site_prefix);
+        let req = TestRequest::get().uri(&url).to_request();
+        let resp = actix_web::test::try_call_service(&app, req).await;
+        let (_, body) = read_response(&url, resp).await?;
+        write_file(&out_dir.join(asset), &body)?;
+    }
+    for file in files
+        .iter()
+        .filter(|f| f.extension().is_none_or(|e| e != "sql"))
+    {
+        let relative = file.strip_prefix(&web_root)?;
+        let target = out_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(file, &target)
+            .with_context(|| format!("Unable to copy {}", file.display()))?;
+    }
+    anyhow::ensure!(
+        failures == 0,
+        "{failures} of the {} pages could not be rendered",
+        pages.len()
+    );
+    log::info!("Exported {} pages to {}", pages.len(), out_dir.display());
+    Ok(())
+}
+
+/// Returns the content type and decompressed body of a response
+async fn read_response<B>(
+    url: &str,
+    resp: actix_web::Result<ServiceResponse<B>>,
+) -> anyhow::Result<(String, Vec<u8>)>
+where
+    B: MessageBody,
+    B::Error: std::fmt::Display,
+{
+    let resp = resp.map_err(|e| anyhow::anyhow!("{e}"))?;
+    let status = resp.status();
+    anyhow::ensure!(status.is_success(), "{url} returned HTTP status {status}");
+    let content_type = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let headers = resp.headers().clone();
+    let body = actix_web::body::to_bytes(resp.into_body())
+        .await
+        .map_err(|e| anyhow::anyhow!("Unable to read the response body of {url}: {e}"))?;
+    let payload = futures_util::stream::iter([Ok(body)]);
+    let mut decompressed_chunks = Decompress::from_headers(payload, &headers);
+    let mut contents = Vec::new();
+    while let Some(chunk) = decompressed_chunks.next().await {
+        contents.extend_from_slice(&chunk.with_context(|| format!("Unable to decode {url}"))?);
+    }
+    Ok((content_type, contents))
+}
+
+/// `reports/sales.sql?year=2024` is written to `reports/sales_year=2024.html`
+fn page_output_path(page: &str, content_type: &str) -> PathBuf {
+    let (path, query) = page.split_once('?').unwrap_or((page, ""));
+    let query = percent_decode_str(query).decode_utf8_lossy();
+    let stem = path.strip_suffix(".sql").unwrap_or(path);
+    let extension = if content_type.starts_with("text/csv") {
+        "csv"
+    } else if content_type.starts_with("application/json") {
+        "json"
+    } else if content_type == XLSX_CONTENT_TYPE {
+        "xlsx"
+    } else {
+        "html"
+    };
+    let query: String = query
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_=".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if query.is_empty() {
+        PathBuf::from(format!("{stem}.{extension}"))
+    } else {
+        PathBuf::from(format!("{stem}_{query}.{extension}"))
+    }
+}
+
+/// Rewrites the links between the exported pages
+struct PageLinks<'a> {
+    site_prefix: &'a str,
+    /// The `.sql` files of the web root, relative to it
+    sql_files: HashSet<String>,
+    /// The files the pages were written to, indexed by their path without extension
+    exported: HashMap<PathBuf, PathBuf>,
+}
+
+impl PageLinks<'_> {
+    /// Replaces the `href` and `src` attributes of `html` that point to a page of the site
+    /// with the relative path of the file it is exported to.
+    /// The pages that are linked to, but not exported, are added to `missing`.
+    fn rewrite(
+        &self,
+        page: &str,
+        output: &Path,
+        html: &str,
+        missing: &mut BTreeSet<String>,
+    ) -> String {
+        let mut result = String::with_capacity(html.len());
+        let mut rest = html;
+        while let Some(value_start) = ["href=\"", "src=\""]
+            .iter()
+            .filter_map(|attribute| find_attribute(rest, attribute))
+            .min()
+        {
+            let Some(value_len) = rest[value_start..].find('"') else {
+                break;
+            };
+            let value = &rest[value_start..value_start + value_len];
+            result.push_str(&rest[..value_start]);
+            match self.link_target(page, &unescape_attribute(value)) {
+                Some((target, fragment)) => {
+                    let html_output = page_output_path(&target, "text/html");
+                    let target_output = self
+                        .exported
+                        .get(&html_output.with_extension(""))
+                        .unwrap_or_else(|| {
+                            missing.insert(target);
+                            &html_output
+                        });
+                    let link = relative_link(output, target_output) + &fragment;
+                    result.push_str(&escape_attribute(&link));
+                }
+                None => result.push_str(value),
+            }
+            rest = &rest[value_start + value_len..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// The page of the site that a link found in `page` points to, with its query string,
+    /// followed by the fragment of the link. Returns None for links to other sites and to static files.
+    fn link_target(&self, page: &str, link: &str) -> Option<(String, String)> {
+        if link.is_empty() || link.starts_with('#') {
+            return None;
+        }
+        let base =
+            url::Url::parse(&format!("http://export.invalid{}{page}", self.site_prefix)).ok()?;
+        let target = base.join(link).ok()?;
+        if target.origin() != base.origin() {
+            return None;
+        }
+        let path = percent_decode_str(target.path()).decode_utf8().ok()?;
+        let path = path.strip_prefix(self.site_prefix)?;
+        let path = if path.is_empty() || path.ends_with('/') {
+            format!("{path}index.sql")
+        } else if Path::new(path).extension().is_some_and(|e| e == "sql") {
+            path.to_string()
+        } else {
+            format!("{path}.sql")
+        };
+        if !self.sql_files.contains(&path) {
+            return None;
+        }
+        let page = match target.query() {
+            Some(query) if !query.is_empty() => format!("{path}?{query}"),
+            _ => path,
+        };
+        let fragment = target
+            .fragment()
+            .map(|f| format!("#{f}"))
+            .unwrap_or_default();
+        Some((page, fragment))
+    }
+}
+
+/// The position of the value of the next `attribute` (like `href="`) in the html
+fn find_attribute(html: &str, attribute: &str) -> Option<usize> {
+    html.match_indices(attribute)
+        .find(|(i, _)| html[..*i].ends_with(char::is_whitespace))
+        .map(|(i, _)| i + attribute.len())
+}
+
+/// Decodes the characters escaped by handlebars in attribute values
+fn unescape_attribute(value: &str) -> String {
+    value
+        .replace("&#x3D;", "=")
+        .replace("&#x27;", "'")
+        .replace("&#x60;", "`")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The path of `to` relative to the directory of `from`, both relative to the output directory
+fn relative_link(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<Component> = from
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from_dir
+        .iter()
+        .zip(&to)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to.len().saturating_sub(1));
+    let mut link = "../".repeat(from_dir.len() - common);
+    let segments: Vec<String> = to[common..]
+        .iter()
+        .map(|c| utf8_percent_encode(&c.as_os_str().to_string_lossy(), PATH_SEGMENT).to_string())
+        .collect();
+    link.push_str(&segments.join("/"));
+    link
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents).with_context(|| format!("Unable to write {}", path.display()))
+}
+
+/// Recursively lists the files in `dir`, ignoring hidden files and the `excluded` directories
+pub(super) fn list_files(
+    dir: &Path,
+    excluded: &[PathBuf],
+    files: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Unable to list {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            || excluded.iter().any(|e| path.starts_with(e))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            list_files(&path, excluded, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_page_output_path() {
+    assert_eq!(
+        page_output_path("index.sql", "text/html; charset=utf-8"),
+        PathBuf::from("index.html")
+    );
+    assert_eq!(
+        page_output_path(
+            "reports/sales.sql?year=2024&q=a b",
+            "text/csv; charset=utf-8"
+        ),
+        PathBuf::from("reports/sales_year=2024_q=a_b.csv")
+    );
+}
+
+#[test]
+fn test_rewrite_links() {
+    let links = PageLinks {
+        site_prefix: "/",
+        sql_files: ["index.sql", "reports/sales.sql", "reports/index.sql"]
+            .map(String::from)
+            .into(),
+        exported: [
+            ("index", "index.html"),
+            ("reports/sales_year=2024", "reports/sales_year=2024.html"),
+            ("reports/sales_format=csv", "reports/sales_format=csv.csv"),
+        ]
+        .map(|(stem, output)| (PathBuf::from(stem), PathBuf::from(output)))
+        .into(),
+    };
+    let mut missing = BTreeSet::new();
+    let html = links.rewrite(
+        "reports/sales.sql?year=2024",
+        Path::new("reports/sales_year=2024.html"),
+        r##"<a href="/">Home</a> <a href="?format&#x3D;csv">CSV</a>
+        <a href="sales.sql?year&#x3D;2025#top">Next</a> <a href="./">Reports</a>
+        <img src="logo.png"> <a href="https://example.com/index.sql">Out</a> <a href="#top">Top</a>"##,
+        &mut missing,
+    );
+    assert_eq!(
+        html,
+        r##"<a href="../index.html">Home</a> <a href="sales_format=csv.csv">CSV</a>
+        <a href="sales_year=2025.html#top">Next</a> <a href="index.html">Reports</a>
+        <img src="logo.png"> <a href="https://example.com/index.sql">Out</a> <a href="#top">Top</a>"##
+    );
+    assert_eq!(
+        missing.into_iter().collect::<Vec<_>>(),
+        ["reports/index.sql", "reports/sales.sql?year=2025"]
+    );
+}
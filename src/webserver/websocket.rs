@@ -0,0 +1,314 @@
+//! WebSocket connections to SQL files.
+//!
+//! When a browser opens a WebSocket to a `.sql` file, each text message it sends executes the file again.
+//! A message that is a JSON object binds its keys as `:variables`, like the fields of a submitted form.
+//! Other messages are bound as `:message`. URL parameters stay available as `$variables`.
+//! Each row returned by the file is sent back to the browser as a JSON text message.
+//!
+//! The database connection is returned to the pool between messages,
+//! so that open `WebSocket`s do not hold database connections while they wait for messages.
+
+use super::database::execute_queries::{
+    stop_at_first_error, stop_at_timeout, stream_query_results_with_conn, DbConn,
+};
+use super::database::{locks, DbItem};
+use super::http::SingleOrVec;
+use super::http_request_info::{extract_request_info, RequestInfo};
+use super::request_variables::ParamMap;
+use crate::{AppState, ParsedSqlFile};
+use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Item, Message};
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError};
+use actix_web::http::header;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{HttpMessage, HttpResponse};
+use base64::Engine;
+use futures_util::StreamExt;
+use serde_json::{json, Value as JsonValue};
+use sha1::{Digest, Sha1};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Defined in RFC 6455, to compute the `Sec-WebSocket-Accept` header
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Whether the browser asks to open a WebSocket, instead of loading a page
+pub(crate) fn is_websocket_upgrade(req: &ServiceRequest) -> bool {
+    req.method() == actix_web::http::Method::GET
+        && req
+            .headers()
+            .get(header::UPGRADE)
+            .and_then(|upgrade| upgrade.to_str().ok())
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+/// Accepts the WebSocket, and executes the SQL file for each message received on it
+pub(crate) async fn serve_websocket(
+    srv_req: &mut ServiceRequest,
+    sql_file: Arc<ParsedSqlFile>,
+) -> actix_web::Result<HttpResponse> {
+    let accept_key = accept_key(srv_req)?;
+    let app_state = srv_req
+        .app_data::<actix_web::web::Data<AppState>>()
+        .ok_or_else(|| ErrorInternalServerError("no state"))?
+        .clone()
+        .into_inner();
+    // The payload contains the messages of the WebSocket, it is not a request body
+    let payload = srv_req.take_payload();
+    let request = extract_request_info(srv_req, Arc::clone(&app_state))
+        .await
+        .map_err(|e| ErrorBadRequest(format!("{e:#}")))?;
+    log::debug!("Opening a WebSocket to {}", sql_file.source_path.display());
+
+    let (frames_send, frames_recv) = mpsc::channel::<Bytes>(16);
+    actix_web::rt::spawn(handle_messages(
+        sql_file,
+        request,
+        app_state,
+        payload,
+        frames_send,
+    ));
+    let frames =
+        tokio_stream::wrappers::ReceiverStream::new(frames_recv).map(Ok::<_, actix_web::Error>);
+    Ok(HttpResponse::SwitchingProtocols()
+        .upgrade("websocket")
+        .insert_header((header::SEC_WEBSOCKET_ACCEPT, accept_key))
+        .streaming(frames))
+}
+
+/// Checks the handshake of the WebSocket, and returns the value of its `Sec-WebSocket-Accept` header
+fn accept_key(req: &ServiceRequest) -> actix_web::Result<String> {
+    let headers = req.headers();
+    if headers
+        .get(header::SEC_WEBSOCKET_VERSION)
+        .is_none_or(|version| version != "13")
+    {
+        return Err(ErrorBadRequest("Unsupported WebSocket version"));
+    }
+    let key = headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .ok_or_else(|| ErrorBadRequest("Missing Sec-WebSocket-Key header"))?;
+    // Browsers let any website open a WebSocket to any server, with the cookies of the user,
+    // and always tell which website opened it
+    let origin = headers.get(header::ORIGIN);
+    let origin_host = origin
+        .and_then(|origin| origin.to_str().ok())
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_scheme, host)| host);
+    if origin_host != Some(req.connection_info().host()) {
+        log::warn!("Refusing a WebSocket opened from another website: {origin:?}");
+        return Err(ErrorForbidden(
+            "WebSockets can only be opened from the pages of the same website",
+        ));
+    }
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    Ok(base64::engine::general_purpose::STANDARD.encode(hasher.finalize()))
+}
+
+async fn handle_messages(
+    sql_file: Arc<ParsedSqlFile>,
+    request: RequestInfo,
+    app_state: Arc<AppState>,
+    mut payload: Payload,
+    frames: mpsc::Sender<Bytes>,
+) {
+    let mut decoder = Codec::new();
+    let mut sender = FrameSender {
+        codec: Codec::new(),
+        frames,
+    };
+    let mut received = BytesMut::new();
+    // The beginning of a text message sent in several frames
+    let mut fragments: Option<BytesMut> = None;
+    let max_message_size = app_state.config.max_uploaded_file_size;
+    while let Some(chunk) = payload.next().await {
+        let Ok(chunk) = chunk else {
+            log::debug!("The WebSocket connection was interrupted");
+            return;
+        };
+        received.extend_from_slice(&chunk);
+        loop {
+            let frame = match decoder.decode(&mut received) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) => {
+                    log::debug!("Invalid WebSocket frame: {e}");
+                    sender.close(CloseCode::Protocol).await;
+                    return;
+                }
+            };
+            let text = match frame {
+                Frame::Text(text) => Some(text),
+                Frame::Continuation(item) => match (item, fragments.as_mut()) {
+                    (Item::FirstText(first), None) => {
+                        fragments = Some(BytesMut::from(&first[..]));
+                        None
+                    }
+                    (Item::Continue(next), Some(message)) => {
+                        message.extend_from_slice(&next);
+                        None
+                    }
+                    (Item::Last(last), Some(message)) => {
+                        message.extend_from_slice(&last);
+                        fragments.take().map(BytesMut::freeze)
+                    }
+                    (Item::FirstBinary(_), None) => {
+                        sender.close(CloseCode::Unsupported).await;
+                        return;
+                    }
+                    _ => {
+                        log::debug!("Invalid sequence of WebSocket message fragments");
+                        sender.close(CloseCode::Protocol).await;
+                        return;
+                    }
+                },
+                Frame::Ping(data) => {
+                    if !sender.send(Message::Pong(data)).await {
+                        return;
+                    }
+                    None
+                }
+                Frame::Pong(_) => None,
+                Frame::Binary(_) => {
+                    sender.close(CloseCode::Unsupported).await;
+                    return;
+                }
+                Frame::Close(reason) => {
+                    sender.send(Message::Close(reason)).await;
+                    return;
+                }
+            };
+            if fragments
+                .as_ref()
+                .is_some_and(|m| m.len() > max_message_size)
+            {
+                sender.close(CloseCode::Size).await;
+                return;
+            }
+            let keep_open = match text {
+                Some(text) => {
+                    let text = String::from_utf8_lossy(&text);
+                    let mut message_request = request.clone();
+                    message_request.post_variables = message_variables(&text);
+                    execute_message(&sql_file, message_request, &app_state, &mut sender).await
+                }
+                None => true,
+            };
+            if !keep_open {
+                return;
+            }
+        }
+    }
+}
+
+/// Executes the SQL file for one message, and sends the rows it returns.
+/// Returns false when the browser closed the connection.
+async fn execute_message(
+    sql_file: &ParsedSqlFile,
+    mut request: RequestInfo,
+    app_state: &AppState,
+    sender: &mut FrameSender,
+) -> bool {
+    let started_at = std::time::Instant::now();
+    let mut conn: DbConn = None;
+    let mut is_error = false;
+    let mut is_open = true;
+    {
+        let results = stream_query_results_with_conn(sql_file, &mut request, &mut conn);
+        let results = match app_state.config.request_timeout_seconds {
+            Some(timeout) => {
+                stop_at_timeout(results, std::time::Duration::from_secs_f64(timeout)).boxed_local()
+            }
+            None => results.boxed_local(),
+        };
+        let mut results = std::pin::pin!(stop_at_first_error(results));
+        while let Some(item) = results.next().await {
+            let row = match item {
+                DbItem::Row(row) => row,
+                DbItem::Error(e) => {
+                    log::error!("Error in WebSocket message handler: {e:?}");
+                    is_error = true;
+                    json!({ "error": e.to_string() })
+                }
+                DbItem::FinishedQuery | DbItem::ColumnTypes(_) => continue,
+            };
+            if !sender.send(Message::Text(row.to_string().into())).await {
+                is_open = false;
+                break;
+            }
+        }
+    }
+    locks::release_all(&request, &mut conn).await;
+    app_state
+        .statistics
+        .record_page(&sql_file.source_path, started_at.elapsed(), is_error);
+    is_open
+}
+
+/// The variables bound by a message: the keys of a JSON object, or the whole message as `:message`
+fn message_variables(text: &str) -> ParamMap {
+    let Ok(JsonValue::Object(object)) = serde_json::from_str(text) else {
+        return ParamMap::from([("message".into(), SingleOrVec::Single(text.into()))]);
+    };
+    object
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                JsonValue::Null => return None,
+                JsonValue::String(s) => SingleOrVec::Single(s),
+                JsonValue::Array(values) => {
+                    SingleOrVec::Vec(values.into_iter().map(json_to_text).collect())
+                }
+                other => SingleOrVec::Single(json_to_text(other)),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn json_to_text(value: JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+struct FrameSender {
+    codec: Codec,
+    frames: mpsc::Sender<Bytes>,
+}
+
+impl FrameSender {
+    /// Returns false if the browser closed the connection
+    async fn send(&mut self, message: Message) -> bool {
+        let mut encoded = BytesMut::new();
+        if let Err(e) = self.codec.encode(message, &mut encoded) {
+            log::error!("Unable to encode a WebSocket message: {e}");
+            return false;
+        }
+        self.frames.send(encoded.freeze()).await.is_ok()
+    }
+
+    async fn close(&mut self, code: CloseCode) {
+        let reason = CloseReason {
+            code,
+            description: None,
+        };
+        self.send(Message::Close(Some(reason))).await;
+    }
+}
+
+#[test]
+fn test_message_variables() {
+    let variables =
+        message_variables(r#"{"name": "Ada", "age": 36, "tags": ["a", "b"], "x": null}"#);
+    assert_eq!(variables.len(), 3);
+    assert!(matches!(&variables["name"], SingleOrVec::Single(s) if s == "Ada"));
+    assert!(matches!(&variables["age"], SingleOrVec::Single(s) if s == "36"));
+    assert!(matches!(&variables["tags"], SingleOrVec::Vec(v) if v == &["a", "b"]));
+    let variables = message_variables("hello");
+    assert!(matches!(&variables["message"], SingleOrVec::Single(s) if s == "hello"));
+}
@@ -0,0 +1,377 @@
+//! Excel spreadsheets created by the `xlsx` component from the rows of the following queries.
+//!
+//! An xlsx file is a zip archive of XML files. The small files that describe the workbook are written first,
+//! then the worksheet is compressed and sent to the browser while the rows are received,
+//! so that large exports are not held in memory.
+//! Number and date formats can be applied to columns with the `column_formats` property.
+
+use crate::webserver::response_writer::ResponseWriter;
+use anyhow::{bail, Context};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+pub const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+const DEFAULT_SHEET_NAME: &str = "Sheet1";
+const MAX_SHEET_NAME_LEN: usize = 31;
+/// Excel cannot open sheets with more rows, including the header
+const MAX_ROWS: usize = 1_048_576;
+/// Longer texts are truncated, because Excel refuses to open files with longer cells
+const MAX_CELL_LEN: usize = 32_767;
+/// Compressed bytes are moved to the response once there are enough of them
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Custom number formats are numbered after the ones built into Excel
+const FIRST_CUSTOM_FORMAT_ID: usize = 164;
+const HEADER_STYLE: usize = 1;
+
+const MAIN_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const RELATIONSHIPS_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+/// Receives the compressed archive, until it is moved to the response
+#[derive(Clone, Default)]
+pub(crate) struct CompressedBytes(Arc<Mutex<Vec<u8>>>);
+
+impl CompressedBytes {
+    pub(crate) fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().expect("no panic while writing"))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().expect("no panic while writing").len()
+    }
+}
+
+impl Write for CompressedBytes {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .expect("no panic while writing")
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct XlsxBodyRenderer {
+    writer: ResponseWriter,
+    // The zip writer is a large struct, so we store it on the heap
+    zip: Box<ZipWriter<StreamWriter<CompressedBytes>>>,
+    compressed: CompressedBytes,
+    /// Style of the cells of each column that has a format
+    column_styles: HashMap<String, usize>,
+    columns: Vec<String>,
+    row_count: usize,
+}
+
+impl XlsxBodyRenderer {
+    pub fn new(writer: ResponseWriter, options: &JsonValue) -> anyhow::Result<Self> {
+        let sheet_name = sheet_name(options)?;
+        let column_formats = column_formats(options)?;
+        let mut formats: Vec<&str> = Vec::new();
+        let mut column_styles = HashMap::new();
+        for (column, format) in &column_formats {
+            let position = formats.iter().position(|f| f == format).unwrap_or_else(|| {
+                formats.push(format);
+                formats.len() - 1
+            });
+            column_styles.insert(column.clone(), HEADER_STYLE + 1 + position);
+        }
+
+        let compressed = CompressedBytes::default();
+        let mut zip = Box::new(ZipWriter::new_stream(compressed.clone()));
+        let files = [
+            ("[Content_Types].xml", content_types()),
+            ("_rels/.rels", root_relationships()),
+            ("xl/workbook.xml", workbook(&sheet_name)),
+            ("xl/_rels/workbook.xml.rels", workbook_relationships()),
+            ("xl/styles.xml", styles(&formats)),
+        ];
+        for (path, contents) in files {
+            zip.start_file(path, file_options())?;
+            zip.write_all(contents.as_bytes())?;
+        }
+        zip.start_file("xl/worksheets/sheet1.xml", file_options())?;
+        write!(
+            zip,
+            r#"{XML_DECLARATION}<worksheet xmlns="{MAIN_NAMESPACE}"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#
+        )?;
+        Ok(Self {
+            writer,
+            zip,
+            compressed,
+            column_styles,
+            columns: Vec::new(),
+            row_count: 0,
+        })
+    }
+
+    pub fn handle_row(&mut self, data: &JsonValue) -> anyhow::Result<()> {
+        let Some(obj) = data.as_object() else {
+            return Ok(());
+        };
+        if self.columns.is_empty() {
+            self.columns = obj.keys().map(String::to_owned).collect();
+            let headers: Vec<JsonValue> = self.columns.iter().map(|c| c.as_str().into()).collect();
+            self.write_row(headers.iter().map(|header| (header, Some(HEADER_STYLE))))?;
+        }
+        let cells: Vec<(&JsonValue, Option<usize>)> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let value = obj.get(column).unwrap_or(&JsonValue::Null);
+                (value, self.column_styles.get(column).copied())
+            })
+            .collect();
+        self.write_row(cells.into_iter())
+    }
+
+    pub fn handle_error(&mut self, error: &anyhow::Error) -> anyhow::Result<()> {
+        let message = JsonValue::String(error.to_string());
+        self.write_row(std::iter::once((&message, None)))
+    }
+
+    fn write_row<'a>(
+        &mut self,
+        cells: impl Iterator<Item = (&'a JsonValue, Option<usize>)>,
+    ) -> anyhow::Result<()> {
+        if self.row_count >= MAX_ROWS {
+            bail!("Excel spreadsheets cannot contain more than {MAX_ROWS} rows. Export the data in several files, or as csv.");
+        }
+        self.row_count += 1;
+        let row_number = self.row_count;
+        let mut xml = format!(r#"<row r="{row_number}">"#);
+        for (index, (value, style)) in cells.enumerate() {
+            write_cell(&mut xml, &cell_reference(index, row_number), value, style);
+        }
+        xml.push_str("</row>");
+        self.zip.write_all(xml.as_bytes())?;
+        if self.compressed.len() >= CHUNK_SIZE {
+            self.writer.write_all(&self.compressed.take())?;
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer.write_all(&self.compressed.take())?;
+        self.writer.async_flush().await?;
+        Ok(())
+    }
+
+    pub fn close(mut self) -> ResponseWriter {
+        let mut zip = *self.zip;
+        let finished = zip
+            .write_all(b"</sheetData></worksheet>")
+            .map_err(anyhow::Error::from)
+            .and_then(|()| Ok(zip.finish()?));
+        if let Err(e) = finished {
+            log::error!("Unable to finish the xlsx file: {e:#}");
+        }
+        if let Err(e) = self.writer.write_all(&self.compressed.take()) {
+            log::error!("Unable to send the end of the xlsx file: {e}");
+        }
+        self.writer
+    }
+}
+
+fn file_options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+fn sheet_name(options: &JsonValue) -> anyhow::Result<String> {
+    let Some(name) = options.get("sheet_name").and_then(JsonValue::as_str) else {
+        return Ok(DEFAULT_SHEET_NAME.to_string());
+    };
+    if name.is_empty()
+        || name.chars().count() > MAX_SHEET_NAME_LEN
+        || name.contains(['[', ']', ':', '*', '?', '/', '\\'])
+        || name.starts_with('\'')
+        || name.ends_with('\'')
+    {
+        bail!("Invalid sheet name: {name:?}. Excel sheet names must have between 1 and {MAX_SHEET_NAME_LEN} characters, and cannot contain [ ] : * ? / \\");
+    }
+    Ok(name.to_string())
+}
+
+/// The number format of each column, from an object like `{"price": "#,##0.00", "created_at": "yyyy-mm-dd"}`
+fn column_formats(options: &JsonValue) -> anyhow::Result<Vec<(String, String)>> {
+    let formats: Map<String, JsonValue> = match options.get("column_formats") {
+        None | Some(JsonValue::Null) => return Ok(Vec::new()),
+        Some(JsonValue::Object(formats)) => formats.clone(),
+        Some(JsonValue::String(formats)) => serde_json::from_str(formats)
+            .with_context(|| format!("Invalid column_formats: {formats}"))?,
+        Some(other) => bail!(
+            "Invalid column_formats: {other}. Expected an object like {{\"price\": \"#,##0.00\"}}"
+        ),
+    };
+    formats
+        .into_iter()
+        .map(|(column, format)| match format {
+            JsonValue::String(format) => Ok((column, format)),
+            other => bail!("Invalid format for the {column} column: {other}. Expected a text like \"#,##0.00\" or \"yyyy-mm-dd\""),
+        })
+        .collect()
+}
+
+fn write_cell(xml: &mut String, reference: &str, value: &JsonValue, style: Option<usize>) {
+    let is_formatted = style.is_some_and(|s| s > HEADER_STYLE);
+    let style = style.map(|s| format!(r#" s="{s}""#)).unwrap_or_default();
+    match value {
+        JsonValue::Null => {}
+        JsonValue::Bool(b) => {
+            let _ = write!(
+                xml,
+                r#"<c r="{reference}"{style} t="b"><v>{}</v></c>"#,
+                u8::from(*b)
+            );
+        }
+        JsonValue::Number(n) => {
+            let _ = write!(xml, r#"<c r="{reference}"{style}><v>{n}</v></c>"#);
+        }
+        // Texts in formatted columns are converted to numbers, so that the format applies to them
+        JsonValue::String(s) if is_formatted && as_number(s).is_some() => {
+            let n = as_number(s).unwrap_or_default();
+            let _ = write!(xml, r#"<c r="{reference}"{style}><v>{n}</v></c>"#);
+        }
+        other => {
+            let text = match other {
+                JsonValue::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            let _ = write!(
+                xml,
+                r#"<c r="{reference}"{style} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                escape_xml(&text)
+            );
+        }
+    }
+}
+
+/// A number, or a date and time as the number of days since 1899-12-30, the way Excel stores them
+fn as_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if let Ok(n) = s.parse::<f64>() {
+        return Some(n).filter(|n| n.is_finite());
+    }
+    let date_time = if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        date.and_hms_opt(0, 0, 0)?
+    } else if let Ok(date_time) = DateTime::parse_from_rfc3339(s) {
+        date_time.naive_local()
+    } else {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+            .ok()?
+    };
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let milliseconds = (date_time - epoch).num_milliseconds();
+    #[allow(clippy::cast_precision_loss)]
+    Some(milliseconds as f64 / 86_400_000.0)
+}
+
+/// Like `C12`
+fn cell_reference(column_index: usize, row_number: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = column_index + 1;
+    while n > 0 {
+        let remainder = u8::try_from((n - 1) % 26).expect("less than 26");
+        letters.push(char::from(b'A' + remainder));
+        n = (n - 1) / 26;
+    }
+    letters.iter().rev().collect::<String>() + &row_number.to_string()
+}
+
+/// Escapes the special characters of XML, and removes the control characters it does not allow
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars().take(MAX_CELL_LEN) {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn content_types() -> String {
+    format!(
+        r#"{XML_DECLARATION}<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#
+    )
+}
+
+fn root_relationships() -> String {
+    format!(
+        r#"{XML_DECLARATION}<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="{RELATIONSHIPS_NAMESPACE}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#
+    )
+}
+
+fn workbook(sheet_name: &str) -> String {
+    format!(
+        r#"{XML_DECLARATION}<workbook xmlns="{MAIN_NAMESPACE}" xmlns:r="{RELATIONSHIPS_NAMESPACE}"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        escape_xml(sheet_name)
+    )
+}
+
+fn workbook_relationships() -> String {
+    format!(
+        r#"{XML_DECLARATION}<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="{RELATIONSHIPS_NAMESPACE}/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="{RELATIONSHIPS_NAMESPACE}/styles" Target="styles.xml"/></Relationships>"#
+    )
+}
+
+/// The default style, a bold style for the header, and one style for each number format
+fn styles(formats: &[&str]) -> String {
+    let mut number_formats = String::new();
+    let mut cell_styles = String::new();
+    for (index, format) in formats.iter().enumerate() {
+        let id = FIRST_CUSTOM_FORMAT_ID + index;
+        let _ = write!(
+            number_formats,
+            r#"<numFmt numFmtId="{id}" formatCode="{}"/>"#,
+            escape_xml(format)
+        );
+        let _ = write!(
+            cell_styles,
+            r#"<xf numFmtId="{id}" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>"#
+        );
+    }
+    let number_formats = if formats.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"<numFmts count="{}">{number_formats}</numFmts>"#,
+            formats.len()
+        )
+    };
+    format!(
+        r#"{XML_DECLARATION}<styleSheet xmlns="{MAIN_NAMESPACE}">{number_formats}<fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="{}"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/>{cell_styles}</cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#,
+        HEADER_STYLE + 1 + formats.len()
+    )
+}
+
+#[test]
+fn test_cell_values() {
+    assert_eq!(cell_reference(0, 1), "A1");
+    assert_eq!(cell_reference(25, 2), "Z2");
+    assert_eq!(cell_reference(26, 3), "AA3");
+    assert_eq!(cell_reference(702, 4), "AAA4");
+    assert_eq!(as_number("12.5"), Some(12.5));
+    assert_eq!(as_number("2024-03-01"), Some(45_352.0));
+    assert_eq!(as_number("2024-03-01 12:00:00"), Some(45_352.5));
+    assert_eq!(as_number("2024-03-01T18:00:00+02:00"), Some(45_352.75));
+    assert_eq!(as_number("not a number"), None);
+    assert_eq!(escape_xml("a < b & \u{1}c"), "a &lt; b &amp; c");
+}
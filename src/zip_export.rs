@@ -0,0 +1,236 @@
+//! Zip archives created by the `zip` component, to download several files at once.
+//!
+//! Each row with a `name` and `contents` adds a file to the archive, typically taken from a binary column.
+//! A row with `'csv' as component` starts a csv file, that contains the rows that follow it,
+//! until the next row with a `component`. The archive is compressed and sent to the browser
+//! while the rows are received, so that large archives are not held in memory.
+
+use crate::render::decode_data_url;
+use crate::webserver::response_writer::ResponseWriter;
+use crate::xlsx_export::CompressedBytes;
+use anyhow::{bail, Context};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashSet;
+use std::io::Write;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+/// Compressed bytes are moved to the response once there are enough of them
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct ZipBodyRenderer {
+    writer: ResponseWriter,
+    // The zip writer is a large struct, so we store it on the heap
+    zip: Box<ZipWriter<StreamWriter<CompressedBytes>>>,
+    compressed: CompressedBytes,
+    /// Names of the files already in the archive
+    names: HashSet<String>,
+    /// The csv file being written, if the last component was `csv`
+    csv: Option<CsvEntry>,
+    error_count: usize,
+}
+
+struct CsvEntry {
+    separator: char,
+    columns: Vec<String>,
+}
+
+impl ZipBodyRenderer {
+    #[must_use]
+    pub fn new(writer: ResponseWriter) -> Self {
+        let compressed = CompressedBytes::default();
+        let zip = Box::new(ZipWriter::new_stream(compressed.clone()));
+        Self {
+            writer,
+            zip,
+            compressed,
+            names: HashSet::new(),
+            csv: None,
+            error_count: 0,
+        }
+    }
+
+    pub fn handle_row(&mut self, data: &JsonValue) -> anyhow::Result<()> {
+        let Some(obj) = data.as_object() else {
+            return Ok(());
+        };
+        match obj.get("component").and_then(JsonValue::as_str) {
+            Some("csv") => self.start_csv(obj)?,
+            Some("files") => self.csv = None,
+            Some(other) => bail!(
+                "Invalid component inside a zip archive: {other:?}. Use 'csv' to add a csv file, or 'files' to add files with a name and contents."
+            ),
+            None if self.csv.is_some() => self.write_csv_row(obj)?,
+            None => self.write_file(obj)?,
+        }
+        self.send_chunk()
+    }
+
+    pub fn handle_error(&mut self, error: &anyhow::Error) -> anyhow::Result<()> {
+        self.csv = None;
+        self.error_count += 1;
+        let name = format!("error-{}.txt", self.error_count);
+        self.start_file(&name)?;
+        self.zip.write_all(format!("{error:#}").as_bytes())?;
+        self.send_chunk()
+    }
+
+    fn write_file(&mut self, obj: &Map<String, JsonValue>) -> anyhow::Result<()> {
+        let name = obj
+            .get("name")
+            .and_then(JsonValue::as_str)
+            .with_context(|| "Each file in a zip archive needs a 'name'")?;
+        let contents = match obj.get("contents") {
+            Some(JsonValue::String(s)) => match s.strip_prefix("data:") {
+                Some(data_url) => decode_data_url(data_url)?.1,
+                None => s.as_bytes().to_vec(),
+            },
+            None | Some(JsonValue::Null) => {
+                bail!("The file {name:?} of the zip archive has no 'contents'")
+            }
+            Some(other) => other.to_string().into_bytes(),
+        };
+        self.start_file(name)?;
+        self.zip.write_all(&contents)?;
+        Ok(())
+    }
+
+    fn start_csv(&mut self, obj: &Map<String, JsonValue>) -> anyhow::Result<()> {
+        let filename = obj
+            .get("filename")
+            .or_else(|| obj.get("title"))
+            .and_then(JsonValue::as_str)
+            .unwrap_or("export");
+        let extension = if filename.contains('.') { "" } else { ".csv" };
+        let separator = match obj.get("separator").and_then(JsonValue::as_str) {
+            None => ',',
+            Some(separator) => {
+                let mut chars = separator.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => bail!(
+                        "Invalid csv separator: {separator:?}. It must be a single character."
+                    ),
+                }
+            }
+        };
+        self.start_file(&format!("{filename}{extension}"))?;
+        self.csv = Some(CsvEntry {
+            separator,
+            columns: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn write_csv_row(&mut self, obj: &Map<String, JsonValue>) -> anyhow::Result<()> {
+        let csv = self.csv.as_mut().expect("called when a csv file is open");
+        let mut line = String::new();
+        if csv.columns.is_empty() {
+            csv.columns = obj.keys().map(String::to_owned).collect();
+            let headers = csv.columns.iter().map(String::as_str);
+            csv_record(&mut line, csv.separator, headers);
+        }
+        let values: Vec<String> = csv
+            .columns
+            .iter()
+            .map(|column| match obj.get(column) {
+                None | Some(JsonValue::Null) => String::new(),
+                Some(JsonValue::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+            })
+            .collect();
+        csv_record(&mut line, csv.separator, values.iter().map(String::as_str));
+        self.zip.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn start_file(&mut self, name: &str) -> anyhow::Result<()> {
+        let name = unique_name(&mut self.names, name);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip.start_file(name, options)?;
+        Ok(())
+    }
+
+    fn send_chunk(&mut self) -> anyhow::Result<()> {
+        if self.compressed.len() >= CHUNK_SIZE {
+            self.writer.write_all(&self.compressed.take())?;
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer.write_all(&self.compressed.take())?;
+        self.writer.async_flush().await?;
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn close(mut self) -> ResponseWriter {
+        let zip = *self.zip;
+        if let Err(e) = zip.finish() {
+            log::error!("Unable to finish the zip archive: {e}");
+        }
+        if let Err(e) = self.writer.write_all(&self.compressed.take()) {
+            log::error!("Unable to send the end of the zip archive: {e}");
+        }
+        self.writer
+    }
+}
+
+/// A relative path inside the archive, that is not already used by another file.
+/// Files with the same name are numbered, like `report (2).pdf`.
+fn unique_name(names: &mut HashSet<String>, name: &str) -> String {
+    let path: Vec<&str> = name
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .collect();
+    let path = if path.is_empty() {
+        "file".to_string()
+    } else {
+        path.join("/")
+    };
+    let (stem, extension) = match path.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.contains('/') => {
+            (stem, format!(".{extension}"))
+        }
+        _ => (path.as_str(), String::new()),
+    };
+    let mut unique = path.clone();
+    let mut number = 1;
+    while names.contains(&unique) {
+        number += 1;
+        unique = format!("{stem} ({number}){extension}");
+    }
+    names.insert(unique.clone());
+    unique
+}
+
+fn csv_record<'a>(line: &mut String, separator: char, values: impl Iterator<Item = &'a str>) {
+    for (index, value) in values.enumerate() {
+        if index > 0 {
+            line.push(separator);
+        }
+        if value.contains([separator, '"', '\n', '\r']) {
+            line.push('"');
+            line.push_str(&value.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(value);
+        }
+    }
+    line.push('\n');
+}
+
+#[test]
+fn test_entry_names() {
+    let mut names = HashSet::new();
+    assert_eq!(unique_name(&mut names, "report.pdf"), "report.pdf");
+    assert_eq!(unique_name(&mut names, "report.pdf"), "report (2).pdf");
+    assert_eq!(unique_name(&mut names, "/report.pdf"), "report (3).pdf");
+    assert_eq!(unique_name(&mut names, "../../etc/passwd"), "etc/passwd");
+    assert_eq!(unique_name(&mut names, "a.b/README"), "a.b/README");
+    assert_eq!(unique_name(&mut names, "a.b/README"), "a.b/README (2)");
+    let mut line = String::new();
+    csv_record(&mut line, ';', ["a", "b;c", "d\"e"].into_iter());
+    assert_eq!(line, "a;\"b;c\";\"d\"\"e\"\n");
+}
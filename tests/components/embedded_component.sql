@@ -0,0 +1,7 @@
+select 'table' as component;
+
+select
+    'Ada' as name,
+    JSON (
+        '{"component":"button","rows":[{"title":"Edit Ada","link":"edit.sql"}]}'
+    ) as actions;
@@ -0,0 +1,2 @@
+select 'form' as component, 'field_locks.sql?record=1' as field_locks;
+select 'name' as name;
@@ -0,0 +1,3 @@
+select 'form' as component, true as spam_protection;
+select 'x' as name;
+select 'text' as component, 'Received ' || :x as contents where :x is not null;
@@ -0,0 +1,5 @@
+set errors = sqlpage.validate('{"age": {"required": true, "min": 18}, "name": {"required": true}}');
+select 'form' as component, $errors as errors;
+select 'age' as name, 'number' as type;
+select 'name' as name;
+select 'email' as name, 'Invalid email address' as error;
@@ -0,0 +1,7 @@
+select 'graph' as component, 'Services' as title, true as directed;
+select 'app' as id, 'Application' as label, '/app.sql' as link
+union all select 'db', 'Database', null;
+select 'app' as source, 'db' as target, 'queries' as label
+union all select 'app', 'cache', null;
+
+select 'text' as component, 'After the graph' as contents;
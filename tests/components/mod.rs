@@ -1,7 +1,9 @@
 use actix_web::{http::StatusCode, test};
 use sqlpage::webserver::http::main_handler;
 
-use crate::common::get_request_to;
+use crate::common::{
+    get_request_to, get_request_to_with_data, make_app_data_from_config, test_config,
+};
 
 #[actix_web::test]
 async fn test_overwrite_variable() -> actix_web::Result<()> {
@@ -23,3 +25,229 @@ async fn test_overwrite_variable() -> actix_web::Result<()> {
     );
     Ok(())
 }
+
+#[actix_web::test]
+async fn test_form_validation_errors() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/components/form_validation.sql")
+        .await?
+        .set_form(std::collections::HashMap::<&str, &str>::from_iter([
+            ("age", "10"),
+            ("name", "Ada"),
+        ]))
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_str.matches("is-invalid").count(), 2, "{body_str}");
+    assert!(
+        body_str.contains("Must be greater than or equal to 18."),
+        "{body_str}"
+    );
+    assert!(body_str.contains("Invalid email address"), "{body_str}");
+    assert!(!body_str.contains("This field is required."), "{body_str}");
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_form_spam_protection() -> actix_web::Result<()> {
+    let mut config = test_config();
+    config.encryption_key = Some("0123456789abcdef0123456789abcdef".into());
+    let app_data = make_app_data_from_config(config).await;
+    let path = "/tests/components/form_spam_protection.sql";
+    let req = get_request_to_with_data(path, app_data.clone())
+        .await?
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    let token = body
+        .split(r#"name="_sqlpage_form_token" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .unwrap_or_else(|| panic!("no form token in {body}"));
+
+    // Submitted immediately, without the proof of work
+    let req = get_request_to_with_data(path, app_data.clone())
+        .await?
+        .set_form([("x", "spam"), ("_sqlpage_form_token", token)])
+        .to_srv_request();
+    let err = main_handler(req)
+        .await
+        .expect_err("spam should be rejected");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    // Once a protected form has been displayed, its target page requires a token
+    let req = get_request_to_with_data(path, app_data.clone())
+        .await?
+        .set_form([("x", "1")])
+        .to_srv_request();
+    let err = main_handler(req)
+        .await
+        .expect_err("submissions without a token should be rejected");
+    assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+
+    // Including under the other paths that execute the same page
+    for other_path in [
+        "/tests/components/form_spam_protection",
+        "/tests/components/form_spam_protection%2Esql",
+        "/tests//components/form_spam_protection.sql",
+    ] {
+        let req = get_request_to_with_data(other_path, app_data.clone())
+            .await?
+            .set_form([("x", "1")])
+            .to_srv_request();
+        let err = main_handler(req)
+            .await
+            .expect_err("submissions without a token should be rejected");
+        assert_eq!(err.as_response_error().status_code(), StatusCode::FORBIDDEN);
+    }
+
+    // Other pages are not checked
+    let req = get_request_to_with_data("/tests/sql_test_files/it_works_simple.sql", app_data)
+        .await?
+        .set_form([("x", "1")])
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_embedded_component() {
+    let app_data = crate::common::make_app_data().await;
+    if !matches!(
+        app_data.db.to_string().to_lowercase().as_str(),
+        "postgres" | "sqlite"
+    ) {
+        log::info!(
+            "Skipping test_embedded_component on database {}",
+            app_data.db
+        );
+        return;
+    }
+
+    let resp = crate::common::req_path("/tests/components/embedded_component.sql")
+        .await
+        .expect("Failed to request /tests/components/embedded_component.sql");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains(r#"href="edit.sql""#),
+        "the button should be rendered as html inside the table, in: {body_str}"
+    );
+    assert!(body_str.contains("Edit Ada"), "{body_str}");
+    assert!(
+        !body_str.contains("&lt;"),
+        "the html of the button should not be escaped, in: {body_str}"
+    );
+}
+
+#[actix_web::test]
+async fn test_profile_component() {
+    let resp = crate::common::req_path("/tests/components/profile.sql")
+        .await
+        .expect("Failed to request /tests/components/profile.sql");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("People"), "{body}");
+    assert!(body.contains("3 rows"), "{body}");
+    assert!(
+        body.contains("33.3%"),
+        "the age column has one null value in: {body}"
+    );
+    assert!(body.contains(r#"title="Max: 2 (66.7%)""#), "{body}");
+    assert!(body.contains("After the profile"), "{body}");
+}
+
+#[actix_web::test]
+async fn test_period_fields() {
+    let resp = crate::common::req_path("/tests/components/period_fields.sql")
+        .await
+        .expect("Failed to request /tests/components/period_fields.sql");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    for (name, value) in [
+        ("week_start", "2023-12-31"),
+        ("week_end", "2024-01-07"),
+        ("month_start", "2024-02-01"),
+        ("month_end", "2024-03-01"),
+        ("period_start", "2024-12-24"),
+        ("period_end", "2025-01-01"),
+    ] {
+        let field = format!(r#"name="{name}""#);
+        let field_html = body
+            .split(&field)
+            .nth(1)
+            .unwrap_or_else(|| panic!("no {name} field in {body}"));
+        let field_html = &field_html[..field_html.find('>').unwrap()];
+        assert!(
+            field_html.contains(&format!(r#"value="{value}""#)),
+            "{name}: {field_html}"
+        );
+    }
+    assert!(body.contains(r#"value="2024-12-31""#), "{body}");
+}
+
+#[actix_web::test]
+async fn test_form_field_locks() {
+    let resp = crate::common::req_path("/tests/components/field_locks.sql")
+        .await
+        .expect("Failed to request /tests/components/field_locks.sql");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(
+        body.contains(r#"data-field-locks="field_locks.sql?record&#x3D;1""#),
+        "{body}"
+    );
+}
+
+#[actix_web::test]
+async fn test_graph_component() {
+    let resp = crate::common::req_path("/tests/components/graph.sql")
+        .await
+        .expect("Failed to request /tests/components/graph.sql");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("Services"), "{body}");
+    assert!(body.contains("3 nodes, 2 edges"), "{body}");
+    assert_eq!(body.matches("<circle").count(), 3, "{body}");
+    assert_eq!(body.matches("<line").count(), 2, "{body}");
+    assert!(body.contains(r#"<a href="/app.sql">"#), "{body}");
+    assert!(body.contains("app → db: queries"), "{body}");
+    assert!(body.contains("marker-end"), "{body}");
+    assert!(body.contains("After the graph"), "{body}");
+}
+
+#[actix_web::test]
+async fn test_org_chart_component() {
+    let resp = crate::common::req_path("/tests/components/org_chart.sql")
+        .await
+        .expect("Failed to request /tests/components/org_chart.sql");
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("Our team"), "{body}");
+    let alice = body.find("Alice Smith").expect("the CEO is displayed");
+    let bob = body.find("Bob Martin").expect("the CTO is displayed");
+    let carol = body
+        .find("Carol Jones")
+        .expect("the developer is displayed");
+    assert!(alice < bob && bob < carol, "{body}");
+    assert_eq!(body.matches("<details").count(), 2, "{body}");
+    assert_eq!(body.matches("<details open").count(), 1, "{body}");
+    let chart =
+        &body[body.find("sqlpage-org-chart").unwrap()..body.find("After the org chart").unwrap()];
+    assert_eq!(chart.matches("<ul").count(), 3, "{chart}");
+    assert_eq!(chart.matches("</ul>").count(), 3, "{chart}");
+    assert_eq!(chart.matches("<li").count(), chart.matches("</li>").count());
+    assert!(
+        body.contains(r#"<a href="/people.sql?id&#x3D;2">Bob Martin</a>"#),
+        "{body}"
+    );
+    assert!(
+        body.contains(">CJ<"),
+        "initials of people without photos: {body}"
+    );
+    assert!(body.contains("After the org chart"), "{body}");
+}
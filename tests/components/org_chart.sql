@@ -0,0 +1,6 @@
+select 'org_chart' as component, 'Our team' as title;
+select 2 as id, 1 as parent_id, 'Bob Martin' as name, 'CTO' as description, '/people.sql?id=2' as link
+union all select 3, 2, 'Carol Jones', 'Developer', null
+union all select 1, null, 'Alice Smith', 'CEO', null;
+
+select 'text' as component, 'After the org chart' as contents;
@@ -0,0 +1,4 @@
+select 'form' as component;
+select 'week' as type, 'week' as name, '2024-W01' as value, 'sunday' as week_start;
+select 'month' as type, 'month' as name, '2024-02' as value;
+select 'date_range' as type, 'period' as name, '2024-12-24/2024-12-31' as value;
@@ -0,0 +1,6 @@
+select 'profile' as component, 'People' as title, 2 as bins;
+select 'Ophir' as name, 29 as age
+union all select 'Max', 37
+union all select 'Max', null;
+
+select 'text' as component, 'After the profile' as contents;
@@ -0,0 +1,3 @@
+select 'cache' as component, 60 as max_age, 600 as stale_while_revalidate,
+    '["/tests/it_works.txt", "/next.sql"]' as prefetch, '/logo.png' as preload;
+select 'text' as component, 'Cached contents' as contents;
@@ -0,0 +1 @@
+select 'text' as component, sqlpage.environment_variable($name) as contents;
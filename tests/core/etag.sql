@@ -0,0 +1,4 @@
+select 'etag' as component, $max_age as max_age;
+select 'text' as component, 'Static contents' as contents;
+-- The page only has the same ETag twice when it is not executed again
+select 'text' as component, sqlpage.random_string(16) as contents where $max_age is not null;
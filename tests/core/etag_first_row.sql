@@ -0,0 +1,4 @@
+select 'etag' as component;
+-- The first row of the body changes at every request
+select 'shell' as component, sqlpage.random_string(16) as title;
+select 'text' as component, 'Static contents' as contents;
@@ -0,0 +1,2 @@
+select 'event_stream' as component, 0.05 as interval;
+select 'tick' as event;
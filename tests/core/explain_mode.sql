@@ -0,0 +1 @@
+select 'text' as component, CONCAT('not ', 'explained: ', $x) as contents;
@@ -0,0 +1,4 @@
+set greeting = CONCAT('hello ', $x);
+delete from table_that_does_not_exist where name = $greeting;
+select 'text' as component, sqlpage.exec('echo', 'executed') as contents;
+select 'text' as component, sqlpage.read_file_as_text($x) as contents from (select 1) as t;
@@ -2,7 +2,10 @@ use actix_web::{http::StatusCode, test};
 use sqlpage::{webserver, AppState};
 use sqlx::Executor as _;
 
-use crate::common::{make_app_data_from_config, req_path, req_path_with_app_data, test_config};
+use crate::common::{
+    get_request_to_with_data, make_app_data_from_config, req_path, req_path_with_app_data,
+    srv_req_path_with_app_data, test_config,
+};
 
 #[actix_web::test]
 async fn test_concurrent_requests() {
@@ -129,6 +132,304 @@ async fn test_routing_with_prefix() {
     assert_eq!(location.to_str().unwrap(), "/prefix/");
 }
 
+#[actix_web::test]
+async fn test_redirect_status() {
+    let resp = req_path("/tests/core/redirect_status.sql").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    for (status, expected) in [
+        ("301", StatusCode::MOVED_PERMANENTLY),
+        ("303", StatusCode::SEE_OTHER),
+        ("307", StatusCode::TEMPORARY_REDIRECT),
+    ] {
+        let resp = req_path(&format!("/tests/core/redirect_status.sql?status={status}"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), expected);
+        assert_eq!(
+            resp.headers().get("location").unwrap(),
+            "/tests/it_works.txt"
+        );
+    }
+    let resp = req_path("/tests/core/redirect_status.sql?status=200")
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[actix_web::test]
+async fn test_post_redirect_get() {
+    let mut config = test_config();
+    config.post_redirect_get = true;
+    config.post_redirect_message = Some("Saved!".into());
+    let app_data = make_app_data_from_config(config).await;
+    let post = |referer: &'static str| {
+        let app_data = app_data.clone();
+        async move {
+            let req = get_request_to_with_data("/tests/core/post_redirect.sql", app_data)
+                .await
+                .unwrap()
+                .method(actix_web::http::Method::POST)
+                .insert_header(("host", "example.com"))
+                .insert_header(("referer", referer))
+                .set_form([("comment", "hello")])
+                .to_srv_request();
+            webserver::http::main_handler(req).await.unwrap()
+        }
+    };
+
+    let resp = post("https://example.com/comments.sql?page=2").await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    assert_eq!(
+        resp.headers().get("location").unwrap(),
+        "/comments.sql?page=2"
+    );
+    let cookies: Vec<_> = resp
+        .headers()
+        .get_all("set-cookie")
+        .map(|c| c.to_str().unwrap())
+        .collect();
+    assert!(
+        cookies.iter().any(|c| c.starts_with("last_comment=hello")),
+        "{cookies:?}"
+    );
+    assert!(
+        cookies
+            .iter()
+            .any(|c| c.starts_with("sqlpage_flash=Saved%21")),
+        "{cookies:?}"
+    );
+
+    // The browser is never sent to another website
+    let resp = post("https://other.example/comments.sql").await;
+    assert_eq!(
+        resp.headers().get("location").unwrap(),
+        "/tests/core/post_redirect.sql"
+    );
+
+    let resp = req_path_with_app_data("/tests/core/post_redirect.sql", app_data)
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_rate_limits() {
+    let mut config = test_config();
+    config.rate_limit_requests = Some(4);
+    config.rate_limits = vec![webserver::rate_limit::PathRateLimit {
+        path: "/tests/sql_test_files/it_works_simple.sql".into(),
+        requests: 2,
+        window_seconds: 60,
+    }];
+    let app_data = make_app_data_from_config(config).await;
+    let app = test::init_service(webserver::http::create_app(app_data)).await;
+    let req = |path: &str, ip: &str| {
+        test::TestRequest::get()
+            .uri(path)
+            .peer_addr(format!("{ip}:1234").parse().unwrap())
+            .to_request()
+    };
+    // The limit applies to the page, whatever the form of its path
+    for path in [
+        "/tests/sql_test_files/it_works_simple",
+        "/tests/sql_test_files/it_works_simple.sql",
+    ] {
+        let resp = test::call_service(&app, req(path, "192.0.2.1")).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{path}");
+    }
+    let resp = test::call_service(
+        &app,
+        req("/tests/sql_test_files/it_works_simple", "192.0.2.1"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "60");
+
+    // Other clients are not limited, and static files are not counted in the global limit
+    let resp = test::call_service(
+        &app,
+        req("/tests/sql_test_files/it_works_simple", "192.0.2.2"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    for _ in 0..5 {
+        let resp = test::call_service(&app, req("/tests/it_works.txt", "192.0.2.1")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+    let resp = test::call_service(&app, req("/tests/core/etag.sql", "192.0.2.1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, req("/tests/core/etag.sql", "192.0.2.1")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn test_etag() {
+    let app_data = make_app_data_from_config(test_config()).await;
+    for path in ["/tests/core/etag.sql", "/tests/core/etag.sql?max_age=60"] {
+        let resp = req_path_with_app_data(path, app_data.clone())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "no-cache");
+        let etag = resp.headers().get("etag").expect("no etag").clone();
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8_lossy(&body).contains("Static contents"));
+
+        // The same user asks again, with the same cookies and credentials
+        let mut req = srv_req_path_with_app_data(path, app_data.clone()).await;
+        req.headers_mut()
+            .insert(actix_web::http::header::IF_NONE_MATCH, etag.clone());
+        let resp = webserver::http::main_handler(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED, "{path}");
+        assert_eq!(resp.headers().get("etag").unwrap(), etag);
+    }
+
+    let path = "/tests/core/etag_first_row.sql";
+    let resp = req_path_with_app_data(path, app_data.clone())
+        .await
+        .unwrap();
+    let etag = resp.headers().get("etag").expect("no etag").clone();
+    assert!(etag.to_str().unwrap().starts_with("W/"), "{etag:?}");
+    let mut req = srv_req_path_with_app_data(path, app_data).await;
+    req.headers_mut()
+        .insert(actix_web::http::header::IF_NONE_MATCH, etag.clone());
+    let resp = webserver::http::main_handler(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_ne!(resp.headers().get("etag").unwrap(), etag);
+}
+
+#[actix_web::test]
+async fn test_cache_component() {
+    let resp = req_path("/tests/core/cache.sql").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("cache-control").unwrap(),
+        "private, max-age=60, stale-while-revalidate=600"
+    );
+    let links: Vec<_> = resp
+        .headers()
+        .get_all("link")
+        .map(|link| link.to_str().unwrap())
+        .collect();
+    assert_eq!(
+        links,
+        [
+            "</tests/it_works.txt>; rel=prefetch",
+            "</next.sql>; rel=prefetch",
+            "</logo.png>; rel=preload; as=image",
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn test_event_stream() {
+    use actix_web::body::MessageBody;
+
+    let resp = req_path("/tests/core/event_stream.sql").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = resp.into_body();
+    let mut received = String::new();
+    let two_runs = async {
+        while received.matches(r#"{"event":"tick"}"#).count() < 2 {
+            let chunk = std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx))
+                .await
+                .expect("the event stream should stay open")
+                .unwrap();
+            received.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(10), two_runs)
+        .await
+        .expect("the page should be executed again after the interval");
+    assert!(
+        received.starts_with(r#"data: {"event":"tick"}"#),
+        "{received}"
+    );
+    assert!(!received.contains("event_stream"), "{received}");
+}
+
+#[actix_web::test]
+async fn test_static_file_range() {
+    let app_data = make_app_data_from_config(test_config()).await;
+    let req = get_request_to_with_data("/tests/it_works.txt", app_data)
+        .await
+        .unwrap()
+        .insert_header(("range", "bytes=3-7"))
+        .to_srv_request();
+    let resp = webserver::http::main_handler(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.headers().get("content-range").unwrap(), "bytes 3-7/10");
+    assert_eq!(test::read_body(resp).await, "works");
+}
+
+#[actix_web::test]
+async fn test_websocket() {
+    use actix_http::ws::{Frame, Item, Message};
+    use futures_util::{SinkExt as _, StreamExt as _};
+
+    let app_data = make_app_data_from_config(test_config()).await;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = actix_web::HttpServer::new(move || webserver::http::create_app(app_data.clone()))
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+    let server_handle = server.handle();
+    tokio::spawn(server);
+
+    let url = format!("ws://127.0.0.1:{port}/tests/core/websocket.sql?room=lobby");
+    // Browsers always send the Origin of the page that opens a WebSocket
+    let refused = awc::Client::new().ws(&url).connect().await.err();
+    assert!(
+        matches!(
+            refused,
+            Some(awc::error::WsClientError::InvalidResponseStatus(
+                StatusCode::FORBIDDEN
+            ))
+        ),
+        "{refused:?}"
+    );
+
+    let (resp, mut socket) = awc::Client::new()
+        .ws(&url)
+        .origin(format!("http://127.0.0.1:{port}"))
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+    socket
+        .send(Message::Text(r#"{"name": "Ada"}"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        socket.next().await.unwrap().unwrap(),
+        Frame::Text(r#"{"name":"Ada","room":"lobby"}"#.into())
+    );
+
+    // A message sent in several frames is executed once, when it is complete
+    for fragment in [
+        Item::FirstText(r#"{"name": "#.into()),
+        Item::Continue(r#""Gra"#.into()),
+        Item::Last(r#"ce"}"#.into()),
+    ] {
+        socket.send(Message::Continuation(fragment)).await.unwrap();
+    }
+    assert_eq!(
+        socket.next().await.unwrap().unwrap(),
+        Frame::Text(r#"{"name":"Grace","room":"lobby"}"#.into())
+    );
+
+    socket.send(Message::Close(None)).await.unwrap();
+    assert_eq!(socket.next().await.unwrap().unwrap(), Frame::Close(None));
+    server_handle.stop(false).await;
+}
+
 #[actix_web::test]
 async fn test_hidden_files() {
     let resp_result = req_path("/tests/core/.hidden.sql").await;
@@ -145,6 +446,317 @@ async fn test_hidden_files() {
         .contains("forbidden"),);
 }
 
+#[actix_web::test]
+async fn test_explain_mode() {
+    let mut config = test_config();
+    config.allow_explain_mode = true;
+    let app_data = make_app_data_from_config(config).await;
+    let resp = req_path_with_app_data(
+        "/tests/core/explain_mode.sql?_sqlpage_explain&x=42",
+        app_data.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("parameter 1: <code>42</code>"),
+        "{body_str}\nexpected to contain the bound parameter value"
+    );
+    assert!(
+        !body_str.contains("not explained: 42"),
+        "{body_str}\nthe statement should not have been executed"
+    );
+
+    let resp = req_path_with_app_data(
+        "/tests/core/explain_mode_side_effects.sql?_sqlpage_explain&x=42",
+        app_data,
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("table_that_does_not_exist"),
+        "{body_str}\nexpected the statement to be displayed"
+    );
+    // Functions are displayed without being called: sqlpage.exec is not allowed in the tests
+    for function in ["sqlpage.exec", "sqlpage.read_file_as_text"] {
+        assert!(body_str.contains(function), "{body_str}\n{function}");
+    }
+    assert!(
+        body_str.contains("not evaluated"),
+        "{body_str}\nexpected the function call not to be evaluated"
+    );
+    assert!(
+        !body_str.contains("hello 42"),
+        "{body_str}\nthe SET statement should not have been executed"
+    );
+}
+
+#[actix_web::test]
+async fn test_explain_mode_disabled_by_default() {
+    let resp = req_path("/tests/core/explain_mode.sql?_sqlpage_explain&x=42")
+        .await
+        .unwrap();
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("not explained: 42"),
+        "{body_str}\nthe page should have been executed normally"
+    );
+}
+
+#[actix_web::test]
+async fn test_explain_mode_ignored_in_production() {
+    let mut config = test_config();
+    config.allow_explain_mode = true;
+    config.environment = sqlpage::app_config::DevOrProd::Production;
+    let app_data = make_app_data_from_config(config).await;
+    let resp = req_path_with_app_data(
+        "/tests/core/explain_mode.sql?_sqlpage_explain&x=42",
+        app_data,
+    )
+    .await
+    .unwrap();
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("not explained: 42"),
+        "{body_str}\nthe page should have been executed normally"
+    );
+    assert!(
+        !body_str.contains("parameter 1:"),
+        "{body_str}\nthe statements should not have been explained"
+    );
+}
+
+#[actix_web::test]
+async fn test_performance_page() {
+    let app_data = make_app_data_from_config(test_config()).await;
+    let resp = req_path_with_app_data("/tests/core/explain_mode.sql?x=1", app_data.clone())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = req_path_with_app_data("/_sqlpage/performance.sql", app_data)
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("tests/core/explain_mode.sql"),
+        "{body_str}\nexpected to list the previously executed page and statement"
+    );
+    assert!(body_str.contains("idle_connections"), "{body_str}");
+
+    let mut config = test_config();
+    config.environment = sqlpage::app_config::DevOrProd::Production;
+    let app_data = make_app_data_from_config(config).await;
+    let resp = req_path_with_app_data("/_sqlpage/performance.sql", app_data).await;
+    assert!(resp.is_err() || resp.unwrap().status() == StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_admin_api() {
+    let mut config = test_config();
+    config.admin_api_token = Some("secret-admin-token".to_string());
+    let app_data = make_app_data_from_config(config).await;
+    let app = test::init_service(webserver::http::create_app(app_data.clone())).await;
+    let page = "/tests/sql_test_files/it_works_simple.sql";
+    let resp = test::call_service(&app, test::TestRequest::get().uri(page).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let admin_request = |method: actix_web::http::Method, path: &str| {
+        test::TestRequest::default()
+            .method(method)
+            .uri(path)
+            .insert_header(("Authorization", "Bearer secret-admin-token"))
+            .to_request()
+    };
+    let req = test::TestRequest::get()
+        .uri("/_sqlpage/admin/files")
+        .insert_header(("Authorization", "Bearer wrong-token"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = admin_request(actix_web::http::Method::GET, "/_sqlpage/admin/files");
+    let files: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        files["sql_files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|file| file["path"] == page.trim_start_matches('/')),
+        "{files}"
+    );
+
+    let req = admin_request(actix_web::http::Method::POST, "/_sqlpage/admin/cache/purge");
+    let purged: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(
+        purged["purged_sql_files"].as_u64().unwrap() >= 1,
+        "{purged}"
+    );
+    let req = admin_request(actix_web::http::Method::GET, "/_sqlpage/admin/files");
+    let files: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(files["sql_files"], serde_json::json!([]));
+
+    let req = admin_request(actix_web::http::Method::GET, "/_sqlpage/admin/pool");
+    let pool: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(pool["connections"].is_u64(), "{pool}");
+
+    // The configuration can only be reloaded by the server started with run_server
+    let req = admin_request(
+        actix_web::http::Method::POST,
+        "/_sqlpage/admin/config/reload",
+    );
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(
+        body["error"].as_str().unwrap().contains("sqlpage command"),
+        "{body}"
+    );
+
+    let req = admin_request(
+        actix_web::http::Method::POST,
+        "/_sqlpage/admin/alerts/none/run",
+    );
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Without a token in the configuration, the API does not exist
+    let app = test::init_service(webserver::http::create_app(
+        make_app_data_from_config(test_config()).await,
+    ))
+    .await;
+    let req = admin_request(actix_web::http::Method::GET, "/_sqlpage/admin/files");
+    let resp = test::call_service(&app, req).await;
+    assert_ne!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_statement_hooks() {
+    use sqlpage::webserver::database::statement_hooks::{
+        StatementHook, StatementInfo, StatementOutcome,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct Executed {
+        sql: String,
+        parameters: Vec<Option<String>>,
+        rows: usize,
+    }
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<Executed>>>);
+
+    impl StatementHook for Recorder {
+        fn before_statement(&self, statement: &StatementInfo<'_>) -> anyhow::Result<()> {
+            anyhow::ensure!(
+                !statement.sql.contains("forbidden"),
+                "Rejected by the test policy"
+            );
+            Ok(())
+        }
+        fn after_statement(&self, statement: &StatementInfo<'_>, outcome: &StatementOutcome<'_>) {
+            assert!(outcome.error.is_none());
+            self.0.lock().unwrap().push(Executed {
+                sql: statement.sql.to_string(),
+                parameters: statement.parameters.to_vec(),
+                rows: outcome.rows,
+            });
+        }
+    }
+
+    let recorder = Recorder::default();
+    let executed = Arc::clone(&recorder.0);
+    let mut state = AppState::init(&test_config()).await.unwrap();
+    state.add_statement_hook(recorder);
+    let resp = req_path_with_app_data(
+        "/tests/core/statement_hooks.sql?name=Ada",
+        actix_web::web::Data::new(state),
+    )
+    .await
+    .unwrap();
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("ada"), "{body}");
+    assert!(body.contains("Rejected by the test policy"), "{body}");
+
+    let executed = executed.lock().unwrap();
+    assert_eq!(executed.len(), 1, "{executed:?}");
+    let Executed {
+        sql,
+        parameters,
+        rows,
+    } = &executed[0];
+    assert!(sql.to_lowercase().contains("lower"), "{sql}");
+    assert_eq!(parameters, &[Some("Ada".to_string())]);
+    assert_eq!(*rows, 1);
+}
+
+#[actix_web::test]
+async fn test_iframe_mode() {
+    let mut config = test_config();
+    config.embed_allowed_origins = vec!["https://wiki.example.com".to_string()];
+    let app_data = make_app_data_from_config(config).await;
+    let resp = req_path_with_app_data(
+        "/tests/sql_test_files/it_works_simple.sql?_sqlpage_embed=iframe",
+        app_data,
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let csp = resp
+        .headers()
+        .get("content-security-policy")
+        .expect("csp header")
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        csp.ends_with("frame-ancestors 'self' https://wiki.example.com"),
+        "{csp}"
+    );
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body_str.contains("It works !"), "{body_str}");
+    assert!(
+        body_str.contains(r#"data-sqlpage-iframe="[&quot;https://wiki.example.com&quot;]""#),
+        "{body_str}"
+    );
+    assert!(!body_str.contains("sqlpage_header"), "{body_str}");
+    assert!(!body_str.contains("sqlpage_footer"), "{body_str}");
+}
+
+#[actix_web::test]
+async fn test_allowed_environment_variables() {
+    let mut config = test_config();
+    config.allowed_environment_variables = Some(vec!["CARGO_PKG_*".to_string()]);
+    let app_data = make_app_data_from_config(config).await;
+    let resp = req_path_with_app_data(
+        "/tests/core/environment_variable.sql?name=CARGO_PKG_NAME",
+        app_data.clone(),
+    )
+    .await
+    .unwrap();
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("sqlpage"), "{body}");
+
+    let resp = req_path_with_app_data("/tests/core/environment_variable.sql?name=PATH", app_data)
+        .await
+        .unwrap();
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(
+        body.contains("not listed in the allowed_environment_variables"),
+        "{body}"
+    );
+}
+
 #[actix_web::test]
 async fn test_official_website_documentation() {
     let app_data = make_app_data_for_official_website().await;
@@ -191,3 +803,304 @@ async fn make_app_data_for_official_website() -> actix_web::web::Data<AppState>
         .unwrap();
     app_state
 }
+
+#[actix_web::test]
+async fn test_request_timeout() {
+    // Accepts connections, but never answers
+    let silent_server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", silent_server.local_addr().unwrap());
+    let mut config = test_config();
+    config.request_timeout_seconds = Some(0.5);
+    let app_data = make_app_data_from_config(config).await;
+    let app = test::init_service(webserver::http::create_app(app_data)).await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/tests/core/request_timeout.sql?url={url}"))
+        .to_request();
+    let started_at = std::time::Instant::now();
+    let body = test::call_and_read_body(&app, req).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(started_at.elapsed() < std::time::Duration::from_secs(3));
+    assert!(body.contains("Rendered before the timeout"), "{body}");
+    assert!(body.contains("request_timeout_seconds"), "{body}");
+}
+
+#[actix_web::test]
+async fn test_preview_databases() {
+    let configuration_directory =
+        std::env::temp_dir().join(format!("sqlpage_previews_{}", std::process::id()));
+    let fixtures = configuration_directory.join("preview_fixtures");
+    std::fs::create_dir_all(&fixtures).unwrap();
+    std::fs::write(
+        fixtures.join("01_users.sql"),
+        "create table preview_users (name text); insert into preview_users values ('Ophir'), ('Max');",
+    )
+    .unwrap();
+    let mut config = test_config();
+    config.database_url = "sqlite::memory:".to_string();
+    config
+        .configuration_directory
+        .clone_from(&configuration_directory);
+    config.preview_databases_directory = Some("previews".into());
+    config.admin_api_token = Some("secret-admin-token".to_string());
+    let app = test::init_service(webserver::http::create_app(
+        make_app_data_from_config(config).await,
+    ))
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/tests/core/preview_database.sql")
+        .insert_header(("X-SQLPage-Preview", "Feature/Users"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("Ophir, Max"), "{body}");
+    assert!(configuration_directory
+        .join("previews/feature-users.sqlite")
+        .exists());
+
+    // The main database does not contain the fixtures
+    let req = test::TestRequest::get()
+        .uri("/tests/core/preview_database.sql")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert!(!String::from_utf8_lossy(&body).contains("Ophir"));
+
+    let admin_request = |method: actix_web::http::Method, path: &str| {
+        test::TestRequest::default()
+            .method(method)
+            .uri(path)
+            .insert_header(("Authorization", "Bearer secret-admin-token"))
+            .to_request()
+    };
+    let req = admin_request(actix_web::http::Method::GET, "/_sqlpage/admin/previews");
+    let previews: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        previews,
+        serde_json::json!({ "previews": ["feature-users"] })
+    );
+    let req = admin_request(
+        actix_web::http::Method::DELETE,
+        "/_sqlpage/admin/previews/feature-users",
+    );
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(!configuration_directory
+        .join("previews/feature-users.sqlite")
+        .exists());
+    let req = admin_request(
+        actix_web::http::Method::DELETE,
+        "/_sqlpage/admin/previews/feature-users",
+    );
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    std::fs::remove_dir_all(configuration_directory).unwrap();
+}
+
+#[actix_web::test]
+async fn test_preview_databases_in_production() {
+    let configuration_directory =
+        std::env::temp_dir().join(format!("sqlpage_previews_prod_{}", std::process::id()));
+    let mut config = test_config();
+    config.database_url = "sqlite::memory:".to_string();
+    config
+        .configuration_directory
+        .clone_from(&configuration_directory);
+    config.preview_databases_directory = Some("previews".into());
+    config.admin_api_token = Some("secret-admin-token".to_string());
+    config.environment = sqlpage::app_config::DevOrProd::Production;
+    config.max_preview_databases = 1;
+    let app = test::init_service(webserver::http::create_app(
+        make_app_data_from_config(config).await,
+    ))
+    .await;
+    let preview_request = |name: &str| {
+        test::TestRequest::get()
+            .uri("/tests/sql_test_files/it_works_simple.sql")
+            .insert_header(("X-SQLPage-Preview", name))
+            .to_request()
+    };
+    let create_request = |name: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/_sqlpage/admin/previews/{name}"))
+            .insert_header(("Authorization", "Bearer secret-admin-token"))
+            .to_request()
+    };
+
+    // Anonymous clients cannot create previews in production
+    let Err(err) = test::try_call_service(&app, preview_request("anything")).await else {
+        panic!("the preview should not be created");
+    };
+    assert_eq!(err.as_response_error().status_code(), StatusCode::NOT_FOUND);
+    assert!(!configuration_directory
+        .join("previews/anything.sqlite")
+        .exists());
+
+    let resp = test::call_service(&app, create_request("first")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::call_and_read_body(&app, preview_request("first")).await;
+    assert!(String::from_utf8_lossy(&body).contains("It works !"));
+
+    // The unused preview is closed to make room for the new one, and can be opened again later
+    let resp = test::call_service(&app, create_request("second")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::call_and_read_body(&app, preview_request("first")).await;
+    assert!(String::from_utf8_lossy(&body).contains("It works !"));
+    std::fs::remove_dir_all(configuration_directory).unwrap();
+}
+
+#[actix_web::test]
+async fn test_short_links() {
+    let app_data = make_app_data_from_config(test_config()).await;
+    app_data
+        .db
+        .connection
+        .execute(
+            "CREATE TABLE sqlpage_short_links (code VARCHAR(64) PRIMARY KEY, url TEXT NOT NULL, clicks INTEGER NOT NULL DEFAULT 0)",
+        )
+        .await
+        .unwrap();
+    let app = test::init_service(webserver::http::create_app(app_data.clone())).await;
+    let shorten = || {
+        test::TestRequest::get()
+            .uri("/tests/core/short_link.sql")
+            .to_request()
+    };
+    let body = test::call_and_read_body(&app, shorten()).await;
+    let body = String::from_utf8_lossy(&body);
+    let short_link = body
+        .split(['<', '>'])
+        .map(str::trim)
+        .find(|part| part.starts_with("/_sqlpage/s/"))
+        .unwrap_or_else(|| panic!("no short link in {body}"))
+        .to_string();
+    assert_eq!(short_link.len(), "/_sqlpage/s/".len() + 8, "{short_link}");
+    // Shortening the same URL again returns the same link
+    let body = test::call_and_read_body(&app, shorten()).await;
+    assert!(String::from_utf8_lossy(&body).contains(&short_link));
+
+    for _ in 0..2 {
+        let req = test::TestRequest::get().uri(&short_link).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers().get("location").unwrap(),
+            "/tests/it_works.txt?report=sales&year=2024"
+        );
+    }
+    let (clicks,): (i32,) = sqlx::query_as("SELECT clicks FROM sqlpage_short_links")
+        .fetch_one(&app_data.db.connection)
+        .await
+        .unwrap();
+    assert_eq!(clicks, 2);
+
+    let req = test::TestRequest::get()
+        .uri("/_sqlpage/s/missing")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_locks_are_released_at_the_end_of_the_page() {
+    // The second page can only acquire the lock without waiting if the first one released it
+    for _ in 0..2 {
+        let resp = req_path("/tests/core/with_lock.sql").await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8_lossy(&body).contains("core_test_lock"));
+    }
+}
+
+#[actix_web::test]
+async fn test_import_from_url() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = actix_web::HttpServer::new(|| {
+        actix_web::App::new().default_service(actix_web::web::to(|| async {
+            actix_web::HttpResponse::Ok().body("name,age\nAda,36\nMax,37\n")
+        }))
+    })
+    .listen(listener)
+    .unwrap()
+    .run();
+    let server_handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let web_root = std::env::temp_dir().join(format!("sqlpage_import_url_{}", std::process::id()));
+    std::fs::create_dir_all(&web_root).unwrap();
+    std::fs::write(
+        web_root.join("import.sql"),
+        format!(
+            "copy remote_people (name, age) from 'http://127.0.0.1:{port}/exports/people.csv';
+            select 'text' as component, group_concat(name, ', ') as contents from remote_people;"
+        ),
+    )
+    .unwrap();
+    let mut config = test_config();
+    config.database_url = "sqlite::memory:".to_string();
+    config.web_root.clone_from(&web_root);
+    let app_data = make_app_data_from_config(config.clone()).await;
+    app_data
+        .db
+        .connection
+        .execute("CREATE TABLE remote_people (name TEXT, age INTEGER)")
+        .await
+        .unwrap();
+    let app = test::init_service(webserver::http::create_app(app_data)).await;
+    let req = || test::TestRequest::get().uri("/import.sql").to_request();
+    let body = test::call_and_read_body(&app, req()).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("import_url_allowlist"), "{body}");
+
+    config.import_url_allowlist = vec![format!("http://127.0.0.1:{port}/exports/")];
+    let app_data = make_app_data_from_config(config).await;
+    app_data
+        .db
+        .connection
+        .execute("CREATE TABLE remote_people (name TEXT, age INTEGER)")
+        .await
+        .unwrap();
+    let app = test::init_service(webserver::http::create_app(app_data)).await;
+    let body = test::call_and_read_body(&app, req()).await;
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("Ada, Max"), "{body}");
+
+    server_handle.stop(false).await;
+    std::fs::remove_dir_all(web_root).unwrap();
+}
+
+#[actix_web::test]
+async fn test_static_export_follows_links() {
+    let web_root = std::env::temp_dir().join(format!("sqlpage_export_{}", std::process::id()));
+    let out_dir = web_root.join("public");
+    std::fs::create_dir_all(&web_root).unwrap();
+    std::fs::write(
+        web_root.join("index.sql"),
+        "select 'list' as component; select 'Sales' as title, 'report.sql?year=2024' as link;",
+    )
+    .unwrap();
+    std::fs::write(
+        web_root.join("report.sql"),
+        "select 'text' as component, 'Sales of ' || $year as contents;",
+    )
+    .unwrap();
+    let mut config = test_config();
+    config.database_url = "sqlite::memory:".to_string();
+    config.web_root.clone_from(&web_root);
+    let state = AppState::init(&config).await.unwrap();
+    let pages = ["index.sql".to_string(), "report.sql?year=2024".to_string()];
+    webserver::static_export::export_static_site(state, &out_dir, &pages)
+        .await
+        .unwrap();
+
+    let index = std::fs::read_to_string(out_dir.join("index.html")).unwrap();
+    let link = index
+        .split("href=\"")
+        .skip(1)
+        .filter_map(|attribute| attribute.split('"').next())
+        .find(|link| link.contains("report"))
+        .unwrap_or_else(|| panic!("no link to the report in {index}"));
+    assert_eq!(link, "report_year=2024.html");
+    let report = std::fs::read_to_string(out_dir.join(link)).unwrap();
+    assert!(report.contains("Sales of 2024"), "{report}");
+    std::fs::remove_dir_all(web_root).unwrap();
+}
@@ -0,0 +1,2 @@
+select 'cookie' as component, 'last_comment' as name, :comment as value where :comment is not null;
+select 'text' as component, 'Received ' || :comment as contents;
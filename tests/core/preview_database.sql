@@ -0,0 +1 @@
+select 'text' as component, group_concat(name, ', ') as contents from preview_users;
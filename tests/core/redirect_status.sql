@@ -0,0 +1 @@
+select 'redirect' as component, '/tests/it_works.txt' as link, $status as status;
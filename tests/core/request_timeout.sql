@@ -0,0 +1,3 @@
+select 'text' as component, 'Rendered before the timeout' as contents;
+-- the server never answers, so this only ends when the page times out
+select 'text' as component, sqlpage.fetch($url) as contents;
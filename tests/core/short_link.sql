@@ -0,0 +1 @@
+select 'text' as component, sqlpage.shorten('/tests/it_works.txt?report=sales&year=2024') as contents;
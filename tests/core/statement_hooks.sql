@@ -0,0 +1,3 @@
+set greeting = lower($name);
+select 'text' as component, $greeting as contents;
+select 'forbidden' as contents where 1 = 0;
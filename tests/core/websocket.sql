@@ -0,0 +1 @@
+select :name as name, $room as room;
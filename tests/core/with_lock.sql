@@ -0,0 +1 @@
+select 'text' as component, sqlpage.with_lock('core_test_lock', 0) as contents;
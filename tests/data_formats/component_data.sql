@@ -0,0 +1,10 @@
+select 'shell' as component, 'Sales' as title;
+
+select 'text' as component, 'Sales by region' as contents;
+
+select 'chart' as component, 'bar' as type;
+select 'North' as label, 10 as value;
+select 'South' as label, 20 as value;
+
+select 'table' as component;
+select 'Total' as label, 30 as value;
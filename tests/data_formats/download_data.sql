@@ -0,0 +1 @@
+select 'download' as component, 'logo.png' as filename, 'data:image/png;base64,iVBORw0KGgo=' as contents;
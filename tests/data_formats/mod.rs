@@ -4,7 +4,9 @@ use actix_web::{
 };
 use sqlpage::webserver::http::main_handler;
 
-use crate::common::get_request_to;
+use crate::common::{
+    get_request_to, get_request_to_with_data, make_app_data_from_config, test_config,
+};
 
 #[actix_web::test]
 async fn test_json_body() -> actix_web::Result<()> {
@@ -27,6 +29,35 @@ async fn test_json_body() -> actix_web::Result<()> {
     Ok(())
 }
 
+#[actix_web::test]
+async fn test_component_data() -> actix_web::Result<()> {
+    for (component_index, expected) in [
+        (
+            3,
+            serde_json::json!([{"label": "North", "value": 10}, {"label": "South", "value": 20}]),
+        ),
+        (4, serde_json::json!([{"label": "Total", "value": 30}])),
+        (2, serde_json::json!([])),
+    ] {
+        let req = get_request_to(&format!(
+            "/tests/data_formats/component_data.sql?_sqlpage_data={component_index}"
+        ))
+        .await?
+        .to_srv_request();
+        let resp = main_handler(req).await?;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = test::read_body(resp).await;
+        let body_json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body_json, expected, "component {component_index}");
+    }
+    Ok(())
+}
+
 #[actix_web::test]
 async fn test_csv_body() -> actix_web::Result<()> {
     let req = get_request_to("/tests/data_formats/csv_data.sql")
@@ -48,6 +79,196 @@ async fn test_csv_body() -> actix_web::Result<()> {
     Ok(())
 }
 
+#[actix_web::test]
+async fn test_xlsx_body() -> actix_web::Result<()> {
+    use calamine::{Data, Reader, Xlsx};
+
+    let req = get_request_to("/tests/data_formats/xlsx_data.sql")
+        .await?
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    );
+    assert_eq!(
+        resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "attachment; filename=prices.xlsx"
+    );
+    let body = test::read_body(resp).await;
+    let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(std::io::Cursor::new(body))
+        .expect("the response should be a valid xlsx file");
+    assert_eq!(workbook.sheet_names(), ["Price list"]);
+    let sheet = workbook.worksheet_range("Price list").unwrap();
+    let rows: Vec<&[Data]> = sheet.rows().collect();
+    assert_eq!(
+        rows[0],
+        [
+            Data::String("product".into()),
+            Data::String("price".into()),
+            Data::String("updated".into()),
+            Data::String("category".into()),
+        ]
+    );
+    assert_eq!(rows[1][0], Data::String("Coffee & tea".into()));
+    assert_eq!(rows[1][1], Data::Float(4.5));
+    assert!(
+        matches!(&rows[1][2], Data::DateTime(date) if date.as_f64() == 45_352.0),
+        "dates are numbers with a date format: {:?}",
+        rows[1][2]
+    );
+    assert_eq!(rows[2][3], Data::String("Desserts".into()));
+    assert_eq!(rows[2][1], Data::Float(12.0), "formatted texts are numbers");
+    assert_eq!(rows[2][2], Data::Empty);
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_zip_body() -> actix_web::Result<()> {
+    use std::io::Read;
+
+    let req = get_request_to("/tests/data_formats/zip_data.sql")
+        .await?
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/zip"
+    );
+    assert_eq!(
+        resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "attachment; filename=case_42.zip"
+    );
+    let body = test::read_body(resp).await;
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body))
+        .expect("the response should be a valid zip file");
+    let mut read = |name: &str| {
+        let mut contents = Vec::new();
+        archive
+            .by_name(name)
+            .unwrap_or_else(|e| panic!("{name} should be in the archive: {e}"))
+            .read_to_end(&mut contents)
+            .unwrap();
+        contents
+    };
+    assert_eq!(read("contract.txt"), b"Signed, sealed, delivered");
+    assert_eq!(read("logo.png"), b"\x89PNG\r\n\x1a\n");
+    assert_eq!(read("invoices.csv"), b"id,product\n1,\"Coffee, tea\"\n");
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_download_body() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/data_formats/download_data.sql")
+        .await?
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "image/png"
+    );
+    assert_eq!(
+        resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+        "attachment; filename=\"logo.png\""
+    );
+    let body = test::read_body(resp).await;
+    assert_eq!(body.as_ref(), b"\x89PNG\r\n\x1a\n");
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_download_range() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/data_formats/download_data.sql")
+        .await?
+        .insert_header((header::RANGE, "bytes=1-3"))
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_RANGE).unwrap(),
+        "bytes 1-3/8"
+    );
+    let body = test::read_body(resp).await;
+    assert_eq!(body.as_ref(), b"PNG");
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_embedded_json_data() -> actix_web::Result<()> {
+    let mut config = test_config();
+    config.embed_allowed_origins = vec!["https://portal.example.com".to_string()];
+    let app_data = make_app_data_from_config(config).await;
+    let req = get_request_to_with_data(
+        "/tests/sql_test_files/it_works_simple.sql?_sqlpage_embed=json",
+        app_data,
+    )
+    .await?
+    .insert_header((header::ORIGIN, "https://portal.example.com"))
+    .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        "https://portal.example.com"
+    );
+    let body = test::read_body(resp).await;
+    let body_json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body_json,
+        serde_json::json!([
+            {"component": "shell", "title": "Hello world !"},
+            {"component": "text", "contents": "It works !"}
+        ])
+    );
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_embedded_json_data_any_origin() -> actix_web::Result<()> {
+    let mut config = test_config();
+    config.embed_allowed_origins = vec!["https://portal.example.com".to_string(), "*".to_string()];
+    let app_data = make_app_data_from_config(config).await;
+    for (origin, allowed_origin, credentials) in [
+        (
+            "https://portal.example.com",
+            "https://portal.example.com",
+            Some("true"),
+        ),
+        ("https://evil.example.com", "*", None),
+    ] {
+        let req = get_request_to_with_data(
+            "/tests/sql_test_files/it_works_simple.sql?_sqlpage_embed=json",
+            app_data.clone(),
+        )
+        .await?
+        .insert_header((header::ORIGIN, origin))
+        .to_srv_request();
+        let resp = main_handler(req).await?;
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            allowed_origin
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .map(|value| value.to_str().unwrap()),
+            credentials,
+            "{origin}"
+        );
+    }
+    Ok(())
+}
+
 #[actix_web::test]
 async fn test_json_columns() {
     let app_data = crate::common::make_app_data().await;
@@ -80,3 +301,28 @@ async fn test_json_columns() {
         "the json should have been parsed, not returned as a string, in: {body_html_escaped}"
     );
 }
+
+#[actix_web::test]
+async fn test_prometheus_body() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/data_formats/prometheus_data.sql")
+        .await?
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(
+        body_str,
+        "# HELP orders_total Number of orders\n\
+        # TYPE orders_total counter\n\
+        orders_total{status=\"paid\"} 42\n\
+        orders_total{status=\"say \\\"hi\\\"\"} 3\n\
+        revenue 1234.5\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,4 @@
+select 'prometheus' as component;
+select 'orders_total' as name, 'Number of orders' as help, 'counter' as type, 42 as value, '{"status": "paid"}' as labels;
+select 'orders_total' as name, 'Number of orders' as help, 'counter' as type, 3 as value, '{"status": "say \"hi\""}' as labels;
+select 'revenue' as name, '1234.5' as value;
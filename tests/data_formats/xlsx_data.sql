@@ -0,0 +1,5 @@
+select 'xlsx' as component, 'prices' as filename, 'Price list' as sheet_name,
+    '{"price": "#,##0.00", "updated": "yyyy-mm-dd"}' as column_formats;
+select 'Coffee & tea' as product, 4.5 as price, '2024-03-01' as updated, 'Drinks' as category
+union all
+select 'Cake', '12', null, 'Desserts';
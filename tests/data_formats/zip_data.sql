@@ -0,0 +1,5 @@
+select 'zip' as component, 'case_42' as filename;
+select 'contract.txt' as name, 'Signed, sealed, delivered' as contents;
+select 'logo.png' as name, 'data:image/png;base64,iVBORw0KGgo=' as contents;
+select 'csv' as component, 'invoices' as filename;
+select 1 as id, 'Coffee, tea' as product;
@@ -0,0 +1,3 @@
+set token = sqlpage.encrypt('user-42');
+select 'shell-empty' as component,
+    coalesce(sqlpage.decrypt($token, 60), 'NULL') || ' ' || coalesce(sqlpage.decrypt($forged), 'NULL') as html;
@@ -0,0 +1,6 @@
+drop table if exists sqlpage_json_import_test_table;
+create table sqlpage_json_import_test_table(name text, age text);
+copy sqlpage_json_import_test_table(name, age) from 'request_body' (format json);
+select 'text' as component,
+    name || ' is ' || age || ' years old. ' as contents
+from sqlpage_json_import_test_table;
@@ -0,0 +1,2 @@
+select 'shell-empty' as component,
+    coalesce(sqlpage.jwt_claim(sqlpage.header('authorization'), 'sub'), 'NULL') as html;
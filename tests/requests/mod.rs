@@ -1,7 +1,9 @@
 use actix_web::{http::StatusCode, test};
 use sqlpage::webserver::http::main_handler;
 
-use crate::common::get_request_to;
+use crate::common::{
+    get_request_to, get_request_to_with_data, make_app_data_from_config, test_config,
+};
 
 #[actix_web::test]
 async fn test_request_body() -> actix_web::Result<()> {
@@ -38,6 +40,47 @@ async fn test_request_body() -> actix_web::Result<()> {
     Ok(())
 }
 
+#[actix_web::test]
+async fn test_request_body_too_large() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/requests/request_body_test.sql")
+        .await?
+        .insert_header(("content-type", "application/json"))
+        .set_payload(vec![b'x'; 1_000_000])
+        .to_srv_request();
+    let err = main_handler(req)
+        .await
+        .expect_err("Expected an error response");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    let err_str = err.to_string();
+    assert!(
+        err_str.contains("max_uploaded_file_size"),
+        "{err_str}\nexpected to mention max_uploaded_file_size"
+    );
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_json_body_import() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/requests/json_import_test.sql")
+        .await?
+        .insert_header(("content-type", "application/json"))
+        .set_payload(r#"[{"name": "Ophir", "age": 29}, {"age": "99", "name": "Max"}]"#)
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("Ophir is 29 years old") && body_str.contains("Max is 99 years old"),
+        "{body_str}\nexpected to contain the imported rows"
+    );
+    Ok(())
+}
+
 #[actix_web::test]
 async fn test_request_body_base64() -> actix_web::Result<()> {
     let binary_data = (0u8..=255u8).collect::<Vec<_>>();
@@ -99,3 +142,72 @@ async fn test_large_form_field_roundtrip() -> actix_web::Result<()> {
     );
     Ok(())
 }
+
+#[actix_web::test]
+async fn test_jwt_claim() -> actix_web::Result<()> {
+    use base64::Engine;
+    use hmac::{Hmac, Mac};
+    let mut config = test_config();
+    config.jwt_secret = Some("secret".to_string());
+    let app_data = make_app_data_from_config(config).await;
+
+    let encode = |s: &[u8]| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(s);
+    let sign = |secret: &[u8]| {
+        let signed = format!(
+            "{}.{}",
+            encode(br#"{"alg":"HS256","typ":"JWT"}"#),
+            encode(br#"{"sub":"user-42"}"#)
+        );
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret).unwrap();
+        mac.update(signed.as_bytes());
+        format!("{signed}.{}", encode(&mac.finalize().into_bytes()))
+    };
+
+    for (token, expected) in [(sign(b"secret"), "user-42"), (sign(b"forged"), "NULL")] {
+        let req = get_request_to_with_data("/tests/requests/jwt_claim_test.sql", app_data.clone())
+            .await?
+            .insert_header(("authorization", format!("Bearer {token}")))
+            .to_srv_request();
+        let resp = main_handler(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap(), expected);
+    }
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_encrypt_decrypt() -> actix_web::Result<()> {
+    let mut config = test_config();
+    config.encryption_key = Some("a secret key of at least 32 characters".to_string());
+    let app_data = make_app_data_from_config(config).await;
+    let req = get_request_to_with_data(
+        "/tests/requests/encrypt_test.sql?forged=dXNlci00Mg",
+        app_data,
+    )
+    .await?
+    .to_srv_request();
+    let resp = main_handler(req).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert_eq!(String::from_utf8(body.to_vec()).unwrap(), "user-42 NULL");
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_state_encode_decode() -> actix_web::Result<()> {
+    let mut config = test_config();
+    config.encryption_key = Some("a secret key of at least 32 characters".to_string());
+    let app_data = make_app_data_from_config(config).await;
+    let req = get_request_to_with_data(
+        "/tests/requests/state_token_test.sql?forged=q1YqSsxLSS1SslIqySzJSVWqBQA",
+        app_data,
+    )
+    .await?
+    .to_srv_request();
+    let resp = main_handler(req).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert_eq!(String::from_utf8(body.to_vec()).unwrap(), "41 NULL");
+    Ok(())
+}
@@ -0,0 +1,5 @@
+set token = sqlpage.state_encode('{"search": "blue shoes", "sizes": [40, 41]}');
+set state = sqlpage.state_decode($token);
+set size = sqlpage.json_get($state, '$.sizes[1]');
+select 'shell-empty' as component,
+    $size || ' ' || coalesce(sqlpage.state_decode($forged), 'NULL') as html;
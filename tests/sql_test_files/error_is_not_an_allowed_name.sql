@@ -0,0 +1,6 @@
+select 'text' as component, 'The column name is checked before the query is sent to the database' as contents;
+
+set col = 'a; drop table users';
+select 'text' as component,
+    sqlpage.identifier($col, 'a,b') as contents
+from (select 'x' as a, 'y' as b) as t;
@@ -0,0 +1,13 @@
+drop table if exists as_of_prices;
+drop table if exists as_of_prices_history;
+create table as_of_prices(product varchar(100), price integer, valid_from varchar(20), valid_to varchar(20));
+create table as_of_prices_history(product varchar(100), price integer, valid_from varchar(20), valid_to varchar(20));
+insert into as_of_prices(product, price, valid_from) values ('shoes', 30, '2024-06-01');
+insert into as_of_prices_history(product, price, valid_from, valid_to) values ('shoes', 10, '2024-01-01', '2024-03-01'), ('shoes', 20, '2024-03-01', '2024-06-01');
+
+set old_price = (select price from sqlpage.as_of('as_of_prices', '2024-04-15') where product = 'shoes');
+set current_price = (select as_of_prices.price from sqlpage.as_of('as_of_prices', null) where as_of_prices.product = 'shoes');
+
+select 'text' as component,
+    case when $old_price = '20' and $current_price = '30' then 'It works !'
+    else 'old price: ' || coalesce($old_price, 'null') || ', current price: ' || coalesce($current_price, 'null') end as contents;
@@ -0,0 +1,8 @@
+-- checks that the client certificate functions return null when the client did not present a certificate
+set fingerprint = sqlpage.client_certificate_fingerprint();
+set subject = sqlpage.client_certificate_subject();
+select 'text' as component,
+    case when $fingerprint is null and $subject is null
+          then 'It works !'
+        else 'Failed. Expected: null. Got: ' || coalesce($fingerprint, $subject)
+        end as contents;
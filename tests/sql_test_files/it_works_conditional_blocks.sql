@@ -0,0 +1,16 @@
+set result = 'It failed ! The first block was skipped';
+-- @if $x = '1'
+set result = 'It works !';
+    -- @if 1 = 0
+    set result = 'It failed ! A false condition was executed';
+    -- @else
+    set nested = 'ok';
+    -- @endif
+-- @else
+set result = 'It failed ! The else branch was executed';
+-- @endif
+-- @if $missing
+set result = 'It failed ! A NULL condition was executed';
+-- @endif
+select 'text' as component,
+    case when $nested = 'ok' then $result else 'It failed ! The nested else branch was skipped' end as contents;
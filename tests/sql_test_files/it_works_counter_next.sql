@@ -0,0 +1,12 @@
+drop table if exists sqlpage_counters;
+create table sqlpage_counters(name varchar(255) primary key, value bigint not null);
+
+set first_invoice = sqlpage.counter_next('invoice');
+set second_invoice = sqlpage.counter_next('invoice');
+set first_order = sqlpage.counter_next('order');
+
+select 'text' as component,
+    case when $first_invoice = '1' and $second_invoice = '2' and $first_order = '1'
+        then 'It works !'
+        else CONCAT('Unexpected counter values: ', $first_invoice, ', ', $second_invoice, ', ', $first_order)
+    end as contents;
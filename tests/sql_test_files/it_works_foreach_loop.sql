@@ -0,0 +1,16 @@
+set total = '';
+-- @foreach
+select 1 as n, 'a' as letter
+union all select 2 as n, 'b' as letter
+union all select 3 as n, 'c' as letter
+order by n;
+set total = $total || $n || $letter;
+-- @if $n = 2
+set two = 'seen';
+-- @endif
+-- @endforeach
+select 'text' as component,
+    case
+        when $total = '1a2b3c' and $two = 'seen' and $n is null then 'It works !'
+        else 'It failed ! ' || $total
+    end as contents;
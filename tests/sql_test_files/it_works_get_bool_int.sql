@@ -0,0 +1,12 @@
+select 'text' as component,
+    case
+        when sqlpage.get_int('x', 5) = '1'
+            and sqlpage.get_int('x', 5, 2, 10) = '2'
+            and sqlpage.get_int('page', 1) = '1'
+            and sqlpage.get_int('page') is null
+            and sqlpage.get_bool('x', false) = '1'
+            and sqlpage.get_bool('active', 'false') = '0'
+            and sqlpage.get_bool('active') is null
+        then 'It works !'
+        else 'It failed !'
+    end as contents;
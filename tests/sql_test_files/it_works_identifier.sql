@@ -0,0 +1,5 @@
+set col = 'B';
+select 'text' as component,
+    sqlpage.identifier($col, 'a,b') as contents
+from (select 'It does not work' as a, 'It works !' as b) as t
+order by sqlpage.identifier($sort, 'a,t.b');
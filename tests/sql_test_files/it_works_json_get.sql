@@ -0,0 +1,12 @@
+set payload = '{"order": {"id": 7, "items": [{"name": "pen"}, {"name": "ink"}], "paid": true, "note": null}}';
+select 'text' as component,
+    case
+        when sqlpage.json_get($payload, '$.order.items[-1].name') = 'ink'
+            and sqlpage.json_get($payload, '$.order.id') = '7'
+            and sqlpage.json_get($payload, '$.order.paid') = 'true'
+            and sqlpage.json_get($payload, '$.order.items[0]') = '{"name":"pen"}'
+            and sqlpage.json_get($payload, '$.order.note') is null
+            and sqlpage.json_get($payload, '$.order.missing[3]') is null
+        then 'It works !'
+        else 'It failed !'
+    end as contents;
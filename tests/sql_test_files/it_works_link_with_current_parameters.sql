@@ -0,0 +1,10 @@
+set actual = sqlpage.link_with_current_parameters('test.sql', json_object('page', 2))
+    || ' ' || sqlpage.link_with_current_parameters('test.sql', json_object('x', 'a b'))
+    || ' ' || sqlpage.link_with_current_parameters('test.sql', json_object('x', null))
+    || ' ' || sqlpage.link_with_current_parameters('test.sql', null);
+set expected = 'test.sql?x=1&page=2 test.sql?x=a%20b test.sql test.sql?x=1';
+select 'text' as component,
+    case $actual
+        when $expected then 'It works !'
+        else 'Expected ' || $expected || ' but got ' || coalesce($actual, 'null')
+    end as contents;
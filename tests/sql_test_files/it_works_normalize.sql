@@ -0,0 +1,10 @@
+select 'text' as component,
+    case
+        when sqlpage.normalize_email(' Ada@Example.COM ') = 'Ada@example.com'
+            and sqlpage.normalize_email('not an email') is null
+            and sqlpage.normalize_phone('06 12 34 56 78', '33') = '+33612345678'
+            and sqlpage.normalize_url('example.com') = 'https://example.com/'
+            and sqlpage.normalize_url('javascript:alert(1)') is null
+        then 'It works !'
+        else 'It failed !'
+    end as contents;
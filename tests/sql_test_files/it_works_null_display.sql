@@ -0,0 +1,2 @@
+select 'text' as component, 'It works !' as null_display;
+select null as contents;
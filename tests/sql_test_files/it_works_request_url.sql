@@ -0,0 +1,7 @@
+select 'text' as component,
+    case
+        when sqlpage.url() = sqlpage.protocol() || '://' || sqlpage.host() || sqlpage.path() || '?x=1'
+            and sqlpage.host() <> ''
+        then 'It works !'
+        else 'It failed ! ' || sqlpage.url()
+    end as contents;
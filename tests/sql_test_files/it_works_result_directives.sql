@@ -0,0 +1,9 @@
+-- @into $letters
+select 'a' as letter union all select 'b' as letter;
+-- @discard
+select 'text' as component, 'error: this row should not be displayed' as contents;
+-- @render text
+select case
+    when $letters = '["a","b"]' then 'It works !'
+    else 'It failed ! ' || $letters
+end as contents;
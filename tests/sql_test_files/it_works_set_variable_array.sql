@@ -0,0 +1,11 @@
+set numbers = ARRAY(select 1 as n union all select 2 as n);
+set people = ARRAY(select 'Alice' as name, 30 as age);
+set nothing = ARRAY(select 1 as n where 1 = 0);
+select 'text' as component,
+    case
+        when $numbers = '[1,2]'
+            and $people = '[{"name":"Alice","age":30}]'
+            and $nothing = '[]'
+        then 'It works !'
+        else 'It failed ! ' || $numbers || ' ' || $people || ' ' || $nothing
+    end as contents;
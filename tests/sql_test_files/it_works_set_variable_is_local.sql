@@ -0,0 +1,11 @@
+-- Variables defined with SET do not modify the URL parameters
+set total = 40 + 2;
+set x = 'shadowed';
+select 'text' as component,
+    case
+        when $total = '42' and $x = 'shadowed'
+            and sqlpage.variables('set') like '%"total":"42"%'
+            and sqlpage.variables('get') = '{"x":"1"}'
+        then 'It works !'
+        else 'It failed ! ' || sqlpage.variables('set')
+    end as contents;
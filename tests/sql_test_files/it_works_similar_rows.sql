@@ -0,0 +1,13 @@
+drop table if exists similar_rows_customers;
+create table similar_rows_customers(id int, name varchar(100), email varchar(100));
+insert into similar_rows_customers(id, name, email) values (1, 'John Smith', 'john@example.com');
+insert into similar_rows_customers(id, name, email) values (2, 'Alice Martin', 'alice@example.com');
+
+set similar = sqlpage.similar_rows('similar_rows_customers', '{"name": "Jon Smith", "email": "john@example.com"}', null, 'id');
+set none = sqlpage.similar_rows('similar_rows_customers', '{"name": "Bob Dylan"}');
+
+select 'text' as component,
+    case
+        when $similar like '[{"id":1,%' and $similar not like '%Alice%' and $none is null then 'It works !'
+        else 'It failed ! ' || $similar
+    end as contents;
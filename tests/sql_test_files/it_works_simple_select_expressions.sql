@@ -0,0 +1,2 @@
+select 'text' as component,
+    'It ' || coalesce($nothing, 'works') || ' !' as contents;
@@ -0,0 +1,9 @@
+set secret = sqlpage.totp_secret();
+select 'text' as component,
+    case
+        when $secret is not null
+            and sqlpage.totp_verify($secret, 'abcdef') = 'false'
+            and sqlpage.totp_verify('not a secret', '123456') = 'false'
+        then 'It works !'
+        else 'It failed !'
+    end as contents;
@@ -0,0 +1,6 @@
+select 'text' as component,
+    CASE sqlpage.url_decode('hello%20w%C3%B6rld+%2B%2F') || coalesce(sqlpage.url_decode(NULL), 'null')
+        WHEN 'hello wörld +/null' THEN 'It works !'
+        ELSE 'It failed ! Got: ' || sqlpage.url_decode('hello%20w%C3%B6rld+%2B%2F')
+    END
+    AS contents;
@@ -0,0 +1,2 @@
+select 'text' as component,
+    case when sqlpage.uuid() like '________-____-4___-____-____________' then 'It works !' else 'It failed !' end as contents;
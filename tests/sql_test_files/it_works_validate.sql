@@ -0,0 +1,7 @@
+set errors = sqlpage.validate('{"x": {"min": 2}, "y": {"required": true, "message": "Please fill y"}}');
+set no_errors = sqlpage.validate('{"x": {"required": true, "one_of": ["1", "2"]}, "y": {"max_length": 3}}');
+select 'text' as component,
+    case
+        when $errors = '{"x":"Must be greater than or equal to 2.","y":"Please fill y"}' and $no_errors is null then 'It works !'
+        else 'It failed ! ' || $errors
+    end as contents;
@@ -0,0 +1,9 @@
+set lock_name = sqlpage.with_lock('it_works_with_lock', 1);
+-- Acquiring a lock that the page already holds does not wait
+set same_lock = sqlpage.with_lock('it_works_with_lock', 0);
+
+select 'text' as component,
+    case when $lock_name = 'it_works_with_lock' and $same_lock = 'it_works_with_lock'
+        then 'It works !'
+        else 'The lock was not acquired'
+    end as contents;
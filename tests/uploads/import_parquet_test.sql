@@ -0,0 +1,7 @@
+drop table if exists sqlpage_parquet_people_test_table;
+create table sqlpage_parquet_people_test_table(name text, birth_date text);
+-- A file on disk, relative to the web root
+copy sqlpage_parquet_people_test_table(name, birth_date) from 'file:tests/uploads/people.parquet' with (format parquet);
+select 'text' as component,
+    name || ' was born on ' || birth_date || '. ' as contents
+from sqlpage_parquet_people_test_table;
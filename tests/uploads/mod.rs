@@ -92,6 +92,31 @@ async fn test_file_upload_too_large() -> actix_web::Result<()> {
     Ok(())
 }
 
+#[actix_web::test]
+async fn test_persist_uploaded_file_max_size() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/uploads/persist_uploaded_file_max_size_test.sql")
+        .await?
+        .insert_header(("content-type", "multipart/form-data; boundary=1234567890"))
+        .set_payload(
+            "--1234567890\r\n\
+            Content-Disposition: form-data; name=\"my_file\"; filename=\"testfile.txt\"\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            Hello, world!\r\n\
+            --1234567890--\r\n",
+        )
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("is too large: 13 bytes"),
+        "{body_str}\nexpected to contain: is too large"
+    );
+    assert!(!std::path::Path::new("tests/uploads/persisted").exists());
+    Ok(())
+}
+
 #[actix_web::test]
 async fn test_upload_file_data_url() -> actix_web::Result<()> {
     let req = get_request_to("/tests/uploads/upload_file_data_url_test.sql")
@@ -136,6 +161,28 @@ async fn test_uploaded_file_name() -> actix_web::Result<()> {
     Ok(())
 }
 
+#[actix_web::test]
+async fn test_uploaded_file_size() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/uploads/uploaded_file_size_test.sql")
+        .await?
+        .insert_header(("content-type", "multipart/form-data; boundary=1234567890"))
+        .set_payload(
+            "--1234567890\r\n\
+            Content-Disposition: form-data; name=\"my_file\"; filename=\"testfile.txt\"\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            Some plain text.\r\n\
+            --1234567890--\r\n",
+        )
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_str, "16");
+    Ok(())
+}
+
 #[actix_web::test]
 async fn test_csv_upload() -> actix_web::Result<()> {
     let req = get_request_to("/tests/uploads/upload_csv_test.sql")
@@ -163,3 +210,48 @@ async fn test_csv_upload() -> actix_web::Result<()> {
     );
     Ok(())
 }
+
+#[actix_web::test]
+async fn test_xlsx_upload() -> actix_web::Result<()> {
+    let mut payload = b"--1234567890\r\n\
+        Content-Disposition: form-data; name=\"people_file\"; filename=\"people.xlsx\"\r\n\
+        Content-Type: application/vnd.openxmlformats-officedocument.spreadsheetml.sheet\r\n\
+        \r\n"
+        .to_vec();
+    payload.extend_from_slice(include_bytes!("people.xlsx"));
+    payload.extend_from_slice(b"\r\n--1234567890--\r\n");
+    let req = get_request_to("/tests/uploads/upload_xlsx_test.sql")
+        .await?
+        .insert_header(("content-type", "multipart/form-data; boundary=1234567890"))
+        .set_payload(payload)
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("Ophir is 29 years old, born 1995-07-11.")
+            && body_str.contains("Max is 99 years old, born on an unknown date."),
+        "{body_str}\nexpected to contain the rows of the People sheet"
+    );
+    Ok(())
+}
+
+#[actix_web::test]
+async fn test_parquet_import_from_disk() -> actix_web::Result<()> {
+    let req = get_request_to("/tests/uploads/import_parquet_test.sql")
+        .await?
+        .to_srv_request();
+    let resp = main_handler(req).await?;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body_str.contains("Ophir was born on 1995-07-11.")
+            && body_str.contains("Max was born on 1970-01-01."),
+        "{body_str}\nexpected to contain the rows of the Parquet file"
+    );
+    Ok(())
+}
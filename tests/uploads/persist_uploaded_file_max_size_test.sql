@@ -0,0 +1,3 @@
+-- the uploaded file is larger than the maximum size, so it should not be persisted
+select 'text' as component,
+    sqlpage.persist_uploaded_file('my_file', 'tests/uploads/persisted', 'txt', '5') as contents;
@@ -0,0 +1,6 @@
+drop table if exists sqlpage_xlsx_people_test_table;
+create table sqlpage_xlsx_people_test_table(name text, age text, birth_date text);
+copy sqlpage_xlsx_people_test_table(name, age, birth_date) from 'people_file#People' with (format xlsx);
+select 'text' as component,
+    name || ' is ' || age || ' years old, born ' || coalesce(birth_date, 'on an unknown date') || '. ' as contents
+from sqlpage_xlsx_people_test_table;
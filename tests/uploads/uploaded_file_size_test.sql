@@ -0,0 +1,3 @@
+-- display the size of the uploaded file, in bytes, and nothing else
+select 'shell-empty' as component,
+    sqlpage.uploaded_file_size('my_file') as html;